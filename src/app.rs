@@ -1,39 +1,145 @@
+use std::ops::Range;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Commits loaded per history page, unless `history_page_size` is configured
+pub const HISTORY_PAGE_SIZE: usize = 100;
+/// Lines of a file's diff shown until the whole diff is asked for
+pub const DIFF_LINE_LIMIT: usize = 5000;
+/// Narrowest and widest a list can get next to its diff or detail pane, in percent
+pub const SPLIT_RANGE: (u16, u16) = (10, 90);
+/// Matches a content search lists before it stops
+pub const GREP_MATCH_LIMIT: usize = 1000;
+
+use serde::Deserialize;
+
+use crate::config::{AppConfig, ConfirmLevel};
+use crate::conflict::ConflictFile;
+use crate::dialog::DialogStack;
+use crate::git::{HistoryCursor, SshKeys};
+use crate::graph::{self, GraphInfo};
+use crate::i18n::tr;
+use crate::keymap::Keymap;
+use crate::panel::{Panel, PanelStack};
+use crate::theme::Theme;
+
+/// The main views; `default_view` in the config names one as `home`, `files`, ...
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum View {
+    /// Overview shown on startup
+    #[serde(rename = "home")]
+    Dashboard,
     History,
     Files,
     Branches,
+    Tags,
 }
 
 #[derive(Debug)]
 pub struct App {
     pub current_view: View,
-    #[allow(dead_code)]
     pub repo_path: PathBuf,
     pub should_quit: bool,
     pub history_state: HistoryState,
     pub files_state: FilesState,
     pub branches_state: BranchesState,
+    pub tags_state: TagsState,
     pub status_message: Option<String>,
-    pub show_commit_dialog: bool,
+    /// Confirm, input and choice dialogs; the topmost gets the keys
+    pub dialogs: DialogStack,
+    /// Panels drawn over the view, under any dialogs; the topmost gets the keys
+    pub panels: PanelStack,
+    /// Message the commit dialog starts from: a template, git's prepared message, or one kept by
+    /// undo or a squash merge
     pub commit_message: String,
-    pub show_branch_dialog: bool,
     pub branch_creation: BranchCreation,
-    pub show_delete_confirm: bool,
-    pub delete_confirmation: String,
-    pub branch_to_delete: Option<String>,
-    pub show_discard_confirm: bool,
-    pub discard_confirmation: String,
-    pub file_to_discard: Option<String>,
-    pub discard_all: bool,
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub startup_warnings: Vec<String>,
+    pub accessible: bool,
+    /// Pinned comparison base shown in the header regardless of the checked-out branch
+    pub compare_base: Option<String>,
+    /// (ahead, behind) of HEAD against `compare_base`; `None` if the base doesn't resolve
+    pub base_divergence: Option<(usize, usize)>,
+    /// Merge, rebase or similar operation stopped midway, shown as a banner in the header
+    pub in_progress: Option<InProgress>,
+    /// Short id HEAD points at while detached from any branch
+    pub detached_at: Option<String>,
+    /// Entries in the stash, for the dashboard
+    pub stash_count: usize,
+    /// History export file, relative to the repository
+    pub export_path: String,
+    /// Patch file to apply, relative to the repository; empty reads the clipboard
+    pub apply_path: String,
+    pub apply_to_index: bool,
+    /// `.patch` file the Files diff is written to, relative to the repository
+    pub diff_export_path: String,
+    /// Export every changed file's diff rather than just the selected one's
+    pub diff_export_all: bool,
+    /// Output directory for format-patch, relative to the repository
+    pub patch_dir: String,
+    /// Progress of the running fetch or push, shown in the footer
+    pub transfer: Option<TransferProgress>,
+    /// Diffs leave out changes that only touch whitespace, like `git diff -w`
+    pub ignore_whitespace: bool,
+    /// Output of the system git commands run for network operations
+    pub command_log: Vec<String>,
+    pub command_log_scroll: usize,
+    /// Shown when started outside a repository: initialize one, clone one or quit
+    pub show_init_prompt: bool,
+    /// Repository to switch to once the main loop returns, e.g. a fresh clone
+    pub open_repo: Option<PathBuf>,
+    /// Network settings for clones, which happen outside any open repository
+    pub ssh_keys: SshKeys,
+    pub shell_git: bool,
+    pub protected_branches: Vec<String>,
+    pub branch_prefix: Option<String>,
+    pub commit_template: Option<String>,
+    /// Merge and pull refuse anything but a fast-forward
+    pub ff_only: bool,
+    /// Default of the new branch dialog's push checkbox
+    pub push_new_branches: bool,
+    /// Command the difftool key runs instead of git's configured `diff.tool`
+    pub difftool: Option<String>,
+    /// Age in days from which the stale branch report lists an unmerged branch
+    pub stale_branch_days: i64,
+    /// Commits loaded per page of history
+    pub history_page_size: usize,
+    /// Which deletions and discards ask first
+    pub confirm: ConfirmLevel,
+    /// Percent of the width the Files list takes; the diff gets the rest
+    pub files_split: u16,
+    /// Percent of the width the History list takes while a commit is open
+    pub history_split: u16,
+    /// The pane border is being dragged with the mouse; the split is saved on release
+    pub dragging_split: bool,
 }
 
 #[derive(Debug)]
 pub struct HistoryState {
     pub selected: usize,
     pub commits: Vec<CommitInfo>,
+    /// Set while the walk has commits beyond the loaded pages
+    pub has_more: bool,
+    /// Where the next page of the walk starts
+    pub cursor: HistoryCursor,
+    /// Graph lanes below the last loaded commit, for laying out the next page
+    pub graph: graph::Layout,
+    pub filter: HistoryFilter,
+    /// Commit marked with `v`; together with the selection it spans a range to diff
+    pub range_anchor: Option<String>,
+    pub detail: Option<CommitDetail>,
+    pub commit_diff: Option<String>,
+    pub detail_scroll: usize,
+}
+
+/// Which commits the History walk visits
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HistoryFilter {
+    /// Walk every local and remote branch instead of just HEAD
+    pub all_branches: bool,
+    /// Follow only first parents, hiding commits brought in by merges
+    pub first_parent: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -44,14 +150,47 @@ pub struct CommitInfo {
     pub message: String,
     pub parent_ids: Vec<String>,
     pub graph_info: Option<GraphInfo>,
+    /// Local then remote branches whose tip is this commit
     pub branches: Vec<String>,
+    pub tags: Vec<String>,
+}
+
+/// Everything shown in the History detail pane for a single commit
+#[derive(Debug, Clone)]
+pub struct CommitDetail {
+    pub id: String,
+    pub author: String,
+    pub author_date: String,
+    pub committer: String,
+    pub commit_date: String,
+    pub parent_ids: Vec<String>,
+    pub message: String,
+    pub files: Vec<CommitFileChange>,
+}
+
+impl CommitDetail {
+    /// Lines the detail pane renders: four header lines and a blank, the message, a blank,
+    /// the totals and one line per file
+    pub fn line_count(&self) -> usize {
+        5 + self.message.lines().count() + 2 + self.files.len()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CommitFileChange {
+    pub path: String,
+    pub status: char,
+    pub insertions: usize,
+    pub deletions: usize,
 }
 
+/// Diff totals of a commit against its first parent
 #[derive(Debug, Clone)]
-pub struct GraphInfo {
-    #[allow(dead_code)]
-    pub column: usize,
-    pub graph_line: String,
+pub struct CommitStats {
+    pub full_id: String,
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
 }
 
 #[derive(Debug)]
@@ -60,12 +199,68 @@ pub struct FilesState {
     pub files: Vec<FileStatus>,
     pub current_diff: Option<String>,
     pub diff_scroll: usize,
+    /// Diff lines that changed on disk at the last auto-refresh, highlighted until the selection moves
+    pub changed_lines: Option<Range<usize>>,
+    /// Hunks the last applied patch couldn't place, shown instead of the diff until dismissed
+    pub rejected_hunks: Option<String>,
+    /// File whose diff is shown in full rather than cut at `DIFF_LINE_LIMIT`
+    pub full_diff_path: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+impl FilesState {
+    /// How many lines of the selected file's diff to build; `None` once it was loaded in full
+    pub fn diff_line_limit(&self) -> Option<usize> {
+        let selected = self.files.get(self.selected).map(|file| &file.path);
+        if selected.is_some() && selected == self.full_diff_path.as_ref() {
+            None
+        } else {
+            Some(DIFF_LINE_LIMIT)
+        }
+    }
+
+    /// The text the diff pane shows: rejected hunks while there are any, else the file's diff
+    pub fn displayed_diff(&self) -> Option<&str> {
+        self.rejected_hunks
+            .as_deref()
+            .or(self.current_diff.as_deref())
+    }
+
+    /// Count the changed files by kind for the header
+    pub fn summary(&self) -> WorkingTreeSummary {
+        let mut summary = WorkingTreeSummary::default();
+        for file in &self.files {
+            match file.status.as_str() {
+                "??" => summary.untracked += 1,
+                "UU" => summary.conflicted += 1,
+                _ if file.staged => summary.staged += 1,
+                _ => summary.modified += 1,
+            }
+        }
+        summary
+    }
+}
+
+/// Number of changed files in each part of the working tree; a file with staged and unstaged
+/// changes counts as both staged and modified
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorkingTreeSummary {
+    pub staged: usize,
+    pub modified: usize,
+    pub untracked: usize,
+    pub conflicted: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FileStatus {
     pub path: String,
+    /// Where a staged rename came from
+    pub old_path: Option<String>,
     pub status: String,
+    /// Whether this row is about the file's staged changes rather than its unstaged ones
+    pub staged: bool,
+    /// Lines added and removed by this row's side: HEAD to index, or index to working tree
+    pub additions: usize,
+    pub deletions: usize,
 }
 
 #[derive(Debug)]
@@ -73,67 +268,710 @@ pub struct BranchesState {
     pub selected: usize,
     pub branches: Vec<BranchInfo>,
     pub current_branch: String,
+    /// Most recently committed branches first instead of alphabetical
+    pub sort_by_recency: bool,
+}
+
+impl BranchesState {
+    /// Commits the current branch is ahead of and behind its upstream, if it tracks one
+    pub fn current_ahead_behind(&self) -> Option<(usize, usize)> {
+        self.branches
+            .iter()
+            .find(|branch| branch.is_current)
+            .and_then(|branch| branch.ahead_behind)
+    }
+
+    /// Indices of the branches whose name contains `filter`, ignoring case
+    pub fn visible(&self, filter: Option<&str>) -> Vec<usize> {
+        let needle = filter.unwrap_or("").to_lowercase();
+        self.branches
+            .iter()
+            .enumerate()
+            .filter(|(_, branch)| branch.name.to_lowercase().contains(&needle))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Order the branches by name or by tip commit time, local branches before remote ones,
+    /// keeping the same branch selected
+    pub fn sort(&mut self) {
+        let selected = self
+            .branches
+            .get(self.selected)
+            .map(|branch| branch.name.clone());
+        if self.sort_by_recency {
+            self.branches.sort_by(|a, b| {
+                (a.remote.is_some(), b.tip_time).cmp(&(b.remote.is_some(), a.tip_time))
+            });
+        } else {
+            self.branches
+                .sort_by(|a, b| (a.remote.is_some(), &a.name).cmp(&(b.remote.is_some(), &b.name)));
+        }
+        if let Some(pos) =
+            selected.and_then(|name| self.branches.iter().position(|b| b.name == name))
+        {
+            self.selected = pos;
+        }
+    }
+
+    /// Keep the selection on a visible branch after the filter changed
+    pub fn select_visible(&mut self, filter: &str) {
+        let visible = self.visible(Some(filter));
+        if !visible.contains(&self.selected) {
+            if let Some(&first) = visible.first() {
+                self.selected = first;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TagsState {
+    pub selected: usize,
+    pub tags: Vec<TagInfo>,
+}
+
+/// One line of `git blame` output
+#[derive(Debug, Clone)]
+pub struct BlameLine {
+    /// Short id of the commit that last changed the line
+    pub id: String,
+    pub author: String,
+    pub date: String,
+    /// The file's path in that commit, which differs from today's if it was renamed since
+    pub path: String,
+    pub content: String,
+}
+
+/// Blame of a file at some revision, with the blames it was dug down from by reblaming
+#[derive(Debug, Default)]
+pub struct BlameView {
+    pub path: String,
+    pub revision: String,
+    pub lines: Vec<BlameLine>,
+    pub selected: usize,
+    /// Earlier (path, revision, selected line) to go back to, most recent last
+    pub previous: Vec<(String, String, usize)>,
+}
+
+/// A line of a tracked file that matched a content search
+#[derive(Debug, Clone)]
+pub struct GrepMatch {
+    pub path: String,
+    /// 1-based line number
+    pub line: usize,
+    pub text: String,
+}
+
+/// Content search over the tracked files, with the matches and a preview of the selected one
+#[derive(Debug, Default)]
+pub struct GrepSearch {
+    pub pattern: String,
+    /// Revision to search instead of the working tree; empty for the working tree
+    pub revision: String,
+    pub editing_revision: bool,
+    /// Keys move through the matches rather than editing the query
+    pub browsing: bool,
+    pub matches: Vec<GrepMatch>,
+    pub selected: usize,
+    /// The search stopped at the match limit
+    pub truncated: bool,
+    /// Path and lines of the file the selected match is in
+    pub preview: Option<(String, Vec<String>)>,
+}
+
+/// What a commit under test turned out to be during a bisect
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BisectMark {
+    Good,
+    Bad,
+    Skip,
+}
+
+/// Where a bisect stands after the marks so far
+#[derive(Debug, Clone, PartialEq)]
+pub enum BisectStep {
+    /// No commit has been marked bad yet
+    NeedBad,
+    /// No commit has been marked good yet
+    NeedGood,
+    /// `commit` is checked out to be tested, with `remaining` suspects left, roughly `steps`
+    /// more marks apart
+    Testing {
+        commit: String,
+        remaining: usize,
+        steps: u32,
+    },
+    /// The first bad commit
+    Found(String),
+    /// Only skipped commits are left, so the first bad one is any of these
+    Inconclusive(Vec<String>),
+}
+
+/// Bisect panel opened from the History view; its keys mark `target`
+#[derive(Debug)]
+pub struct BisectPanel {
+    pub target: String,
+    pub step: BisectStep,
+}
+
+#[derive(Debug, Clone)]
+pub struct TagInfo {
+    pub name: String,
+    pub target: String,
+    /// First line of the tag message; `None` for lightweight tags
+    pub message: Option<String>,
+}
+
+/// One commit in the rebase plan, in the order it will be replayed
+#[derive(Debug, Clone)]
+pub struct RebaseEntry {
+    pub id: String,
+    pub subject: String,
+    pub files: Vec<String>,
+}
+
+/// Progress of a running fetch (objects received, deltas resolved) or push (objects sent)
+#[derive(Debug, Clone, Default)]
+pub struct TransferProgress {
+    pub sending: bool,
+    pub objects: usize,
+    pub total_objects: usize,
+    pub bytes: usize,
+    pub deltas: usize,
+    pub total_deltas: usize,
+}
+
+/// A network operation waiting for a remote to be picked
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemoteAction {
+    Fetch,
+    Pull {
+        ff_only: bool,
+    },
+    Push,
+    Sync,
+    /// Push one tag, or all of them
+    PushTags {
+        tag: Option<String>,
+    },
+    /// Delete a tag on the remote, and with `locally` the local one after it
+    DeleteRemoteTag {
+        tag: String,
+        locally: bool,
+    },
+}
+
+/// Username and password (or token) asked for while a network operation waits
+#[derive(Debug)]
+pub struct CredentialPrompt {
+    pub url: String,
+    pub username: String,
+    pub password: String,
+    /// Whether typing goes to the password rather than the username
+    pub on_password: bool,
+}
+
+impl CredentialPrompt {
+    /// A username from the URL is filled in, so typing starts at the password
+    pub fn new(url: String, username: Option<String>) -> Self {
+        Self {
+            url,
+            on_password: username.is_some(),
+            username: username.unwrap_or_default(),
+            password: String::new(),
+        }
+    }
+
+    pub fn field_mut(&mut self) -> &mut String {
+        if self.on_password {
+            &mut self.password
+        } else {
+            &mut self.username
+        }
+    }
+}
+
+/// An operation that stopped midway, usually on conflicts, as `repo.state()` reports it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InProgress {
+    Merge,
+    /// (current step, total steps) when the rebase can be opened by libgit2
+    Rebase(Option<(usize, usize)>),
+    CherryPick,
+    Revert,
+    Bisect,
+    ApplyMailbox,
+}
+
+impl InProgress {
+    /// The operation's name for status messages
+    pub fn name(self) -> &'static str {
+        match self {
+            InProgress::Merge => tr!("merge"),
+            InProgress::Rebase(_) => tr!("rebase"),
+            InProgress::CherryPick => tr!("cherry-pick"),
+            InProgress::Revert => tr!("revert"),
+            InProgress::Bisect => tr!("bisect"),
+            InProgress::ApplyMailbox => tr!("git am"),
+        }
+    }
+}
+
+/// A checkout that local changes may be in the way of
+#[derive(Debug, Clone, PartialEq)]
+pub enum BranchCheckout {
+    /// A branch from the Branches view
+    Branch {
+        branch: String,
+        /// Remote of a remote-tracking branch, which is checked out as a local branch tracking it
+        remote: Option<String>,
+    },
+    /// The current branch fast-forwarded to the commit a pull fetched
+    FastForward { branch: String, target: String },
+}
+
+/// A conflicted file being resolved region by region
+#[derive(Debug)]
+pub struct ConflictEditor {
+    pub path: String,
+    pub file: ConflictFile,
+    /// Index of the selected conflict region
+    pub selected: usize,
+}
+
+impl ConflictEditor {
+    pub fn conflict_count(&self) -> usize {
+        self.file.conflicts().count()
+    }
+}
+
+/// URL and destination of a repository to clone
+#[derive(Debug, Default)]
+pub struct CloneDialog {
+    pub url: String,
+    /// Empty for a directory named after the repository, as `git clone` does
+    pub destination: String,
+    /// Whether typing goes to the destination rather than the URL
+    pub on_destination: bool,
+}
+
+impl CloneDialog {
+    pub fn field_mut(&mut self) -> &mut String {
+        if self.on_destination {
+            &mut self.destination
+        } else {
+            &mut self.url
+        }
+    }
+
+    /// The directory `git clone` would pick: the URL's last path component without `.git`
+    pub fn default_destination(&self) -> String {
+        let url = self.url.trim().trim_end_matches('/');
+        let name = url.rsplit(['/', ':']).next().unwrap_or(url);
+        name.strip_suffix(".git").unwrap_or(name).to_string()
+    }
+}
+
+/// A local branch in the stale report: merged into HEAD, or not committed to for a while
+#[derive(Debug, Clone)]
+pub struct StaleBranch {
+    pub name: String,
+    /// Days since the tip was committed
+    pub age_days: i64,
+    /// Commits HEAD doesn't contain; 0 for merged branches
+    pub unmerged: usize,
+    pub tip_subject: String,
+}
+
+/// Stale branch report opened from the Branches view, oldest first
+#[derive(Debug, Default)]
+pub struct StaleBranchReport {
+    pub branches: Vec<StaleBranch>,
+    pub selected: usize,
+}
+
+/// A working tree of the repository, as `git worktree list` shows it
+#[derive(Debug, Clone)]
+pub struct WorktreeInfo {
+    /// Name under `.git/worktrees`; `None` for the main working tree
+    pub name: Option<String>,
+    pub path: PathBuf,
+    /// Checked-out branch; `None` when HEAD is detached or can't be read
+    pub branch: Option<String>,
+    /// Set when locked, with the reason if one was given
+    pub locked: Option<String>,
+    /// The directory is gone, so `git worktree prune` would drop the entry
+    pub prunable: bool,
+    /// The working tree gtek is running in
+    pub is_current: bool,
+}
+
+/// Worktree list opened from the Branches view
+#[derive(Debug, Default)]
+pub struct WorktreeList {
+    pub worktrees: Vec<WorktreeInfo>,
+    pub selected: usize,
+}
+
+/// A submodule of the repository and how its checkout compares to what's recorded
+#[derive(Debug, Clone)]
+pub struct SubmoduleInfo {
+    pub name: String,
+    pub path: String,
+    /// URL from `.gitmodules`
+    pub url: Option<String>,
+    /// Registered in `.git/config`, as `git submodule init` does
+    pub initialized: bool,
+    /// The submodule's repository has been cloned into its path
+    pub checked_out: bool,
+    /// Its HEAD isn't the commit the superproject records
+    pub out_of_date: bool,
+    /// Uncommitted or untracked changes inside it
+    pub dirty: bool,
+    /// `.git/config` has a different URL than `.gitmodules`, so a sync would change it
+    pub url_changed: bool,
+}
+
+/// Submodule list opened from the Files view
+#[derive(Debug, Default)]
+pub struct SubmoduleList {
+    pub submodules: Vec<SubmoduleInfo>,
+    pub selected: usize,
+}
+
+/// Interactive rebase plan: commits after `base`, oldest first, reorderable before running
+#[derive(Debug, Default)]
+pub struct RebasePlan {
+    pub base: String,
+    pub entries: Vec<RebaseEntry>,
+    /// Commit ids in their original order, to tell which entries were moved past each other
+    pub original_order: Vec<String>,
+    pub selected: usize,
+}
+
+impl RebasePlan {
+    pub fn new(base: String, entries: Vec<RebaseEntry>) -> Self {
+        let original_order = entries.iter().map(|entry| entry.id.clone()).collect();
+        Self {
+            base,
+            entries,
+            original_order,
+            selected: 0,
+        }
+    }
+
+    /// Swap the selected entry with its neighbour above (`up`) or below, keeping it selected
+    pub fn move_selected(&mut self, up: bool) {
+        let from = self.selected;
+        let to = if up {
+            from.checked_sub(1)
+        } else {
+            Some(from + 1).filter(|to| *to < self.entries.len())
+        };
+        if let Some(to) = to {
+            self.entries.swap(from, to);
+            self.selected = to;
+        }
+    }
+
+    /// For each entry, the commits it now crosses compared with the original order that
+    /// touch some of the same files, with those files: the likely sources of conflicts
+    pub fn conflict_hints(&self) -> Vec<Vec<(String, Vec<String>)>> {
+        let original_position = |id: &str| self.original_order.iter().position(|o| o == id);
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                self.entries
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, other)| {
+                        // Relative order flipped between the two commits
+                        let now_before = *j < i;
+                        let was_before =
+                            original_position(&other.id) < original_position(&entry.id);
+                        other.id != entry.id && now_before != was_before
+                    })
+                    .filter_map(|(_, other)| {
+                        let shared: Vec<String> = entry
+                            .files
+                            .iter()
+                            .filter(|file| other.files.contains(file))
+                            .cloned()
+                            .collect();
+                        (!shared.is_empty()).then(|| (other.id.clone(), shared))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn is_reordered(&self) -> bool {
+        self.entries
+            .iter()
+            .map(|entry| &entry.id)
+            .ne(self.original_order.iter())
+    }
 }
 
 #[derive(Debug)]
 pub struct BranchCreation {
-    pub new_branch_name: String,
     pub base_branch_selected: usize,
-    pub selecting_base: bool,
+    /// Push the new branch to origin right away instead of keeping it local
+    pub push: bool,
+}
+
+/// Text field for entering a revision (branch, tag or commit hash) with
+/// completion suggestions drawn from the repository's refs and recent commits.
+#[derive(Debug, Default)]
+pub struct RevisionInput {
+    pub text: String,
+    pub candidates: Vec<String>,
+    pub suggestions: Vec<String>,
+    pub selected_suggestion: usize,
+}
+
+impl RevisionInput {
+    pub fn reset(&mut self, candidates: Vec<String>) {
+        self.text.clear();
+        self.candidates = candidates;
+        self.update_suggestions();
+    }
+
+    /// Start from `text`, e.g. the revision selected in a view
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.update_suggestions();
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.text.push(c);
+        self.update_suggestions();
+    }
+
+    pub fn pop(&mut self) {
+        self.text.pop();
+        self.update_suggestions();
+    }
+
+    pub fn next_suggestion(&mut self) {
+        if !self.suggestions.is_empty() {
+            self.selected_suggestion = (self.selected_suggestion + 1) % self.suggestions.len();
+        }
+    }
+
+    pub fn previous_suggestion(&mut self) {
+        if !self.suggestions.is_empty() {
+            self.selected_suggestion = self
+                .selected_suggestion
+                .checked_sub(1)
+                .unwrap_or(self.suggestions.len() - 1);
+        }
+    }
+
+    /// Replace the typed text with the highlighted suggestion
+    pub fn complete(&mut self) {
+        if let Some(suggestion) = self.suggestions.get(self.selected_suggestion) {
+            self.text = suggestion.clone();
+            self.update_suggestions();
+        }
+    }
+
+    /// The revision to act on: the typed text, or the highlighted suggestion if nothing was typed
+    pub fn value(&self) -> Option<String> {
+        let text = self.text.trim();
+        if !text.is_empty() {
+            Some(text.to_string())
+        } else {
+            self.suggestions.get(self.selected_suggestion).cloned()
+        }
+    }
+
+    fn update_suggestions(&mut self) {
+        let needle = self.text.trim().to_lowercase();
+
+        // Prefix matches first, then anything containing the typed text
+        let mut prefix: Vec<String> = Vec::new();
+        let mut contains: Vec<String> = Vec::new();
+        for candidate in &self.candidates {
+            let lower = candidate.to_lowercase();
+            if lower.starts_with(&needle) {
+                prefix.push(candidate.clone());
+            } else if lower.contains(&needle) {
+                contains.push(candidate.clone());
+            }
+        }
+        prefix.extend(contains);
+
+        self.suggestions = prefix;
+        self.selected_suggestion = 0;
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct BranchInfo {
     pub name: String,
     pub is_current: bool,
+    /// Remote the branch belongs to, for remote-tracking branches (`name` is then `origin/...`)
+    pub remote: Option<String>,
+    /// Configured upstream (`branch.<name>.remote`/`merge`) of a local branch, e.g. `origin/main`
+    pub upstream: Option<String>,
+    /// Commits ahead of and behind the upstream, for local branches that track one
+    pub ahead_behind: Option<(usize, usize)>,
+    /// Committer time of the tip commit, in seconds since the epoch
+    pub tip_time: i64,
+    /// Subject line of the tip commit
+    pub tip_subject: String,
 }
 
 impl App {
-    pub fn new(repo_path: PathBuf) -> Self {
+    pub fn new(repo_path: PathBuf, config: AppConfig) -> Self {
+        let (keymap, mut startup_warnings) = Keymap::from_config(&config.keybindings);
+        let (theme, theme_warnings) = Theme::from_config(config.theme.as_deref(), &config.colors);
+        startup_warnings.extend(theme_warnings);
+
+        let mut panels = PanelStack::default();
+        if !startup_warnings.is_empty() {
+            panels.push(Panel::StartupWarnings);
+        }
+
         Self {
-            current_view: View::Files,
+            current_view: config.default_view.unwrap_or(View::Dashboard),
             repo_path,
             should_quit: false,
-            show_branch_dialog: false,
-            show_delete_confirm: false,
-            delete_confirmation: String::new(),
-            branch_to_delete: None,
-            show_discard_confirm: false,
-            discard_confirmation: String::new(),
-            file_to_discard: None,
-            discard_all: false,
+            dialogs: DialogStack::default(),
+            panels,
+            compare_base: config.compare_base.clone(),
+            base_divergence: None,
+            in_progress: None,
+            stash_count: 0,
+            detached_at: None,
+            export_path: String::new(),
+            apply_path: String::new(),
+            apply_to_index: false,
+            diff_export_path: String::new(),
+            diff_export_all: false,
+            patch_dir: String::new(),
+            transfer: None,
+            ignore_whitespace: false,
+            command_log: Vec::new(),
+            command_log_scroll: 0,
+            show_init_prompt: false,
+            open_repo: None,
+            ssh_keys: config.ssh_keys(),
+            shell_git: config.shell_git,
+            protected_branches: config.protected_branches.clone(),
+            branch_prefix: config.branch_prefix.clone(),
+            commit_template: config.commit_template.clone(),
+            ff_only: config.ff_only,
+            push_new_branches: config.push_new_branches.unwrap_or(true),
+            difftool: config.difftool.clone(),
+            stale_branch_days: config.stale_branch_days.unwrap_or(90),
+            history_page_size: config.history_page_size.unwrap_or(HISTORY_PAGE_SIZE).max(1),
+            confirm: config.confirm,
+            files_split: config
+                .files_split
+                .unwrap_or(40)
+                .clamp(SPLIT_RANGE.0, SPLIT_RANGE.1),
+            history_split: config
+                .history_split
+                .unwrap_or(50)
+                .clamp(SPLIT_RANGE.0, SPLIT_RANGE.1),
+            dragging_split: false,
+            keymap,
+            theme,
+            startup_warnings,
+            accessible: config.accessible,
+            tags_state: TagsState {
+                selected: 0,
+                tags: Vec::new(),
+            },
             branch_creation: BranchCreation {
-                new_branch_name: String::new(),
                 base_branch_selected: 0,
-                selecting_base: false,
+                push: true,
             },
             history_state: HistoryState {
                 selected: 0,
                 commits: Vec::new(),
+                has_more: false,
+                cursor: HistoryCursor::default(),
+                graph: graph::Layout::default(),
+                range_anchor: None,
+                filter: HistoryFilter {
+                    all_branches: true,
+                    first_parent: false,
+                },
+                detail: None,
+                commit_diff: None,
+                detail_scroll: 0,
             },
             files_state: FilesState {
                 selected: 0,
                 files: Vec::new(),
                 current_diff: None,
                 diff_scroll: 0,
+                changed_lines: None,
+                rejected_hunks: None,
+                full_diff_path: None,
             },
             branches_state: BranchesState {
                 selected: 0,
                 branches: Vec::new(),
                 current_branch: String::new(),
+                sort_by_recency: false,
             },
             status_message: None,
-            show_commit_dialog: false,
             commit_message: String::new(),
         }
     }
 
+    /// A dialog or one of the panels drawn over the view is open and takes the keys
+    pub fn is_overlay_open(&self) -> bool {
+        !self.dialogs.is_empty() || !self.panels.is_empty()
+    }
+
+    /// The Branches filter being typed, if any
+    pub fn branch_filter(&self) -> Option<&str> {
+        self.panels.iter().find_map(|panel| match panel {
+            Panel::BranchFilter(filter) => Some(filter.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The marked History range as (older, newer) commit ids, if it spans more than one row
+    pub fn history_range(&self) -> Option<(String, String)> {
+        let anchor = self.history_state.range_anchor.as_ref()?;
+        let anchor_pos = self
+            .history_state
+            .commits
+            .iter()
+            .position(|commit| &commit.id == anchor)?;
+        let selected = self.history_state.selected;
+        if anchor_pos == selected {
+            return None;
+        }
+        let commits = &self.history_state.commits;
+        // The list is newest first, so the higher index is the older end
+        let (older, newer) = if anchor_pos > selected {
+            (anchor_pos, selected)
+        } else {
+            (selected, anchor_pos)
+        };
+        Some((commits[older].id.clone(), commits[newer].id.clone()))
+    }
+
+    pub fn is_protected_branch(&self, name: &str) -> bool {
+        self.protected_branches.iter().any(|branch| branch == name)
+    }
+
     pub fn switch_view(&mut self, view: View) {
         self.current_view = view;
     }
 
     pub fn next_item(&mut self) {
         match self.current_view {
+            View::Dashboard => {}
             View::History => {
                 if !self.history_state.commits.is_empty() {
                     self.history_state.selected =
@@ -152,11 +990,18 @@ impl App {
                         .min(self.branches_state.branches.len() - 1);
                 }
             }
+            View::Tags => {
+                if !self.tags_state.tags.is_empty() {
+                    self.tags_state.selected =
+                        (self.tags_state.selected + 1).min(self.tags_state.tags.len() - 1);
+                }
+            }
         }
     }
 
     pub fn previous_item(&mut self) {
         match self.current_view {
+            View::Dashboard => {}
             View::History => {
                 if self.history_state.selected > 0 {
                     self.history_state.selected -= 1;
@@ -172,6 +1017,78 @@ impl App {
                     self.branches_state.selected -= 1;
                 }
             }
+            View::Tags => {
+                if self.tags_state.selected > 0 {
+                    self.tags_state.selected -= 1;
+                }
+            }
+        }
+    }
+
+    /// One-line description of the selected item, announced in screen-reader mode
+    pub fn selection_summary(&self) -> String {
+        match self.current_view {
+            View::Dashboard => {
+                tr!("Home: f Files, h History, b Branches, t Tags, g run gc").to_string()
+            }
+            View::Files => match self.files_state.files.get(self.files_state.selected) {
+                Some(file) => tr!(
+                    "File {} of {}: {} {}",
+                    self.files_state.selected + 1,
+                    self.files_state.files.len(),
+                    file.status.trim(),
+                    file.path
+                ),
+                None => tr!("No changed files").to_string(),
+            },
+            View::History => match self.history_state.commits.get(self.history_state.selected) {
+                Some(commit) => tr!(
+                    "Commit {} of {}: {} by {} on {}: {}",
+                    self.history_state.selected + 1,
+                    self.history_state.commits.len(),
+                    commit.id,
+                    commit.author,
+                    commit.date,
+                    commit.message
+                ),
+                None => tr!("No commits").to_string(),
+            },
+            View::Branches => match self
+                .branches_state
+                .branches
+                .get(self.branches_state.selected)
+            {
+                Some(branch) => tr!(
+                    "Branch {} of {}: {}{}{}",
+                    self.branches_state.selected + 1,
+                    self.branches_state.branches.len(),
+                    branch.name,
+                    if branch.is_current {
+                        tr!(" (current)")
+                    } else if branch.remote.is_some() {
+                        tr!(" (remote)")
+                    } else {
+                        ""
+                    },
+                    match branch.ahead_behind {
+                        Some((ahead, behind)) if ahead + behind > 0 => {
+                            tr!(", {} ahead and {} behind upstream", ahead, behind)
+                        }
+                        _ => String::new(),
+                    }
+                ),
+                None => tr!("No branches").to_string(),
+            },
+            View::Tags => match self.tags_state.tags.get(self.tags_state.selected) {
+                Some(tag) => tr!(
+                    "Tag {} of {}: {} at {}",
+                    self.tags_state.selected + 1,
+                    self.tags_state.tags.len(),
+                    tag.name,
+                    tag.target
+                ),
+                None => tr!("No tags").to_string(),
+            },
         }
     }
 
@@ -179,17 +1096,113 @@ impl App {
         self.status_message = Some(message);
     }
 
+    /// Append a line to the command log, dropping the oldest lines past its limit
+    pub fn log_command_output(&mut self, line: String) {
+        const MAX_LOG_LINES: usize = 1000;
+        self.command_log.push(line);
+        if self.command_log.len() > MAX_LOG_LINES {
+            let excess = self.command_log.len() - MAX_LOG_LINES;
+            self.command_log.drain(..excess);
+        }
+    }
+
     pub fn scroll_diff_up(&mut self) {
         if self.files_state.diff_scroll > 0 {
             self.files_state.diff_scroll -= 1;
         }
     }
 
+    /// Scroll the diff one line, stopping with its last line at the top
     pub fn scroll_diff_down(&mut self) {
-        self.files_state.diff_scroll += 1;
+        let line_count = self
+            .files_state
+            .displayed_diff()
+            .map_or(0, |diff| diff.lines().count());
+        if self.files_state.diff_scroll + 1 < line_count {
+            self.files_state.diff_scroll += 1;
+        }
     }
 
     pub fn reset_diff_scroll(&mut self) {
         self.files_state.diff_scroll = 0;
     }
+
+    pub fn scroll_detail_up(&mut self) {
+        if self.history_state.detail_scroll > 0 {
+            self.history_state.detail_scroll -= 1;
+        }
+    }
+
+    /// Scroll the commit detail or diff one line, stopping with its last line at the top
+    pub fn scroll_detail_down(&mut self) {
+        let line_count = match (&self.history_state.commit_diff, &self.history_state.detail) {
+            (Some(diff), _) => diff.lines().count(),
+            (None, Some(detail)) => detail.line_count(),
+            (None, None) => 0,
+        };
+        if self.history_state.detail_scroll + 1 < line_count {
+            self.history_state.detail_scroll += 1;
+        }
+    }
+
+    /// Scroll the diff the current view shows so the next (or previous) hunk header is at the
+    /// top. Returns false when there is no hunk in that direction.
+    pub fn jump_to_hunk(&mut self, forward: bool) -> bool {
+        let (diff, scroll) = match self.current_view {
+            View::Files => (
+                self.files_state
+                    .rejected_hunks
+                    .as_deref()
+                    .or(self.files_state.current_diff.as_deref()),
+                &mut self.files_state.diff_scroll,
+            ),
+            View::History => (
+                self.history_state.commit_diff.as_deref(),
+                &mut self.history_state.detail_scroll,
+            ),
+            _ => return false,
+        };
+        let starts = hunk_starts(diff.unwrap_or_default());
+        let target = if forward {
+            starts.into_iter().find(|&line| line > *scroll)
+        } else {
+            starts.into_iter().rev().find(|&line| line < *scroll)
+        };
+        match target {
+            Some(line) => {
+                *scroll = line;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// The hunk whose `@@` header is line `header` of the patch text, up to the next hunk or file
+pub fn hunk_text(diff_text: &str, header: usize) -> String {
+    diff_text
+        .lines()
+        .skip(header)
+        .enumerate()
+        .take_while(|(i, line)| *i == 0 || !(line.starts_with("@@") || line.starts_with("diff ")))
+        .map(|(_, line)| format!("{}\n", line))
+        .collect()
+}
+
+/// Line indices of the `@@` hunk headers in patch text
+pub fn hunk_starts(diff_text: &str) -> Vec<usize> {
+    diff_text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.starts_with("@@"))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// The hunk header a diff scrolled to `scroll` is in: the last one at or above the top line
+pub fn current_hunk(diff_text: &str, scroll: usize) -> Option<usize> {
+    hunk_starts(diff_text)
+        .into_iter()
+        .take_while(|&line| line <= scroll)
+        .last()
 }
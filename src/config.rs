@@ -0,0 +1,211 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::app::View;
+use crate::git::SshKeys;
+use crate::i18n::tr;
+
+/// User configuration read from `~/.config/gtek/config.toml`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Screen-reader friendly output: no decorative glyphs, announced selection changes
+    pub accessible: bool,
+    /// UI language, e.g. `"de"`; defaults to the `LANG` environment
+    pub locale: Option<String>,
+    /// Key overrides per context, e.g. `[keybindings.files]` with `stage = "x"`
+    pub keybindings: BTreeMap<String, BTreeMap<String, String>>,
+    /// Revision the header compares HEAD against, e.g. `"origin/main"`
+    pub compare_base: Option<String>,
+    /// Branches that can't be deleted and warn before committing to them
+    pub protected_branches: Vec<String>,
+    /// Prefilled at the start of new branch names, e.g. `"feature/"`
+    pub branch_prefix: Option<String>,
+    /// Prefilled commit message
+    pub commit_template: Option<String>,
+    /// Merge and pull only fast-forward, failing instead of creating merges
+    pub ff_only: bool,
+    /// Whether the new branch dialog starts with "push to origin" checked (default true)
+    pub push_new_branches: Option<bool>,
+    /// Color theme: `dark` (the default) or `light`
+    pub theme: Option<String>,
+    /// Colors overriding the theme's, e.g. `[colors]` with `accent = "#ff8800"`
+    pub colors: BTreeMap<String, String>,
+    /// SSH identity file tried before the agent, e.g. `"~/.ssh/id_ed25519_work"`
+    pub ssh_key: Option<String>,
+    /// Identity files for particular remotes, e.g. `[ssh_keys]` with `upstream = "~/.ssh/id_oss"`
+    pub ssh_keys: BTreeMap<String, String>,
+    /// Run fetch, pull and push through the system `git`, for credentials only it can reach
+    pub shell_git: bool,
+    /// Command `T` runs on the old and new version of a file, e.g. `"meld"`; without it
+    /// `git difftool` uses its own `diff.tool` setting
+    pub difftool: Option<String>,
+    /// Days without a commit after which the stale branch report lists a branch (default 90)
+    pub stale_branch_days: Option<i64>,
+    /// View shown on startup: `home` (the default), `files`, `history`, `branches` or `tags`
+    pub default_view: Option<View>,
+    /// Commits History loads at a time (default 100)
+    pub history_page_size: Option<usize>,
+    /// Unchanged lines shown around each change in diffs (default 3)
+    pub diff_context: Option<u32>,
+    /// Which deletions and discards ask for confirmation
+    pub confirm: ConfirmLevel,
+    /// Percent of the width the Files list takes next to the diff (default 40); `<`/`>` adjust it
+    pub files_split: Option<u16>,
+    /// Percent of the width the History list takes next to an open commit (default 50)
+    pub history_split: Option<u16>,
+}
+
+/// How much asks for confirmation. Deleting a branch with commits nothing else has always
+/// takes its name typed, and deleting a branch on a remote always asks, whatever the level.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmLevel {
+    /// Every branch deletion and discard asks first
+    #[default]
+    All,
+    /// Only what loses work asks: deleting a merged branch doesn't
+    Destructive,
+    /// Discarding changes doesn't ask either
+    Minimal,
+}
+
+/// Team settings from `.gtek.toml` in the repository root; anything set here wins over
+/// the user config
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoConfig {
+    pub protected_branches: Option<Vec<String>>,
+    pub branch_prefix: Option<String>,
+    pub commit_template: Option<String>,
+    pub theme: Option<String>,
+    pub history_page_size: Option<usize>,
+    pub diff_context: Option<u32>,
+}
+
+impl RepoConfig {
+    /// Load `<workdir>/.gtek.toml`; a missing file yields no overrides
+    pub fn load(workdir: &Path) -> Result<Self> {
+        let path = workdir.join(".gtek.toml");
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(&path)?;
+        toml::from_str(&contents).map_err(|e| {
+            anyhow::anyhow!(tr!("Invalid repository config {}: {}", path.display(), e))
+        })
+    }
+
+    pub fn apply(self, config: &mut AppConfig) {
+        if let Some(branches) = self.protected_branches {
+            config.protected_branches = branches;
+        }
+        if self.branch_prefix.is_some() {
+            config.branch_prefix = self.branch_prefix;
+        }
+        if self.commit_template.is_some() {
+            config.commit_template = self.commit_template;
+        }
+        if self.theme.is_some() {
+            config.theme = self.theme;
+        }
+        if self.history_page_size.is_some() {
+            config.history_page_size = self.history_page_size;
+        }
+        if self.diff_context.is_some() {
+            config.diff_context = self.diff_context;
+        }
+    }
+}
+
+impl AppConfig {
+    /// Directory holding gtek's configuration (`$XDG_CONFIG_HOME/gtek` or `~/.config/gtek`)
+    pub fn config_dir() -> Option<PathBuf> {
+        if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+            return Some(PathBuf::from(dir).join("gtek"));
+        }
+        let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+        Some(PathBuf::from(home).join(".config").join("gtek"))
+    }
+
+    /// The configured SSH identity files, with a leading `~` expanded to the home directory
+    pub fn ssh_keys(&self) -> SshKeys {
+        SshKeys {
+            default: self.ssh_key.as_deref().map(expand_home),
+            per_remote: self
+                .ssh_keys
+                .iter()
+                .map(|(remote, path)| (remote.clone(), expand_home(path)))
+                .collect(),
+        }
+    }
+
+    /// Set a top-level `key = value` in the user config file, creating it if needed; everything
+    /// else in the file, comments included, stays as written
+    pub fn save_setting(key: &str, value: &str) -> Result<()> {
+        let dir = Self::config_dir().ok_or_else(|| anyhow::anyhow!(tr!("No home directory")))?;
+        let path = dir.join("config.toml");
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let setting = format!("{} = {}", key, value);
+        let mut lines: Vec<String> = contents.lines().map(String::from).collect();
+        // Top-level keys come before the first table header
+        let tables = lines
+            .iter()
+            .position(|line| line.trim_start().starts_with('['))
+            .unwrap_or(lines.len());
+        let existing = lines[..tables].iter().position(|line| {
+            line.split_once('=')
+                .is_some_and(|(name, _)| name.trim() == key)
+        });
+        match existing {
+            Some(i) => lines[i] = setting,
+            None => {
+                // Above the comments and blank lines leading into the first table
+                let mut at = tables;
+                while at > 0 && {
+                    let line = lines[at - 1].trim();
+                    line.is_empty() || line.starts_with('#')
+                } {
+                    at -= 1;
+                }
+                lines.insert(at, setting);
+            }
+        }
+
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(&path, lines.join("\n") + "\n")?;
+        Ok(())
+    }
+
+    /// Load the user config file; a missing file yields the defaults
+    pub fn load() -> Result<Self> {
+        let path = match Self::config_dir() {
+            Some(dir) => dir.join("config.toml"),
+            None => return Ok(Self::default()),
+        };
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(&path)?;
+        toml::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!(tr!("Invalid config {}: {}", path.display(), e)))
+    }
+}
+
+/// `~/rest` as a path under the home directory; other paths are taken as they are
+pub fn expand_home(path: &str) -> PathBuf {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    match (path.strip_prefix("~/"), home) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
@@ -0,0 +1,219 @@
+/// Which side of a conflict region goes into the resolved file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choice {
+    Ours,
+    Theirs,
+    /// Ours followed by theirs
+    Both,
+}
+
+/// A stretch of a conflicted file: lines both sides agree on, or a conflict region
+#[derive(Debug, Clone)]
+pub enum Segment {
+    Common(Vec<String>),
+    Conflict(Conflict),
+}
+
+/// One `<<<<<<<` ... `>>>>>>>` block. Lines keep their line endings; a diff3 base section
+/// (`|||||||`) is dropped since neither choice keeps it.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub ours_label: String,
+    pub ours: Vec<String>,
+    pub theirs_label: String,
+    pub theirs: Vec<String>,
+    pub choice: Option<Choice>,
+}
+
+impl Conflict {
+    /// The lines the region resolves to; `None` until a side is chosen
+    pub fn resolved(&self) -> Option<Vec<&str>> {
+        let ours = self.ours.iter().map(String::as_str);
+        let theirs = self.theirs.iter().map(String::as_str);
+        match self.choice? {
+            Choice::Ours => Some(ours.collect()),
+            Choice::Theirs => Some(theirs.collect()),
+            Choice::Both => Some(ours.chain(theirs).collect()),
+        }
+    }
+}
+
+/// A conflicted file split into common stretches and conflict regions
+#[derive(Debug, Clone)]
+pub struct ConflictFile {
+    pub segments: Vec<Segment>,
+}
+
+impl ConflictFile {
+    /// Split `text` at its conflict markers; `None` if a block isn't closed
+    pub fn parse(text: &str) -> Option<Self> {
+        enum State {
+            Common,
+            Ours,
+            Base,
+            Theirs,
+        }
+
+        let mut segments = Vec::new();
+        let mut common = Vec::new();
+        let mut conflict: Option<Conflict> = None;
+        let mut state = State::Common;
+
+        for line in text.split_inclusive('\n') {
+            match state {
+                State::Common => {
+                    if let Some(label) = marker(line, '<') {
+                        if !common.is_empty() {
+                            segments.push(Segment::Common(std::mem::take(&mut common)));
+                        }
+                        conflict = Some(Conflict {
+                            ours_label: label,
+                            ours: Vec::new(),
+                            theirs_label: String::new(),
+                            theirs: Vec::new(),
+                            choice: None,
+                        });
+                        state = State::Ours;
+                    } else {
+                        common.push(line.to_string());
+                    }
+                }
+                State::Ours | State::Base => {
+                    if marker(line, '=').is_some() {
+                        state = State::Theirs;
+                    } else if marker(line, '|').is_some() {
+                        state = State::Base;
+                    } else if let (State::Ours, Some(conflict)) = (&state, conflict.as_mut()) {
+                        conflict.ours.push(line.to_string());
+                    }
+                }
+                State::Theirs => {
+                    let current = conflict.as_mut()?;
+                    if let Some(label) = marker(line, '>') {
+                        current.theirs_label = label;
+                        segments.push(Segment::Conflict(conflict.take()?));
+                        state = State::Common;
+                    } else {
+                        current.theirs.push(line.to_string());
+                    }
+                }
+            }
+        }
+
+        if conflict.is_some() {
+            return None;
+        }
+        if !common.is_empty() {
+            segments.push(Segment::Common(common));
+        }
+        Some(Self { segments })
+    }
+
+    pub fn conflicts(&self) -> impl Iterator<Item = &Conflict> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Conflict(conflict) => Some(conflict),
+            Segment::Common(_) => None,
+        })
+    }
+
+    pub fn conflict_mut(&mut self, index: usize) -> Option<&mut Conflict> {
+        self.segments
+            .iter_mut()
+            .filter_map(|segment| match segment {
+                Segment::Conflict(conflict) => Some(conflict),
+                Segment::Common(_) => None,
+            })
+            .nth(index)
+    }
+
+    /// The file with every conflict replaced by its chosen lines; `None` while any is open
+    pub fn resolve(&self) -> Option<String> {
+        let mut text = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Common(lines) => text.extend(lines.iter().map(String::as_str)),
+                Segment::Conflict(conflict) => text.extend(conflict.resolved()?),
+            }
+        }
+        Some(text)
+    }
+}
+
+/// The label after a conflict marker made of seven `ch`, e.g. `HEAD` for `<<<<<<< HEAD`
+fn marker(line: &str, ch: char) -> Option<String> {
+    let rest = line.strip_prefix(&ch.to_string().repeat(7))?;
+    let rest = rest.trim_end_matches(['\r', '\n']);
+    if rest.is_empty() || rest.starts_with(' ') {
+        Some(rest.trim().to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse `text` and resolve every conflict with `choice`
+    fn resolve_all(text: &str, choice: Choice) -> String {
+        let mut file = ConflictFile::parse(text).unwrap();
+        let count = file.conflicts().count();
+        for index in 0..count {
+            file.conflict_mut(index).unwrap().choice = Some(choice);
+        }
+        file.resolve().unwrap()
+    }
+
+    #[test]
+    fn parses_labels_and_sides() {
+        let text = "top\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> feature\nbottom\n";
+        let file = ConflictFile::parse(text).unwrap();
+        let conflict = file.conflicts().next().unwrap();
+        assert_eq!(conflict.ours_label, "HEAD");
+        assert_eq!(conflict.theirs_label, "feature");
+        assert_eq!(conflict.ours, ["ours\n"]);
+        assert_eq!(conflict.theirs, ["theirs\n"]);
+        assert_eq!(resolve_all(text, Choice::Theirs), "top\ntheirs\nbottom\n");
+    }
+
+    #[test]
+    fn diff3_base_section_is_dropped() {
+        let text = "<<<<<<< HEAD\nours\n||||||| base\nbase\n=======\ntheirs\n>>>>>>> feature\n";
+        let file = ConflictFile::parse(text).unwrap();
+        let conflict = file.conflicts().next().unwrap();
+        assert_eq!(conflict.ours, ["ours\n"]);
+        assert_eq!(conflict.theirs, ["theirs\n"]);
+        assert_eq!(resolve_all(text, Choice::Ours), "ours\n");
+    }
+
+    #[test]
+    fn unclosed_block_is_rejected() {
+        assert!(ConflictFile::parse("<<<<<<< HEAD\nours\n=======\ntheirs\n").is_none());
+        assert!(ConflictFile::parse("<<<<<<< HEAD\nours\n").is_none());
+    }
+
+    #[test]
+    fn crlf_line_endings_are_kept() {
+        let text = "a\r\n<<<<<<< HEAD\r\nours\r\n=======\r\ntheirs\r\n>>>>>>> feature\r\nb\r\n";
+        let file = ConflictFile::parse(text).unwrap();
+        assert_eq!(file.conflicts().next().unwrap().theirs_label, "feature");
+        assert_eq!(resolve_all(text, Choice::Ours), "a\r\nours\r\nb\r\n");
+    }
+
+    #[test]
+    fn both_puts_ours_before_theirs() {
+        let text = "<<<<<<< HEAD\none\ntwo\n=======\nthree\n>>>>>>> feature\n";
+        assert_eq!(resolve_all(text, Choice::Both), "one\ntwo\nthree\n");
+    }
+
+    #[test]
+    fn resolve_waits_for_every_choice() {
+        let text =
+            "<<<<<<< HEAD\na\n=======\nb\n>>>>>>> x\n<<<<<<< HEAD\nc\n=======\nd\n>>>>>>> x\n";
+        let mut file = ConflictFile::parse(text).unwrap();
+        file.conflict_mut(0).unwrap().choice = Some(Choice::Ours);
+        assert!(file.resolve().is_none());
+        file.conflict_mut(1).unwrap().choice = Some(Choice::Theirs);
+        assert_eq!(file.resolve().unwrap(), "a\nd\n");
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::Result;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::app::{HistoryFilter, DIFF_LINE_LIMIT};
+use crate::git::{GitBackend, GitRepo};
+use crate::i18n::tr;
+
+/// Time a single operation, returning its result alongside the elapsed time
+fn timed<T>(operation: impl FnOnce() -> Result<T>) -> (Result<T>, Duration) {
+    let start = Instant::now();
+    let result = operation();
+    (result, start.elapsed())
+}
+
+fn report_line(label: &str, elapsed: Duration, detail: &str) {
+    println!(
+        "  {:<28} {:>10.2} ms  {}",
+        label,
+        elapsed.as_secs_f64() * 1000.0,
+        detail
+    );
+}
+
+/// Time the git operations the UI runs on refresh and print a report to stdout.
+/// Used by `gtek --diagnose` to investigate slow repositories.
+pub fn run(repo_path: &Path) -> Result<()> {
+    println!("{}", tr!("gtek diagnostics for {}", repo_path.display()));
+    println!();
+
+    let (git_repo, elapsed) = timed(|| GitRepo::open(repo_path));
+    let git_repo = git_repo?;
+    report_line(tr!("open repository"), elapsed, "");
+
+    let (branch, elapsed) = timed(|| git_repo.get_current_branch());
+    report_line(
+        tr!("current branch"),
+        elapsed,
+        &branch.unwrap_or_else(|e| e.to_string()),
+    );
+
+    let (branches, elapsed) = timed(|| git_repo.get_branches());
+    report_line(
+        tr!("list branches"),
+        elapsed,
+        &match branches {
+            Ok(branches) => tr!("{} branches", branches.len()),
+            Err(e) => e.to_string(),
+        },
+    );
+
+    let (files, elapsed) = timed(|| git_repo.get_status());
+    let files = files.unwrap_or_default();
+    report_line(
+        tr!("status scan"),
+        elapsed,
+        &tr!("{} changed files", files.len()),
+    );
+
+    // Diff every changed file the way the Files view does on selection
+    let mut total_diff = Duration::ZERO;
+    let mut slowest: Option<(Duration, String)> = None;
+    let mut diff_bytes = 0;
+    for file in &files {
+        let (diff, elapsed) = timed(|| {
+            git_repo.get_diff_for_file(&file.path, file.staged, false, Some(DIFF_LINE_LIMIT))
+        });
+        diff_bytes += diff.map(|d| d.len()).unwrap_or(0);
+        total_diff += elapsed;
+        if slowest.as_ref().is_none_or(|(max, _)| elapsed > *max) {
+            slowest = Some((elapsed, file.path.clone()));
+        }
+    }
+    report_line(
+        tr!("diff all changed files"),
+        total_diff,
+        &tr!("{} bytes of patch text", diff_bytes),
+    );
+    if let Some((elapsed, path)) = slowest {
+        report_line(tr!("slowest file diff"), elapsed, &path);
+    }
+
+    let (commits, elapsed) = timed(|| {
+        git_repo.get_commits(
+            100,
+            HistoryFilter {
+                all_branches: true,
+                first_parent: false,
+            },
+        )
+    });
+    report_line(
+        tr!("history (100 commits, all branches)"),
+        elapsed,
+        &match commits {
+            Ok(commits) => tr!("{} commits with graph", commits.len()),
+            Err(e) => e.to_string(),
+        },
+    );
+
+    let (count, elapsed) = timed(|| git_repo.count_commits());
+    report_line(
+        tr!("full revwalk"),
+        elapsed,
+        &match count {
+            Ok(count) => tr!("{} commits reachable from HEAD", count),
+            Err(e) => e.to_string(),
+        },
+    );
+
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use crate::app::{BranchCheckout, RemoteAction, RevisionInput};
+
+/// A modal dialog on the `App::dialogs` stack. Only the topmost one gets keys; once answered it
+/// is popped and its action runs, which may push the next step.
+#[derive(Debug)]
+pub struct Dialog {
+    pub title: String,
+    /// Explanation shown above the answer
+    pub message: Option<String>,
+    /// Dimmed line below the answer, e.g. an option toggled with a key
+    pub hint: Option<String>,
+    pub kind: DialogKind,
+    pub action: DialogAction,
+    /// Drawn with the danger color, for answers that lose work
+    pub danger: bool,
+    /// An input may be submitted empty, e.g. to take a default
+    pub allow_blank: bool,
+}
+
+#[derive(Debug)]
+pub enum DialogKind {
+    /// `y` accepts, `n` or `Esc` declines
+    Confirm,
+    /// A line of text, submitted with `Enter` once it isn't blank
+    Input(String),
+    /// A revision, completed with `Tab` from suggestions picked with `↑`/`↓`
+    Revision(RevisionInput),
+    /// One of several options, picked with `j`/`k` and `Enter`, or at once with its key
+    Choice {
+        options: Vec<String>,
+        selected: usize,
+        /// Key picking each option, if they have keys
+        keys: Vec<char>,
+    },
+    /// Options ticked with `Space` (`a` ticks or clears them all), submitted with `Enter`
+    Checklist {
+        options: Vec<(String, bool)>,
+        selected: usize,
+    },
+}
+
+/// What answering a dialog does; input.rs runs it
+#[derive(Debug, Clone, PartialEq)]
+pub enum DialogAction {
+    /// Commit the staged changes with the entered message
+    Commit,
+    /// Create a branch with the entered name from `BranchCreation::base_branch_selected`
+    CreateBranch,
+    /// Pick the base of the branch being created
+    ChooseBranchBase,
+    /// Delete a local branch, asking for its name next if it has unmerged commits
+    DeleteBranch(String),
+    /// Force-delete a local branch with unmerged commits once its name is typed
+    ForceDeleteBranch(String),
+    /// Delete a branch on its remote, named by its remote-tracking branch (`origin/feature`)
+    DeleteRemoteBranch(String),
+    /// Delete a stale branch along with its unmerged commits
+    DeleteStaleBranch(String),
+    /// Throw away the changes to `path`, or all of them, once `y`/`yes` is typed
+    Discard {
+        path: Option<String>,
+        /// Only the unstaged side of a file that has staged changes too
+        keep_staged: bool,
+    },
+    /// Create the entered branch at the detached HEAD and commit on it; blank commits detached
+    CommitOnDetached,
+    /// Apply the patch file entered, or the clipboard if none, to `App::apply_to_index`'s target
+    ApplyPatch,
+    /// Write the diff of the selected file, or of all of them with `App::diff_export_all`
+    ExportDiff,
+    AddToGitignore,
+    /// Write the marked range, or the selected commit, as patch files into the entered directory
+    FormatPatch,
+    ExportHistory,
+    /// Ask which revisions to bundle into the entered file
+    BundleFile {
+        range: String,
+    },
+    CreateBundle {
+        path: String,
+    },
+    PinBase,
+    SetUpstream(String),
+    JumpToRevision,
+    /// Revert a commit, committing the reversal or only staging it
+    Revert(String),
+    /// Merge a branch into the current one, fast-forward only or squashed
+    Merge(String),
+    /// Delete a tag locally, on the remote or both
+    DeleteTag(String),
+    /// Check a tag out detached, or ask for a branch to start from it
+    CheckoutTag(String),
+    BranchFromTag(String),
+    /// Ask for the message of a tag at the revision, once named
+    CreateTag {
+        target: String,
+    },
+    TagMessage {
+        target: String,
+        name: String,
+    },
+    /// Stash or discard the local changes in the way of a checkout
+    LocalChanges(BranchCheckout),
+    /// Run a network operation against the picked remote
+    PickRemote {
+        action: RemoteAction,
+        remotes: Vec<String>,
+    },
+    /// Copy the selected file's path, current hunk or whole diff
+    Copy,
+    /// Delete the ticked merged branches
+    CleanUpBranches(Vec<String>),
+    /// Ask which revision to check the entered path out from
+    CheckOutFile {
+        revision: Option<String>,
+    },
+    CheckOutFileFrom {
+        path: String,
+    },
+    /// Ask where to put a worktree for the entered branch
+    AddWorktree,
+    AddWorktreeAt {
+        branch: String,
+    },
+    RemoveWorktree(String),
+    /// Run `git gc` on the repository
+    RunMaintenance,
+}
+
+/// How a dialog was answered
+#[derive(Debug)]
+pub enum Answer {
+    Yes,
+    /// Declined or cancelled with `Esc`
+    No,
+    Text(String),
+    Choice(usize),
+    /// Whether each checklist option was ticked
+    Checked(Vec<bool>),
+}
+
+impl Dialog {
+    pub fn confirm(title: String, message: String, action: DialogAction) -> Self {
+        Self {
+            title,
+            message: Some(message),
+            hint: None,
+            kind: DialogKind::Confirm,
+            action,
+            danger: false,
+            allow_blank: false,
+        }
+    }
+
+    pub fn input(title: String, text: String, action: DialogAction) -> Self {
+        Self {
+            title,
+            message: None,
+            hint: None,
+            kind: DialogKind::Input(text),
+            action,
+            danger: false,
+            allow_blank: false,
+        }
+    }
+
+    pub fn choice(
+        title: String,
+        options: Vec<String>,
+        selected: usize,
+        action: DialogAction,
+    ) -> Self {
+        Self {
+            title,
+            message: None,
+            hint: None,
+            kind: DialogKind::Choice {
+                options,
+                selected,
+                keys: Vec::new(),
+            },
+            action,
+            danger: false,
+            allow_blank: false,
+        }
+    }
+
+    pub fn revision(title: String, input: RevisionInput, action: DialogAction) -> Self {
+        Self {
+            title,
+            message: None,
+            hint: None,
+            kind: DialogKind::Revision(input),
+            action,
+            danger: false,
+            allow_blank: false,
+        }
+    }
+
+    pub fn checklist(title: String, options: Vec<(String, bool)>, action: DialogAction) -> Self {
+        Self {
+            title,
+            message: None,
+            hint: None,
+            kind: DialogKind::Checklist {
+                options,
+                selected: 0,
+            },
+            action,
+            danger: false,
+            allow_blank: false,
+        }
+    }
+
+    /// Give a choice's options, in order, a key each
+    pub fn with_keys(mut self, option_keys: &[char]) -> Self {
+        if let DialogKind::Choice { keys, .. } = &mut self.kind {
+            *keys = option_keys.to_vec();
+        }
+        self
+    }
+
+    pub fn allowing_blank(mut self) -> Self {
+        self.allow_blank = true;
+        self
+    }
+
+    pub fn with_message(mut self, message: String) -> Self {
+        self.message = Some(message);
+        self
+    }
+
+    pub fn with_hint(mut self, hint: String) -> Self {
+        self.hint = Some(hint);
+        self
+    }
+
+    pub fn dangerous(mut self) -> Self {
+        self.danger = true;
+        self
+    }
+}
+
+/// Open dialogs, the last one on top
+#[derive(Debug, Default)]
+pub struct DialogStack {
+    dialogs: Vec<Dialog>,
+}
+
+impl DialogStack {
+    pub fn push(&mut self, dialog: Dialog) {
+        self.dialogs.push(dialog);
+    }
+
+    pub fn pop(&mut self) -> Option<Dialog> {
+        self.dialogs.pop()
+    }
+
+    pub fn top(&self) -> Option<&Dialog> {
+        self.dialogs.last()
+    }
+
+    pub fn top_mut(&mut self) -> Option<&mut Dialog> {
+        self.dialogs.last_mut()
+    }
+
+    /// The open dialog with this action, wherever it is in the stack
+    pub fn find_mut(&mut self, action: &DialogAction) -> Option<&mut Dialog> {
+        self.dialogs
+            .iter_mut()
+            .find(|dialog| dialog.action == *action)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dialogs.is_empty()
+    }
+
+    /// Bottom to top, the order they are drawn in
+    pub fn iter(&self) -> impl Iterator<Item = &Dialog> {
+        self.dialogs.iter()
+    }
+}
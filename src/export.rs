@@ -0,0 +1,55 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+
+use crate::app::CommitInfo;
+use crate::git::GitBackend;
+
+/// One exported history row
+#[derive(Debug, Serialize)]
+pub struct CommitRecord {
+    pub hash: String,
+    pub author: String,
+    pub date: String,
+    pub subject: String,
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// Write `commits` with their diff stats to `path`: JSON for a `.json` extension, CSV otherwise.
+/// Returns the number of commits written.
+pub fn export_commits(
+    git_repo: &dyn GitBackend,
+    commits: &[CommitInfo],
+    path: &Path,
+) -> Result<usize> {
+    let mut records = Vec::with_capacity(commits.len());
+    for commit in commits {
+        let stats = git_repo.get_commit_stats(&commit.id)?;
+        records.push(CommitRecord {
+            hash: stats.full_id,
+            author: commit.author.clone(),
+            date: commit.date.clone(),
+            subject: commit.message.clone(),
+            files_changed: stats.files_changed,
+            insertions: stats.insertions,
+            deletions: stats.deletions,
+        });
+    }
+
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if is_json {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, &records)?;
+    } else {
+        let mut writer = csv::Writer::from_path(path)?;
+        for record in &records {
+            writer.serialize(record)?;
+        }
+        writer.flush()?;
+    }
+    Ok(records.len())
+}
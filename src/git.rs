@@ -1,687 +1,4021 @@
 use anyhow::Result;
-use git2::{BranchType, DiffOptions, Repository, Status, StatusOptions};
-use std::path::Path;
+use git2::{BranchType, DiffOptions, Oid, ReferenceType, Repository, Status, StatusOptions};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::app::{
+    BisectMark, BisectStep, BlameLine, BranchInfo, CommitDetail, CommitFileChange, CommitInfo,
+    CommitStats, FileStatus, GrepMatch, HistoryFilter, InProgress, RebaseEntry, SubmoduleInfo,
+    TagInfo, TransferProgress, WorktreeInfo,
+};
+use crate::graph;
+use crate::i18n::tr;
+use crate::image::{has_image_extension, image_info};
+
+/// The user's side of a network operation: watches its progress, may cancel it, and answers
+/// credential prompts. Network operations run on a worker thread, so this is their way back
+/// to the UI.
+pub trait RemoteUi {
+    fn progress(&mut self, transfer: &TransferProgress);
+    /// Whether the user gave up on the operation; checked at every callback
+    fn cancelled(&self) -> bool;
+    /// Ask for a username and password (or token) for `url`; None when the user declines
+    fn credentials(&mut self, url: &str, username: Option<&str>) -> Option<(String, String)>;
+    /// A line of output from the system git, when network operations shell out to it
+    fn output(&mut self, line: &str);
+}
+
+/// Everything the UI does with a repository. `GitRepo` implements it with libgit2; input
+/// handling only sees the trait, so another implementation (the git CLI, a mock) can stand in.
+pub trait GitBackend: Send {
+    /// A second handle on the same repository, for work done on another thread
+    fn reopen(&self) -> Result<Box<dyn GitBackend>>;
+
+    /// Explain why network operations against `remote_name` cannot work with this backend
+    /// (e.g. libgit2 built without SSH or HTTPS support), if that is the case
+    fn unsupported_transport(&self, remote_name: &str) -> Option<String>;
+
+    fn get_current_branch(&self) -> Result<String>;
+
+    fn get_branches(&self) -> Result<Vec<BranchInfo>>;
+
+    fn get_tags(&self) -> Result<Vec<TagInfo>>;
+
+    /// Root of the working tree; `None` for bare repositories
+    fn workdir(&self) -> Option<&Path>;
+
+    /// Commits HEAD is (ahead of, behind) `base`
+    fn ahead_behind(&self, base: &str) -> Result<(usize, usize)>;
+
+    /// True if tracked files have staged or unstaged changes (untracked files don't count)
+    fn is_worktree_dirty(&self) -> Result<bool>;
+
+    /// Short id of the commit HEAD points at when it is detached from any branch
+    fn detached_head(&self) -> Option<String>;
+
+    /// Check out a commit as a detached HEAD, keeping local changes that don't conflict
+    fn checkout_commit(&self, id: &str) -> Result<()>;
+
+    /// Whether the index holds unresolved conflicts, e.g. after a merge or revert stopped
+    fn has_conflicts(&self) -> Result<bool>;
+
+    /// Check out a tag's commit as a detached HEAD
+    fn checkout_tag(&self, tag_name: &str) -> Result<()>;
+
+    /// Tag the commit `target` resolves to as `name`: an annotated tag carrying `message`, or a
+    /// lightweight one without. An existing tag of that name is an error.
+    fn create_tag(&self, name: &str, target: &str, message: Option<&str>) -> Result<()>;
+
+    /// Delete a local tag; the remote keeps its copy
+    fn delete_tag(&self, name: &str) -> Result<()>;
+
+    /// The commit that last changed each line of `path` as of `revision`, like `git blame`
+    fn blame(&self, path: &str, revision: &str) -> Result<Vec<BlameLine>>;
+
+    /// Lines of tracked files containing `pattern`, in the working tree or at `revision`, like
+    /// `git grep -F`: case-insensitive unless the pattern has capitals. Binary files are
+    /// skipped and the search stops after `limit` matches.
+    fn grep(&self, pattern: &str, revision: Option<&str>, limit: usize) -> Result<Vec<GrepMatch>>;
+
+    /// Text of `path` in the working tree, or at `revision`
+    fn file_text(&self, path: &str, revision: Option<&str>) -> Result<String>;
+
+    /// Open `path` at `line` in the editor git would use (`GIT_EDITOR`, `core.editor`,
+    /// `VISUAL`, `EDITOR`, then vi), in the foreground
+    fn edit_file(&self, path: &str, line: usize) -> Result<()>;
+
+    /// Mark commit `id` good, bad or skipped, starting a bisect from HEAD if none is running,
+    /// and check out the next commit to test. The state lives where `git bisect` keeps it, so
+    /// either can carry on with it.
+    fn bisect_mark(&self, id: &str, mark: BisectMark) -> Result<BisectStep>;
+
+    /// Where the bisect in progress stands, without checking anything out
+    fn bisect_status(&self) -> Result<BisectStep>;
+
+    /// End the bisect and check out what HEAD was when it started, like `git bisect reset`.
+    /// Returns that branch name or commit id.
+    fn bisect_reset(&self) -> Result<String>;
+
+    /// Every working tree of the repository, the main one first
+    fn worktrees(&self) -> Result<Vec<WorktreeInfo>>;
+
+    /// Add a working tree at `path` with `branch` checked out, creating the branch at HEAD if it
+    /// doesn't exist yet, like `git worktree add [-b]`
+    fn add_worktree(&self, branch: &str, path: &Path) -> Result<()>;
+
+    /// Delete linked worktree `name` and its directory, like `git worktree remove`. Locked
+    /// worktrees and ones with uncommitted or untracked changes are refused.
+    fn remove_worktree(&self, name: &str) -> Result<()>;
+
+    /// Every submodule in `.gitmodules` with the state of its checkout
+    fn submodules(&self) -> Result<Vec<SubmoduleInfo>>;
+
+    /// Register submodule `name`'s URL in `.git/config`, like `git submodule init`
+    fn init_submodule(&self, name: &str) -> Result<()>;
+
+    /// Clone submodule `name` if needed and check out the commit the superproject records,
+    /// like `git submodule update --init`
+    fn update_submodule(&self, name: &str, ui: &mut dyn RemoteUi) -> Result<()>;
+
+    /// Copy submodule `name`'s URL from `.gitmodules` into `.git/config` and its own remote,
+    /// like `git submodule sync`
+    fn sync_submodule(&self, name: &str) -> Result<()>;
+
+    /// Names that can be offered as completions wherever a revision is entered:
+    /// local branches, remote-tracking branches, tags and recent commit hashes.
+    fn get_revision_candidates(&self, recent_commits: usize) -> Result<Vec<String>>;
+
+    /// Resolve a revision expression to the abbreviated commit id used in `CommitInfo`
+    fn resolve_revision(&self, revision: &str) -> Result<String>;
+
+    /// Number of commits reachable from HEAD
+    fn count_commits(&self) -> Result<usize>;
+
+    fn get_commits(&self, limit: usize, filter: HistoryFilter) -> Result<Vec<CommitInfo>>;
+
+    /// Walk history once for `filter`, for `get_commit_page` to load page by page
+    fn history_cursor(&self, filter: HistoryFilter) -> Result<HistoryCursor>;
+
+    /// The next `limit` commits of `cursor`'s walk, without graph layout.
+    /// Used to append further pages to an already loaded list.
+    fn get_commit_page(&self, cursor: &mut HistoryCursor, limit: usize) -> Result<Vec<CommitInfo>>;
+
+    fn get_commit_detail(&self, id: &str) -> Result<CommitDetail>;
+
+    /// Patch text for a commit against its first parent (or the empty tree for a root commit)
+    /// With `ignore_whitespace`, lines that differ only in whitespace don't count as changed.
+    fn get_commit_diff(&self, id: &str, ignore_whitespace: bool) -> Result<String>;
+
+    /// Combined diffstat and patch between two commits, like `git diff from..to`
+    fn get_range_diff(&self, from: &str, to: &str, ignore_whitespace: bool) -> Result<String>;
+
+    /// Write `NNNN-subject.patch` mbox files into `dir`, like `git format-patch`: the commits
+    /// in `from..to`, or just `to` without `from`. Returns the paths written.
+    fn format_patch(
+        &self,
+        from: Option<&str>,
+        to: &str,
+        dir: &Path,
+    ) -> Result<Vec<std::path::PathBuf>>;
+
+    fn get_commit_stats(&self, id: &str) -> Result<CommitStats>;
+
+    /// Changed files, staged ones first. A file with both staged and unstaged changes is listed
+    /// twice, once for each.
+    fn get_status(&self) -> Result<Vec<FileStatus>>;
+
+    /// Patch text for one changed file: its staged changes, or its unstaged ones (falling back to
+    /// the staged ones if there are none). With `max_lines` the text stops after that many lines
+    /// and ends with a note of how many were left out.
+    fn get_diff_for_file(
+        &self,
+        path: &str,
+        staged: bool,
+        ignore_whitespace: bool,
+        max_lines: Option<usize>,
+    ) -> Result<String>;
+
+    fn stage_file(&self, path: &str) -> Result<()>;
+
+    fn stage_all(&self) -> Result<()>;
+
+    /// Take a file's staged changes back out of the index, like `git restore --staged`; a staged
+    /// rename is undone on both paths
+    fn unstage_file(&self, path: &str) -> Result<()>;
+
+    /// Throw away a file's changes: with `keep_staged` only the unstaged ones, restoring the
+    /// working tree from the index, otherwise everything back to HEAD
+    fn discard_file(&self, path: &str, keep_staged: bool) -> Result<()>;
+
+    fn discard_all(&self) -> Result<()>;
+
+    /// Write `path` (a file or directory) as it is in `revision` into the working tree, like
+    /// `git restore --source`; the index is left alone, so the change shows up unstaged
+    fn checkout_file_from(&self, revision: &str, path: &str) -> Result<()>;
+
+    /// Append `pattern` as a line of the repository's top-level `.gitignore`, creating the file
+    /// if needed. A pattern that is already there is an error.
+    fn add_to_gitignore(&self, pattern: &str) -> Result<()>;
+
+    /// Resolve a conflicted file wholesale with our side (the checked-out branch; during a
+    /// rebase, the branch being rebased onto) or their side, and stage the result. A side that
+    /// deleted the file resolves to the deletion.
+    fn resolve_conflict(&self, path: &str, theirs: bool) -> Result<()>;
+
+    /// Resolve a conflicted file by writing `content` over it and staging the result
+    fn write_resolution(&self, path: &str, content: &str) -> Result<()>;
+
+    /// Run `git mergetool` (the `merge.tool` configured one) on a conflicted file in the
+    /// foreground; the caller hands it the terminal. Git stages the file if the tool resolved it.
+    fn mergetool(&self, path: &str) -> Result<()>;
+
+    /// Run `git difftool` on a changed file's staged or unstaged changes in the foreground.
+    /// `command` replaces the configured `diff.tool`, called with the old and new version like
+    /// `git difftool --extcmd`.
+    fn difftool(&self, path: &str, staged: bool, command: Option<&str>) -> Result<()>;
+
+    /// Commit the index on top of HEAD. During a merge every `MERGE_HEAD` becomes a parent too,
+    /// and a cherry-pick keeps the picked commit's author; either way the operation is finished.
+    fn commit(&self, message: &str) -> Result<()>;
+
+    /// The message git prepared in `MERGE_MSG` for a stopped merge, cherry-pick or revert,
+    /// without the `#` comment lines `git commit` would drop
+    fn prepared_message(&self) -> Option<String>;
+
+    /// Apply the inverse of commit `id` to the index and working tree. With `commit` the revert
+    /// is committed right away; otherwise it is left staged. Returns the revert message.
+    fn revert(&self, id: &str, commit: bool) -> Result<String>;
+
+    /// Merge `branch_name` (local or remote-tracking) into HEAD: fast-forward when possible,
+    /// otherwise create a merge commit. On conflicts the merge is left in progress with the
+    /// conflicted files in the index. With `ff_only` a merge that can't fast-forward fails
+    /// without touching anything. Returns a description of what happened.
+    fn merge(&self, branch_name: &str, ff_only: bool) -> Result<String>;
+
+    /// Bring the changes of `branch_name` into the index and worktree without committing or
+    /// recording a merge, like `git merge --squash`. Conflicts are left in the index. Returns a
+    /// commit message listing the squashed commits. `ff_only` doesn't apply: a squash always
+    /// ends in a new commit of its own, never a fast-forward.
+    fn merge_squash(&self, branch_name: &str) -> Result<String>;
+
+    /// Rebase the current branch onto `onto`, replaying its commits one by one. Stops on the
+    /// first conflict with the rebase left in progress and returns its (step, total) position;
+    /// returns `None` once every commit was replayed.
+    fn rebase_start(&self, onto: &str) -> Result<Option<(usize, usize)>>;
+
+    /// Carry on with the stopped operation once its conflicts are resolved and staged: commit
+    /// the rebase step and replay the rest, or commit the merge, cherry-pick or revert with the
+    /// message git prepared. Returns the rebase step it stopped at on the next conflict.
+    fn continue_operation(&self) -> Result<Option<(usize, usize)>>;
+
+    /// Drop the commit a rebase, cherry-pick or revert stopped on, discarding its changes, and
+    /// replay the rest of a rebase. Returns the rebase step it stopped at on the next conflict.
+    fn skip_operation(&self) -> Result<Option<(usize, usize)>>;
+
+    /// Abandon the stopped merge, rebase, cherry-pick or revert. A rebase restores the branch as
+    /// it was before; the others reset the index and working tree to HEAD, like `git reset --hard`.
+    fn abort_operation(&self) -> Result<()>;
+
+    /// The merge, rebase, cherry-pick, revert, bisect or `git am` in progress, if any
+    fn in_progress(&self) -> Option<InProgress>;
+
+    /// Commits between `base` (exclusive) and HEAD, oldest first, with the files each touches
+    fn get_rebase_todo(&self, base: &str) -> Result<Vec<RebaseEntry>>;
+
+    /// Replay `ids` in order on top of `base` and move HEAD to the result. Everything is
+    /// applied in memory first, so a conflict leaves the branch and working tree untouched.
+    fn rebase_onto(&self, base: &str, ids: &[String]) -> Result<()>;
+
+    /// Soft-reset HEAD to its parent, keeping the commit's changes staged.
+    /// Returns the id and message of the undone commit.
+    fn undo_last_commit(&self) -> Result<(String, String)>;
+
+    /// Apply a patch (plain diff or format-patch mail) to the worktree or the index, like
+    /// `git apply --reject`: hunks that don't apply are skipped and the rest still go in.
+    /// Returns the number of hunks applied and the rejected hunks as patch text.
+    fn apply_patch(&self, patch: &[u8], to_index: bool) -> Result<(usize, Vec<String>)>;
+
+    /// Turn staged hunks into `fixup!` commits on top of HEAD, each aimed at the most recent
+    /// commit that last touched the hunk's lines. Hunks that only add lines, whole-file adds
+    /// and deletes, and hunks whose lines come from already pushed commits stay staged.
+    /// Returns the number of fixup commits created and the number of hunks left staged.
+    fn absorb_staged(&self) -> Result<(usize, usize)>;
+
+    fn create_branch(&self, branch_name: &str, base_branch: &str) -> Result<()>;
+
+    /// Delete a local branch. Unless `force` is set, a branch with commits HEAD doesn't
+    /// contain is refused, since deleting it would lose them.
+    fn delete_branch(&self, branch_name: &str, force: bool) -> Result<()>;
+
+    /// Keep a branch's commits as the tag `archive/<name>` and delete the branch. Returns the
+    /// tag name.
+    fn archive_branch(&self, branch_name: &str) -> Result<String>;
+
+    /// Local branches other than the current one whose commits are all contained in HEAD
+    fn merged_branches(&self) -> Result<Vec<String>>;
+
+    /// Number of commits on a local branch that HEAD doesn't contain
+    fn unmerged_commits(&self, branch_name: &str) -> Result<usize>;
+
+    /// Check out a branch (or any revision, detached). Unless `force` is set, local changes the
+    /// checkout would overwrite make it fail; see `is_checkout_conflict`.
+    fn checkout_branch(&self, branch_name: &str, force: bool) -> Result<()>;
+
+    /// Point `branch_name` at `upstream` (a remote branch such as `origin/main`, or a local
+    /// branch), or clear its `branch.<name>.remote`/`merge` with `None`
+    fn set_upstream(&self, branch_name: &str, upstream: Option<&str>) -> Result<()>;
+
+    /// Names of the configured remotes
+    fn remotes(&self) -> Result<Vec<String>>;
+
+    /// The remote a local branch's upstream lives on, if it has one
+    fn branch_remote(&self, branch_name: &str) -> Option<String>;
+
+    /// Check out `origin/feature` as a local `feature` tracking it, creating the branch unless
+    /// one with that name already tracks it. Returns the local branch name.
+    fn checkout_remote_branch(
+        &self,
+        remote_branch: &str,
+        remote: &str,
+        force: bool,
+    ) -> Result<String>;
+
+    /// Stash local changes, untracked files included, like `git stash -u`. Returns false when
+    /// there was nothing to stash.
+    fn stash_push(&self, message: &str) -> Result<bool>;
+
+    /// Number of entries in the stash
+    fn stash_count(&self) -> Result<usize>;
+
+    /// Write the commits `revisions` select (a branch, `a..b`, ...) into a bundle file with the
+    /// system `git bundle create`, passing its output to `ui`
+    fn create_bundle(&self, path: &Path, revisions: &str, ui: &mut dyn RemoteUi) -> Result<()>;
+
+    /// Check that a bundle file is valid and its prerequisite commits are in this repository,
+    /// with `git bundle verify`; the refs it contains go to `ui`
+    fn verify_bundle(&self, path: &Path, ui: &mut dyn RemoteUi) -> Result<()>;
+
+    /// Prune loose objects and repack with the system `git gc`, passing its output to `ui`
+    fn run_maintenance(&self, ui: &mut dyn RemoteUi) -> Result<()>;
+
+    /// Reapply the latest stash and drop it, like `git stash pop`. On conflicts the stash is kept.
+    fn stash_pop(&self) -> Result<()>;
+
+    /// Fetch the current branch from `remote` and fast-forward to it, or merge it when the
+    /// histories have diverged; with `ff_only`, fail instead of merging. When fast-forwarding
+    /// would overwrite local changes it fails with a `FastForwardConflict`.
+    fn pull(&self, remote: &str, ff_only: bool, ui: &mut dyn RemoteUi) -> Result<()>;
+
+    /// Check out commit `target` and move the current branch `branch_name` to it. Unless
+    /// `force` is set, local changes the checkout would overwrite make it fail; see
+    /// `is_checkout_conflict`.
+    fn fast_forward(&self, branch_name: &str, target: &str, force: bool) -> Result<()>;
+
+    /// Fetch from `remote` without merging, updating its remote-tracking branches
+    fn fetch(&self, remote: &str, ui: &mut dyn RemoteUi) -> Result<()>;
+
+    fn push(&self, remote: &str, ui: &mut dyn RemoteUi) -> Result<()>;
+
+    /// Push a local branch; a branch without an upstream gets the pushed-to branch as its
+    /// upstream, like `git push -u`. Without a `remote`, the upstream's remote or origin is used.
+    fn push_branch(
+        &self,
+        branch_name: &str,
+        remote: Option<&str>,
+        ui: &mut dyn RemoteUi,
+    ) -> Result<()>;
+
+    /// Delete the branch behind a remote-tracking branch such as `origin/feature` on its
+    /// remote, then drop the remote-tracking ref
+    fn delete_remote_branch(&self, remote_branch: &str, ui: &mut dyn RemoteUi) -> Result<()>;
+
+    fn sync(&self, remote: &str, ff_only: bool, ui: &mut dyn RemoteUi) -> Result<()>;
+
+    /// Push one tag to `remote`, or every local tag without a `tag`
+    fn push_tags(&self, remote: &str, tag: Option<&str>, ui: &mut dyn RemoteUi) -> Result<()>;
+
+    /// Delete tag `name` on `remote`, leaving the local tag alone
+    fn delete_remote_tag(&self, remote: &str, name: &str, ui: &mut dyn RemoteUi) -> Result<()>;
+}
+
+/// Identifies a staged hunk by file and position in the HEAD -> index diff
+type HunkKey = (std::path::PathBuf, u32, u32, u32, u32);
+
+fn hunk_key(path: &Path, hunk: &git2::DiffHunk) -> HunkKey {
+    (
+        path.to_path_buf(),
+        hunk.old_start(),
+        hunk.old_lines(),
+        hunk.new_start(),
+        hunk.new_lines(),
+    )
+}
+
+/// Commit the current rebase step, keeping the original author; a step whose changes are
+/// already upstream is dropped like `git rebase` does
+fn commit_rebase_step(rebase: &mut git2::Rebase, signature: &git2::Signature) -> Result<()> {
+    match rebase.commit(None, signature, None) {
+        Err(e) if e.code() != git2::ErrorCode::Applied => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Why continue, skip or abort can't handle `state`, pointing at the git command that can
+fn unsupported_operation(state: git2::RepositoryState, flag: &str) -> String {
+    use git2::RepositoryState as State;
+    match state {
+        State::Clean => tr!("No merge, rebase, cherry-pick or revert is in progress").to_string(),
+        State::CherryPickSequence => {
+            tr!("Finish this cherry-pick with git cherry-pick {}", flag)
+        }
+        State::RevertSequence => tr!("Finish this revert with git revert {}", flag),
+        State::Bisect => {
+            tr!("Mark commits with m in History, or end the bisect with X in Branches").to_string()
+        }
+        State::ApplyMailbox | State::ApplyMailboxOrRebase => {
+            tr!("Finish applying the patches with git am {}", flag)
+        }
+        _ => tr!("Finish this operation with git {}", flag),
+    }
+}
+
+/// Commit time and subject of the commit a branch points at
+fn tip_summary(reference: &git2::Reference) -> (i64, String) {
+    match reference.peel_to_commit() {
+        Ok(commit) => (
+            commit.time().seconds(),
+            commit.summary().unwrap_or("").to_string(),
+        ),
+        Err(_) => (0, String::new()),
+    }
+}
+
+/// `0001-Fix-the-thing.patch`: the subject with runs of other characters turned into dashes
+fn patch_file_name(number: usize, subject: &str) -> String {
+    let mut slug = String::new();
+    for c in subject.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug.trim_end_matches('-').chars().take(52).collect();
+    format!("{:04}-{}.patch", number, slug.trim_end_matches('-'))
+}
+
+/// Branch and tag names pointing at a commit
+type RefNames = (Vec<String>, Vec<String>);
+
+/// SSH identity files to try before the agent: one for every remote, and per remote name
+#[derive(Debug, Clone, Default)]
+pub struct SshKeys {
+    pub default: Option<PathBuf>,
+    pub per_remote: BTreeMap<String, PathBuf>,
+}
+
+impl SshKeys {
+    fn for_remote(&self, remote: &str) -> Option<PathBuf> {
+        self.per_remote
+            .get(remote)
+            .or(self.default.as_ref())
+            .cloned()
+    }
+}
+
+/// A history walk loaded page by page: the commits it visits in display order, and the ref
+/// decorations as they were when it started
+#[derive(Debug, Default)]
+pub struct HistoryCursor {
+    ids: Vec<Oid>,
+    /// Index of the first commit not loaded yet
+    next: usize,
+    first_parent: bool,
+    decorations: HashMap<Oid, RefNames>,
+}
+
+impl HistoryCursor {
+    /// Whether commits remain beyond the pages loaded so far
+    pub fn has_more(&self) -> bool {
+        self.next < self.ids.len()
+    }
+
+    /// Where in the walk the commit with the abbreviated id `id` comes
+    pub fn position(&self, id: &str) -> Option<usize> {
+        self.ids.iter().position(|oid| format!("{:.7}", oid) == id)
+    }
+}
+
+/// A username and password
+type Credential = (String, String);
+
+/// Usernames and passwords that worked this session, by host, so they aren't asked for again
+type CredentialCache = Arc<Mutex<HashMap<String, Credential>>>;
+
+/// The username and password last handed to libgit2 for a host, cached only once the operation
+/// they were for has succeeded
+#[derive(Clone, Default)]
+struct PendingCredential(Rc<RefCell<Option<(String, Credential)>>>);
+
+impl PendingCredential {
+    /// The operation succeeded, so whatever was handed out was accepted
+    fn accept(&self, cache: &CredentialCache) {
+        if let Some((host, credential)) = self.0.borrow_mut().take() {
+            cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(host, credential);
+        }
+    }
+}
+
+pub struct GitRepo {
+    repo: Repository,
+    ssh_keys: SshKeys,
+    credentials: CredentialCache,
+    /// Run fetch/pull/push through the system `git` instead of libgit2
+    shell_git: bool,
+    /// Unchanged lines shown around each change in diffs
+    diff_context: u32,
+}
+
+impl GitRepo {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let repo = Repository::discover(path)?;
+        Ok(Self {
+            repo,
+            ssh_keys: SshKeys::default(),
+            credentials: CredentialCache::default(),
+            shell_git: false,
+            diff_context: 3,
+        })
+    }
+
+    pub fn set_ssh_keys(&mut self, ssh_keys: SshKeys) {
+        self.ssh_keys = ssh_keys;
+    }
+
+    pub fn set_shell_git(&mut self, shell_git: bool) {
+        self.shell_git = shell_git;
+    }
+
+    pub fn set_diff_context(&mut self, diff_context: u32) {
+        self.diff_context = diff_context;
+    }
+
+    /// Whether network operations against `remote_name` go through the system git: always with
+    /// `shell_git`, and for SSH remotes when `GIT_SSH_COMMAND`, `GIT_SSH` or `core.sshCommand`
+    /// pick the ssh program or its options, which libgit2 ignores
+    fn uses_shell_git(&self, remote_name: &str) -> bool {
+        if self.shell_git {
+            return true;
+        }
+        let is_ssh = self
+            .repo
+            .find_remote(remote_name)
+            .ok()
+            .and_then(|remote| remote.url().map(is_ssh_url))
+            .unwrap_or(false);
+        if !is_ssh {
+            return false;
+        }
+        self.repo
+            .config()
+            .is_ok_and(|config| custom_ssh_command(&config))
+    }
+
+    /// Push `refspecs` to `remote_name` in one go, through the system git when it has to
+    fn push_refspecs(
+        &self,
+        remote_name: &str,
+        refspecs: &[String],
+        ui: &mut dyn RemoteUi,
+    ) -> Result<()> {
+        if self.uses_shell_git(remote_name) {
+            let mut args = vec!["push", "--progress", remote_name];
+            args.extend(refspecs.iter().map(String::as_str));
+            self.run_git(&args, ui)?;
+        } else {
+            let mut remote = self.repo.find_remote(remote_name)?;
+            let (callbacks, pending) = self.remote_callbacks(&remote, ui)?;
+            let mut push_options = git2::PushOptions::new();
+            push_options.remote_callbacks(callbacks);
+            remote.push(refspecs, Some(&mut push_options))?;
+            pending.accept(&self.credentials);
+        }
+        Ok(())
+    }
+
+    /// Whether HEAD names a branch without commits yet, as in a new repository
+    fn head_unborn(&self) -> bool {
+        matches!(self.repo.head(), Err(e) if e.code() == git2::ErrorCode::UnbornBranch)
+    }
+
+    /// Map commits to the (branches, tags) pointing at them, like `git log --decorate`.
+    /// Branches list local names before remote ones; remote `HEAD` aliases are skipped.
+    fn get_ref_decorations(&self) -> Result<HashMap<Oid, RefNames>> {
+        let mut decorations: HashMap<Oid, RefNames> = HashMap::new();
+        let mut remote_branches: Vec<(Oid, String)> = Vec::new();
+
+        for reference in self.repo.references()?.flatten() {
+            if reference.kind() != Some(ReferenceType::Direct) {
+                continue;
+            }
+            let (Some(name), Ok(commit)) = (reference.shorthand(), reference.peel_to_commit())
+            else {
+                continue;
+            };
+            let entry = decorations.entry(commit.id()).or_default();
+            if reference.is_branch() {
+                entry.0.push(name.to_string());
+            } else if reference.is_tag() {
+                entry.1.push(name.to_string());
+            } else if reference.is_remote() {
+                remote_branches.push((commit.id(), name.to_string()));
+            }
+        }
+
+        for (oid, name) in remote_branches {
+            decorations.entry(oid).or_default().0.push(name);
+        }
+        Ok(decorations)
+    }
+
+    /// Merge `annotated` into HEAD and commit the result with `message`. On conflicts the merge
+    /// is left in progress, with the conflicts in the index, for the user to resolve.
+    fn merge_commit(
+        &self,
+        annotated: &git2::AnnotatedCommit,
+        name: &str,
+        message: &str,
+    ) -> Result<git2::Oid> {
+        self.repo.merge(&[annotated], None, None)?;
+        let mut index = self.repo.index()?;
+        if index.has_conflicts() {
+            anyhow::bail!(tr!(
+                "Merging {} has conflicts; resolve them and commit",
+                name
+            ));
+        }
+
+        let theirs = self.repo.find_commit(annotated.id())?;
+        let tree = self.repo.find_tree(index.write_tree()?)?;
+        let signature = self.repo.signature()?;
+        let head_commit = self.repo.head()?.peel_to_commit()?;
+        let merge_id = self.repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &[&head_commit, &theirs],
+        )?;
+        self.repo.cleanup_state()?;
+        Ok(merge_id)
+    }
+
+    /// Describe how submodule `path` changed, like `git diff --submodule=log`: the staged move of
+    /// its commit, or the unstaged one of its checked-out commit (the staged one when there is
+    /// none), with the commits in between, then whether its working tree has changes of its own.
+    /// `None` if `path` isn't a submodule.
+    fn submodule_diff(&self, path: &str, staged: bool) -> Option<String> {
+        let submodule = self.repo.find_submodule(path).ok()?;
+        let head_id = submodule.head_id();
+        let index_id = submodule.index_id();
+        let (old, new) = match (index_id, submodule.workdir_id()) {
+            (index, workdir) if !staged && workdir.is_some() && index != workdir => {
+                (index, workdir)
+            }
+            _ => (head_id, index_id),
+        };
+
+        let short =
+            |id: Option<Oid>| id.map_or_else(|| "0000000".to_string(), |id| format!("{:.7}", id));
+        let mut text = format!("Submodule {}\n", path);
+        if old != new {
+            text.push_str(&format!(
+                "Subproject commit {} → {}\n",
+                short(old),
+                short(new)
+            ));
+            if let (Some(old), Some(new), Ok(sub_repo)) = (old, new, submodule.open()) {
+                for (from, to, marker) in [(old, new, '>'), (new, old, '<')] {
+                    for commit in commits_between(&sub_repo, from, to) {
+                        text.push_str(&format!("  {} {}\n", marker, commit));
+                    }
+                }
+            }
+        }
+
+        let status = self
+            .repo
+            .submodule_status(path, git2::SubmoduleIgnore::None)
+            .unwrap_or(git2::SubmoduleStatus::empty());
+        if status.intersects(
+            git2::SubmoduleStatus::WD_INDEX_MODIFIED | git2::SubmoduleStatus::WD_WD_MODIFIED,
+        ) {
+            text.push_str("Submodule contains modified content\n");
+        }
+        if status.contains(git2::SubmoduleStatus::WD_UNTRACKED) {
+            text.push_str("Submodule contains untracked content\n");
+        }
+        if submodule.workdir_id().is_none() {
+            text.push_str("Submodule is not checked out\n");
+        }
+        Some(text)
+    }
+
+    /// The path a staged file was renamed from, if it is the target of a rename
+    fn staged_rename_source(&self, path: &str) -> Option<String> {
+        let head_tree = self.repo.head().and_then(|head| head.peel_to_tree()).ok()?;
+        let mut diff = self
+            .repo
+            .diff_tree_to_index(Some(&head_tree), None, None)
+            .ok()?;
+        find_renames(&mut diff).ok()?;
+        let delta = diff.deltas().find(|delta| {
+            delta.status() == git2::Delta::Renamed
+                && delta.new_file().path() == Some(Path::new(path))
+        })?;
+        Some(delta.old_file().path()?.to_string_lossy().into_owned())
+    }
+
+    /// Lines added and removed per path by the staged changes (HEAD to index) or the unstaged
+    /// ones (index to working tree). This runs on every status read, so untracked files, binary
+    /// files and files over `BIG_FILE_THRESHOLD` get no counts, and once `BIG_FILE_THRESHOLD`
+    /// bytes have been diffed the remaining files get none either.
+    fn line_counts(&self, staged: bool) -> Result<HashMap<String, (usize, usize)>> {
+        let mut opts = diff_options(false, self.diff_context);
+        let mut diff = if staged {
+            let head_tree = self.repo.head().and_then(|head| head.peel_to_tree()).ok();
+            self.repo
+                .diff_tree_to_index(head_tree.as_ref(), None, Some(&mut opts))?
+        } else {
+            self.repo.diff_index_to_workdir(None, Some(&mut opts))?
+        };
+        find_renames(&mut diff)?;
+
+        let mut counts = HashMap::new();
+        let mut budget = BIG_FILE_THRESHOLD;
+        for (i, delta) in diff.deltas().enumerate() {
+            let Some(path) = delta.new_file().path().or(delta.old_file().path()) else {
+                continue;
+            };
+            let size = delta.old_file().size().max(delta.new_file().size());
+            if delta.flags().is_binary() || size > BIG_FILE_THRESHOLD {
+                continue;
+            }
+            if size > budget {
+                break;
+            }
+            budget -= size;
+            let Some(patch) = git2::Patch::from_diff(&diff, i)? else {
+                continue;
+            };
+            let (_, additions, deletions) = patch.line_stats()?;
+            counts.insert(path.to_string_lossy().into_owned(), (additions, deletions));
+        }
+        Ok(counts)
+    }
+
+    /// Throw away the index and working tree changes, like `git reset --hard HEAD`. Unlike
+    /// `Repository::reset`, this leaves the operation's state files alone.
+    fn reset_hard_to_head(&self) -> Result<()> {
+        let tree = self.repo.head()?.peel_to_tree()?;
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.force();
+        self.repo
+            .checkout_tree(tree.as_object(), Some(&mut checkout))?;
+        let mut index = self.repo.index()?;
+        index.read_tree(&tree)?;
+        index.write()?;
+        Ok(())
+    }
+
+    fn run_rebase(&self, rebase: &mut git2::Rebase) -> Result<Option<(usize, usize)>> {
+        let signature = self.repo.signature()?;
+        while let Some(operation) = rebase.next() {
+            operation?;
+            if self.repo.index()?.has_conflicts() {
+                let current = rebase.operation_current().map_or(0, |i| i + 1);
+                return Ok(Some((current, rebase.len())));
+            }
+            commit_rebase_step(rebase, &signature)?;
+        }
+        rebase.finish(Some(&signature))?;
+        Ok(None)
+    }
+
+    /// Apply only the hunks `accept` picks out of `diff`, or just check that they would apply
+    fn apply_hunks(
+        &self,
+        diff: &git2::Diff,
+        location: git2::ApplyLocation,
+        check: bool,
+        accept: impl Fn(&HunkKey) -> bool,
+    ) -> Result<()> {
+        let current_path = std::cell::RefCell::new(None);
+        let mut apply_options = git2::ApplyOptions::new();
+        apply_options.check(check);
+        apply_options.delta_callback(|delta| {
+            *current_path.borrow_mut() = delta
+                .and_then(|delta| delta.old_file().path().or(delta.new_file().path()))
+                .map(|path| path.to_path_buf());
+            true
+        });
+        apply_options.hunk_callback(|hunk| match (hunk, current_path.borrow().as_ref()) {
+            (Some(hunk), Some(path)) => accept(&hunk_key(path, &hunk)),
+            _ => false,
+        });
+        self.repo.apply(diff, location, Some(&mut apply_options))?;
+        Ok(())
+    }
+
+    /// Where pushing `branch_name` goes: `remote` (by default its upstream's remote, else
+    /// `origin`), to the upstream branch when it lives there and the same name otherwise
+    fn push_target(
+        &self,
+        branch_name: &str,
+        remote: Option<&str>,
+    ) -> Result<(git2::Remote<'_>, String)> {
+        let remote_name = match remote {
+            Some(remote) => remote.to_string(),
+            None => self
+                .branch_remote(branch_name)
+                .unwrap_or_else(|| "origin".to_string()),
+        };
+        let local_ref = format!("refs/heads/{}", branch_name);
+        let remote_ref = self.remote_branch_ref(branch_name, &remote_name);
+        Ok((
+            self.repo.find_remote(&remote_name)?,
+            format!("{}:{}", local_ref, remote_ref),
+        ))
+    }
+
+    /// The ref a local branch corresponds to on `remote`: its upstream's merge ref when the
+    /// upstream lives there, otherwise the branch of the same name
+    fn remote_branch_ref(&self, branch_name: &str, remote: &str) -> String {
+        let merge = self
+            .repo
+            .config()
+            .and_then(|config| config.get_string(&format!("branch.{}.merge", branch_name)))
+            .ok();
+        match merge {
+            Some(merge) if self.branch_remote(branch_name).as_deref() == Some(remote) => merge,
+            _ => format!("refs/heads/{}", branch_name),
+        }
+    }
+
+    /// Callbacks for fetching from and pushing to `remote`: progress and cancellation go through
+    /// `ui`. Credentials come, for SSH, from the identity file configured for the remote and then
+    /// the SSH agent; for HTTPS from this session's cache, the configured credential helpers and
+    /// then `ui`'s prompt. Each source is tried once, since libgit2 asks again after a rejection.
+    /// A password only joins the cache once `PendingCredential::accept` is called for it.
+    fn remote_callbacks<'a>(
+        &self,
+        remote: &git2::Remote<'_>,
+        ui: &'a mut dyn RemoteUi,
+    ) -> Result<(git2::RemoteCallbacks<'a>, PendingCredential)> {
+        let config = self.repo.config()?;
+        let identity = self.ssh_keys.for_remote(remote.name().unwrap_or_default());
+        let pending = PendingCredential::default();
+        let callbacks = remote_callbacks(
+            config,
+            identity,
+            Arc::clone(&self.credentials),
+            pending.clone(),
+            ui,
+        );
+        Ok((callbacks, pending))
+    }
+
+    /// Add to `.git/BISECT_LOG`, which `git bisect log` and `replay` read
+    fn append_bisect_log(&self, entry: &str) -> Result<()> {
+        use std::io::Write;
+        let mut log = std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(self.repo.path().join("BISECT_LOG"))?;
+        log.write_all(entry.as_bytes())?;
+        Ok(())
+    }
+
+    /// Run the system `git` in this repository for a network operation or maintenance
+    fn run_git(&self, args: &[&str], ui: &mut dyn RemoteUi) -> Result<()> {
+        let dir = self.repo.workdir().unwrap_or_else(|| self.repo.path());
+        run_git(dir, args, ui)
+    }
+}
+
+impl GitBackend for GitRepo {
+    fn reopen(&self) -> Result<Box<dyn GitBackend>> {
+        let repo = Repository::open(self.repo.path())?;
+        Ok(Box::new(Self {
+            repo,
+            ssh_keys: self.ssh_keys.clone(),
+            credentials: Arc::clone(&self.credentials),
+            shell_git: self.shell_git,
+            diff_context: self.diff_context,
+        }))
+    }
+
+    fn unsupported_transport(&self, remote_name: &str) -> Option<String> {
+        if self.uses_shell_git(remote_name) {
+            return None;
+        }
+        let remote = self.repo.find_remote(remote_name).ok()?;
+        let url = remote.url()?.to_string();
+        let version = git2::Version::get();
+
+        let is_https = url.starts_with("https://");
+        let is_ssh = is_ssh_url(&url);
+
+        if is_https && !version.https() {
+            Some(tr!(
+                "Pull/push disabled: libgit2 was built without HTTPS support ({} is {})",
+                remote_name,
+                url
+            ))
+        } else if is_ssh && !version.ssh() {
+            Some(tr!(
+                "Pull/push disabled: libgit2 was built without SSH support ({} is {})",
+                remote_name,
+                url
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn get_current_branch(&self) -> Result<String> {
+        match self.repo.head() {
+            Ok(head) => Ok(head.shorthand().unwrap_or("HEAD").to_string()),
+            // A new repository's HEAD names a branch that doesn't exist until the first commit
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => {
+                let head = self.repo.find_reference("HEAD")?;
+                let target = head.symbolic_target().unwrap_or("HEAD");
+                Ok(target
+                    .strip_prefix("refs/heads/")
+                    .unwrap_or(target)
+                    .to_string())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn get_branches(&self) -> Result<Vec<BranchInfo>> {
+        let mut branches = Vec::new();
+        let current_branch = self.get_current_branch().unwrap_or_default();
+
+        for (branch, _) in self.repo.branches(Some(BranchType::Local))?.flatten() {
+            if let Some(name) = branch.name()? {
+                let (tip_time, tip_subject) = tip_summary(branch.get());
+                let upstream = branch.upstream().ok();
+                let ahead_behind = match (branch.get().target(), &upstream) {
+                    (Some(local), Some(upstream)) => upstream
+                        .get()
+                        .target()
+                        .and_then(|upstream| self.repo.graph_ahead_behind(local, upstream).ok()),
+                    _ => None,
+                };
+                branches.push(BranchInfo {
+                    name: name.to_string(),
+                    is_current: name == current_branch,
+                    remote: None,
+                    upstream: upstream
+                        .as_ref()
+                        .and_then(|upstream| upstream.name().ok().flatten())
+                        .map(str::to_string),
+                    ahead_behind,
+                    tip_time,
+                    tip_subject,
+                });
+            }
+        }
+
+        // Remote-tracking branches follow the local ones; `origin/HEAD` is just an alias
+        for (branch, _) in self.repo.branches(Some(BranchType::Remote))?.flatten() {
+            let reference = branch.get();
+            if reference.kind() == Some(ReferenceType::Symbolic) {
+                continue;
+            }
+            let (Some(name), Some(refname)) = (branch.name()?, reference.name()) else {
+                continue;
+            };
+            let remote = self.repo.branch_remote_name(refname)?;
+            let (tip_time, tip_subject) = tip_summary(reference);
+            branches.push(BranchInfo {
+                name: name.to_string(),
+                is_current: false,
+                remote: remote.as_str().map(str::to_string),
+                upstream: None,
+                ahead_behind: None,
+                tip_time,
+                tip_subject,
+            });
+        }
+
+        Ok(branches)
+    }
+
+    fn get_tags(&self) -> Result<Vec<TagInfo>> {
+        let mut tags = Vec::new();
+
+        for name in self.repo.tag_names(None)?.iter().flatten() {
+            let object = self.repo.revparse_single(&format!("refs/tags/{}", name))?;
+            let message = object.as_tag().map(|tag| {
+                tag.message()
+                    .unwrap_or("")
+                    .lines()
+                    .next()
+                    .unwrap_or("")
+                    .to_string()
+            });
+            let target = match object.peel_to_commit() {
+                Ok(commit) => format!("{:.7}", commit.id()),
+                Err(_) => format!("{:.7}", object.id()),
+            };
+
+            tags.push(TagInfo {
+                name: name.to_string(),
+                target,
+                message,
+            });
+        }
+
+        Ok(tags)
+    }
+
+    fn workdir(&self) -> Option<&Path> {
+        self.repo.workdir()
+    }
+
+    fn ahead_behind(&self, base: &str) -> Result<(usize, usize)> {
+        let head = self.repo.head()?.peel_to_commit()?.id();
+        let base = self.repo.revparse_single(base)?.peel_to_commit()?.id();
+        Ok(self.repo.graph_ahead_behind(head, base)?)
+    }
+
+    fn is_worktree_dirty(&self) -> Result<bool> {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(false).include_ignored(false);
+        let statuses = self.repo.statuses(Some(&mut opts))?;
+        Ok(statuses
+            .iter()
+            .any(|entry| entry.status() != Status::CURRENT))
+    }
+
+    fn detached_head(&self) -> Option<String> {
+        if !self.repo.head_detached().ok()? {
+            return None;
+        }
+        let commit = self.repo.head().ok()?.peel_to_commit().ok()?;
+        Some(format!("{:.7}", commit.id()))
+    }
+
+    fn checkout_commit(&self, id: &str) -> Result<()> {
+        let commit = self.repo.revparse_single(id)?.peel_to_commit()?;
+        self.repo.checkout_tree(commit.as_object(), None)?;
+        self.repo.set_head_detached(commit.id())?;
+        Ok(())
+    }
+
+    fn has_conflicts(&self) -> Result<bool> {
+        Ok(self.repo.index()?.has_conflicts())
+    }
+
+    fn checkout_tag(&self, tag_name: &str) -> Result<()> {
+        let commit = self
+            .repo
+            .revparse_single(&format!("refs/tags/{}", tag_name))?
+            .peel_to_commit()?;
+
+        self.repo.checkout_tree(commit.as_object(), None)?;
+        self.repo.set_head_detached(commit.id())?;
+        Ok(())
+    }
+
+    fn create_tag(&self, name: &str, target: &str, message: Option<&str>) -> Result<()> {
+        let commit = self.repo.revparse_single(target)?.peel_to_commit()?;
+        match message {
+            Some(message) => {
+                let signature = self.repo.signature()?;
+                self.repo
+                    .tag(name, commit.as_object(), &signature, message, false)?;
+            }
+            None => {
+                self.repo.tag_lightweight(name, commit.as_object(), false)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn delete_tag(&self, name: &str) -> Result<()> {
+        self.repo.tag_delete(name)?;
+        Ok(())
+    }
+
+    fn blame(&self, path: &str, revision: &str) -> Result<Vec<BlameLine>> {
+        let commit = self.repo.revparse_single(revision)?.peel_to_commit()?;
+        let blob = commit
+            .tree()?
+            .get_path(Path::new(path))
+            .map_err(|_| anyhow::anyhow!(tr!("{} does not exist in {}", path, revision)))?
+            .to_object(&self.repo)?
+            .peel_to_blob()?;
+        if blob.is_binary() {
+            anyhow::bail!(tr!("{} is a binary file", path));
+        }
+
+        let mut options = git2::BlameOptions::new();
+        options.newest_commit(commit.id());
+        let blame = self.repo.blame_file(Path::new(path), Some(&mut options))?;
+        let content = String::from_utf8_lossy(blob.content());
+        let lines = content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                let hunk = blame.get_line(i + 1);
+                let signature = hunk.as_ref().map(|hunk| hunk.final_signature());
+                BlameLine {
+                    id: hunk
+                        .as_ref()
+                        .map(|hunk| format!("{:.7}", hunk.final_commit_id()))
+                        .unwrap_or_default(),
+                    author: signature
+                        .as_ref()
+                        .and_then(|signature| signature.name().map(str::to_string))
+                        .unwrap_or_default(),
+                    date: signature
+                        .and_then(|signature| {
+                            chrono::DateTime::from_timestamp(signature.when().seconds(), 0)
+                        })
+                        .map(|dt| dt.format("%Y-%m-%d").to_string())
+                        .unwrap_or_default(),
+                    path: hunk
+                        .as_ref()
+                        .and_then(|hunk| hunk.path())
+                        .map_or_else(|| path.to_string(), |p| p.to_string_lossy().into_owned()),
+                    content: line.to_string(),
+                }
+            })
+            .collect();
+        Ok(lines)
+    }
+
+    fn grep(&self, pattern: &str, revision: Option<&str>, limit: usize) -> Result<Vec<GrepMatch>> {
+        let ignore_case = !pattern.chars().any(char::is_uppercase);
+        let needle = if ignore_case {
+            pattern.to_lowercase()
+        } else {
+            pattern.to_string()
+        };
+
+        // Tracked files with their blob at the revision, or read from disk for the working tree
+        let mut files: Vec<(String, Option<Oid>)> = Vec::new();
+        match revision {
+            Some(revision) => {
+                let tree = self.repo.revparse_single(revision)?.peel_to_tree()?;
+                tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+                    if entry.kind() == Some(git2::ObjectType::Blob) {
+                        let name = entry.name().unwrap_or_default();
+                        files.push((format!("{}{}", dir, name), Some(entry.id())));
+                    }
+                    git2::TreeWalkResult::Ok
+                })?;
+            }
+            None => {
+                for entry in self.repo.index()?.iter() {
+                    // Submodules are directories of their own
+                    if entry.mode == 0o160000 {
+                        continue;
+                    }
+                    files.push((String::from_utf8_lossy(&entry.path).into_owned(), None));
+                }
+                files.dedup_by(|a, b| a.0 == b.0);
+            }
+        }
+
+        let workdir = self.repo.workdir();
+        let mut matches = Vec::new();
+        for (path, blob) in files {
+            let data = match blob {
+                Some(id) => self.repo.find_blob(id)?.content().to_vec(),
+                None => match workdir.and_then(|dir| std::fs::read(dir.join(&path)).ok()) {
+                    Some(data) => data,
+                    // Deleted from the working tree
+                    None => continue,
+                },
+            };
+            if data[..data.len().min(8000)].contains(&0) {
+                continue;
+            }
+            for (number, line) in String::from_utf8_lossy(&data).lines().enumerate() {
+                let found = if ignore_case {
+                    line.to_lowercase().contains(&needle)
+                } else {
+                    line.contains(&needle)
+                };
+                if !found {
+                    continue;
+                }
+                matches.push(GrepMatch {
+                    path: path.clone(),
+                    line: number + 1,
+                    text: line.trim_end().to_string(),
+                });
+                if matches.len() >= limit {
+                    return Ok(matches);
+                }
+            }
+        }
+        Ok(matches)
+    }
+
+    fn file_text(&self, path: &str, revision: Option<&str>) -> Result<String> {
+        let data = match revision {
+            Some(revision) => {
+                let tree = self.repo.revparse_single(revision)?.peel_to_tree()?;
+                let entry = tree.get_path(Path::new(path))?;
+                self.repo.find_blob(entry.id())?.content().to_vec()
+            }
+            None => {
+                let dir = self.repo.workdir().unwrap_or_else(|| self.repo.path());
+                std::fs::read(dir.join(path))?
+            }
+        };
+        Ok(String::from_utf8_lossy(&data).into_owned())
+    }
+
+    fn edit_file(&self, path: &str, line: usize) -> Result<()> {
+        let config = self.repo.config()?;
+        let editor = std::env::var("GIT_EDITOR")
+            .ok()
+            .or_else(|| config.get_string("core.editor").ok())
+            .or_else(|| std::env::var("VISUAL").ok())
+            .or_else(|| std::env::var("EDITOR").ok())
+            .filter(|editor| !editor.trim().is_empty())
+            .unwrap_or_else(|| "vi".to_string());
+        let dir = self.repo.workdir().unwrap_or_else(|| self.repo.path());
+        // Through the shell, as git runs it, so an editor setting can carry arguments
+        let status = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$@\"", editor))
+            .arg(&editor)
+            .arg(format!("+{}", line))
+            .arg(path)
+            .current_dir(dir)
+            .status()
+            .map_err(|e| anyhow::anyhow!(tr!("Failed to run {}: {}", editor, e)))?;
+        if !status.success() {
+            anyhow::bail!(tr!("{} exited with {}", editor, status));
+        }
+        Ok(())
+    }
+
+    fn bisect_mark(&self, id: &str, mark: BisectMark) -> Result<BisectStep> {
+        let commit = self.repo.revparse_single(id)?.peel_to_commit()?;
+        let git_dir = self.repo.path();
+        let start = git_dir.join("BISECT_START");
+        if !start.exists() {
+            let head = self.repo.head()?;
+            let original = if head.is_branch() {
+                head.shorthand().unwrap_or_default().to_string()
+            } else {
+                head.peel_to_commit()?.id().to_string()
+            };
+            std::fs::write(&start, format!("{}\n", original))?;
+            std::fs::write(git_dir.join("BISECT_TERMS"), "bad\ngood\n")?;
+            std::fs::write(git_dir.join("BISECT_NAMES"), "\n")?;
+            std::fs::write(git_dir.join("BISECT_LOG"), "git bisect start\n")?;
+        }
+
+        let (term, reference) = match mark {
+            BisectMark::Good => ("good", format!("refs/bisect/good-{}", commit.id())),
+            BisectMark::Bad => ("bad", "refs/bisect/bad".to_string()),
+            BisectMark::Skip => ("skip", format!("refs/bisect/skip-{}", commit.id())),
+        };
+        self.repo
+            .reference(&reference, commit.id(), true, &format!("bisect {}", term))?;
+        self.append_bisect_log(&format!(
+            "# {}: [{}] {}\ngit bisect {} {}\n",
+            term,
+            commit.id(),
+            commit.summary().unwrap_or(""),
+            term,
+            commit.id()
+        ))?;
+
+        let step = self.bisect_status()?;
+        match &step {
+            BisectStep::Testing { commit, .. } => self.checkout_commit(commit)?,
+            BisectStep::Found(id) => {
+                let culprit = self.repo.revparse_single(id)?.peel_to_commit()?;
+                self.append_bisect_log(&format!(
+                    "# first bad commit: [{}] {}\n",
+                    culprit.id(),
+                    culprit.summary().unwrap_or("")
+                ))?;
+            }
+            _ => {}
+        }
+        Ok(step)
+    }
+
+    fn bisect_status(&self) -> Result<BisectStep> {
+        let mut bad = None;
+        let mut good = Vec::new();
+        let mut skipped = HashSet::new();
+        for reference in self.repo.references_glob("refs/bisect/*")? {
+            let reference = reference?;
+            let (Some(name), Some(id)) = (reference.name(), reference.target()) else {
+                continue;
+            };
+            if name == "refs/bisect/bad" {
+                bad = Some(id);
+            } else if name.starts_with("refs/bisect/good-") {
+                good.push(id);
+            } else if name.starts_with("refs/bisect/skip-") {
+                skipped.insert(id);
+            }
+        }
+        let Some(bad) = bad else {
+            return Ok(BisectStep::NeedBad);
+        };
+        if good.is_empty() {
+            return Ok(BisectStep::NeedGood);
+        }
+
+        // The suspects below a commit: those it contains that no good commit does
+        let suspects_below = |tip: Oid| -> Result<Vec<Oid>> {
+            let mut walk = self.repo.revwalk()?;
+            walk.push(tip)?;
+            for id in &good {
+                walk.hide(*id)?;
+            }
+            Ok(walk.collect::<Result<Vec<_>, _>>()?)
+        };
+        let suspects = suspects_below(bad)?;
+        let short = |id: &Oid| format!("{:.7}", id);
+        match suspects.len() {
+            0 => anyhow::bail!(tr!("The bad commit is contained in a good one")),
+            1 => return Ok(BisectStep::Found(short(&bad))),
+            _ => {}
+        }
+
+        // Test the suspect that splits them most evenly: if it's bad, only the ones it contains
+        // are left, if it's good, only the others. Counting is quadratic, so long ranges only
+        // weigh a sample of the suspects.
+        let total = suspects.len();
+        let testable: Vec<Oid> = suspects
+            .iter()
+            .copied()
+            .filter(|id| *id != bad && !skipped.contains(id))
+            .collect();
+        let mut best: Option<(Oid, usize)> = None;
+        for &id in testable.iter().step_by((testable.len() / 256).max(1)) {
+            let below = suspects_below(id)?.len();
+            let balance = below.min(total - below);
+            if best.is_none_or(|(_, best_balance)| balance > best_balance) {
+                best = Some((id, balance));
+            }
+        }
+        Ok(match best {
+            Some((id, _)) => BisectStep::Testing {
+                commit: short(&id),
+                remaining: total,
+                steps: total.ilog2(),
+            },
+            None => BisectStep::Inconclusive(suspects.iter().map(short).collect()),
+        })
+    }
+
+    fn bisect_reset(&self) -> Result<String> {
+        let git_dir = self.repo.path();
+        let original = std::fs::read_to_string(git_dir.join("BISECT_START"))
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        if !original.is_empty() {
+            self.checkout_branch(&original, false)?;
+        }
+        let names: Vec<String> = self
+            .repo
+            .references_glob("refs/bisect/*")?
+            .filter_map(|reference| reference.ok()?.name().map(str::to_string))
+            .collect();
+        for name in names {
+            self.repo.find_reference(&name)?.delete()?;
+        }
+        for file in [
+            "BISECT_START",
+            "BISECT_LOG",
+            "BISECT_TERMS",
+            "BISECT_NAMES",
+            "BISECT_EXPECTED_REV",
+            "BISECT_ANCESTORS_OK",
+        ] {
+            let _ = std::fs::remove_file(git_dir.join(file));
+        }
+        Ok(original)
+    }
+
+    fn worktrees(&self) -> Result<Vec<WorktreeInfo>> {
+        let current = self.repo.workdir().and_then(|dir| dir.canonicalize().ok());
+        let is_current = |path: &Path| current.is_some() && path.canonicalize().ok() == current;
+        let branch_of = |repo: &Repository| {
+            repo.head()
+                .ok()
+                .filter(|head| head.is_branch())
+                .and_then(|head| head.shorthand().map(str::to_string))
+        };
+
+        let mut worktrees = Vec::new();
+        // A linked worktree's git dir is `<common dir>/worktrees/<name>`
+        let common_dir = if self.repo.is_worktree() {
+            self.repo
+                .path()
+                .ancestors()
+                .nth(2)
+                .unwrap_or(self.repo.path())
+        } else {
+            self.repo.path()
+        };
+        let main = Repository::open(common_dir)?;
+        if let Some(path) = main.workdir() {
+            worktrees.push(WorktreeInfo {
+                name: None,
+                path: path.to_path_buf(),
+                branch: branch_of(&main),
+                locked: None,
+                prunable: false,
+                is_current: is_current(path),
+            });
+        }
+        for name in self.repo.worktrees()?.iter().flatten() {
+            let worktree = self.repo.find_worktree(name)?;
+            let locked = match worktree.is_locked()? {
+                git2::WorktreeLockStatus::Locked(reason) => Some(reason.unwrap_or_default()),
+                git2::WorktreeLockStatus::Unlocked => None,
+            };
+            let prunable = worktree.validate().is_err();
+            let branch = if prunable {
+                None
+            } else {
+                Repository::open_from_worktree(&worktree)
+                    .ok()
+                    .and_then(|repo| branch_of(&repo))
+            };
+            worktrees.push(WorktreeInfo {
+                name: Some(name.to_string()),
+                path: worktree.path().to_path_buf(),
+                branch,
+                locked,
+                prunable,
+                is_current: is_current(worktree.path()),
+            });
+        }
+        Ok(worktrees)
+    }
+
+    fn add_worktree(&self, branch: &str, path: &Path) -> Result<()> {
+        let branch = match self.repo.find_branch(branch, BranchType::Local) {
+            Ok(branch) => branch,
+            Err(_) => {
+                let head = self.repo.head()?.peel_to_commit()?;
+                self.repo.branch(branch, &head, false)?
+            }
+        };
+        // The worktree's name under .git/worktrees is its directory's, as git does
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow::anyhow!(tr!("{} is not a usable path", path.display())))?;
+        let mut options = git2::WorktreeAddOptions::new();
+        options.reference(Some(branch.get()));
+        self.repo.worktree(name, path, Some(&options))?;
+        Ok(())
+    }
+
+    fn remove_worktree(&self, name: &str) -> Result<()> {
+        let worktree = self.repo.find_worktree(name)?;
+        if let git2::WorktreeLockStatus::Locked(_) = worktree.is_locked()? {
+            anyhow::bail!(tr!(
+                "{} is locked; unlock it with git worktree unlock first",
+                name
+            ));
+        }
+        if worktree.validate().is_ok() {
+            let repo = Repository::open_from_worktree(&worktree)?;
+            let mut opts = StatusOptions::new();
+            opts.include_untracked(true).include_ignored(false);
+            if !repo.statuses(Some(&mut opts))?.is_empty() {
+                anyhow::bail!(tr!("{} has uncommitted or untracked changes", name));
+            }
+        }
+        let mut options = git2::WorktreePruneOptions::new();
+        options.valid(true).working_tree(true);
+        worktree.prune(Some(&mut options))?;
+        Ok(())
+    }
+
+    fn submodules(&self) -> Result<Vec<SubmoduleInfo>> {
+        let config = self.repo.config()?.snapshot()?;
+        // libgit2 lets `.git/config` override the URL it reports, so read `.gitmodules` itself
+        let gitmodules = self
+            .repo
+            .workdir()
+            .and_then(|dir| git2::Config::open(&dir.join(".gitmodules")).ok());
+        let mut submodules = Vec::new();
+        for submodule in self.repo.submodules()? {
+            let name = submodule.name().unwrap_or_default().to_string();
+            let status = self
+                .repo
+                .submodule_status(&name, git2::SubmoduleIgnore::None)?;
+            let key = format!("submodule.{}.url", name);
+            let url = gitmodules
+                .as_ref()
+                .and_then(|gitmodules| gitmodules.get_string(&key).ok())
+                .or_else(|| submodule.url().map(str::to_string));
+            let configured_url = config.get_string(&key).ok();
+            submodules.push(SubmoduleInfo {
+                path: submodule.path().to_string_lossy().into_owned(),
+                initialized: configured_url.is_some(),
+                checked_out: !status.contains(git2::SubmoduleStatus::WD_UNINITIALIZED)
+                    && submodule.open().is_ok(),
+                out_of_date: status.contains(git2::SubmoduleStatus::WD_MODIFIED),
+                dirty: status.intersects(
+                    git2::SubmoduleStatus::WD_INDEX_MODIFIED
+                        | git2::SubmoduleStatus::WD_WD_MODIFIED
+                        | git2::SubmoduleStatus::WD_UNTRACKED,
+                ),
+                url_changed: configured_url.is_some_and(|configured| Some(configured) != url),
+                name,
+                url,
+            });
+        }
+        submodules.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(submodules)
+    }
+
+    fn init_submodule(&self, name: &str) -> Result<()> {
+        self.repo.find_submodule(name)?.init(false)?;
+        Ok(())
+    }
+
+    fn update_submodule(&self, name: &str, ui: &mut dyn RemoteUi) -> Result<()> {
+        let mut submodule = self.repo.find_submodule(name)?;
+        let url = submodule.url().unwrap_or_default();
+        if self.shell_git || (is_ssh_url(url) && custom_ssh_command(&self.repo.config()?)) {
+            let path = submodule.path().to_string_lossy().into_owned();
+            return self.run_git(
+                &["submodule", "update", "--init", "--progress", "--", &path],
+                ui,
+            );
+        }
+        let config = self.repo.config()?;
+        let identity = self.ssh_keys.for_remote("origin");
+        let pending = PendingCredential::default();
+        let credentials = Arc::clone(&self.credentials);
+        let callbacks = remote_callbacks(config, identity, credentials, pending.clone(), ui);
+        let mut fetch_options = git2::FetchOptions::new();
+        fetch_options.remote_callbacks(callbacks);
+        let mut options = git2::SubmoduleUpdateOptions::new();
+        options.fetch(fetch_options);
+        submodule.update(true, Some(&mut options))?;
+        pending.accept(&self.credentials);
+        Ok(())
+    }
+
+    fn sync_submodule(&self, name: &str) -> Result<()> {
+        self.repo.find_submodule(name)?.sync()?;
+        Ok(())
+    }
+
+    fn get_revision_candidates(&self, recent_commits: usize) -> Result<Vec<String>> {
+        let mut candidates = Vec::new();
+
+        for (branch, _) in self.repo.branches(Some(BranchType::Local))?.flatten() {
+            if let Some(name) = branch.name()? {
+                candidates.push(name.to_string());
+            }
+        }
+
+        for (branch, _) in self.repo.branches(Some(BranchType::Remote))?.flatten() {
+            if let Some(name) = branch.name()? {
+                if !name.ends_with("/HEAD") {
+                    candidates.push(name.to_string());
+                }
+            }
+        }
+
+        for name in self.repo.tag_names(None)?.iter().flatten() {
+            candidates.push(name.to_string());
+        }
+
+        if let Ok(mut revwalk) = self.repo.revwalk() {
+            if revwalk.push_head().is_ok() {
+                for oid in revwalk.take(recent_commits).flatten() {
+                    candidates.push(format!("{:.7}", oid));
+                }
+            }
+        }
+
+        Ok(candidates)
+    }
+
+    fn resolve_revision(&self, revision: &str) -> Result<String> {
+        let commit = self.repo.revparse_single(revision)?.peel_to_commit()?;
+        Ok(format!("{:.7}", commit.id()))
+    }
+
+    fn count_commits(&self) -> Result<usize> {
+        if self.head_unborn() {
+            return Ok(0);
+        }
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push_head()?;
+        Ok(revwalk.count())
+    }
+
+    fn get_commits(&self, limit: usize, filter: HistoryFilter) -> Result<Vec<CommitInfo>> {
+        let mut cursor = self.history_cursor(filter)?;
+        let mut commits = self.get_commit_page(&mut cursor, limit)?;
+
+        graph::layout(&mut commits);
+
+        Ok(commits)
+    }
+
+    fn history_cursor(&self, filter: HistoryFilter) -> Result<HistoryCursor> {
+        let mut revwalk = self.repo.revwalk()?;
+
+        if !self.head_unborn() {
+            revwalk.push_head()?;
+        }
+        if filter.all_branches {
+            for (branch, _) in self.repo.branches(None)?.flatten() {
+                if let Some(oid) = branch.get().target() {
+                    let _ = revwalk.push(oid);
+                }
+            }
+        }
+
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+        if filter.first_parent {
+            revwalk.simplify_first_parent()?;
+        }
+
+        // A topological walk visits the whole history before yielding its first commit anyway,
+        // so keeping the ids costs little and saves walking again for every page
+        Ok(HistoryCursor {
+            ids: revwalk.collect::<Result<_, _>>()?,
+            next: 0,
+            first_parent: filter.first_parent,
+            decorations: self.get_ref_decorations()?,
+        })
+    }
 
-use crate::app::{BranchInfo, CommitInfo, FileStatus};
+    fn get_commit_page(&self, cursor: &mut HistoryCursor, limit: usize) -> Result<Vec<CommitInfo>> {
+        let end = cursor.ids.len().min(cursor.next + limit);
+        let mut commits = Vec::with_capacity(end - cursor.next);
+
+        for &oid in &cursor.ids[cursor.next..end] {
+            let commit = self.repo.find_commit(oid)?;
+
+            let author = commit.author();
+            let date = chrono::DateTime::from_timestamp(commit.time().seconds(), 0)
+                .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default();
+
+            // Merged-in parents are never walked in first-parent mode, so don't draw lanes for them
+            let parent_count = if cursor.first_parent { 1 } else { usize::MAX };
+            let parent_ids: Vec<String> = commit
+                .parent_ids()
+                .take(parent_count)
+                .map(|id| format!("{:.7}", id))
+                .collect();
+
+            let (branches, tags) = cursor.decorations.get(&oid).cloned().unwrap_or_default();
+            let oid_str = format!("{:.7}", oid);
+
+            commits.push(CommitInfo {
+                id: oid_str,
+                author: author.name().unwrap_or("Unknown").to_string(),
+                date,
+                message: commit
+                    .message()
+                    .unwrap_or("")
+                    .lines()
+                    .next()
+                    .unwrap_or("")
+                    .to_string(),
+                parent_ids,
+                graph_info: None,
+                branches,
+                tags,
+            });
+        }
+        cursor.next = end;
+
+        Ok(commits)
+    }
+
+    fn get_commit_detail(&self, id: &str) -> Result<CommitDetail> {
+        let commit = self.repo.revparse_single(id)?.peel_to_commit()?;
+
+        let format_signature = |sig: &git2::Signature| {
+            let name = sig.name().unwrap_or("Unknown");
+            match sig.email() {
+                Some(email) => format!("{} <{}>", name, email),
+                None => name.to_string(),
+            }
+        };
+        let format_time = |time: git2::Time| {
+            chrono::DateTime::from_timestamp(time.seconds(), 0)
+                .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default()
+        };
+
+        // Compare against the first parent (or an empty tree for the root commit)
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        let diff = self
+            .repo
+            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+
+        let mut files = Vec::new();
+        for (idx, delta) in diff.deltas().enumerate() {
+            let path = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            let (insertions, deletions) = match git2::Patch::from_diff(&diff, idx)? {
+                Some(patch) => {
+                    let (_, insertions, deletions) = patch.line_stats()?;
+                    (insertions, deletions)
+                }
+                None => (0, 0),
+            };
+
+            files.push(CommitFileChange {
+                path,
+                status: delta_status_char(delta.status()),
+                insertions,
+                deletions,
+            });
+        }
+
+        let author = commit.author();
+        let committer = commit.committer();
+
+        Ok(CommitDetail {
+            id: commit.id().to_string(),
+            author: format_signature(&author),
+            author_date: format_time(author.when()),
+            committer: format_signature(&committer),
+            commit_date: format_time(committer.when()),
+            parent_ids: commit.parent_ids().map(|id| format!("{:.7}", id)).collect(),
+            message: commit.message().unwrap_or("").trim_end().to_string(),
+            files,
+        })
+    }
+
+    fn get_commit_diff(&self, id: &str, ignore_whitespace: bool) -> Result<String> {
+        let commit = self.repo.revparse_single(id)?.peel_to_commit()?;
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        let mut diff = self.repo.diff_tree_to_tree(
+            parent_tree.as_ref(),
+            Some(&tree),
+            Some(&mut diff_options(ignore_whitespace, self.diff_context)),
+        )?;
+        find_renames(&mut diff)?;
+
+        let mut diff_text = String::new();
+        append_patch_text(&self.repo, &diff, &mut diff_text, None)?;
+
+        if diff_text.is_empty() {
+            diff_text = format!("No changes in commit {:.7}", commit.id());
+        }
+
+        Ok(diff_text)
+    }
+
+    fn get_range_diff(&self, from: &str, to: &str, ignore_whitespace: bool) -> Result<String> {
+        let from_tree = self.repo.revparse_single(from)?.peel_to_commit()?.tree()?;
+        let to_tree = self.repo.revparse_single(to)?.peel_to_commit()?.tree()?;
+        let mut diff = self.repo.diff_tree_to_tree(
+            Some(&from_tree),
+            Some(&to_tree),
+            Some(&mut diff_options(ignore_whitespace, self.diff_context)),
+        )?;
+        find_renames(&mut diff)?;
+
+        let stats = diff.stats()?.to_buf(git2::DiffStatsFormat::FULL, 80)?;
+        let mut diff_text = stats.as_str().unwrap_or("").to_string();
+        diff_text.push('\n');
+        append_patch_text(&self.repo, &diff, &mut diff_text, None)?;
+        Ok(diff_text)
+    }
+
+    fn format_patch(
+        &self,
+        from: Option<&str>,
+        to: &str,
+        dir: &Path,
+    ) -> Result<Vec<std::path::PathBuf>> {
+        let to = self.repo.revparse_single(to)?.peel_to_commit()?;
+        let commits = match from {
+            Some(from) => {
+                let from = self.repo.revparse_single(from)?.peel_to_commit()?;
+                let mut revwalk = self.repo.revwalk()?;
+                revwalk.push(to.id())?;
+                revwalk.hide(from.id())?;
+                revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+                revwalk
+                    .map(|oid| Ok(self.repo.find_commit(oid?)?))
+                    .collect::<Result<Vec<_>>>()?
+            }
+            None => vec![to],
+        };
+        if let Some(merge) = commits.iter().find(|commit| commit.parent_count() > 1) {
+            anyhow::bail!(tr!(
+                "{} is a merge commit and can't be exported as a patch",
+                format!("{:.7}", merge.id())
+            ));
+        }
+
+        std::fs::create_dir_all(dir)?;
+        let mut written = Vec::new();
+        for (i, commit) in commits.iter().enumerate() {
+            let parent_tree = match commit.parent(0) {
+                Ok(parent) => Some(parent.tree()?),
+                Err(_) => None,
+            };
+            let diff =
+                self.repo
+                    .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+            let summary = commit.summary().unwrap_or("");
+            let email = git2::Email::from_diff(
+                &diff,
+                i + 1,
+                commits.len(),
+                &commit.id(),
+                summary,
+                commit.body().unwrap_or(""),
+                &commit.author(),
+                &mut git2::EmailCreateOptions::new(),
+            )?;
+
+            let path = dir.join(patch_file_name(i + 1, summary));
+            std::fs::write(&path, email.as_slice())?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    fn get_commit_stats(&self, id: &str) -> Result<CommitStats> {
+        let commit = self.repo.revparse_single(id)?.peel_to_commit()?;
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        let stats = self
+            .repo
+            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?
+            .stats()?;
+
+        Ok(CommitStats {
+            full_id: commit.id().to_string(),
+            files_changed: stats.files_changed(),
+            insertions: stats.insertions(),
+            deletions: stats.deletions(),
+        })
+    }
+
+    fn get_status(&self) -> Result<Vec<FileStatus>> {
+        let mut files = Vec::new();
+        let mut opts = StatusOptions::new();
+        // Like `git status`, only staged renames are paired up
+        opts.include_untracked(true).renames_head_to_index(true);
+
+        let statuses = self.repo.statuses(Some(&mut opts))?;
+        let staged_counts = self.line_counts(true).unwrap_or_default();
+        let unstaged_counts = self.line_counts(false).unwrap_or_default();
+        let mut unstaged_files = Vec::new();
+
+        for entry in statuses.iter() {
+            let mut path = entry.path().unwrap_or("").to_string();
+            let mut old_path = None;
+            if entry.status().contains(Status::INDEX_RENAMED) {
+                // The entry's own path is the one it was renamed from
+                if let Some(new_path) = entry.head_to_index().and_then(|d| d.new_file().path()) {
+                    old_path = Some(std::mem::replace(
+                        &mut path,
+                        new_path.to_string_lossy().into_owned(),
+                    ));
+                }
+            }
+            let status = status_code(entry.status());
+            let (index, worktree) = match status.as_str() {
+                "??" | "UU" => (false, true),
+                _ => (!status.starts_with(' '), !status.ends_with(' ')),
+            };
+            for (staged, listed, counts) in [
+                (true, index, &staged_counts),
+                (false, worktree, &unstaged_counts),
+            ] {
+                if !listed {
+                    continue;
+                }
+                let (additions, deletions) = counts.get(&path).copied().unwrap_or_default();
+                let file = FileStatus {
+                    path: path.clone(),
+                    old_path: old_path.clone(),
+                    status: status.clone(),
+                    staged,
+                    additions,
+                    deletions,
+                };
+                if staged {
+                    files.push(file);
+                } else {
+                    unstaged_files.push(file);
+                }
+            }
+        }
+
+        files.extend(unstaged_files);
+        Ok(files)
+    }
+
+    fn get_diff_for_file(
+        &self,
+        path: &str,
+        staged: bool,
+        ignore_whitespace: bool,
+        max_lines: Option<usize>,
+    ) -> Result<String> {
+        let mut diff_text = String::new();
+
+        // A conflicted file has no diff to speak of; show it with its conflict markers
+        if self
+            .repo
+            .status_file(Path::new(path))
+            .is_ok_and(|status| status.contains(Status::CONFLICTED))
+        {
+            diff_text.push_str(&format!("Conflicted: {}\n", path));
+            let workdir = self.repo.workdir().unwrap_or_else(|| self.repo.path());
+            let file = workdir.join(path);
+            match std::fs::metadata(&file) {
+                Ok(meta) if meta.len() > BIG_FILE_THRESHOLD => {
+                    diff_text.push_str(&binary_summary(meta.len(), "conflicted"))
+                }
+                Ok(_) => match std::fs::read(&file) {
+                    Ok(content) if is_binary(&content) => {
+                        diff_text.push_str(&binary_summary(content.len() as u64, "conflicted"))
+                    }
+                    Ok(content) => diff_text.push_str(&String::from_utf8_lossy(&content)),
+                    Err(_) => diff_text.push_str("(deleted on one side)\n"),
+                },
+                Err(_) => diff_text.push_str("(deleted on one side)\n"),
+            }
+            return Ok(diff_text);
+        }
+
+        // A submodule's diff is the commit its gitlink points at, plus any local changes
+        if let Some(text) = self.submodule_diff(path, staged) {
+            return Ok(text);
+        }
+
+        // Check if file is untracked
+        let file_path = Path::new(path);
+        if !staged && file_path.exists() {
+            let mut opts = StatusOptions::new();
+            opts.pathspec(path).include_untracked(true);
+            let statuses = self.repo.statuses(Some(&mut opts))?;
+
+            if let Some(entry) = statuses.get(0) {
+                if entry.status().contains(Status::WT_NEW) {
+                    if let Ok(target) = std::fs::read_link(file_path) {
+                        diff_text.push_str(&format!("New file: {}\n", path));
+                        diff_text.push_str("new file mode 120000 (symbolic link)\n");
+                        diff_text.push_str("--- /dev/null\n");
+                        diff_text.push_str(&format!("+++ {}\n", path));
+                        diff_text.push_str("@@ -0,0 +1 @@\n");
+                        diff_text.push_str(&format!("+-> {}\n", target.display()));
+                        return Ok(diff_text);
+                    }
+                    // For untracked files, show the content as all new lines; big or binary
+                    // ones only by size, without reading them whole
+                    let size = file_path.metadata().map_or(0, |meta| meta.len());
+                    if size > BIG_FILE_THRESHOLD {
+                        diff_text.push_str(&format!("New file: {}\n", path));
+                        diff_text.push_str(&binary_summary(size, "added"));
+                        return Ok(diff_text);
+                    }
+                    if let Ok(content) = std::fs::read(file_path) {
+                        diff_text.push_str(&format!("New file: {}\n", path));
+                        if has_image_extension(path) && image_info(&content).is_some() {
+                            let new = describe_image(Some(&content), size);
+                            diff_text.push_str(&format!("Image added: {}\n", new));
+                            return Ok(diff_text);
+                        }
+                        if is_binary(&content) {
+                            diff_text.push_str(&binary_summary(size, "added"));
+                            return Ok(diff_text);
+                        }
+                        let content = String::from_utf8_lossy(&content);
+                        diff_text.push_str("--- /dev/null\n");
+                        diff_text.push_str(&format!("+++ {}\n", path));
+                        let line_count = content.lines().count();
+                        diff_text.push_str(&format!("@@ -0,0 +1,{} @@\n", line_count));
+                        let shown = max_lines.map_or(line_count, |max| max.min(line_count));
+                        for line in content.lines().take(shown) {
+                            diff_text.push('+');
+                            diff_text.push_str(line);
+                            diff_text.push('\n');
+                        }
+                        push_omitted_note(&mut diff_text, line_count - shown);
+                        return Ok(diff_text);
+                    }
+                }
+            }
+        }
+
+        // Get the diff for the working directory changes
+        let mut opts = diff_options(ignore_whitespace, self.diff_context);
+        opts.pathspec(path);
+        opts.include_untracked(true);
+
+        let mut omitted = 0;
+        if !staged {
+            let diff = self.repo.diff_index_to_workdir(None, Some(&mut opts))?;
+            omitted = append_patch_text(&self.repo, &diff, &mut diff_text, max_lines)?;
+        }
+
+        if diff_text.is_empty() {
+            // Try staged changes; before the first commit, everything staged is new
+            let head_tree = self.repo.head().and_then(|head| head.peel_to_tree());
+            if head_tree.is_ok() || self.head_unborn() {
+                // A staged rename needs its old path in the diff to pair up with
+                if let Some(old_path) = self.staged_rename_source(path) {
+                    opts.pathspec(old_path);
+                }
+                let mut diff =
+                    self.repo
+                        .diff_tree_to_index(head_tree.ok().as_ref(), None, Some(&mut opts))?;
+                find_renames(&mut diff)?;
+
+                omitted = append_patch_text(&self.repo, &diff, &mut diff_text, max_lines)?;
+            }
+        }
+        push_omitted_note(&mut diff_text, omitted);
+
+        if diff_text.is_empty() {
+            diff_text = format!("No changes to display for: {}", path);
+        }
+
+        Ok(diff_text)
+    }
+
+    fn stage_file(&self, path: &str) -> Result<()> {
+        let mut index = self.repo.index()?;
+        index.add_path(Path::new(path))?;
+        index.write()?;
+        Ok(())
+    }
+
+    fn stage_all(&self) -> Result<()> {
+        let mut index = self.repo.index()?;
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+        Ok(())
+    }
+
+    fn unstage_file(&self, path: &str) -> Result<()> {
+        let mut paths = vec![path.to_string()];
+        paths.extend(self.staged_rename_source(path));
+        match self.repo.head().and_then(|head| head.peel_to_commit()) {
+            Ok(head) => self.repo.reset_default(Some(head.as_object()), &paths)?,
+            Err(_) => {
+                // Before the first commit there is nothing to go back to
+                let mut index = self.repo.index()?;
+                for path in &paths {
+                    index.remove_path(Path::new(path))?;
+                }
+                index.write()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn discard_file(&self, path: &str, keep_staged: bool) -> Result<()> {
+        // Check if the file is untracked (newly created)
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true);
+        let statuses = self.repo.statuses(Some(&mut opts))?;
+
+        let mut is_untracked = false;
+        for entry in statuses.iter() {
+            if let Some(entry_path) = entry.path() {
+                if entry_path == path && entry.status().contains(Status::WT_NEW) {
+                    is_untracked = true;
+                    break;
+                }
+            }
+        }
+
+        if is_untracked {
+            // Delete the untracked file from filesystem
+            let workdir = self
+                .repo
+                .workdir()
+                .ok_or_else(|| anyhow::anyhow!(tr!("No working directory")))?;
+            let file_path = workdir.join(path);
+            std::fs::remove_file(&file_path)?;
+        } else if keep_staged {
+            // Checkout the file from the index to discard only the unstaged changes
+            let mut checkout_builder = git2::build::CheckoutBuilder::new();
+            checkout_builder.path(path);
+            checkout_builder.force();
+
+            self.repo
+                .checkout_index(None, Some(&mut checkout_builder))?;
+        } else {
+            // Checkout the file from HEAD to discard changes
+            let head = self.repo.head()?;
+            let tree = head.peel_to_tree()?;
+
+            let mut checkout_builder = git2::build::CheckoutBuilder::new();
+            checkout_builder.path(path);
+            checkout_builder.force();
+
+            self.repo
+                .checkout_tree(tree.as_object(), Some(&mut checkout_builder))?;
+        }
+        Ok(())
+    }
+
+    fn discard_all(&self) -> Result<()> {
+        // First, remove all untracked files
+        let workdir = self
+            .repo
+            .workdir()
+            .ok_or_else(|| anyhow::anyhow!(tr!("No working directory")))?;
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true);
+        let statuses = self.repo.statuses(Some(&mut opts))?;
+
+        for entry in statuses.iter() {
+            if let Some(path) = entry.path() {
+                if entry.status().contains(Status::WT_NEW) {
+                    let file_path = workdir.join(path);
+                    let _ = std::fs::remove_file(&file_path);
+                }
+            }
+        }
+
+        // Then checkout all tracked files from HEAD to discard changes
+        let head = self.repo.head()?;
+        let tree = head.peel_to_tree()?;
+
+        let mut checkout_builder = git2::build::CheckoutBuilder::new();
+        checkout_builder.force();
+
+        self.repo
+            .checkout_tree(tree.as_object(), Some(&mut checkout_builder))?;
+        Ok(())
+    }
+
+    fn checkout_file_from(&self, revision: &str, path: &str) -> Result<()> {
+        let tree = self.repo.revparse_single(revision)?.peel_to_tree()?;
+        if tree.get_path(Path::new(path)).is_err() {
+            anyhow::bail!(tr!("{} does not exist in {}", path, revision));
+        }
+
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.path(path).force().update_index(false);
+        self.repo
+            .checkout_tree(tree.as_object(), Some(&mut checkout))?;
+        Ok(())
+    }
+
+    fn add_to_gitignore(&self, pattern: &str) -> Result<()> {
+        let workdir = self
+            .repo
+            .workdir()
+            .ok_or_else(|| anyhow::anyhow!(tr!("No working directory")))?;
+        let gitignore = workdir.join(".gitignore");
+        let mut content = match std::fs::read_to_string(&gitignore) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        if content.lines().any(|line| line.trim_end() == pattern) {
+            anyhow::bail!(tr!("{} is already in .gitignore", pattern));
+        }
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(pattern);
+        content.push('\n');
+        std::fs::write(gitignore, content)?;
+        Ok(())
+    }
+
+    fn resolve_conflict(&self, path: &str, theirs: bool) -> Result<()> {
+        let mut index = self.repo.index()?;
+        let conflict = index
+            .conflicts()?
+            .flatten()
+            .find(|conflict| {
+                [&conflict.ancestor, &conflict.our, &conflict.their]
+                    .into_iter()
+                    .flatten()
+                    .any(|entry| entry.path == path.as_bytes())
+            })
+            .ok_or_else(|| anyhow::anyhow!(tr!("{} has no conflict to resolve", path)))?;
+        let side = if theirs { conflict.their } else { conflict.our };
+
+        if side.is_some() {
+            let mut checkout = git2::build::CheckoutBuilder::new();
+            checkout.path(path).force();
+            if theirs {
+                checkout.use_theirs(true);
+            } else {
+                checkout.use_ours(true);
+            }
+            self.repo
+                .checkout_index(Some(&mut index), Some(&mut checkout))?;
+            index.add_path(Path::new(path))?;
+        } else {
+            let workdir = self.repo.workdir().unwrap_or_else(|| self.repo.path());
+            let file = workdir.join(path);
+            if file.exists() {
+                std::fs::remove_file(file)?;
+            }
+            index.remove_path(Path::new(path))?;
+        }
+        index.write()?;
+        Ok(())
+    }
 
-pub struct GitRepo {
-    repo: Repository,
-}
+    fn write_resolution(&self, path: &str, content: &str) -> Result<()> {
+        let workdir = self
+            .repo
+            .workdir()
+            .ok_or_else(|| anyhow::anyhow!(tr!("No working directory")))?;
+        std::fs::write(workdir.join(path), content)?;
+        // Adding the path clears its conflict entries from the index
+        self.stage_file(path)
+    }
 
-impl GitRepo {
-    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let repo = Repository::discover(path)?;
-        Ok(Self { repo })
+    fn mergetool(&self, path: &str) -> Result<()> {
+        let dir = self.repo.workdir().unwrap_or_else(|| self.repo.path());
+        let status = std::process::Command::new("git")
+            .args(["mergetool", "--no-prompt", "--", path])
+            .current_dir(dir)
+            .status()
+            .map_err(|e| anyhow::anyhow!(tr!("Failed to run git: {}", e)))?;
+        if !status.success() {
+            anyhow::bail!(tr!("git mergetool exited with {}", status));
+        }
+        Ok(())
     }
 
-    pub fn get_current_branch(&self) -> Result<String> {
-        let head = self.repo.head()?;
-        Ok(head.shorthand().unwrap_or("HEAD").to_string())
+    fn difftool(&self, path: &str, staged: bool, command: Option<&str>) -> Result<()> {
+        let dir = self.repo.workdir().unwrap_or_else(|| self.repo.path());
+        let mut args = vec!["difftool".to_string(), "--no-prompt".to_string()];
+        if staged {
+            args.push("--cached".to_string());
+        }
+        if let Some(command) = command {
+            args.push(format!("--extcmd={}", command));
+        }
+        args.extend(["--".to_string(), path.to_string()]);
+
+        let status = std::process::Command::new("git")
+            .args(&args)
+            .current_dir(dir)
+            .status()
+            .map_err(|e| anyhow::anyhow!(tr!("Failed to run git: {}", e)))?;
+        if !status.success() {
+            anyhow::bail!(tr!("git difftool exited with {}", status));
+        }
+        Ok(())
     }
 
-    pub fn get_branches(&self) -> Result<Vec<BranchInfo>> {
-        let mut branches = Vec::new();
-        let current_branch = self.get_current_branch().unwrap_or_default();
+    fn commit(&self, message: &str) -> Result<()> {
+        let mut index = self.repo.index()?;
+        let oid = index.write_tree()?;
+        let signature = self.repo.signature()?;
+        let tree = self.repo.find_tree(oid)?;
 
-        for branch_result in self.repo.branches(Some(BranchType::Local))? {
-            if let Ok((branch, _)) = branch_result {
-                if let Some(name) = branch.name()? {
-                    branches.push(BranchInfo {
-                        name: name.to_string(),
-                        is_current: name == current_branch,
-                    });
+        // The first commit of a new repository has no parent
+        let mut parents = Vec::new();
+        if !self.head_unborn() {
+            parents.push(self.repo.head()?.peel_to_commit()?);
+        }
+        let mut author = signature.clone();
+
+        use git2::RepositoryState as State;
+        let state = self.repo.state();
+        match state {
+            State::Merge => {
+                let heads = std::fs::read_to_string(self.repo.path().join("MERGE_HEAD"))?;
+                for id in heads.split_whitespace() {
+                    parents.push(self.repo.find_commit(Oid::from_str(id)?)?);
                 }
             }
+            State::CherryPick => {
+                let picked = self
+                    .repo
+                    .revparse_single("CHERRY_PICK_HEAD")?
+                    .peel_to_commit()?;
+                author = picked.author().to_owned();
+            }
+            _ => {}
         }
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
 
-        Ok(branches)
+        self.repo
+            .commit(Some("HEAD"), &author, &signature, message, &tree, &parents)?;
+
+        if matches!(state, State::Merge | State::CherryPick | State::Revert) {
+            self.repo.cleanup_state()?;
+        }
+        Ok(())
     }
 
-    pub fn get_commits(&self, limit: usize) -> Result<Vec<CommitInfo>> {
-        let mut commits = Vec::new();
-        let mut revwalk = self.repo.revwalk()?;
+    fn prepared_message(&self) -> Option<String> {
+        let prepared = self.repo.message().ok()?;
+        let message = prepared
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let message = message.trim();
+        (!message.is_empty()).then(|| message.to_string())
+    }
 
-        // Build map of commit OID to branch names
-        use std::collections::HashMap;
-        let mut commit_branches: HashMap<String, Vec<String>> = HashMap::new();
+    fn revert(&self, id: &str, commit: bool) -> Result<String> {
+        let target = self.repo.revparse_single(id)?.peel_to_commit()?;
 
-        // Push all local branches to show complete history
-        revwalk.push_head()?;
-        let branches = self.repo.branches(Some(BranchType::Local))?;
-        for branch in branches {
-            if let Ok((branch, _)) = branch {
-                if let Some(oid) = branch.get().target() {
-                    let _ = revwalk.push(oid);
+        // Merges are reverted relative to their mainline, like `git revert -m 1`
+        let mut options = git2::RevertOptions::new();
+        if target.parent_count() > 1 {
+            options.mainline(1);
+        }
+        self.repo.revert(&target, Some(&mut options))?;
+
+        let message = format!(
+            "Revert \"{}\"\n\nThis reverts commit {}.",
+            target.summary().unwrap_or(""),
+            target.id()
+        );
+
+        if self.repo.index()?.has_conflicts() {
+            anyhow::bail!(tr!(
+                "Revert of {} has conflicts; resolve them and commit",
+                format!("{:.7}", target.id())
+            ));
+        }
 
-                    // Record which branch points to this commit
-                    if let Some(name) = branch.name()? {
-                        let oid_str = format!("{:.7}", oid);
-                        commit_branches
-                            .entry(oid_str)
-                            .or_insert_with(Vec::new)
-                            .push(name.to_string());
-                    }
-                }
+        if commit {
+            self.commit(&message)?;
+        }
+        self.repo.cleanup_state()?;
+        Ok(message)
+    }
+
+    fn merge(&self, branch_name: &str, ff_only: bool) -> Result<String> {
+        let (branch, branch_type) = match self.repo.find_branch(branch_name, BranchType::Local) {
+            Ok(branch) => (branch, BranchType::Local),
+            Err(_) => (
+                self.repo.find_branch(branch_name, BranchType::Remote)?,
+                BranchType::Remote,
+            ),
+        };
+        let annotated = self.repo.reference_to_annotated_commit(branch.get())?;
+        let (analysis, _) = self.repo.merge_analysis(&[&annotated])?;
+        let theirs = self.repo.find_commit(annotated.id())?;
+
+        if analysis.is_up_to_date() {
+            return Ok(tr!("Already up to date with {}", branch_name));
+        }
+
+        if analysis.is_fast_forward() {
+            self.repo.checkout_tree(theirs.as_object(), None)?;
+            let mut head = self.repo.head()?;
+            if head.is_branch() {
+                head.set_target(theirs.id(), &format!("merge {}: Fast-forward", branch_name))?;
+            } else {
+                self.repo.set_head_detached(theirs.id())?;
             }
+            return Ok(tr!(
+                "Fast-forwarded to {} ({})",
+                branch_name,
+                format!("{:.7}", theirs.id())
+            ));
         }
 
-        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+        if ff_only {
+            anyhow::bail!(tr!(
+                "Can't fast-forward to {}: the branches have diverged",
+                branch_name
+            ));
+        }
+        if !analysis.is_normal() {
+            anyhow::bail!(tr!("Can't merge {} into the current HEAD", branch_name));
+        }
 
-        for oid in revwalk.take(limit) {
-            let oid = oid?;
-            let commit = self.repo.find_commit(oid)?;
+        let message = match branch_type {
+            BranchType::Local => format!("Merge branch '{}'", branch_name),
+            BranchType::Remote => format!("Merge remote-tracking branch '{}'", branch_name),
+        };
+        let merge_id = self.merge_commit(&annotated, branch_name, &message)?;
+        Ok(tr!(
+            "Merged {} ({})",
+            branch_name,
+            format!("{:.7}", merge_id)
+        ))
+    }
 
-            let author = commit.author();
-            let date = chrono::DateTime::from_timestamp(commit.time().seconds(), 0)
-                .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
-                .unwrap_or_default();
+    fn merge_squash(&self, branch_name: &str) -> Result<String> {
+        let theirs = self.repo.revparse_single(branch_name)?.peel_to_commit()?;
+        let annotated = self.repo.find_annotated_commit(theirs.id())?;
+        let (analysis, _) = self.repo.merge_analysis(&[&annotated])?;
+        if analysis.is_up_to_date() {
+            anyhow::bail!(tr!("Already up to date with {}", branch_name));
+        }
+
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push(theirs.id())?;
+        revwalk.hide(self.repo.head()?.peel_to_commit()?.id())?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+        let mut message = format!("Squashed commit of branch '{}'\n", branch_name);
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            message.push_str(&format!("\n* {}", commit.summary().unwrap_or("")));
+        }
 
-            let parent_ids: Vec<String> =
-                commit.parent_ids().map(|id| format!("{:.7}", id)).collect();
+        self.repo.merge(&[&annotated], None, None)?;
+        // No MERGE_HEAD: the result is committed as an ordinary single-parent commit
+        self.repo.cleanup_state()?;
+        Ok(message)
+    }
 
-            let oid_str = format!("{:.7}", oid);
-            let branches = commit_branches.get(&oid_str).cloned().unwrap_or_default();
+    fn rebase_start(&self, onto: &str) -> Result<Option<(usize, usize)>> {
+        let (object, reference) = self.repo.revparse_ext(onto)?;
+        let upstream = match reference {
+            Some(reference) => self.repo.reference_to_annotated_commit(&reference)?,
+            None => self
+                .repo
+                .find_annotated_commit(object.peel_to_commit()?.id())?,
+        };
+        let mut rebase = self.repo.rebase(None, Some(&upstream), None, None)?;
+        self.run_rebase(&mut rebase)
+    }
 
-            commits.push(CommitInfo {
-                id: oid_str,
-                author: author.name().unwrap_or("Unknown").to_string(),
-                date,
-                message: commit
-                    .message()
-                    .unwrap_or("")
-                    .lines()
-                    .next()
-                    .unwrap_or("")
-                    .to_string(),
-                parent_ids,
-                graph_info: None,
-                branches,
+    fn continue_operation(&self) -> Result<Option<(usize, usize)>> {
+        use git2::RepositoryState as State;
+        if self.repo.index()?.has_conflicts() {
+            anyhow::bail!(tr!(
+                "Resolve and stage the conflicted files before continuing"
+            ));
+        }
+        match self.repo.state() {
+            State::Rebase | State::RebaseInteractive | State::RebaseMerge => {
+                let mut rebase = self.repo.open_rebase(None)?;
+                if rebase.operation_current().is_some() {
+                    commit_rebase_step(&mut rebase, &self.repo.signature()?)?;
+                }
+                self.run_rebase(&mut rebase)
+            }
+            State::Merge | State::CherryPick | State::Revert => {
+                let message = self.prepared_message().ok_or_else(|| {
+                    anyhow::anyhow!(tr!("No commit message was prepared; commit with c instead"))
+                })?;
+                self.commit(&message)?;
+                Ok(None)
+            }
+            state => anyhow::bail!(unsupported_operation(state, "--continue")),
+        }
+    }
+
+    fn skip_operation(&self) -> Result<Option<(usize, usize)>> {
+        use git2::RepositoryState as State;
+        match self.repo.state() {
+            State::Rebase | State::RebaseInteractive | State::RebaseMerge => {
+                let mut rebase = self.repo.open_rebase(None)?;
+                self.reset_hard_to_head()?;
+                self.run_rebase(&mut rebase)
+            }
+            State::CherryPick | State::Revert => {
+                self.reset_hard_to_head()?;
+                self.repo.cleanup_state()?;
+                Ok(None)
+            }
+            State::Merge => {
+                anyhow::bail!(tr!("A merge has no commit to skip; continue or abort it"))
+            }
+            state => anyhow::bail!(unsupported_operation(state, "--skip")),
+        }
+    }
+
+    fn abort_operation(&self) -> Result<()> {
+        use git2::RepositoryState as State;
+        match self.repo.state() {
+            State::Rebase | State::RebaseInteractive | State::RebaseMerge => {
+                self.repo.open_rebase(None)?.abort()?;
+            }
+            State::Merge | State::CherryPick | State::Revert => {
+                self.reset_hard_to_head()?;
+                self.repo.cleanup_state()?;
+            }
+            State::Bisect => {
+                self.bisect_reset()?;
+            }
+            state => anyhow::bail!(unsupported_operation(state, "--abort")),
+        }
+        Ok(())
+    }
+
+    fn in_progress(&self) -> Option<InProgress> {
+        use git2::RepositoryState as State;
+        match self.repo.state() {
+            State::Clean => None,
+            State::Merge => Some(InProgress::Merge),
+            State::Rebase | State::RebaseInteractive | State::RebaseMerge => {
+                let step = self.repo.open_rebase(None).ok().map(|mut rebase| {
+                    let current = rebase.operation_current().map_or(0, |i| i + 1);
+                    (current, rebase.len())
+                });
+                Some(InProgress::Rebase(step))
+            }
+            State::CherryPick | State::CherryPickSequence => Some(InProgress::CherryPick),
+            State::Revert | State::RevertSequence => Some(InProgress::Revert),
+            State::Bisect => Some(InProgress::Bisect),
+            State::ApplyMailbox | State::ApplyMailboxOrRebase => Some(InProgress::ApplyMailbox),
+        }
+    }
+
+    fn get_rebase_todo(&self, base: &str) -> Result<Vec<RebaseEntry>> {
+        let base = self.repo.revparse_single(base)?.peel_to_commit()?;
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push_head()?;
+        revwalk.hide(base.id())?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+
+        let mut entries = Vec::new();
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            if commit.parent_count() > 1 {
+                anyhow::bail!(tr!(
+                    "Commit {} is a merge; reordering across merges isn't supported",
+                    format!("{:.7}", commit.id())
+                ));
+            }
+            let parent_tree = commit.parent(0)?.tree()?;
+            let diff =
+                self.repo
+                    .diff_tree_to_tree(Some(&parent_tree), Some(&commit.tree()?), None)?;
+            let files = diff
+                .deltas()
+                .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+                .map(|path| path.to_string_lossy().to_string())
+                .collect();
+
+            entries.push(RebaseEntry {
+                id: format!("{:.7}", commit.id()),
+                subject: commit.summary().unwrap_or("").to_string(),
+                files,
             });
         }
+        Ok(entries)
+    }
 
-        // Generate graph visualization
-        self.generate_commit_graph(&mut commits);
+    fn rebase_onto(&self, base: &str, ids: &[String]) -> Result<()> {
+        let mut tip = self.repo.revparse_single(base)?.peel_to_commit()?;
+        let committer = self.repo.signature()?;
+        for id in ids {
+            let commit = self.repo.revparse_single(id)?.peel_to_commit()?;
+            // Commits still sitting on the same parent are kept as they are
+            if commit.parent_id(0).ok() == Some(tip.id()) {
+                tip = commit;
+                continue;
+            }
+            let mut index = self.repo.cherrypick_commit(&commit, &tip, 0, None)?;
+            if index.has_conflicts() {
+                anyhow::bail!(tr!(
+                    "{} \"{}\" conflicts at this position; nothing was changed",
+                    format!("{:.7}", commit.id()),
+                    commit.summary().unwrap_or("")
+                ));
+            }
+            let tree = self.repo.find_tree(index.write_tree_to(&self.repo)?)?;
+            let new_id = self.repo.commit(
+                None,
+                &commit.author(),
+                &committer,
+                commit.message().unwrap_or(""),
+                &tree,
+                &[&tip],
+            )?;
+            tip = self.repo.find_commit(new_id)?;
+        }
 
-        Ok(commits)
+        self.repo.checkout_tree(
+            tip.as_object(),
+            Some(git2::build::CheckoutBuilder::new().safe()),
+        )?;
+        let head = self.repo.head()?;
+        if head.is_branch() {
+            self.repo.reference(
+                head.name().unwrap_or("HEAD"),
+                tip.id(),
+                true,
+                "gtek: rebase (reorder)",
+            )?;
+        } else {
+            self.repo.set_head_detached(tip.id())?;
+        }
+        Ok(())
     }
 
-    fn generate_commit_graph(&self, commits: &mut Vec<CommitInfo>) {
-        use crate::app::GraphInfo;
-        use std::collections::HashMap;
+    fn undo_last_commit(&self) -> Result<(String, String)> {
+        let head_commit = self.repo.head()?.peel_to_commit()?;
+        if head_commit.parent_count() == 0 {
+            anyhow::bail!(tr!("Cannot undo the initial commit"));
+        }
 
-        let mut commit_columns: HashMap<String, usize> = HashMap::new();
-        let mut active_lanes: Vec<Option<String>> = Vec::new();
+        let parent = head_commit.parent(0)?;
+        self.repo
+            .reset(parent.as_object(), git2::ResetType::Soft, None)?;
 
-        for commit in commits.iter_mut() {
-            // Find or assign a column for this commit
-            let column = if let Some(&col) = commit_columns.get(&commit.id) {
-                col
-            } else {
-                // Find first available lane or create a new one
-                let col = active_lanes
-                    .iter()
-                    .position(|lane| lane.is_none())
-                    .unwrap_or_else(|| {
-                        active_lanes.push(None);
-                        active_lanes.len() - 1
-                    });
-                commit_columns.insert(commit.id.clone(), col);
-                col
+        Ok((
+            format!("{:.7}", head_commit.id()),
+            head_commit.message().unwrap_or("").trim_end().to_string(),
+        ))
+    }
+
+    fn apply_patch(&self, patch: &[u8], to_index: bool) -> Result<(usize, Vec<String>)> {
+        let diff = git2::Diff::from_buffer(patch)
+            .map_err(|e| anyhow::anyhow!(tr!("Not a patch: {}", e.message())))?;
+        let location = if to_index {
+            git2::ApplyLocation::Index
+        } else {
+            git2::ApplyLocation::WorkDir
+        };
+
+        let mut hunks = Vec::new();
+        for i in 0..diff.deltas().len() {
+            let Some(patch) = git2::Patch::from_diff(&diff, i)? else {
+                continue;
+            };
+            let delta = patch.delta();
+            let Some(path) = delta.old_file().path().or(delta.new_file().path()) else {
+                continue;
             };
+            for h in 0..patch.num_hunks() {
+                let (hunk, line_count) = patch.hunk(h)?;
+                let mut text = format!("--- {}\n", path.display());
+                text.push_str(&String::from_utf8_lossy(hunk.header()));
+                for l in 0..line_count {
+                    let line = patch.line_in_hunk(h, l)?;
+                    if matches!(line.origin(), '+' | '-' | ' ') {
+                        text.push(line.origin());
+                    }
+                    text.push_str(&String::from_utf8_lossy(line.content()));
+                }
+                hunks.push((hunk_key(path, &hunk), text));
+            }
+        }
+        if hunks.is_empty() {
+            anyhow::bail!(tr!("The patch contains no changes"));
+        }
 
-            // Ensure active_lanes is large enough
-            if column >= active_lanes.len() {
-                active_lanes.resize(column + 1, None);
+        // Try each hunk on its own first, so one bad hunk doesn't sink the whole patch
+        let mut rejected = Vec::new();
+        for (key, text) in &hunks {
+            if self
+                .apply_hunks(&diff, location, true, |candidate| candidate == key)
+                .is_err()
+            {
+                rejected.push((key.clone(), text.clone()));
             }
+        }
+        if rejected.len() == hunks.len() {
+            anyhow::bail!(tr!("None of the {} hunk(s) apply", hunks.len()));
+        }
 
-            // Build graph line with enough space for all lanes
-            let max_lanes = active_lanes.len().max(column + 1);
-            let mut graph = vec![' '; max_lanes * 2];
+        self.apply_hunks(&diff, location, false, |candidate| {
+            !rejected.iter().any(|(key, _)| key == candidate)
+        })?;
+        Ok((
+            hunks.len() - rejected.len(),
+            rejected.into_iter().map(|(_, text)| text).collect(),
+        ))
+    }
 
-            // Draw all active branches
-            for (i, lane) in active_lanes.iter().enumerate() {
-                if i * 2 >= graph.len() {
-                    break;
-                }
-                if i < column {
-                    if lane.is_some() {
-                        graph[i * 2] = '│';
-                    }
-                } else if i == column {
-                    graph[i * 2] = if commit.parent_ids.len() > 1 {
-                        '┬'
-                    } else {
-                        '●'
-                    };
-                } else if lane.is_some() {
-                    graph[i * 2] = '│';
-                }
-            }
-
-            // Update current lane
-            active_lanes[column] = Some(commit.id.clone());
-
-            // Process parent commits
-            let parents = commit.parent_ids.clone();
-            if !parents.is_empty() {
-                // First parent continues in same lane
-                active_lanes[column] = Some(parents[0].clone());
-                commit_columns.insert(parents[0].clone(), column);
-
-                // Additional parents (merges) get new lanes
-                for parent_id in parents.iter().skip(1) {
-                    if !commit_columns.contains_key(parent_id) {
-                        let new_col = active_lanes
-                            .iter()
-                            .position(|lane| lane.is_none())
-                            .unwrap_or_else(|| {
-                                active_lanes.push(None);
-                                active_lanes.len() - 1
-                            });
-                        if new_col >= active_lanes.len() {
-                            active_lanes.resize(new_col + 1, None);
+    fn absorb_staged(&self) -> Result<(usize, usize)> {
+        let head_commit = self.repo.head()?.peel_to_commit()?;
+        let head_tree = head_commit.tree()?;
+        let index = self.repo.index()?;
+        // Zero context keeps neighbouring edits in separate hunks and out of each other's blame
+        let mut diff_options = DiffOptions::new();
+        diff_options.context_lines(0);
+        let diff = self.repo.diff_tree_to_index(
+            Some(&head_tree),
+            Some(&index),
+            Some(&mut diff_options),
+        )?;
+
+        // Commits already on the upstream branch are shared history and never targeted
+        let upstream = self
+            .repo
+            .head()
+            .ok()
+            .and_then(|head| head.shorthand().map(|name| name.to_string()))
+            .and_then(|name| self.repo.find_branch(&name, BranchType::Local).ok())
+            .and_then(|branch| branch.upstream().ok())
+            .and_then(|upstream| upstream.get().target());
+
+        let mut assignments: HashMap<HunkKey, Oid> = HashMap::new();
+        let mut skipped = 0;
+
+        for delta_index in 0..diff.deltas().len() {
+            let Some(patch) = git2::Patch::from_diff(&diff, delta_index)? else {
+                continue;
+            };
+            let delta = patch.delta();
+            let path = delta.old_file().path().map(|p| p.to_path_buf());
+            let (Some(path), git2::Delta::Modified) = (path, delta.status()) else {
+                skipped += patch.num_hunks().max(1);
+                continue;
+            };
+
+            let mut blame_options = git2::BlameOptions::new();
+            blame_options.newest_commit(head_commit.id());
+            let blame = self.repo.blame_file(&path, Some(&mut blame_options))?;
+
+            for hunk_index in 0..patch.num_hunks() {
+                let (hunk, _) = patch.hunk(hunk_index)?;
+                let lines = hunk.old_start()..hunk.old_start() + hunk.old_lines();
+
+                // Pick the newest commit among those that last touched the replaced lines
+                let target = lines
+                    .filter_map(|line| blame.get_line(line as usize))
+                    .map(|blame_hunk| blame_hunk.final_commit_id())
+                    .reduce(|newest, oid| {
+                        if self.repo.graph_descendant_of(oid, newest).unwrap_or(false) {
+                            oid
+                        } else {
+                            newest
                         }
-                        active_lanes[new_col] = Some(parent_id.clone());
-                        commit_columns.insert(parent_id.clone(), new_col);
+                    });
+
+                let pushed = |oid: Oid| {
+                    upstream.is_some_and(|upstream| {
+                        upstream == oid
+                            || self
+                                .repo
+                                .graph_descendant_of(upstream, oid)
+                                .unwrap_or(false)
+                    })
+                };
+                match target {
+                    Some(oid) if !pushed(oid) => {
+                        assignments.insert(hunk_key(&path, &hunk), oid);
                     }
+                    _ => skipped += 1,
+                }
+            }
+        }
+
+        // Oldest target first, so the fixups read in history order. Blame only names ancestors
+        // of HEAD, so one walk from HEAD finds every target.
+        let wanted: HashSet<Oid> = assignments.values().copied().collect();
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push(head_commit.id())?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+        let mut targets: Vec<Oid> = Vec::new();
+        for oid in revwalk {
+            let oid = oid?;
+            if wanted.contains(&oid) {
+                targets.push(oid);
+                if targets.len() == wanted.len() {
+                    break;
                 }
-            } else {
-                // No parents (initial commit), clear this lane
-                active_lanes[column] = None;
             }
+        }
 
-            commit.graph_info = Some(GraphInfo {
-                column,
-                graph_line: graph.iter().collect(),
+        // Each fixup's tree is HEAD plus the hunks of this and all earlier fixups
+        let signature = self.repo.signature()?;
+        let mut parent = head_commit;
+        let mut absorbed: Vec<HunkKey> = Vec::new();
+        for target in &targets {
+            absorbed.extend(
+                assignments
+                    .iter()
+                    .filter(|(_, oid)| *oid == target)
+                    .map(|(key, _)| key.clone()),
+            );
+
+            let current_path = std::cell::RefCell::new(None);
+            let mut apply_options = git2::ApplyOptions::new();
+            apply_options.delta_callback(|delta| {
+                *current_path.borrow_mut() = delta
+                    .and_then(|delta| delta.old_file().path())
+                    .map(|path| path.to_path_buf());
+                true
+            });
+            apply_options.hunk_callback(|hunk| match (hunk, current_path.borrow().as_ref()) {
+                (Some(hunk), Some(path)) => absorbed.contains(&hunk_key(path, &hunk)),
+                _ => false,
             });
+            let mut fixup_index =
+                self.repo
+                    .apply_to_tree(&head_tree, &diff, Some(&mut apply_options))?;
+            let tree = self
+                .repo
+                .find_tree(fixup_index.write_tree_to(&self.repo)?)?;
+
+            let target_commit = self.repo.find_commit(*target)?;
+            let message = format!("fixup! {}", target_commit.summary().unwrap_or(""));
+            let fixup_id = self.repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                &message,
+                &tree,
+                &[&parent],
+            )?;
+            parent = self.repo.find_commit(fixup_id)?;
         }
+
+        Ok((targets.len(), skipped))
     }
 
-    pub fn get_status(&self) -> Result<Vec<FileStatus>> {
-        let mut files = Vec::new();
-        let mut opts = StatusOptions::new();
-        opts.include_untracked(true);
+    fn create_branch(&self, branch_name: &str, base_branch: &str) -> Result<()> {
+        let base_commit = self.repo.revparse_single(base_branch)?.peel_to_commit()?;
+        self.repo.branch(branch_name, &base_commit, false)?;
+        Ok(())
+    }
 
-        let statuses = self.repo.statuses(Some(&mut opts))?;
+    fn delete_branch(&self, branch_name: &str, force: bool) -> Result<()> {
+        if !force {
+            let unmerged = self.unmerged_commits(branch_name)?;
+            if unmerged > 0 {
+                anyhow::bail!(tr!(
+                    "Branch {} has {} commit(s) not merged into HEAD",
+                    branch_name,
+                    unmerged
+                ));
+            }
+        }
+        let mut branch = self
+            .repo
+            .find_branch(branch_name, git2::BranchType::Local)?;
+        branch.delete()?;
+        Ok(())
+    }
 
-        for entry in statuses.iter() {
-            let path = entry.path().unwrap_or("").to_string();
-            let status = match entry.status() {
-                s if s.contains(Status::INDEX_NEW) => "A ",
-                s if s.contains(Status::INDEX_MODIFIED) => "M ",
-                s if s.contains(Status::INDEX_DELETED) => "D ",
-                s if s.contains(Status::WT_NEW) => "??",
-                s if s.contains(Status::WT_MODIFIED) => " M",
-                s if s.contains(Status::WT_DELETED) => " D",
-                _ => "  ",
+    fn archive_branch(&self, branch_name: &str) -> Result<String> {
+        let mut branch = self.repo.find_branch(branch_name, BranchType::Local)?;
+        let tip = branch.get().peel_to_commit()?;
+        let tag = format!("archive/{}", branch_name);
+        self.repo.tag_lightweight(&tag, tip.as_object(), false)?;
+        branch.delete()?;
+        Ok(tag)
+    }
+
+    fn merged_branches(&self) -> Result<Vec<String>> {
+        let current = self.get_current_branch().unwrap_or_default();
+        let mut merged = Vec::new();
+        for (branch, _) in self.repo.branches(Some(BranchType::Local))?.flatten() {
+            let Some(name) = branch.name()? else {
+                continue;
             };
+            if name != current && self.unmerged_commits(name)? == 0 {
+                merged.push(name.to_string());
+            }
+        }
+        Ok(merged)
+    }
 
-            files.push(FileStatus {
-                path,
-                status: status.to_string(),
-            });
+    fn unmerged_commits(&self, branch_name: &str) -> Result<usize> {
+        let branch = self.repo.find_branch(branch_name, BranchType::Local)?;
+        let tip = branch.get().peel_to_commit()?.id();
+        let head = self.repo.head()?.peel_to_commit()?.id();
+        Ok(self.repo.graph_ahead_behind(tip, head)?.0)
+    }
+
+    fn checkout_branch(&self, branch_name: &str, force: bool) -> Result<()> {
+        let (object, reference) = self.repo.revparse_ext(branch_name)?;
+
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        if force {
+            checkout.force();
         }
+        self.repo.checkout_tree(&object, Some(&mut checkout))?;
 
-        Ok(files)
+        match reference {
+            Some(gref) => self.repo.set_head(gref.name().unwrap())?,
+            None => self.repo.set_head_detached(object.id())?,
+        }
+
+        Ok(())
     }
 
-    pub fn get_diff_for_file(&self, path: &str) -> Result<String> {
-        let mut diff_text = String::new();
+    fn set_upstream(&self, branch_name: &str, upstream: Option<&str>) -> Result<()> {
+        let mut branch = self.repo.find_branch(branch_name, BranchType::Local)?;
+        branch.set_upstream(upstream)?;
+        Ok(())
+    }
 
-        // Check if file is untracked
-        let file_path = Path::new(path);
-        if file_path.exists() {
-            let mut opts = StatusOptions::new();
-            opts.pathspec(path);
-            let statuses = self.repo.statuses(Some(&mut opts))?;
+    fn remotes(&self) -> Result<Vec<String>> {
+        Ok(self
+            .repo
+            .remotes()?
+            .iter()
+            .flatten()
+            .map(String::from)
+            .collect())
+    }
 
-            if let Some(entry) = statuses.get(0) {
-                if entry.status().contains(Status::WT_NEW) {
-                    // For untracked files, show the content as all new lines
-                    if let Ok(content) = std::fs::read_to_string(file_path) {
-                        diff_text.push_str(&format!("New file: {}\n", path));
-                        diff_text.push_str("--- /dev/null\n");
-                        diff_text.push_str(&format!("+++ {}\n", path));
-                        for line in content.lines() {
-                            diff_text.push('+');
-                            diff_text.push_str(line);
-                            diff_text.push('\n');
-                        }
-                        return Ok(diff_text);
-                    }
+    fn branch_remote(&self, branch_name: &str) -> Option<String> {
+        let remote = self
+            .repo
+            .branch_upstream_remote(&format!("refs/heads/{}", branch_name))
+            .ok()?;
+        remote.as_str().map(str::to_string)
+    }
+
+    fn checkout_remote_branch(
+        &self,
+        remote_branch: &str,
+        remote: &str,
+        force: bool,
+    ) -> Result<String> {
+        let local_name = remote_branch
+            .strip_prefix(remote)
+            .and_then(|name| name.strip_prefix('/'))
+            .ok_or_else(|| {
+                anyhow::anyhow!(tr!("{} is not a branch of {}", remote_branch, remote))
+            })?;
+
+        match self.repo.find_branch(local_name, BranchType::Local) {
+            Ok(existing) => {
+                let upstream = existing.upstream().ok();
+                let tracks = upstream
+                    .as_ref()
+                    .and_then(|upstream| upstream.name().ok().flatten())
+                    == Some(remote_branch);
+                if !tracks {
+                    anyhow::bail!(tr!(
+                        "A local branch {} already exists and doesn't track {}",
+                        local_name,
+                        remote_branch
+                    ));
                 }
             }
+            Err(_) => {
+                let target = self
+                    .repo
+                    .find_branch(remote_branch, BranchType::Remote)?
+                    .get()
+                    .peel_to_commit()?;
+                let mut branch = self.repo.branch(local_name, &target, false)?;
+                branch.set_upstream(Some(remote_branch))?;
+            }
+        }
+
+        self.checkout_branch(local_name, force)?;
+        Ok(local_name.to_string())
+    }
+
+    fn stash_push(&self, message: &str) -> Result<bool> {
+        // Stashing needs a mutable repository; a second handle on the same one will do
+        let mut repo = Repository::open(self.repo.path())?;
+        let signature = repo.signature()?;
+        match repo.stash_save(
+            &signature,
+            message,
+            Some(git2::StashFlags::INCLUDE_UNTRACKED),
+        ) {
+            Ok(_) => Ok(true),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn stash_count(&self) -> Result<usize> {
+        // Each stash entry is an entry in the reflog of refs/stash
+        match self.repo.reflog("refs/stash") {
+            Ok(reflog) => Ok(reflog.len()),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn create_bundle(&self, path: &Path, revisions: &str, ui: &mut dyn RemoteUi) -> Result<()> {
+        let path = path.to_string_lossy();
+        let mut args = vec!["bundle", "create", "--progress", &path];
+        args.extend(revisions.split_whitespace());
+        self.run_git(&args, ui)
+    }
+
+    fn verify_bundle(&self, path: &Path, ui: &mut dyn RemoteUi) -> Result<()> {
+        self.run_git(&["bundle", "verify", &path.to_string_lossy()], ui)
+    }
+
+    fn run_maintenance(&self, ui: &mut dyn RemoteUi) -> Result<()> {
+        // Object counts before and after show what gc cleaned up
+        self.run_git(&["count-objects", "-vH"], ui)?;
+        self.run_git(&["gc"], ui)?;
+        self.run_git(&["count-objects", "-vH"], ui)
+    }
+
+    fn stash_pop(&self) -> Result<()> {
+        let mut repo = Repository::open(self.repo.path())?;
+        let mut options = git2::StashApplyOptions::new();
+        options.reinstantiate_index();
+        // libgit2 drops the stash even when applying it left conflicts; `git stash pop` keeps it
+        repo.stash_apply(0, Some(&mut options))?;
+        if repo.index()?.has_conflicts() {
+            anyhow::bail!(tr!("the stashed changes conflict; resolve them in Files"));
+        }
+        repo.stash_drop(0)?;
+        Ok(())
+    }
+
+    fn pull(&self, remote: &str, ff_only: bool, ui: &mut dyn RemoteUi) -> Result<()> {
+        if self.repo.head_detached()? {
+            anyhow::bail!(tr!("HEAD is detached; check out a branch to pull into"));
+        }
+        let branch = self.get_current_branch()?;
+        let remote_ref = self.remote_branch_ref(&branch, remote);
+        if self.uses_shell_git(remote) {
+            let mut args = vec!["pull", "--progress", "--no-rebase"];
+            if ff_only {
+                args.push("--ff-only");
+            }
+            args.extend([remote, remote_ref.as_str()]);
+            return self.run_git(&args, ui);
+        }
+        // Simplified pull - fetch and fast-forward merge
+        let mut remote = self.repo.find_remote(remote)?;
+        {
+            let (callbacks, pending) = self.remote_callbacks(&remote, &mut *ui)?;
+            let mut fetch_options = git2::FetchOptions::new();
+            fetch_options.remote_callbacks(callbacks);
+            remote.fetch(&[&remote_ref], Some(&mut fetch_options), None)?;
+            pending.accept(&self.credentials);
+        }
+        if ui.cancelled() {
+            anyhow::bail!(tr!("Cancelled"));
         }
 
-        // Get the diff for the working directory changes
-        let mut opts = DiffOptions::new();
-        opts.pathspec(path);
-        opts.include_untracked(true);
-
-        let diff = self.repo.diff_index_to_workdir(None, Some(&mut opts))?;
-
-        diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
-            let origin = line.origin();
-            let content = std::str::from_utf8(line.content()).unwrap_or("");
+        let fetch_head = self.repo.find_reference("FETCH_HEAD")?;
+        let fetch_commit = self.repo.reference_to_annotated_commit(&fetch_head)?;
 
-            match origin {
-                '+' | '-' | ' ' => {
-                    diff_text.push(origin);
-                    diff_text.push_str(content);
-                }
-                _ => {
-                    diff_text.push_str(content);
-                }
-            }
-            true
-        })?;
+        let analysis = self.repo.merge_analysis(&[&fetch_commit])?;
 
-        if diff_text.is_empty() {
-            // Try staged changes
-            if let Ok(head) = self.repo.head() {
-                if let Ok(tree) = head.peel_to_tree() {
-                    let diff = self
-                        .repo
-                        .diff_tree_to_index(Some(&tree), None, Some(&mut opts))?;
-
-                    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
-                        let origin = line.origin();
-                        let content = std::str::from_utf8(line.content()).unwrap_or("");
-
-                        match origin {
-                            '+' | '-' | ' ' => {
-                                diff_text.push(origin);
-                                diff_text.push_str(content);
-                            }
-                            _ => {
-                                diff_text.push_str(content);
-                            }
-                        }
-                        true
-                    })?;
+        if analysis.0.is_fast_forward() {
+            let target = fetch_commit.id().to_string();
+            if let Err(e) = self.fast_forward(&branch, &target, false) {
+                if is_checkout_conflict(&e) {
+                    return Err(FastForwardConflict { branch, target }.into());
                 }
+                return Err(e);
             }
+        } else if ff_only && !analysis.0.is_up_to_date() {
+            anyhow::bail!(tr!(
+                "Can't fast-forward: the local and remote branches have diverged (fast-forward only)"
+            ));
+        } else if analysis.0.is_normal() {
+            let short = remote_ref
+                .strip_prefix("refs/heads/")
+                .unwrap_or(&remote_ref);
+            let url = remote.url().unwrap_or("remote");
+            let message = format!("Merge branch '{}' of {}", short, url);
+            self.merge_commit(&fetch_commit, &format!("{} of {}", short, url), &message)?;
         }
 
-        if diff_text.is_empty() {
-            diff_text = format!("No changes to display for: {}", path);
-        }
-
-        Ok(diff_text)
+        Ok(())
     }
 
-    pub fn stage_file(&self, path: &str) -> Result<()> {
-        let mut index = self.repo.index()?;
-        index.add_path(Path::new(path))?;
-        index.write()?;
+    fn fast_forward(&self, branch_name: &str, target: &str, force: bool) -> Result<()> {
+        let target = self.repo.find_commit(Oid::from_str(target)?)?;
+        // Update the working tree first, so that a conflict leaves the branch where it was
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        if force {
+            checkout.force();
+        }
+        self.repo
+            .checkout_tree(target.as_object(), Some(&mut checkout))?;
+
+        let refname = format!("refs/heads/{}", branch_name);
+        let mut reference = self.repo.find_reference(&refname)?;
+        reference.set_target(target.id(), "Fast-Forward")?;
+        self.repo.set_head(&refname)?;
         Ok(())
     }
 
-    pub fn stage_all(&self) -> Result<()> {
-        let mut index = self.repo.index()?;
-        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
-        index.write()?;
+    fn fetch(&self, remote: &str, ui: &mut dyn RemoteUi) -> Result<()> {
+        if self.uses_shell_git(remote) {
+            return self.run_git(&["fetch", "--progress", remote], ui);
+        }
+        let mut remote = self.repo.find_remote(remote)?;
+
+        let (callbacks, pending) = self.remote_callbacks(&remote, ui)?;
+        let mut fetch_options = git2::FetchOptions::new();
+        fetch_options.remote_callbacks(callbacks);
+
+        // No refspecs: use the remote's configured ones, as `git fetch` does
+        remote.fetch(&[] as &[&str], Some(&mut fetch_options), None)?;
+        pending.accept(&self.credentials);
         Ok(())
     }
 
-    pub fn discard_file(&self, path: &str) -> Result<()> {
-        // Check if the file is untracked (newly created)
-        let mut opts = StatusOptions::new();
-        opts.include_untracked(true);
-        let statuses = self.repo.statuses(Some(&mut opts))?;
-
-        let mut is_untracked = false;
-        for entry in statuses.iter() {
-            if let Some(entry_path) = entry.path() {
-                if entry_path == path && entry.status().contains(Status::WT_NEW) {
-                    is_untracked = true;
-                    break;
-                }
-            }
+    fn push(&self, remote: &str, ui: &mut dyn RemoteUi) -> Result<()> {
+        if self.repo.head_detached()? {
+            anyhow::bail!(tr!("HEAD is detached; create a branch to push"));
         }
+        let branch = self.get_current_branch()?;
+        self.push_branch(&branch, Some(remote), ui)
+    }
 
-        if is_untracked {
-            // Delete the untracked file from filesystem
-            let workdir = self
-                .repo
-                .workdir()
-                .ok_or_else(|| anyhow::anyhow!("No working directory"))?;
-            let file_path = workdir.join(path);
-            std::fs::remove_file(&file_path)?;
+    fn push_branch(
+        &self,
+        branch_name: &str,
+        remote: Option<&str>,
+        ui: &mut dyn RemoteUi,
+    ) -> Result<()> {
+        let (mut remote, refspec) = self.push_target(branch_name, remote)?;
+
+        let remote_name = remote.name().unwrap_or("origin").to_string();
+        if self.uses_shell_git(&remote_name) {
+            self.run_git(&["push", "--progress", &remote_name, &refspec], ui)?;
         } else {
-            // Checkout the file from HEAD to discard changes
-            let head = self.repo.head()?;
-            let tree = head.peel_to_tree()?;
-
-            let mut checkout_builder = git2::build::CheckoutBuilder::new();
-            checkout_builder.path(path);
-            checkout_builder.force();
+            let (callbacks, pending) = self.remote_callbacks(&remote, ui)?;
+            let mut push_options = git2::PushOptions::new();
+            push_options.remote_callbacks(callbacks);
+            remote.push(&[&refspec], Some(&mut push_options))?;
+            pending.accept(&self.credentials);
+        }
 
-            self.repo
-                .checkout_tree(tree.as_object(), Some(&mut checkout_builder))?;
+        let local_ref = format!("refs/heads/{}", branch_name);
+        if self.repo.branch_upstream_remote(&local_ref).is_err() {
+            let mut config = self.repo.config()?;
+            config.set_str(&format!("branch.{}.remote", branch_name), &remote_name)?;
+            config.set_str(&format!("branch.{}.merge", branch_name), &local_ref)?;
         }
         Ok(())
     }
 
-    pub fn discard_all(&self) -> Result<()> {
-        // First, remove all untracked files
-        let workdir = self
+    fn delete_remote_branch(&self, remote_branch: &str, ui: &mut dyn RemoteUi) -> Result<()> {
+        let refname = self
             .repo
-            .workdir()
-            .ok_or_else(|| anyhow::anyhow!("No working directory"))?;
-        let mut opts = StatusOptions::new();
-        opts.include_untracked(true);
-        let statuses = self.repo.statuses(Some(&mut opts))?;
-
-        for entry in statuses.iter() {
-            if let Some(path) = entry.path() {
-                if entry.status().contains(Status::WT_NEW) {
-                    let file_path = workdir.join(path);
-                    let _ = std::fs::remove_file(&file_path);
-                }
-            }
+            .find_branch(remote_branch, BranchType::Remote)?
+            .get()
+            .name()
+            .ok_or_else(|| anyhow::anyhow!(tr!("Invalid branch name {}", remote_branch)))?
+            .to_string();
+        let remote_name = self.repo.branch_remote_name(&refname)?;
+        let remote_name = remote_name
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!(tr!("Invalid remote name for {}", remote_branch)))?;
+        let branch = remote_branch
+            .strip_prefix(remote_name)
+            .and_then(|name| name.strip_prefix('/'))
+            .ok_or_else(|| {
+                anyhow::anyhow!(tr!("{} is not a branch of {}", remote_branch, remote_name))
+            })?;
+
+        let refspec = format!(":refs/heads/{}", branch);
+        if self.uses_shell_git(remote_name) {
+            self.run_git(&["push", "--progress", remote_name, &refspec], ui)?;
+        } else {
+            let mut remote = self.repo.find_remote(remote_name)?;
+            let (callbacks, pending) = self.remote_callbacks(&remote, ui)?;
+            let mut push_options = git2::PushOptions::new();
+            push_options.remote_callbacks(callbacks);
+            remote.push(&[&refspec], Some(&mut push_options))?;
+            pending.accept(&self.credentials);
         }
 
-        // Then checkout all tracked files from HEAD to discard changes
-        let head = self.repo.head()?;
-        let tree = head.peel_to_tree()?;
-
-        let mut checkout_builder = git2::build::CheckoutBuilder::new();
-        checkout_builder.force();
-
-        self.repo
-            .checkout_tree(tree.as_object(), Some(&mut checkout_builder))?;
+        // The push may already have pruned the remote-tracking ref
+        if let Ok(mut tracking) = self.repo.find_branch(remote_branch, BranchType::Remote) {
+            tracking.delete()?;
+        }
         Ok(())
     }
 
-    pub fn commit(&self, message: &str) -> Result<()> {
-        let mut index = self.repo.index()?;
-        let oid = index.write_tree()?;
-        let signature = self.repo.signature()?;
-        let tree = self.repo.find_tree(oid)?;
-
-        let parent_commit = self.repo.head()?.peel_to_commit()?;
-
-        self.repo.commit(
-            Some("HEAD"),
-            &signature,
-            &signature,
-            message,
-            &tree,
-            &[&parent_commit],
-        )?;
-
+    fn sync(&self, remote: &str, ff_only: bool, ui: &mut dyn RemoteUi) -> Result<()> {
+        self.pull(remote, ff_only, ui)?;
+        self.push(remote, ui)?;
         Ok(())
     }
 
-    pub fn create_branch(&self, branch_name: &str, base_branch: &str) -> Result<()> {
-        let base_commit = self.repo.revparse_single(base_branch)?.peel_to_commit()?;
-        self.repo.branch(branch_name, &base_commit, false)?;
-        Ok(())
+    fn push_tags(&self, remote: &str, tag: Option<&str>, ui: &mut dyn RemoteUi) -> Result<()> {
+        let names: Vec<String> = match tag {
+            Some(tag) => vec![tag.to_string()],
+            None => self
+                .repo
+                .tag_names(None)?
+                .iter()
+                .flatten()
+                .map(str::to_string)
+                .collect(),
+        };
+        if names.is_empty() {
+            anyhow::bail!(tr!("There are no tags to push"));
+        }
+        let refspecs: Vec<String> = names
+            .iter()
+            .map(|name| format!("refs/tags/{0}:refs/tags/{0}", name))
+            .collect();
+        self.push_refspecs(remote, &refspecs, ui)
     }
 
-    pub fn delete_branch(&self, branch_name: &str) -> Result<()> {
-        let mut branch = self
-            .repo
-            .find_branch(branch_name, git2::BranchType::Local)?;
-        branch.delete()?;
-        Ok(())
+    fn delete_remote_tag(&self, remote: &str, name: &str, ui: &mut dyn RemoteUi) -> Result<()> {
+        self.push_refspecs(remote, &[format!(":refs/tags/{}", name)], ui)
     }
+}
 
-    pub fn checkout_branch(&self, branch_name: &str) -> Result<()> {
-        let (object, reference) = self.repo.revparse_ext(branch_name)?;
+/// Files larger than this are diffed as binary rather than loaded line by line
+const BIG_FILE_THRESHOLD: u64 = 5 * 1024 * 1024;
+
+/// Diff options with `context` lines around changes that, with `ignore_whitespace`, skip changes
+/// in whitespace like `git diff -w`. Files over `BIG_FILE_THRESHOLD` are treated as binary.
+fn diff_options(ignore_whitespace: bool, context: u32) -> DiffOptions {
+    let mut options = DiffOptions::new();
+    options
+        .context_lines(context)
+        .ignore_whitespace(ignore_whitespace)
+        .ignore_whitespace_change(ignore_whitespace)
+        .max_size(BIG_FILE_THRESHOLD as i64);
+    options
+}
+
+/// Pair up deleted and added files with similar content as renames, and added files with
+/// similar modified ones as copies, so the patch shows a rename header instead of both files whole
+fn find_renames(diff: &mut git2::Diff) -> Result<()> {
+    diff.find_similar(Some(
+        git2::DiffFindOptions::new().renames(true).copies(true),
+    ))?;
+    Ok(())
+}
 
-        self.repo.checkout_tree(&object, None)?;
+/// Whether file content looks binary, by git's rule: a NUL byte in the first 8000 bytes
+fn is_binary(content: &[u8]) -> bool {
+    content.iter().take(8000).any(|&byte| byte == 0)
+}
 
-        match reference {
-            Some(gref) => self.repo.set_head(gref.name().unwrap())?,
-            None => self.repo.set_head_detached(object.id())?,
-        }
+/// Placeholder for a diff too big or binary to show, e.g. `Binary file (12.30 MiB) changed`
+fn binary_summary(size: u64, what: &str) -> String {
+    format!("Binary file ({}) {}\n", format_bytes(size as usize), what)
+}
 
-        Ok(())
+/// Old and new dimensions and size of a changed image, e.g.
+/// `Image: 640×480 PNG, 12.30 KiB → 800×600 PNG, 20.00 KiB`; `None` for other binary files
+fn image_summary(repo: &Repository, delta: &git2::DiffDelta) -> Option<String> {
+    let path = delta.new_file().path().or(delta.old_file().path())?;
+    if !has_image_extension(&path.to_string_lossy()) {
+        return None;
     }
+    let side = |file: git2::DiffFile| {
+        let content = file_content(repo, &file);
+        let size = content
+            .as_ref()
+            .map_or(file.size(), |content| content.len() as u64);
+        describe_image(content.as_deref(), size)
+    };
+    Some(match delta.status() {
+        git2::Delta::Added | git2::Delta::Untracked => {
+            format!("Image added: {}\n", side(delta.new_file()))
+        }
+        git2::Delta::Deleted => format!("Image deleted: {}\n", side(delta.old_file())),
+        _ => format!(
+            "Image: {} → {}\n",
+            side(delta.old_file()),
+            side(delta.new_file())
+        ),
+    })
+}
 
-    pub fn pull(&self) -> Result<()> {
-        // Simplified pull - fetch and fast-forward merge
-        let mut remote = self.repo.find_remote("origin")?;
-        let config = self.repo.config()?;
+/// Content of one side of a delta: its blob, or the working tree file when that side was
+/// never written to the object database. Files over `BIG_FILE_THRESHOLD` aren't read.
+fn file_content(repo: &Repository, file: &git2::DiffFile) -> Option<Vec<u8>> {
+    if file.size() > BIG_FILE_THRESHOLD {
+        return None;
+    }
+    if let Ok(blob) = repo.find_blob(file.id()) {
+        return Some(blob.content().to_vec());
+    }
+    let workdir = repo.workdir()?;
+    let path = workdir.join(file.path()?);
+    if path.metadata().ok()?.len() > BIG_FILE_THRESHOLD {
+        return None;
+    }
+    std::fs::read(path).ok()
+}
 
-        // Set up authentication callbacks
-        let mut callbacks = git2::RemoteCallbacks::new();
-        callbacks.credentials(move |url, username_from_url, allowed_types| {
-            // For HTTPS, try credential helper
-            if url.starts_with("https://") {
-                if let Ok(cred) = git2::Cred::credential_helper(&config, url, username_from_url) {
-                    return Ok(cred);
-                }
-            }
+/// Dimensions, format and size of an image, or just its size if the header can't be read
+fn describe_image(content: Option<&[u8]>, size: u64) -> String {
+    match content.and_then(image_info) {
+        Some(info) => format!(
+            "{}×{} {}, {}",
+            info.width,
+            info.height,
+            info.format,
+            format_bytes(size as usize)
+        ),
+        None => format_bytes(size as usize),
+    }
+}
 
-            // For SSH, try SSH agent
-            if url.starts_with("git@") || url.starts_with("ssh://") {
-                if let Ok(cred) = git2::Cred::ssh_key_from_agent(username_from_url.unwrap_or("git"))
-                {
-                    return Ok(cred);
-                }
-            }
+/// A pull that stopped before fast-forwarding `branch` to the fetched commit `target`, because
+/// that would overwrite local changes; `GitBackend::fast_forward` finishes it
+#[derive(Debug)]
+pub struct FastForwardConflict {
+    pub branch: String,
+    pub target: String,
+}
 
-            // Try username/password if allowed
-            if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
-                if let Ok(cred) = git2::Cred::credential_helper(&config, url, username_from_url) {
-                    return Ok(cred);
-                }
-            }
+impl std::fmt::Display for FastForwardConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Fast-forwarding {} would overwrite uncommitted changes",
+            self.branch
+        )
+    }
+}
 
-            // Try SSH key if allowed
-            if allowed_types.contains(git2::CredentialType::SSH_KEY) {
-                if let Ok(cred) = git2::Cred::ssh_key_from_agent(username_from_url.unwrap_or("git"))
-                {
-                    return Ok(cred);
-                }
-            }
+impl std::error::Error for FastForwardConflict {}
 
-            // Try default
-            if allowed_types.contains(git2::CredentialType::DEFAULT) {
-                if let Ok(cred) = git2::Cred::default() {
-                    return Ok(cred);
-                }
-            }
+/// Whether a checkout failed because it would overwrite local changes
+pub fn is_checkout_conflict(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<git2::Error>()
+        .is_some_and(|e| e.code() == git2::ErrorCode::Conflict)
+}
 
-            Err(git2::Error::from_str("No valid credentials found"))
-        });
+/// Create an empty repository at `path`, like `git init`
+pub fn init_repository(path: &Path) -> Result<()> {
+    Repository::init(path)?;
+    Ok(())
+}
 
+/// Clone `url` into `dest`, checking out the remote's default branch. Like the other network
+/// operations, this goes through the system git with `shell_git`, or for an SSH URL when a
+/// custom ssh command is configured; otherwise libgit2 does it with the usual credentials.
+pub fn clone_repository(
+    url: &str,
+    dest: &Path,
+    ssh_keys: &SshKeys,
+    shell_git: bool,
+    ui: &mut dyn RemoteUi,
+) -> Result<()> {
+    let config = git2::Config::open_default()?;
+    let existed = dest.exists();
+    let result = if shell_git || (is_ssh_url(url) && custom_ssh_command(&config)) {
+        let target = dest.to_string_lossy();
+        std::env::current_dir()
+            .map_err(anyhow::Error::from)
+            .and_then(|dir| run_git(&dir, &["clone", "--progress", url, &target], ui))
+    } else {
+        let identity = ssh_keys.for_remote("origin");
+        let callbacks = remote_callbacks(
+            config,
+            identity,
+            CredentialCache::default(),
+            PendingCredential::default(),
+            ui,
+        );
         let mut fetch_options = git2::FetchOptions::new();
         fetch_options.remote_callbacks(callbacks);
+        git2::build::RepoBuilder::new()
+            .fetch_options(fetch_options)
+            .clone(url, dest)
+            .map(|_| ())
+            .map_err(anyhow::Error::from)
+    };
+
+    // A cancelled git is killed before it can clean up after itself
+    if result.is_err() && !existed {
+        let _ = std::fs::remove_dir_all(dest);
+    }
+    result
+}
+
+/// Callbacks reporting a transfer to `ui` and answering credential requests: the SSH
+/// `identity` and agent, then cached, helper-provided (per `config`) or prompted passwords.
+/// The password last handed out goes to `pending`, for the caller to cache once it worked.
+fn remote_callbacks<'a>(
+    config: git2::Config,
+    identity: Option<PathBuf>,
+    credential_cache: CredentialCache,
+    pending: PendingCredential,
+    ui: &'a mut dyn RemoteUi,
+) -> git2::RemoteCallbacks<'a> {
+    let ui = Rc::new(RefCell::new(ui));
+    let mut callbacks = git2::RemoteCallbacks::new();
+
+    let receiving = Rc::clone(&ui);
+    callbacks.transfer_progress(move |stats| {
+        let mut ui = receiving.borrow_mut();
+        ui.progress(&TransferProgress {
+            sending: false,
+            objects: stats.received_objects(),
+            total_objects: stats.total_objects(),
+            bytes: stats.received_bytes(),
+            deltas: stats.indexed_deltas(),
+            total_deltas: stats.total_deltas(),
+        });
+        !ui.cancelled()
+    });
+
+    let sending = Rc::clone(&ui);
+    callbacks.push_transfer_progress(move |current, total, bytes| {
+        sending.borrow_mut().progress(&TransferProgress {
+            sending: true,
+            objects: current,
+            total_objects: total,
+            bytes,
+            ..TransferProgress::default()
+        });
+    });
 
-        remote.fetch(&["HEAD"], Some(&mut fetch_options), None)?;
+    // libgit2 can't interrupt a push midway, but it can before the pack is sent
+    let negotiating = Rc::clone(&ui);
+    callbacks.push_negotiation(move |_| {
+        if negotiating.borrow().cancelled() {
+            Err(git2::Error::from_str(tr!("Cancelled")))
+        } else {
+            Ok(())
+        }
+    });
+
+    let mut tried_cache = false;
+    let mut supplied_cached = false;
+    let mut tried_identity = false;
+    let mut tried_agent = false;
+    let mut tried_helper = false;
+    callbacks.credentials(move |url, username_from_url, allowed_types| {
+        let mut ui = ui.borrow_mut();
+        // Don't prompt for an operation that was given up on
+        if ui.cancelled() {
+            return Err(git2::Error::from_str(tr!("Cancelled")));
+        }
 
-        let fetch_head = self.repo.find_reference("FETCH_HEAD")?;
-        let fetch_commit = self.repo.reference_to_annotated_commit(&fetch_head)?;
+        if allowed_types.contains(git2::CredentialType::USERNAME) {
+            return git2::Cred::username(username_from_url.unwrap_or("git"));
+        }
 
-        let analysis = self.repo.merge_analysis(&[&fetch_commit])?;
+        if allowed_types.contains(git2::CredentialType::SSH_KEY) && !tried_identity {
+            tried_identity = true;
+            if let Some(identity) = &identity {
+                let mut public = identity.clone().into_os_string();
+                public.push(".pub");
+                let public = PathBuf::from(public);
+                let public = public.exists().then_some(public.as_path());
+                let username = username_from_url.unwrap_or("git");
+                return git2::Cred::ssh_key(username, public, identity, None);
+            }
+        }
 
-        if analysis.0.is_fast_forward() {
-            let refname = format!("refs/heads/{}", self.get_current_branch()?);
-            let mut reference = self.repo.find_reference(&refname)?;
-            reference.set_target(fetch_commit.id(), "Fast-Forward")?;
-            self.repo.set_head(&refname)?;
-            self.repo
-                .checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
+        if allowed_types.contains(git2::CredentialType::SSH_KEY) && !tried_agent {
+            tried_agent = true;
+            if let Ok(cred) = git2::Cred::ssh_key_from_agent(username_from_url.unwrap_or("git")) {
+                return Ok(cred);
+            }
         }
 
-        Ok(())
-    }
+        if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
+            let host = url_host(url);
+            // Being asked again means what was handed out last was rejected
+            pending.0.borrow_mut().take();
+            if supplied_cached {
+                supplied_cached = false;
+                let mut cache = credential_cache.lock().unwrap_or_else(|e| e.into_inner());
+                cache.remove(&host);
+            }
 
-    pub fn push(&self) -> Result<()> {
-        let mut remote = self.repo.find_remote("origin")?;
-        let branch = self.get_current_branch()?;
-        let refspec = format!("refs/heads/{}", branch);
-
-        // Set up authentication callbacks
-        let mut callbacks = git2::RemoteCallbacks::new();
-        callbacks.credentials(|url, username_from_url, allowed_types| {
-            // For HTTPS, use git credential fill
-            if url.starts_with("https://")
-                && allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT)
-            {
-                // Call git credential fill
-                use std::io::Write;
-                use std::process::{Command, Stdio};
-
-                let mut child = Command::new("git")
-                    .arg("credential")
-                    .arg("fill")
-                    .stdin(Stdio::piped())
-                    .stdout(Stdio::piped())
-                    .stderr(Stdio::inherit())
-                    .spawn()
-                    .map_err(|e| {
-                        git2::Error::from_str(&format!("Failed to spawn git credential: {}", e))
-                    })?;
-
-                // Write the credential request
-                if let Some(stdin) = child.stdin.as_mut() {
-                    let _ = writeln!(stdin, "protocol=https");
-                    let _ = writeln!(stdin, "host=github.com");
-                    if let Some(username) = username_from_url {
-                        let _ = writeln!(stdin, "username={}", username);
-                    }
-                    let _ = writeln!(stdin);
+            let mut found = None;
+            if !tried_cache {
+                tried_cache = true;
+                let cache = credential_cache.lock().unwrap_or_else(|e| e.into_inner());
+                found = cache.get(&host).cloned();
+                supplied_cached = found.is_some();
+            }
+            if found.is_none() && !tried_helper {
+                tried_helper = true;
+                let mut helper = git2::CredentialHelper::new(url);
+                helper.config(&config);
+                if let Some(username) = username_from_url {
+                    helper.username(Some(username));
                 }
+                found = helper.execute();
+            }
+            // Nothing stored, or it was rejected: ask, as often as the user keeps answering.
+            // No lock is held here, since the prompt waits for as long as the user takes.
+            let Some((username, password)) =
+                found.or_else(|| ui.credentials(url, username_from_url))
+            else {
+                return Err(git2::Error::from_str(tr!("Authentication cancelled")));
+            };
+            let cred = git2::Cred::userpass_plaintext(&username, &password);
+            *pending.0.borrow_mut() = Some((host, (username, password)));
+            return cred;
+        }
 
-                let output = child.wait_with_output().map_err(|e| {
-                    git2::Error::from_str(&format!("Failed to get git credential output: {}", e))
-                })?;
+        if allowed_types.contains(git2::CredentialType::DEFAULT) {
+            return git2::Cred::default();
+        }
 
-                if output.status.success() {
-                    let stdout = String::from_utf8_lossy(&output.stdout);
-                    let mut username = String::new();
-                    let mut password = String::new();
+        Err(git2::Error::from_str(tr!("No valid credentials found")))
+    });
+    callbacks
+}
 
-                    for line in stdout.lines() {
-                        if let Some(user) = line.strip_prefix("username=") {
-                            username = user.to_string();
-                        } else if let Some(pass) = line.strip_prefix("password=") {
-                            password = pass.to_string();
+/// Run the system `git` in `dir` for a network operation, passing its output to `ui` line by
+/// line and its progress meters to `ui.progress`. Git never prompts on the terminal, so
+/// credentials must come from its own helpers (OS keychains and the like).
+fn run_git(dir: &Path, args: &[&str], ui: &mut dyn RemoteUi) -> Result<()> {
+    use std::io::Read;
+    use std::process::{Command, Stdio};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    ui.output(&format!("$ git {}", args.join(" ")));
+    let mut command = Command::new("git");
+    command
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // ssh opens /dev/tty for host key and passphrase prompts, which would draw over the TUI and
+    // wait for keys it never gets. In a session of its own git has no terminal to open, so ssh
+    // uses `SSH_ASKPASS` if there is one and otherwise fails with a message shown below.
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // SAFETY: setsid is async-signal-safe and only touches the child
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|e| anyhow::anyhow!(tr!("Failed to run git: {}", e)))?;
+
+    // Git redraws progress meters with `\r`, so split on that as well as newlines
+    let (tx, rx) = mpsc::channel::<String>();
+    let readers: Vec<_> = [
+        child
+            .stdout
+            .take()
+            .map(|out| Box::new(out) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|err| Box::new(err) as Box<dyn Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|mut pipe| {
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            let mut line = Vec::new();
+            while let Ok(n) = pipe.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                for &byte in &buf[..n] {
+                    if byte == b'\n' || byte == b'\r' {
+                        if !line.is_empty() {
+                            let _ = tx.send(String::from_utf8_lossy(&line).into_owned());
+                            line.clear();
                         }
-                    }
-
-                    if !username.is_empty() && !password.is_empty() {
-                        return git2::Cred::userpass_plaintext(&username, &password);
+                    } else {
+                        line.push(byte);
                     }
                 }
             }
-
-            // For SSH
-            if url.starts_with("git@")
-                || url.starts_with("ssh://")
-                || allowed_types.contains(git2::CredentialType::SSH_KEY)
-            {
-                if let Ok(cred) = git2::Cred::ssh_key_from_agent(username_from_url.unwrap_or("git"))
+            if !line.is_empty() {
+                let _ = tx.send(String::from_utf8_lossy(&line).into_owned());
+            }
+        })
+    })
+    .collect();
+    drop(tx);
+
+    let mut progress = TransferProgress::default();
+    let mut last_line = String::new();
+    // When the transport fails, git follows what ssh said with a generic explanation; the
+    // failure is what ssh said
+    let mut transport_error = None;
+    loop {
+        if ui.cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!(tr!("Cancelled"));
+        }
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(line) => {
+                let line = line.trim_end().to_string();
+                if parse_git_progress(&line, &mut progress) {
+                    ui.progress(&progress);
+                    if !line.ends_with("done.") {
+                        continue;
+                    }
+                }
+                ui.output(&line);
+                if line.starts_with("fatal: Could not read from remote repository")
+                    && transport_error.is_none()
+                    && !last_line.is_empty()
                 {
-                    return Ok(cred);
+                    transport_error = Some(std::mem::take(&mut last_line));
                 }
+                last_line = line;
             }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    for reader in readers {
+        let _ = reader.join();
+    }
 
-            // Try default
-            if allowed_types.contains(git2::CredentialType::DEFAULT) {
-                return git2::Cred::default();
-            }
+    let status = child.wait()?;
+    if !status.success() {
+        let message = transport_error.unwrap_or(last_line);
+        anyhow::bail!(tr!("git {} failed: {}", args[0], message));
+    }
+    Ok(())
+}
 
-            Err(git2::Error::from_str("No valid credentials found"))
-        });
+/// Whether `GIT_SSH_COMMAND`, `GIT_SSH` or `core.sshCommand` pick the ssh program or its
+/// options, which libgit2 ignores
+fn custom_ssh_command(config: &git2::Config) -> bool {
+    let configured = |var: &str| std::env::var_os(var).is_some_and(|value| !value.is_empty());
+    configured("GIT_SSH_COMMAND")
+        || configured("GIT_SSH")
+        || config
+            .get_string("core.sshCommand")
+            .is_ok_and(|command| !command.is_empty())
+}
 
-        let mut push_options = git2::PushOptions::new();
-        push_options.remote_callbacks(callbacks);
+/// Update `progress` from a git progress meter line such as
+/// `Receiving objects:  45% (9/20), 1.20 MiB | 2.00 MiB/s`; false for any other line
+fn parse_git_progress(line: &str, progress: &mut TransferProgress) -> bool {
+    let line = line.strip_prefix("remote: ").unwrap_or(line);
+    let Some((stage, rest)) = line.split_once(": ") else {
+        return false;
+    };
+    let Some(counts) = rest
+        .split_once('(')
+        .and_then(|(_, rest)| rest.split_once(')'))
+        .map(|(counts, _)| counts)
+    else {
+        return false;
+    };
+    let Some((done, total)) = counts.split_once('/') else {
+        return false;
+    };
+    let (Ok(done), Ok(total)) = (done.trim().parse::<usize>(), total.trim().parse::<usize>())
+    else {
+        return false;
+    };
+
+    match stage {
+        "Receiving objects" | "Writing objects" => {
+            progress.sending = stage == "Writing objects";
+            progress.objects = done;
+            progress.total_objects = total;
+            if let Some(bytes) = rest
+                .split_once("), ")
+                .and_then(|(_, rest)| rest.split(" | ").next())
+                .and_then(parse_git_size)
+            {
+                progress.bytes = bytes;
+            }
+        }
+        "Resolving deltas" => {
+            progress.deltas = done;
+            progress.total_deltas = total;
+        }
+        _ => return line.contains('%'),
+    }
+    true
+}
 
-        remote.push(&[&refspec], Some(&mut push_options))?;
-        Ok(())
+/// Byte count in the largest unit that keeps it above 1, like `git` prints transfer sizes
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
     }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.2} {}", value, UNITS[unit])
+}
 
-    pub fn push_branch(&self, branch_name: &str) -> Result<()> {
-        let mut remote = self.repo.find_remote("origin")?;
-        let refspec = format!("refs/heads/{}:refs/heads/{}", branch_name, branch_name);
+/// Parse a size as git prints it, e.g. `1.20 MiB` or `250 bytes`
+fn parse_git_size(size: &str) -> Option<usize> {
+    let (number, unit) = size.trim().split_once(' ')?;
+    let number: f64 = number.parse().ok()?;
+    let scale = match unit.trim_end_matches(',') {
+        "bytes" | "byte" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number * scale) as usize)
+}
 
-        // Set up authentication callbacks
-        let mut callbacks = git2::RemoteCallbacks::new();
-        callbacks.credentials(|url, username_from_url, allowed_types| {
-            // For HTTPS, use git credential fill
-            if url.starts_with("https://")
-                && allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT)
-            {
-                // Call git credential fill
-                use std::io::Write;
-                use std::process::{Command, Stdio};
-
-                let mut child = Command::new("git")
-                    .arg("credential")
-                    .arg("fill")
-                    .stdin(Stdio::piped())
-                    .stdout(Stdio::piped())
-                    .stderr(Stdio::inherit())
-                    .spawn()
-                    .map_err(|e| {
-                        git2::Error::from_str(&format!("Failed to spawn git credential: {}", e))
-                    })?;
-
-                // Write the credential request
-                if let Some(stdin) = child.stdin.as_mut() {
-                    let _ = writeln!(stdin, "protocol=https");
-                    let _ = writeln!(stdin, "host=github.com");
-                    if let Some(username) = username_from_url {
-                        let _ = writeln!(stdin, "username={}", username);
-                    }
-                    let _ = writeln!(stdin);
-                }
-
-                // Read the response
-                if let Ok(output) = child.wait_with_output() {
-                    if output.status.success() {
-                        let stdout = String::from_utf8_lossy(&output.stdout);
-                        let mut username = String::new();
-                        let mut password = String::new();
-
-                        for line in stdout.lines() {
-                            if let Some(value) = line.strip_prefix("username=") {
-                                username = value.to_string();
-                            } else if let Some(value) = line.strip_prefix("password=") {
-                                password = value.to_string();
-                            }
-                        }
+/// Whether a remote URL uses SSH, including scp-like `git@host:path`
+fn is_ssh_url(url: &str) -> bool {
+    url.starts_with("ssh://")
+        || url.starts_with("git+ssh://")
+        || (!url.contains("://") && url.contains('@') && url.contains(':'))
+}
 
-                        return git2::Cred::userpass_plaintext(&username, &password);
-                    }
-                }
-            }
+/// Host (with port) of a remote URL such as `https://user@example.com:8443/repo.git`
+fn url_host(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split('/').next().unwrap_or(rest);
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    host.to_string()
+}
 
-            // For SSH
-            if url.starts_with("git@")
-                || url.starts_with("ssh://")
-                || allowed_types.contains(git2::CredentialType::SSH_KEY)
-            {
-                if let Ok(cred) = git2::Cred::ssh_key_from_agent(username_from_url.unwrap_or("git"))
-                {
-                    return Ok(cred);
+/// Render a diff as patch text, prefixing content lines with their origin marker
+fn append_patch_text(
+    repo: &Repository,
+    diff: &git2::Diff,
+    diff_text: &mut String,
+    max_lines: Option<usize>,
+) -> Result<usize> {
+    // Past `max_lines` the rest is only counted
+    let mut lines = 0;
+    let mut omitted = 0;
+    diff.print(git2::DiffFormat::Patch, |delta, _hunk, line| {
+        let origin = line.origin();
+        let content = std::str::from_utf8(line.content()).unwrap_or("");
+        let count = content.matches('\n').count().max(1);
+        if omitted > 0 || max_lines.is_some_and(|max| lines + count > max) {
+            omitted += count;
+            return true;
+        }
+        lines += count;
+
+        let is_link = |file: git2::DiffFile| file.mode() == git2::FileMode::Link;
+        match origin {
+            // A symlink's content is its target
+            '+' if is_link(delta.new_file()) => {
+                diff_text.push_str(&format!("+-> {}\n", content.trim_end_matches('\n')))
+            }
+            '-' if is_link(delta.old_file()) => {
+                diff_text.push_str(&format!("--> {}\n", content.trim_end_matches('\n')))
+            }
+            '+' | '-' | ' ' => {
+                diff_text.push(origin);
+                diff_text.push_str(content);
+            }
+            // Link targets never end in a newline, so git's note saying so is noise
+            '=' | '>' | '<' if is_link(delta.new_file()) || is_link(delta.old_file()) => {}
+            // File header, with `old mode`/`new mode` lines for mode changes
+            'F' => diff_text.push_str(&describe_modes(content)),
+            // "Binary files a/x and b/x differ"
+            'B' => {
+                if let Some(summary) = image_summary(repo, &delta) {
+                    diff_text.push_str(&summary);
+                    return true;
                 }
+                let file = match delta.status() {
+                    git2::Delta::Deleted => delta.old_file(),
+                    _ => delta.new_file(),
+                };
+                diff_text.push_str(&binary_summary(file.size(), "changed"));
             }
+            _ => {
+                diff_text.push_str(content);
+            }
+        }
+        true
+    })?;
+    Ok(omitted)
+}
 
-            // Try default
-            if allowed_types.contains(git2::CredentialType::DEFAULT) {
-                return git2::Cred::default();
+/// Patch header with what each file mode means spelled out, e.g. `new mode 100755 (executable)`
+fn describe_modes(header: &str) -> String {
+    header
+        .split_inclusive('\n')
+        .map(|line| {
+            let description = line
+                .trim_end()
+                .rsplit_once(' ')
+                .filter(|(key, _)| key.ends_with(" mode"))
+                .and_then(|(_, mode)| match mode {
+                    "100644" => Some("regular file"),
+                    "100755" => Some("executable"),
+                    "120000" => Some("symbolic link"),
+                    "160000" => Some("submodule"),
+                    _ => None,
+                });
+            match description {
+                Some(description) => format!("{} ({})\n", line.trim_end(), description),
+                None => line.to_string(),
             }
+        })
+        .collect()
+}
 
-            Err(git2::Error::from_str("No valid credentials found"))
-        });
+/// Short ids and subjects of the commits reachable from `to` but not from `from`, newest first
+fn commits_between(repo: &Repository, from: Oid, to: Oid) -> Vec<String> {
+    let Ok(mut revwalk) = repo.revwalk() else {
+        return Vec::new();
+    };
+    if revwalk.push(to).is_err() || revwalk.hide(from).is_err() {
+        return Vec::new();
+    }
+    revwalk
+        .filter_map(|id| repo.find_commit(id.ok()?).ok())
+        .map(|commit| format!("{:.7} {}", commit.id(), commit.summary().unwrap_or("")))
+        .collect()
+}
 
-        let mut push_options = git2::PushOptions::new();
-        push_options.remote_callbacks(callbacks);
+/// End truncated patch text with how much of it was left out
+fn push_omitted_note(diff_text: &mut String, omitted: usize) {
+    if omitted > 0 {
+        diff_text.push_str(&tr!("… {} more lines, press l to load", omitted));
+        diff_text.push('\n');
+    }
+}
 
-        remote.push(&[&refspec], Some(&mut push_options))?;
-        Ok(())
+/// Two-column status code as `git status --porcelain` prints it: the index change, then the
+/// worktree change, e.g. `MM` for a file with staged and unstaged edits
+fn status_code(status: Status) -> String {
+    if status.contains(Status::CONFLICTED) {
+        return "UU".to_string();
     }
+    if status.contains(Status::WT_NEW) {
+        return "??".to_string();
+    }
+    let index = match status {
+        s if s.contains(Status::INDEX_NEW) => 'A',
+        s if s.contains(Status::INDEX_MODIFIED) => 'M',
+        s if s.contains(Status::INDEX_DELETED) => 'D',
+        s if s.contains(Status::INDEX_RENAMED) => 'R',
+        s if s.contains(Status::INDEX_TYPECHANGE) => 'T',
+        _ => ' ',
+    };
+    let worktree = match status {
+        s if s.contains(Status::WT_MODIFIED) => 'M',
+        s if s.contains(Status::WT_DELETED) => 'D',
+        s if s.contains(Status::WT_RENAMED) => 'R',
+        s if s.contains(Status::WT_TYPECHANGE) => 'T',
+        _ => ' ',
+    };
+    format!("{}{}", index, worktree)
+}
 
-    pub fn sync(&self) -> Result<()> {
-        self.pull()?;
-        self.push()?;
-        Ok(())
+/// Single-letter code for a diff delta, as shown by `git show --name-status`
+fn delta_status_char(status: git2::Delta) -> char {
+    match status {
+        git2::Delta::Added => 'A',
+        git2::Delta::Deleted => 'D',
+        git2::Delta::Modified => 'M',
+        git2::Delta::Renamed => 'R',
+        git2::Delta::Copied => 'C',
+        git2::Delta::Typechange => 'T',
+        git2::Delta::Untracked => '?',
+        git2::Delta::Conflicted => 'U',
+        _ => ' ',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn git_progress_lines() {
+        // (line, recognized, objects, total objects, bytes, deltas, total deltas)
+        let cases = [
+            (
+                "remote: Enumerating objects: 42, done.",
+                false,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ),
+            (
+                "remote: Counting objects: 100% (42/42), done.",
+                true,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ),
+            (
+                "remote: Compressing objects:  50% (10/20)",
+                true,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ),
+            ("Receiving objects:  37% (15/40)", true, 15, 40, 0, 0, 0),
+            (
+                "Receiving objects:  62% (25/40), 1.50 MiB | 2.00 MiB/s",
+                true,
+                25,
+                40,
+                1_572_864,
+                0,
+                0,
+            ),
+            (
+                "Receiving objects: 100% (40/40), 512.00 KiB | 1.10 MiB/s, done.",
+                true,
+                40,
+                40,
+                524_288,
+                0,
+                0,
+            ),
+            (
+                "Resolving deltas: 100% (12/12), done.",
+                true,
+                0,
+                0,
+                0,
+                12,
+                12,
+            ),
+            ("From github.com:ekeel/Gitui", false, 0, 0, 0, 0, 0),
+        ];
+        for (line, recognized, objects, total_objects, bytes, deltas, total_deltas) in cases {
+            let mut progress = TransferProgress::default();
+            assert_eq!(
+                parse_git_progress(line, &mut progress),
+                recognized,
+                "{}",
+                line
+            );
+            assert_eq!(progress.objects, objects, "{}", line);
+            assert_eq!(progress.total_objects, total_objects, "{}", line);
+            assert_eq!(progress.bytes, bytes, "{}", line);
+            assert_eq!(progress.deltas, deltas, "{}", line);
+            assert_eq!(progress.total_deltas, total_deltas, "{}", line);
+            assert!(!progress.sending, "{}", line);
+        }
+    }
+
+    #[test]
+    fn push_progress_is_sending() {
+        let mut progress = TransferProgress::default();
+        assert!(parse_git_progress(
+            "Writing objects: 100% (3/3), 290 bytes | 290.00 KiB/s, done.",
+            &mut progress
+        ));
+        assert!(progress.sending);
+        assert_eq!((progress.objects, progress.bytes), (3, 290));
+    }
+
+    #[test]
+    fn git_sizes() {
+        let cases = [
+            ("250 bytes", Some(250)),
+            ("1 byte", Some(1)),
+            ("512.00 KiB", Some(524_288)),
+            ("1.50 MiB", Some(1_572_864)),
+            ("2.00 GiB", Some(2_147_483_648)),
+            (" 3.25 KiB, ", Some(3328)),
+            ("12 parsecs", None),
+            ("MiB", None),
+        ];
+        for (size, expected) in cases {
+            assert_eq!(parse_git_size(size), expected, "{}", size);
+        }
     }
 }
@@ -0,0 +1,285 @@
+use crate::app::CommitInfo;
+
+/// One character of a history row's graph, tagged with the lane colour it belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphCell {
+    pub glyph: char,
+    pub color: usize,
+}
+
+impl GraphCell {
+    const EMPTY: GraphCell = GraphCell {
+        glyph: ' ',
+        color: 0,
+    };
+}
+
+/// Graph layout for a single commit row
+#[derive(Debug, Clone)]
+pub struct GraphInfo {
+    /// Two cells per lane: the lane glyph and the gap to its right
+    pub cells: Vec<GraphCell>,
+}
+
+/// A lane waiting for the commit it will draw next, with the colour it was started with
+#[derive(Debug, Clone)]
+struct Lane {
+    commit_id: String,
+    color: usize,
+}
+
+/// Lanes as they stand below the last row laid out, so that further pages of history carry on
+/// from there instead of laying out everything again
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    lanes: Vec<Option<Lane>>,
+    next_color: usize,
+}
+
+/// Lay out `commits` from scratch; see `Layout::extend`
+pub fn layout(commits: &mut [CommitInfo]) {
+    Layout::default().extend(commits);
+}
+
+impl Layout {
+    /// Assign lanes to `commits` (newest first, topologically sorted, following the rows laid
+    /// out so far) and fill in their `graph_info`, drawing merge joins where lanes fork off or
+    /// converge.
+    pub fn extend(&mut self, commits: &mut [CommitInfo]) {
+        let lanes = &mut self.lanes;
+        let next_color = &mut self.next_color;
+        let mut new_color = || {
+            *next_color += 1;
+            *next_color - 1
+        };
+
+        for commit in commits.iter_mut() {
+            // The commit sits in the first lane expecting it, or a fresh lane for branch tips
+            let expecting: Vec<usize> = lanes
+                .iter()
+                .enumerate()
+                .filter(|(_, lane)| lane.as_ref().is_some_and(|l| l.commit_id == commit.id))
+                .map(|(i, _)| i)
+                .collect();
+            let column = match expecting.first() {
+                Some(&col) => col,
+                None => {
+                    let col = free_lane(lanes);
+                    lanes[col] = Some(Lane {
+                        commit_id: commit.id.clone(),
+                        color: new_color(),
+                    });
+                    col
+                }
+            };
+            let color = lanes[column].as_ref().map_or(0, |lane| lane.color);
+
+            let mut cells = vec![GraphCell::EMPTY; lanes.len() * 2];
+            for (i, lane) in lanes.iter().enumerate() {
+                if let Some(lane) = lane {
+                    cells[i * 2] = GraphCell {
+                        glyph: '│',
+                        color: lane.color,
+                    };
+                }
+            }
+
+            // Other children's lanes end here and converge on this commit
+            for &other in expecting.iter().skip(1) {
+                let lane_color = lanes[other].as_ref().map_or(color, |lane| lane.color);
+                let glyph = if other > column { '┘' } else { '└' };
+                connect(&mut cells, column, other, glyph, lane_color);
+            }
+
+            // First parent continues this lane; further parents join or fork off
+            let mut parents = commit.parent_ids.iter();
+            lanes[column] = parents.next().map(|parent| Lane {
+                commit_id: parent.clone(),
+                color,
+            });
+            for parent in parents {
+                let existing = lanes
+                    .iter()
+                    .position(|lane| lane.as_ref().is_some_and(|l| l.commit_id == *parent));
+                match existing {
+                    Some(target) if target != column => {
+                        let lane_color = lanes[target].as_ref().map_or(color, |lane| lane.color);
+                        let glyph = if target > column { '┤' } else { '├' };
+                        connect(&mut cells, column, target, glyph, lane_color);
+                    }
+                    Some(_) => {}
+                    None => {
+                        let target = free_lane(lanes);
+                        let lane_color = new_color();
+                        lanes[target] = Some(Lane {
+                            commit_id: parent.clone(),
+                            color: lane_color,
+                        });
+                        if cells.len() < lanes.len() * 2 {
+                            cells.resize(lanes.len() * 2, GraphCell::EMPTY);
+                        }
+                        let glyph = if target > column { '┐' } else { '┌' };
+                        connect(&mut cells, column, target, glyph, lane_color);
+                    }
+                }
+            }
+
+            // Free converged lanes only now, so a fork on this row can't draw over their join
+            for &other in expecting.iter().skip(1) {
+                lanes[other] = None;
+            }
+
+            cells[column * 2] = GraphCell {
+                glyph: if commit.parent_ids.len() > 1 {
+                    '◎'
+                } else {
+                    '●'
+                },
+                color,
+            };
+
+            // Drop trailing lanes that have ended so the graph doesn't keep growing
+            while matches!(lanes.last(), Some(None)) {
+                lanes.pop();
+            }
+            while cells.len() > 2 && cells[cells.len() - 2..].iter().all(|c| c.glyph == ' ') {
+                cells.truncate(cells.len() - 2);
+            }
+
+            commit.graph_info = Some(GraphInfo { cells });
+        }
+    }
+}
+
+/// Reuse the leftmost free lane, or open a new one on the right
+fn free_lane(lanes: &mut Vec<Option<Lane>>) -> usize {
+    match lanes.iter().position(|lane| lane.is_none()) {
+        Some(col) => col,
+        None => {
+            lanes.push(None);
+            lanes.len() - 1
+        }
+    }
+}
+
+/// Draw a horizontal join from the node at `from` to the lane at `to`, ending in `glyph`
+fn connect(cells: &mut [GraphCell], from: usize, to: usize, glyph: char, color: usize) {
+    let (start, end) = if from < to {
+        (from * 2 + 1, to * 2)
+    } else {
+        (to * 2 + 1, from * 2)
+    };
+    for cell in &mut cells[start..end] {
+        cell.glyph = match cell.glyph {
+            '│' => '┼',
+            '┘' | '└' => '┴',
+            '┐' | '┌' => '┬',
+            ' ' | '─' => '─',
+            other => other,
+        };
+        if cell.glyph == '─' {
+            cell.color = color;
+        }
+    }
+    cells[to * 2] = GraphCell { glyph, color };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(id, parents)` rows, newest first, as commits without a layout
+    fn commits(history: &[(&str, &[&str])]) -> Vec<CommitInfo> {
+        history
+            .iter()
+            .map(|(id, parents)| CommitInfo {
+                id: id.to_string(),
+                author: String::new(),
+                date: String::new(),
+                message: String::new(),
+                parent_ids: parents.iter().map(|p| p.to_string()).collect(),
+                graph_info: None,
+                branches: Vec::new(),
+                tags: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Each laid-out row's glyphs
+    fn glyphs(commits: &[CommitInfo]) -> Vec<String> {
+        commits
+            .iter()
+            .map(|commit| {
+                let cells = &commit.graph_info.as_ref().unwrap().cells;
+                let row: String = cells.iter().map(|cell| cell.glyph).collect();
+                row.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    /// Lay out `(id, parents)` rows, newest first, and render each row's glyphs
+    fn rows(history: &[(&str, &[&str])]) -> Vec<String> {
+        let mut commits = commits(history);
+        layout(&mut commits);
+        glyphs(&commits)
+    }
+
+    #[test]
+    fn linear_history_stays_in_one_lane() {
+        assert_eq!(
+            rows(&[("c", &["b"]), ("b", &["a"]), ("a", &[])]),
+            ["●", "●", "●"]
+        );
+    }
+
+    #[test]
+    fn fork_converges_on_the_common_parent() {
+        assert_eq!(
+            rows(&[("d", &["b"]), ("c", &["b"]), ("b", &["a"]), ("a", &[])]),
+            ["●", "│ ●", "●─┘", "●"]
+        );
+    }
+
+    #[test]
+    fn merge_forks_a_lane_for_the_second_parent() {
+        assert_eq!(
+            rows(&[("m", &["b", "c"]), ("c", &["a"]), ("b", &["a"]), ("a", &[])]),
+            ["◎─┐", "│ ●", "● │", "●─┘"]
+        );
+    }
+
+    #[test]
+    fn octopus_merge_forks_a_lane_per_parent() {
+        assert_eq!(
+            rows(&[
+                ("m", &["b", "c", "d"]),
+                ("d", &["a"]),
+                ("c", &["a"]),
+                ("b", &["a"]),
+                ("a", &[]),
+            ]),
+            ["◎─┬─┐", "│ │ ●", "│ ● │", "● │ │", "●─┴─┘"]
+        );
+    }
+
+    #[test]
+    fn pages_continue_the_lanes_of_earlier_ones() {
+        let history: &[(&str, &[&str])] =
+            &[("m", &["b", "c"]), ("c", &["a"]), ("b", &["a"]), ("a", &[])];
+        let mut paged = commits(history);
+        let (first, rest) = paged.split_at_mut(2);
+        let mut graph = Layout::default();
+        graph.extend(first);
+        graph.extend(rest);
+        assert_eq!(glyphs(&paged), rows(history));
+    }
+
+    #[test]
+    fn ended_lanes_are_reused_and_trimmed() {
+        // `x` ends its lane at the root; the next tip takes the freed lane
+        assert_eq!(
+            rows(&[("b", &["a"]), ("x", &[]), ("y", &["a"]), ("a", &[])]),
+            ["●", "│ ●", "│ ●", "●─┘"]
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use crate::config::AppConfig;
+
+/// Active message catalog: English message id -> translated text
+static CATALOG: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Translate a user-facing message, optionally filling `{}` / `{0}` placeholders.
+///
+/// English text doubles as the message id, so untranslated messages fall back to it.
+macro_rules! tr {
+    ($msg:literal) => {
+        $crate::i18n::translate($msg)
+    };
+    ($msg:literal, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::format_message(
+            $crate::i18n::translate($msg),
+            &[$(&$arg as &dyn std::fmt::Display),+],
+        )
+    };
+}
+pub(crate) use tr;
+
+/// Pick the locale from config, then `LC_ALL`/`LC_MESSAGES`/`LANG` (e.g. `de_DE.UTF-8` -> `de`)
+pub fn resolve_locale(configured: Option<&str>) -> Option<String> {
+    if let Some(locale) = configured {
+        return Some(locale.to_string());
+    }
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .split(['_', '.', '@'])
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .filter(|locale| locale != "C" && locale != "POSIX" && locale != "en")
+}
+
+/// Load `<config dir>/locales/<locale>.toml`, a flat table of `"English" = "translation"`.
+/// Only an explicitly configured locale without a catalog is reported as an error.
+pub fn init(locale: Option<&str>, explicit: bool) -> Result<()> {
+    let Some(locale) = locale else {
+        return Ok(());
+    };
+    let Some(path) =
+        AppConfig::config_dir().map(|dir| dir.join("locales").join(format!("{}.toml", locale)))
+    else {
+        return Ok(());
+    };
+
+    if !path.exists() {
+        if explicit {
+            anyhow::bail!(tr!(
+                "No message catalog for locale '{}' at {}",
+                locale,
+                path.display()
+            ));
+        }
+        return Ok(());
+    }
+
+    let contents = std::fs::read_to_string(&path)?;
+    let catalog: HashMap<String, String> = toml::from_str(&contents)
+        .map_err(|e| anyhow::anyhow!(tr!("Invalid message catalog {}: {}", path.display(), e)))?;
+    let _ = CATALOG.set(catalog);
+    Ok(())
+}
+
+pub fn translate(msgid: &'static str) -> &'static str {
+    CATALOG
+        .get()
+        .and_then(|catalog| catalog.get(msgid))
+        .map(|text| text.as_str())
+        .unwrap_or(msgid)
+}
+
+/// Substitute `{}` placeholders in order, or `{N}` by position so translations can reorder them
+pub fn format_message(template: &str, args: &[&dyn Display]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut next_arg = 0;
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            result.push_str(&rest[start..]);
+            return result;
+        };
+
+        let placeholder = &after[..end];
+        let index = if placeholder.is_empty() {
+            next_arg += 1;
+            Some(next_arg - 1)
+        } else {
+            placeholder.parse::<usize>().ok()
+        };
+
+        match index.and_then(|i| args.get(i)) {
+            Some(arg) => result.push_str(&arg.to_string()),
+            None => result.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &after[end + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
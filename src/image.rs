@@ -0,0 +1,94 @@
+/// Format and pixel size of an image, read from its header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub format: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Whether `path` has the extension of an image format `image_info` understands
+pub fn has_image_extension(path: &str) -> bool {
+    let Some((_, extension)) = path.rsplit_once('.') else {
+        return false;
+    };
+    ["png", "gif", "jpg", "jpeg", "bmp", "webp"]
+        .iter()
+        .any(|known| extension.eq_ignore_ascii_case(known))
+}
+
+/// Read the format and dimensions from the start of a PNG, GIF, JPEG, BMP or WebP file
+pub fn image_info(data: &[u8]) -> Option<ImageInfo> {
+    let (format, width, height) = if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        ("PNG", be32(data, 16)?, be32(data, 20)?)
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        ("GIF", le16(data, 6)?, le16(data, 8)?)
+    } else if data.starts_with(b"BM") {
+        let height = le32(data, 22)? as i32;
+        ("BMP", le32(data, 18)?, height.unsigned_abs())
+    } else if data.starts_with(b"\xff\xd8") {
+        let (width, height) = jpeg_size(data)?;
+        ("JPEG", width, height)
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        let (width, height) = webp_size(data)?;
+        ("WebP", width, height)
+    } else {
+        return None;
+    };
+    Some(ImageInfo {
+        format,
+        width,
+        height,
+    })
+}
+
+/// Walk the JPEG segments up to the start-of-frame marker holding the size
+fn jpeg_size(data: &[u8]) -> Option<(u32, u32)> {
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xff {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        // SOF0-SOF15, except DHT (C4), JPG (C8) and DAC (CC)
+        if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+            return Some((be16(data, pos + 7)?, be16(data, pos + 5)?));
+        }
+        pos += 2 + be16(data, pos + 2)? as usize;
+    }
+}
+
+/// Size from the first chunk of a WebP file: lossy (`VP8 `), lossless (`VP8L`) or extended (`VP8X`)
+fn webp_size(data: &[u8]) -> Option<(u32, u32)> {
+    match data.get(12..16)? {
+        b"VP8 " => Some((le16(data, 26)? & 0x3fff, le16(data, 28)? & 0x3fff)),
+        b"VP8L" => {
+            let bits = le32(data, 21)?;
+            Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+        }
+        b"VP8X" => Some((le24(data, 24)? + 1, le24(data, 27)? + 1)),
+        _ => None,
+    }
+}
+
+fn be16(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]) as u32)
+}
+
+fn be32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le16(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]) as u32)
+}
+
+fn le24(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 3)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]))
+}
+
+fn le32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
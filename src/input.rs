@@ -1,293 +1,268 @@
 use anyhow::Result;
-use crossterm::event::{KeyCode, KeyEvent, MouseEvent, MouseEventKind};
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::layout::{Position, Rect};
+use ratatui::widgets::Block;
+use ratatui::{backend::CrosstermBackend, Terminal};
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
-use crate::app::{App, View};
-use crate::git::GitRepo;
+use crate::app::{
+    current_hunk, hunk_starts, hunk_text, App, BisectMark, BisectPanel, BisectStep, BlameView,
+    BranchCheckout, CloneDialog, ConflictEditor, CredentialPrompt, GrepSearch, InProgress,
+    RebasePlan, RemoteAction, RevisionInput, StaleBranch, StaleBranchReport, SubmoduleList,
+    TransferProgress, View, WorktreeInfo, WorktreeList, GREP_MATCH_LIMIT, SPLIT_RANGE,
+};
+use crate::config::{expand_home, AppConfig, ConfirmLevel};
+use crate::conflict::{Choice, ConflictFile};
+use crate::dialog::{Answer, Dialog, DialogAction, DialogKind};
+use crate::export;
+use crate::git::{self, GitBackend, RemoteUi};
+use crate::graph;
+use crate::i18n::tr;
+use crate::panel::Panel;
+use crate::terminal::{
+    copy_to_clipboard, request_redraw, take_redraw_request, with_terminal_suspended,
+};
+use crate::ui::{pane_borders, render_ui, screen_layout, split_panes};
 
-pub fn handle_mouse_event(app: &mut App, mouse: MouseEvent) -> Result<()> {
+pub fn handle_mouse_event(
+    app: &mut App,
+    mouse: MouseEvent,
+    git_repo: &dyn GitBackend,
+) -> Result<()> {
     match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) if !app.is_overlay_open() => {
+            let main = main_area()?;
+            // Either side of the line between the list and the diff grabs it
+            let border = open_split(app).map(|split| split_panes(main, split)[0].right());
+            if border.is_some_and(|border| mouse.column + 1 == border || mouse.column == border) {
+                app.dragging_split = true;
+            } else {
+                select_clicked_row(app, main, mouse.column, mouse.row, git_repo)?;
+            }
+        }
+        MouseEventKind::Drag(MouseButton::Left) if app.dragging_split => {
+            let main = main_area()?;
+            let list_width = (mouse.column + 1).saturating_sub(main.x);
+            let width = u32::from(main.width.max(1));
+            let percent = ((u32::from(list_width) * 100 + width / 2) / width) as u16;
+            if let Some((split, _)) = split_mut(app) {
+                *split = percent.clamp(SPLIT_RANGE.0, SPLIT_RANGE.1);
+            }
+        }
+        MouseEventKind::Up(MouseButton::Left) if app.dragging_split => {
+            app.dragging_split = false;
+            if let Some((split, key)) = split_mut(app) {
+                let list = *split;
+                save_split(app, key, list);
+            }
+        }
         MouseEventKind::ScrollDown => {
             if app.current_view == View::Files {
                 app.scroll_diff_down();
             }
+            if app.current_view == View::History {
+                app.scroll_detail_down();
+            }
         }
         MouseEventKind::ScrollUp => {
             if app.current_view == View::Files {
                 app.scroll_diff_up();
             }
+            if app.current_view == View::History {
+                app.scroll_detail_up();
+            }
         }
         _ => {}
     }
     Ok(())
 }
 
-pub fn handle_key_event(app: &mut App, key: KeyEvent, git_repo: &GitRepo) -> Result<()> {
-    // Global key bindings (only when no dialog is open)
-    match key.code {
-        KeyCode::Char('q')
-            if !app.show_commit_dialog && !app.show_branch_dialog && !app.show_delete_confirm =>
+/// Select the list row under a click in the Files, History or Branches list, as if the
+/// selection had been moved there with `j`/`k`
+fn select_clicked_row(
+    app: &mut App,
+    main: Rect,
+    column: u16,
+    row: u16,
+    git_repo: &dyn GitBackend,
+) -> Result<()> {
+    let list = match open_split(app) {
+        Some(split) => split_panes(main, split)[0],
+        None => main,
+    };
+    let Some(row) = list_row(app, list, column, row) else {
+        return Ok(());
+    };
+    match app.current_view {
+        View::Files => {
+            // Rows are the files with a heading above the staged and the unstaged ones
+            let mut rows = Vec::new();
+            let mut section = None;
+            for (i, file) in app.files_state.files.iter().enumerate() {
+                if section != Some(file.staged) {
+                    section = Some(file.staged);
+                    rows.push(None);
+                }
+                rows.push(Some(i));
+            }
+            let Some(&Some(index)) = rows.get(row) else {
+                return Ok(());
+            };
+            if index != app.files_state.selected {
+                app.files_state.selected = index;
+                app.reset_diff_scroll();
+                update_file_diff(app, git_repo)?;
+            }
+        }
+        View::History => {
+            // The list scrolls just far enough to keep the selection on its last row
+            let rows = Block::default()
+                .borders(pane_borders(app))
+                .inner(list)
+                .height as usize;
+            let first = (app.history_state.selected + 1).saturating_sub(rows);
+            let index = first + row;
+            if index >= app.history_state.commits.len() || index == app.history_state.selected {
+                return Ok(());
+            }
+            app.history_state.selected = index;
+            ensure_history_loaded(app, git_repo)?;
+            update_history_pane(app, git_repo)?;
+        }
+        View::Branches => {
+            let Some(&index) = app.branches_state.visible(app.branch_filter()).get(row) else {
+                return Ok(());
+            };
+            app.branches_state.selected = index;
+        }
+        View::Dashboard | View::Tags => return Ok(()),
+    }
+    announce_selection(app);
+    Ok(())
+}
+
+/// The part of the terminal between the header and the footer the views draw in
+fn main_area() -> Result<Rect> {
+    let (width, height) = crossterm::terminal::size()?;
+    Ok(screen_layout(Rect::new(0, 0, width, height))[1])
+}
+
+/// Percent of the width the list takes when the current view shows a diff or detail pane beside it
+fn open_split(app: &App) -> Option<u16> {
+    match app.current_view {
+        View::Files => Some(app.files_split),
+        View::History
+            if app.history_state.detail.is_some() || app.history_state.commit_diff.is_some() =>
         {
+            Some(app.history_split)
+        }
+        _ => None,
+    }
+}
+
+/// Row of a click inside the bordered list drawn in `area`, counted from its first item
+fn list_row(app: &App, area: Rect, column: u16, row: u16) -> Option<usize> {
+    let inner = Block::default().borders(pane_borders(app)).inner(area);
+    inner
+        .contains(Position::new(column, row))
+        .then(|| usize::from(row - inner.y))
+}
+
+pub fn handle_key_event(app: &mut App, key: KeyEvent, git_repo: &dyn GitBackend) -> Result<()> {
+    // Confirm, input and choice dialogs, over whatever view or panel opened them
+    if !app.dialogs.is_empty() {
+        return handle_dialog_keys(app, key, git_repo);
+    }
+
+    if !app.panels.is_empty() {
+        return handle_panel_keys(app, key, git_repo);
+    }
+
+    // Apply user keybindings; dialogs and panels receive the raw keys
+    let Some(key) = app.keymap.translate(app.current_view, key) else {
+        return Ok(());
+    };
+
+    // Global key bindings
+    match key.code {
+        KeyCode::Char('q') => {
             app.should_quit = true;
             return Ok(());
         }
-        KeyCode::Char('1')
-            if !app.show_commit_dialog && !app.show_branch_dialog && !app.show_delete_confirm =>
-        {
+        KeyCode::Char('0') => {
+            app.switch_view(View::Dashboard);
+            refresh_dashboard(app, git_repo)?;
+            announce_selection(app);
+            return Ok(());
+        }
+        KeyCode::Char('1') => {
             app.switch_view(View::Files);
             refresh_files(app, git_repo)?;
+            announce_selection(app);
             return Ok(());
         }
-        KeyCode::Char('2')
-            if !app.show_commit_dialog && !app.show_branch_dialog && !app.show_delete_confirm =>
-        {
+        KeyCode::Char('2') => {
             app.switch_view(View::History);
             refresh_history(app, git_repo)?;
+            announce_selection(app);
             return Ok(());
         }
-        KeyCode::Char('3')
-            if !app.show_commit_dialog && !app.show_branch_dialog && !app.show_delete_confirm =>
-        {
+        KeyCode::Char('3') => {
             app.switch_view(View::Branches);
             refresh_branches(app, git_repo)?;
+            announce_selection(app);
             return Ok(());
         }
-        KeyCode::Char('r')
-            if !app.show_commit_dialog && !app.show_branch_dialog && !app.show_delete_confirm =>
-        {
-            refresh_current_view(app, git_repo)?;
-            app.set_status("Refreshed".to_string());
+        KeyCode::Char('4') => {
+            app.switch_view(View::Tags);
+            refresh_tags(app, git_repo)?;
+            announce_selection(app);
             return Ok(());
         }
-        _ => {}
-    }
-
-    // Commit dialog handling
-    if app.show_commit_dialog {
-        match key.code {
-            KeyCode::Esc => {
-                app.show_commit_dialog = false;
-                app.commit_message.clear();
-            }
-            KeyCode::Enter => {
-                if !app.commit_message.trim().is_empty() {
-                    match git_repo.commit(&app.commit_message) {
-                        Ok(_) => {
-                            app.set_status("Committed successfully".to_string());
-                            app.commit_message.clear();
-                            app.show_commit_dialog = false;
-                            refresh_files(app, git_repo)?;
-                        }
-                        Err(e) => {
-                            app.set_status(format!("Commit failed: {}", e));
-                        }
-                    }
-                }
-            }
-            KeyCode::Char(c) => {
-                app.commit_message.push(c);
-            }
-            KeyCode::Backspace => {
-                app.commit_message.pop();
-            }
-            _ => {}
+        KeyCode::Char('r') => {
+            refresh_current_view(app, git_repo)?;
+            app.set_status(tr!("Refreshed").to_string());
+            return Ok(());
         }
-        return Ok(());
-    }
-
-    // Branch creation dialog handling
-    if app.show_branch_dialog {
-        if app.branch_creation.selecting_base {
-            // Selecting base branch
-            match key.code {
-                KeyCode::Esc => {
-                    app.show_branch_dialog = false;
-                    app.branch_creation.new_branch_name.clear();
-                    app.branch_creation.selecting_base = false;
-                }
-                KeyCode::Up | KeyCode::Char('k') => {
-                    if app.branch_creation.base_branch_selected > 0 {
-                        app.branch_creation.base_branch_selected -= 1;
-                    }
-                }
-                KeyCode::Down | KeyCode::Char('j') => {
-                    if !app.branches_state.branches.is_empty() {
-                        app.branch_creation.base_branch_selected =
-                            (app.branch_creation.base_branch_selected + 1)
-                                .min(app.branches_state.branches.len() - 1);
-                    }
-                }
-                KeyCode::Enter => {
-                    // Confirm base branch selection, go back to name entry
-                    app.branch_creation.selecting_base = false;
-                }
-                _ => {}
-            }
-        } else {
-            // Entering branch name
-            match key.code {
-                KeyCode::Esc => {
-                    app.show_branch_dialog = false;
-                    app.branch_creation.new_branch_name.clear();
-                }
-                KeyCode::Enter => {
-                    if !app.branch_creation.new_branch_name.trim().is_empty() {
-                        let base_branch = &app
-                            .branches_state
-                            .branches
-                            .get(app.branch_creation.base_branch_selected)
-                            .map(|b| b.name.clone())
-                            .unwrap_or_else(|| app.branches_state.current_branch.clone());
-
-                        let branch_name = app.branch_creation.new_branch_name.clone();
-                        match git_repo.create_branch(&branch_name, base_branch) {
-                            Ok(_) => {
-                                // Push the new branch to remote
-                                app.set_status(format!("Pushing branch to remote..."));
-                                let _ = disable_raw_mode();
-                                let push_result = git_repo.push_branch(&branch_name);
-                                let _ = enable_raw_mode();
-
-                                match push_result {
-                                    Ok(_) => {
-                                        app.set_status(format!(
-                                            "Created and pushed branch: {}",
-                                            branch_name
-                                        ));
-                                    }
-                                    Err(e) => {
-                                        app.set_status(format!(
-                                            "Created branch locally but failed to push: {}",
-                                            e
-                                        ));
-                                    }
-                                }
-                                app.branch_creation.new_branch_name.clear();
-                                app.show_branch_dialog = false;
-                                refresh_branches(app, git_repo)?;
-                            }
-                            Err(e) => {
-                                app.set_status(format!("Failed to create branch: {}", e));
-                            }
-                        }
-                    }
-                }
-                KeyCode::Char(c) => {
-                    app.branch_creation.new_branch_name.push(c);
-                }
-                KeyCode::Backspace => {
-                    app.branch_creation.new_branch_name.pop();
-                }
-                KeyCode::Tab => {
-                    // Switch to base branch selection
-                    app.branch_creation.selecting_base = true;
+        KeyCode::Char('w') => {
+            // Regenerate whichever diff is showing with the new whitespace setting
+            app.ignore_whitespace = !app.ignore_whitespace;
+            app.set_status(if app.ignore_whitespace {
+                tr!("Ignoring whitespace changes in diffs").to_string()
+            } else {
+                tr!("Showing whitespace changes in diffs").to_string()
+            });
+            match app.current_view {
+                View::Files => update_file_diff(app, git_repo)?,
+                View::History if app.history_state.commit_diff.is_some() => {
+                    update_commit_diff(app, git_repo)?
                 }
                 _ => {}
             }
+            return Ok(());
         }
-        return Ok(());
-    }
-
-    // Delete confirmation dialog handling
-    if app.show_delete_confirm {
-        match key.code {
-            KeyCode::Esc => {
-                app.show_delete_confirm = false;
-                app.delete_confirmation.clear();
-                app.branch_to_delete = None;
-            }
-            KeyCode::Enter => {
-                let confirmation = app.delete_confirmation.trim().to_lowercase();
-                if confirmation == "y" || confirmation == "yes" {
-                    if let Some(branch_name) = &app.branch_to_delete {
-                        match git_repo.delete_branch(branch_name) {
-                            Ok(_) => {
-                                app.set_status(format!("Deleted branch: {}", branch_name));
-                                app.show_delete_confirm = false;
-                                app.delete_confirmation.clear();
-                                app.branch_to_delete = None;
-                                refresh_branches(app, git_repo)?;
-                            }
-                            Err(e) => {
-                                app.set_status(format!("Failed to delete branch: {}", e));
-                            }
-                        }
-                    }
-                } else {
-                    app.set_status("Delete cancelled".to_string());
-                    app.show_delete_confirm = false;
-                    app.delete_confirmation.clear();
-                    app.branch_to_delete = None;
-                }
-            }
-            KeyCode::Char(c) => {
-                app.delete_confirmation.push(c);
-            }
-            KeyCode::Backspace => {
-                app.delete_confirmation.pop();
-            }
-            _ => {}
+        KeyCode::Char('O') => {
+            app.panels.push(Panel::Clone(CloneDialog::default()));
+            return Ok(());
         }
-        return Ok(());
-    }
-
-    // Discard confirmation dialog handling
-    if app.show_discard_confirm {
-        match key.code {
-            KeyCode::Esc => {
-                app.show_discard_confirm = false;
-                app.discard_confirmation.clear();
-                app.file_to_discard = None;
-                app.discard_all = false;
-            }
-            KeyCode::Enter => {
-                let confirmation = app.discard_confirmation.trim().to_lowercase();
-                if confirmation == "y" || confirmation == "yes" {
-                    if app.discard_all {
-                        match git_repo.discard_all() {
-                            Ok(_) => {
-                                app.set_status("Discarded all changes".to_string());
-                                app.show_discard_confirm = false;
-                                app.discard_confirmation.clear();
-                                app.file_to_discard = None;
-                                app.discard_all = false;
-                                refresh_files(app, git_repo)?;
-                            }
-                            Err(e) => {
-                                app.set_status(format!("Failed to discard changes: {}", e));
-                            }
-                        }
-                    } else if let Some(file_path) = &app.file_to_discard {
-                        match git_repo.discard_file(file_path) {
-                            Ok(_) => {
-                                app.set_status(format!("Discarded changes to: {}", file_path));
-                                app.show_discard_confirm = false;
-                                app.discard_confirmation.clear();
-                                app.file_to_discard = None;
-                                refresh_files(app, git_repo)?;
-                            }
-                            Err(e) => {
-                                app.set_status(format!("Failed to discard changes: {}", e));
-                            }
-                        }
-                    }
-                } else {
-                    app.set_status("Discard cancelled".to_string());
-                    app.show_discard_confirm = false;
-                    app.discard_confirmation.clear();
-                    app.file_to_discard = None;
-                    app.discard_all = false;
-                }
-            }
-            KeyCode::Char(c) => {
-                app.discard_confirmation.push(c);
-            }
-            KeyCode::Backspace => {
-                app.discard_confirmation.pop();
-            }
-            _ => {}
+        KeyCode::Char('?') => {
+            app.panels.push(Panel::Grep(GrepSearch::default()));
+            return Ok(());
         }
-        return Ok(());
+        KeyCode::Char('L') => {
+            open_command_log(app);
+            return Ok(());
+        }
+        _ => {}
     }
 
     // Navigation
@@ -298,13 +273,36 @@ pub fn handle_key_event(app: &mut App, key: KeyEvent, git_repo: &GitRepo) -> Res
                 app.reset_diff_scroll();
                 update_file_diff(app, git_repo)?;
             }
+            if app.current_view == View::History {
+                update_history_pane(app, git_repo)?;
+            }
+            announce_selection(app);
         }
         KeyCode::Down | KeyCode::Char('j') => {
             app.next_item();
+            if app.current_view == View::History {
+                ensure_history_loaded(app, git_repo)?;
+            }
             if app.current_view == View::Files && !app.files_state.files.is_empty() {
                 app.reset_diff_scroll();
                 update_file_diff(app, git_repo)?;
             }
+            if app.current_view == View::History {
+                update_history_pane(app, git_repo)?;
+            }
+            announce_selection(app);
+        }
+        KeyCode::Char(c @ ('n' | 'N'))
+            if matches!(app.current_view, View::Files | View::History) =>
+        {
+            if !app.jump_to_hunk(c == 'n') {
+                app.set_status(if c == 'n' {
+                    tr!("No further hunk").to_string()
+                } else {
+                    tr!("No earlier hunk").to_string()
+                });
+            }
+            return Ok(());
         }
         KeyCode::PageUp => {
             if app.current_view == View::Files {
@@ -312,6 +310,11 @@ pub fn handle_key_event(app: &mut App, key: KeyEvent, git_repo: &GitRepo) -> Res
                     app.scroll_diff_up();
                 }
             }
+            if app.current_view == View::History {
+                for _ in 0..10 {
+                    app.scroll_detail_up();
+                }
+            }
         }
         KeyCode::PageDown => {
             if app.current_view == View::Files {
@@ -319,229 +322,3985 @@ pub fn handle_key_event(app: &mut App, key: KeyEvent, git_repo: &GitRepo) -> Res
                     app.scroll_diff_down();
                 }
             }
+            if app.current_view == View::History {
+                for _ in 0..10 {
+                    app.scroll_detail_down();
+                }
+            }
         }
         _ => {}
     }
 
     // View-specific key bindings
     match app.current_view {
+        View::Dashboard => handle_dashboard_keys(app, key, git_repo)?,
         View::Files => handle_files_keys(app, key, git_repo)?,
         View::Branches => handle_branches_keys(app, key, git_repo)?,
-        View::History => {}
+        View::History => handle_history_keys(app, key, git_repo)?,
+        View::Tags => handle_tags_keys(app, key, git_repo)?,
     }
 
     Ok(())
 }
 
-fn handle_files_keys(app: &mut App, key: KeyEvent, git_repo: &GitRepo) -> Result<()> {
+fn handle_history_keys(app: &mut App, key: KeyEvent, git_repo: &dyn GitBackend) -> Result<()> {
     match key.code {
-        KeyCode::Char('s') => {
-            // Stage selected file
-            if let Some(file) = app.files_state.files.get(app.files_state.selected) {
-                match git_repo.stage_file(&file.path) {
-                    Ok(_) => {
-                        app.set_status(format!("Staged: {}", file.path));
-                        refresh_files(app, git_repo)?;
-                    }
-                    Err(e) => {
-                        app.set_status(format!("Failed to stage: {}", e));
-                    }
-                }
-            }
+        KeyCode::Char('<') => resize_split(app, true),
+        KeyCode::Char('>') => resize_split(app, false),
+        KeyCode::Char('G') => {
+            let revision = app
+                .history_state
+                .commits
+                .get(app.history_state.selected)
+                .map(|commit| commit.id.clone());
+            open_file_from_revision(app, git_repo, String::new(), revision);
+        }
+        KeyCode::Char('g') => open_jump_dialog(app, git_repo, ""),
+        KeyCode::Char('u') => {
+            undo_last_commit(app, git_repo)?;
         }
         KeyCode::Char('a') => {
-            // Stage all files
-            match git_repo.stage_all() {
-                Ok(_) => {
-                    app.set_status("Staged all files".to_string());
-                    refresh_files(app, git_repo)?;
+            app.history_state.filter.all_branches = !app.history_state.filter.all_branches;
+            reload_filtered_history(app, git_repo)?;
+            app.set_status(if app.history_state.filter.all_branches {
+                tr!("Showing history of all branches").to_string()
+            } else {
+                tr!("Showing history of the current branch").to_string()
+            });
+        }
+        KeyCode::Char('F') => {
+            app.history_state.filter.first_parent = !app.history_state.filter.first_parent;
+            reload_filtered_history(app, git_repo)?;
+            app.set_status(if app.history_state.filter.first_parent {
+                tr!("Following first parents only").to_string()
+            } else {
+                tr!("Showing merged commits").to_string()
+            });
+        }
+        KeyCode::Char('i') => {
+            // Plan a reorder of the commits after the selected one
+            let Some(base) = app
+                .history_state
+                .commits
+                .get(app.history_state.selected)
+                .map(|commit| commit.id.clone())
+            else {
+                return Ok(());
+            };
+            if git_repo.is_worktree_dirty()? {
+                app.set_status(tr!("Commit or discard your changes before rebasing").to_string());
+                return Ok(());
+            }
+            match git_repo.get_rebase_todo(&base) {
+                Ok(entries) if entries.len() < 2 => {
+                    app.set_status(
+                        tr!("Select a commit with at least two commits above it on this branch")
+                            .to_string(),
+                    );
+                }
+                Ok(entries) => {
+                    app.panels
+                        .push(Panel::RebasePlan(RebasePlan::new(base, entries)));
                 }
                 Err(e) => {
-                    app.set_status(format!("Failed to stage all: {}", e));
+                    app.set_status(tr!("Cannot plan rebase: {}", e));
                 }
             }
         }
-        KeyCode::Char('c') => {
-            // Show commit dialog
-            app.show_commit_dialog = true;
-            app.commit_message.clear();
+        KeyCode::Char('R') => {
+            let Some(commit) = app.history_state.commits.get(app.history_state.selected) else {
+                return Ok(());
+            };
+            // Reverting touches the worktree, so start from a clean one
+            if git_repo.is_worktree_dirty()? {
+                app.set_status(tr!("Commit or discard your changes before reverting").to_string());
+            } else {
+                let options = vec![
+                    tr!("Create the revert commit").to_string(),
+                    tr!("Stage the reversal to edit it before committing").to_string(),
+                ];
+                let dialog = Dialog::choice(
+                    tr!("Revert commit {} (Enter to pick, Esc to cancel)", commit.id),
+                    options,
+                    0,
+                    DialogAction::Revert(commit.id.clone()),
+                )
+                .with_keys(&['c', 's'])
+                .with_message(commit.message.clone());
+                app.dialogs.push(dialog);
+            }
         }
-        KeyCode::Char('P') => {
-            // Push - temporarily restore terminal for credential prompts
-            app.set_status("Pushing...".to_string());
-            let _ = disable_raw_mode();
-            let result = git_repo.push();
-            let _ = enable_raw_mode();
-
-            match result {
-                Ok(_) => app.set_status("Pushed successfully".to_string()),
-                Err(e) => app.set_status(format!("Push failed: {}", e)),
+        KeyCode::Char('t') => {
+            // Tag the selected commit
+            if let Some(commit) = app.history_state.commits.get(app.history_state.selected) {
+                app.dialogs.push(tag_dialog(commit.id.clone()));
             }
         }
-        KeyCode::Char('p') => {
-            // Pull - temporarily restore terminal for credential prompts
-            app.set_status("Pulling...".to_string());
-            let _ = disable_raw_mode();
-            let result = git_repo.pull();
-            let _ = enable_raw_mode();
-
-            match result {
+        KeyCode::Char('o') => {
+            // Check out the selected commit as a detached HEAD
+            let Some(id) = app
+                .history_state
+                .commits
+                .get(app.history_state.selected)
+                .map(|commit| commit.id.clone())
+            else {
+                return Ok(());
+            };
+            if git_repo.is_worktree_dirty()? {
+                app.set_status(
+                    tr!("Commit or discard your changes before checking out a commit").to_string(),
+                );
+                return Ok(());
+            }
+            match git_repo.checkout_commit(&id) {
                 Ok(_) => {
-                    app.set_status("Pulled successfully".to_string());
-                    refresh_current_view(app, git_repo)?;
+                    app.set_status(tr!(
+                        "HEAD is now detached at {}; create a branch (n in Branches) to keep new commits",
+                        id
+                    ));
+                    refresh_history(app, git_repo)?;
+                    refresh_branches(app, git_repo)?;
+                }
+                Err(e) => {
+                    app.set_status(tr!("Checkout failed: {}", e));
                 }
-                Err(e) => app.set_status(format!("Pull failed: {}", e)),
             }
         }
-        KeyCode::Char('S') => {
-            // Sync (pull + push) - temporarily restore terminal
-            app.set_status("Syncing...".to_string());
-            let _ = disable_raw_mode();
-            let result = git_repo.sync();
-            let _ = enable_raw_mode();
-
-            match result {
-                Ok(_) => {
-                    app.set_status("Synced successfully".to_string());
-                    refresh_current_view(app, git_repo)?;
-                }
-                Err(e) => app.set_status(format!("Sync failed: {}", e)),
+        KeyCode::Char('m') => {
+            // Open the bisect panel on the selected commit
+            let Some(id) = app
+                .history_state
+                .commits
+                .get(app.history_state.selected)
+                .map(|commit| commit.id.clone())
+            else {
+                return Ok(());
+            };
+            match git_repo.bisect_status() {
+                Ok(step) => app
+                    .panels
+                    .push(Panel::Bisect(BisectPanel { target: id, step })),
+                Err(e) => app.set_status(tr!("Failed to read the bisect state: {}", e)),
             }
         }
-        KeyCode::Char('d') => {
-            // Discard changes to selected file
-            if let Some(file) = app.files_state.files.get(app.files_state.selected) {
-                app.show_discard_confirm = true;
-                app.file_to_discard = Some(file.path.clone());
-                app.discard_all = false;
-                app.discard_confirmation.clear();
+        KeyCode::Char('p') => {
+            if app.patch_dir.is_empty() {
+                app.patch_dir = "patches".to_string();
             }
+            let dialog = patch_dialog(app);
+            app.dialogs.push(dialog);
         }
-        KeyCode::Char('D') => {
-            // Discard all changes
-            if !app.files_state.files.is_empty() {
-                app.show_discard_confirm = true;
-                app.file_to_discard = None;
-                app.discard_all = true;
-                app.discard_confirmation.clear();
+        KeyCode::Char('x') => {
+            if app.export_path.is_empty() {
+                app.export_path = "commits.csv".to_string();
             }
+            app.dialogs.push(export_dialog(app.export_path.clone()));
+        }
+        KeyCode::Char('e') => {
+            // Bundle the marked range, or the whole current branch (HEAD when detached)
+            let branch = match app.detached_at {
+                Some(_) => "HEAD".to_string(),
+                None => app.branches_state.current_branch.clone(),
+            };
+            let range = match app.history_range() {
+                // The older marked commit is part of the range, so exclude from its parent on;
+                // a root commit has none and the bundle takes everything up to the newer one
+                Some((from, to)) => {
+                    let commit = |id: &str| {
+                        app.history_state
+                            .commits
+                            .iter()
+                            .find(move |commit| commit.id == id)
+                    };
+                    let has_parent = commit(&from).is_some_and(|c| !c.parent_ids.is_empty());
+                    // Bundles only carry refs, so name the newer end by a branch or tag on it
+                    let to = commit(&to)
+                        .and_then(|c| c.branches.first().or(c.tags.first()))
+                        .cloned()
+                        .unwrap_or(to);
+                    if has_parent {
+                        format!("{}^..{}", from, to)
+                    } else {
+                        to
+                    }
+                }
+                None => branch.clone(),
+            };
+            let path = format!("{}.bundle", branch.replace('/', "-"));
+            app.dialogs.push(bundle_dialog(path, range));
+        }
+        KeyCode::Char('b') => {
+            // Pick a revision to pin as the header's comparison base
+            open_base_dialog(app, git_repo, "");
+        }
+        KeyCode::Char('B') if app.compare_base.is_some() => {
+            app.compare_base = None;
+            app.base_divergence = None;
+            app.set_status(tr!("Comparison base unpinned").to_string());
         }
         KeyCode::Enter => {
-            // Update diff for selected file
-            update_file_diff(app, git_repo)?;
+            // Open the detail pane for the selected commit
+            app.history_state.commit_diff = None;
+            update_commit_detail(app, git_repo)?;
         }
-        _ => {}
-    }
-    Ok(())
-}
-
-fn handle_branches_keys(app: &mut App, key: KeyEvent, git_repo: &GitRepo) -> Result<()> {
-    match key.code {
-        KeyCode::Char('n') => {
-            // Open branch creation dialog
-            app.show_branch_dialog = true;
-            app.branch_creation.new_branch_name.clear();
-            app.branch_creation.selecting_base = false;
-            // Set default base to current branch index
-            if let Some(pos) = app
-                .branches_state
-                .branches
-                .iter()
-                .position(|b| b.is_current)
+        KeyCode::Char('d') => {
+            // Show the selected commit's diff against its parent, or the marked range's
+            app.history_state.detail = None;
+            update_commit_diff(app, git_repo)?;
+        }
+        KeyCode::Char('v') => {
+            if app.history_state.range_anchor.take().is_some() {
+                app.set_status(tr!("Range cleared").to_string());
+            } else if let Some(id) = app
+                .history_state
+                .commits
+                .get(app.history_state.selected)
+                .map(|commit| commit.id.clone())
             {
-                app.branch_creation.base_branch_selected = pos;
+                app.set_status(tr!(
+                    "Range starts at {} - move to the other end and press d",
+                    id
+                ));
+                app.history_state.range_anchor = Some(id);
             }
-        }
-        KeyCode::Char('d') => {
-            // Open delete confirmation dialog
-            if let Some(branch) = app.branches_state.branches.get(app.branches_state.selected) {
-                if !branch.is_current {
-                    app.show_delete_confirm = true;
-                    app.branch_to_delete = Some(branch.name.clone());
-                    app.delete_confirmation.clear();
-                } else {
-                    app.set_status("Cannot delete the current branch".to_string());
-                }
+            if app.history_state.commit_diff.is_some() {
+                update_commit_diff(app, git_repo)?;
             }
         }
-        KeyCode::Enter | KeyCode::Char('o') => {
-            // Checkout selected branch
-            if let Some(branch) = app.branches_state.branches.get(app.branches_state.selected) {
-                if !branch.is_current {
-                    match git_repo.checkout_branch(&branch.name) {
-                        Ok(_) => {
-                            app.set_status(format!("Checked out: {}", branch.name));
-                            refresh_branches(app, git_repo)?;
-                            refresh_files(app, git_repo)?;
-                        }
-                        Err(e) => {
-                            app.set_status(format!("Checkout failed: {}", e));
-                        }
-                    }
-                }
-            }
+        KeyCode::Esc => {
+            app.history_state.detail = None;
+            app.history_state.commit_diff = None;
+            app.history_state.range_anchor = None;
         }
         _ => {}
     }
     Ok(())
 }
 
-fn update_file_diff(app: &mut App, git_repo: &GitRepo) -> Result<()> {
-    if let Some(file) = app.files_state.files.get(app.files_state.selected) {
-        match git_repo.get_diff_for_file(&file.path) {
-            Ok(diff) => {
-                app.files_state.current_diff = Some(diff);
+/// Reload whichever pane (detail or diff) is open for the newly selected commit
+fn update_history_pane(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    if app.history_state.detail.is_some() {
+        update_commit_detail(app, git_repo)?;
+    }
+    if app.history_state.commit_diff.is_some() {
+        update_commit_diff(app, git_repo)?;
+    }
+    Ok(())
+}
+
+fn update_commit_diff(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    if let Some((from, to)) = app.history_range() {
+        let diff = match git_repo.get_range_diff(&from, &to, app.ignore_whitespace) {
+            Ok(diff) => diff,
+            Err(e) => tr!("Error getting diff: {}", e),
+        };
+        app.history_state.commit_diff = Some(diff);
+        app.history_state.detail_scroll = 0;
+    } else if let Some(commit) = app.history_state.commits.get(app.history_state.selected) {
+        let diff = match git_repo.get_commit_diff(&commit.id, app.ignore_whitespace) {
+            Ok(diff) => diff,
+            Err(e) => tr!("Error getting diff: {}", e),
+        };
+        app.history_state.commit_diff = Some(diff);
+        app.history_state.detail_scroll = 0;
+    }
+    Ok(())
+}
+
+fn update_commit_detail(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    if let Some(commit) = app.history_state.commits.get(app.history_state.selected) {
+        match git_repo.get_commit_detail(&commit.id) {
+            Ok(detail) => {
+                app.history_state.detail = Some(detail);
+                app.history_state.detail_scroll = 0;
             }
             Err(e) => {
-                app.files_state.current_diff = Some(format!("Error getting diff: {}", e));
+                app.history_state.detail = None;
+                app.set_status(tr!("Failed to load commit {}: {}", commit.id, e));
             }
         }
     }
     Ok(())
 }
 
-fn refresh_files(app: &mut App, git_repo: &GitRepo) -> Result<()> {
-    app.files_state.files = git_repo.get_status()?;
-    if !app.files_state.files.is_empty() {
-        app.files_state.selected = app
-            .files_state
-            .selected
-            .min(app.files_state.files.len() - 1);
-        update_file_diff(app, git_repo)?;
-    } else {
-        app.files_state.current_diff = None;
+fn undo_last_commit(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    match git_repo.undo_last_commit() {
+        Ok((commit_id, message)) => {
+            app.commit_message = message;
+            app.set_status(tr!(
+                "Undid commit {} - changes kept staged, press c to edit the message",
+                commit_id
+            ));
+            refresh_files(app, git_repo)?;
+            refresh_history(app, git_repo)?;
+            app.history_state.detail = None;
+            app.history_state.commit_diff = None;
+        }
+        Err(e) => {
+            app.set_status(tr!("Undo commit failed: {}", e));
+        }
     }
     Ok(())
 }
 
-fn refresh_history(app: &mut App, git_repo: &GitRepo) -> Result<()> {
-    app.history_state.commits = git_repo.get_commits(100)?;
-    if !app.history_state.commits.is_empty() {
-        app.history_state.selected = app
-            .history_state
-            .selected
-            .min(app.history_state.commits.len() - 1);
+/// Fetch, pull or push: straight away when there is a single remote, otherwise after picking
+/// one in the remote picker, which starts on the current branch's configured remote
+fn start_remote_action(
+    app: &mut App,
+    git_repo: &dyn GitBackend,
+    action: RemoteAction,
+) -> Result<()> {
+    let remotes = git_repo.remotes()?;
+    if remotes.len() <= 1 {
+        let remote = remotes.first().map_or("origin", String::as_str);
+        return run_remote_action(app, git_repo, action, remote);
     }
+    let configured = git_repo.branch_remote(&app.branches_state.current_branch);
+    let selected = remotes
+        .iter()
+        .position(|remote| Some(remote) == configured.as_ref())
+        .unwrap_or(0);
+    let title = tr!(
+        "{} Remote (Enter to pick, Esc to cancel)",
+        remote_action_label(&action)
+    );
+    app.dialogs.push(Dialog::choice(
+        title,
+        remotes.clone(),
+        selected,
+        DialogAction::PickRemote { action, remotes },
+    ));
     Ok(())
 }
 
-fn refresh_branches(app: &mut App, git_repo: &GitRepo) -> Result<()> {
-    app.branches_state.branches = git_repo.get_branches()?;
-    app.branches_state.current_branch = git_repo.get_current_branch()?;
-    if !app.branches_state.branches.is_empty() {
-        app.branches_state.selected = app
-            .branches_state
-            .selected
-            .min(app.branches_state.branches.len() - 1);
+/// How the remote picker's title names `action`
+fn remote_action_label(action: &RemoteAction) -> &'static str {
+    match action {
+        RemoteAction::Fetch => tr!("Fetch from"),
+        RemoteAction::Pull { .. } => tr!("Pull from"),
+        RemoteAction::Push => tr!("Push to"),
+        RemoteAction::Sync => tr!("Sync with"),
+        RemoteAction::PushTags { .. } => tr!("Push tags to"),
+        RemoteAction::DeleteRemoteTag { .. } => tr!("Delete the tag on"),
     }
-    Ok(())
 }
 
-fn refresh_current_view(app: &mut App, git_repo: &GitRepo) -> Result<()> {
-    match app.current_view {
-        View::Files => refresh_files(app, git_repo)?,
-        View::History => refresh_history(app, git_repo)?,
+/// Run a network operation against `remote`, handing the terminal over for credential prompts
+fn run_remote_action(
+    app: &mut App,
+    git_repo: &dyn GitBackend,
+    action: RemoteAction,
+    remote: &str,
+) -> Result<()> {
+    // Network transport missing from libgit2: explain instead of failing mid-push
+    if let Some(reason) = git_repo.unsupported_transport(remote) {
+        app.set_status(reason);
+        return Ok(());
+    }
+
+    let name = remote.to_string();
+    match action {
+        RemoteAction::Fetch => {
+            app.set_status(tr!("Fetching from {}...", remote));
+            let result =
+                with_transfer_progress(app, git_repo, move |repo, ui| repo.fetch(&name, ui));
+            match result {
+                Ok(_) => {
+                    app.set_status(tr!("Fetched from {}", remote));
+                    refresh_branches(app, git_repo)?;
+                    refresh_current_view(app, git_repo)?;
+                }
+                Err(e) => app.set_status(tr!("Fetch failed: {}", e)),
+            }
+        }
+        RemoteAction::Pull { ff_only } => {
+            app.set_status(tr!("Pulling from {}...", remote));
+            let result = with_transfer_progress(app, git_repo, move |repo, ui| {
+                repo.pull(&name, ff_only, ui)
+            });
+            match result {
+                Ok(_) => {
+                    app.set_status(tr!("Pulled from {}", remote));
+                    refresh_current_view(app, git_repo)?;
+                }
+                Err(e) => {
+                    app.set_status(tr!("Pull failed: {}", e));
+                    offer_fast_forward(app, &e);
+                    // A merge stopped on conflicts leaves them to resolve in Files
+                    refresh_current_view(app, git_repo)?;
+                }
+            }
+        }
+        RemoteAction::Push => {
+            app.set_status(tr!("Pushing to {}...", remote));
+            let result =
+                with_transfer_progress(app, git_repo, move |repo, ui| repo.push(&name, ui));
+            match result {
+                Ok(_) => app.set_status(tr!("Pushed to {}", remote)),
+                Err(e) => app.set_status(tr!("Push failed: {}", e)),
+            }
+        }
+        RemoteAction::Sync => {
+            app.set_status(tr!("Syncing with {}...", remote));
+            let ff_only = app.ff_only;
+            let result = with_transfer_progress(app, git_repo, move |repo, ui| {
+                repo.sync(&name, ff_only, ui)
+            });
+            match result {
+                Ok(_) => {
+                    app.set_status(tr!("Synced with {}", remote));
+                    refresh_current_view(app, git_repo)?;
+                }
+                Err(e) => {
+                    app.set_status(tr!("Sync failed: {}", e));
+                    offer_fast_forward(app, &e);
+                    refresh_current_view(app, git_repo)?;
+                }
+            }
+        }
+        RemoteAction::PushTags { tag } => {
+            let what = match &tag {
+                Some(tag) => tr!("tag {}", tag),
+                None => tr!("all tags").to_string(),
+            };
+            app.set_status(tr!("Pushing {} to {}...", what, remote));
+            let result = with_transfer_progress(app, git_repo, move |repo, ui| {
+                repo.push_tags(&name, tag.as_deref(), ui)
+            });
+            match result {
+                Ok(_) => app.set_status(tr!("Pushed {} to {}", what, remote)),
+                Err(e) => app.set_status(tr!("Push failed: {}", e)),
+            }
+        }
+        RemoteAction::DeleteRemoteTag { tag, locally } => {
+            app.set_status(tr!("Deleting tag {} on {}...", tag, remote));
+            let deleted = tag.clone();
+            let result = with_transfer_progress(app, git_repo, move |repo, ui| {
+                repo.delete_remote_tag(&name, &deleted, ui)
+            });
+            match result {
+                // The local tag goes only once the remote one is gone, so a failure can be retried
+                Ok(_) if locally => match git_repo.delete_tag(&tag) {
+                    Ok(()) => {
+                        app.set_status(tr!("Deleted tag {} locally and on {}", tag, remote));
+                        refresh_tags(app, git_repo)?;
+                    }
+                    Err(e) => app.set_status(tr!(
+                        "Deleted tag {} on {}, but not locally: {}",
+                        tag,
+                        remote,
+                        e
+                    )),
+                },
+                Ok(_) => app.set_status(tr!("Deleted tag {} on {}", tag, remote)),
+                Err(e) => app.set_status(tr!("Failed to delete tag {} on {}: {}", tag, remote, e)),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A pull that couldn't fast-forward over local changes asks what to do with them, as a
+/// checkout does
+fn offer_fast_forward(app: &mut App, error: &anyhow::Error) {
+    if let Some(conflict) = error.downcast_ref::<git::FastForwardConflict>() {
+        app.dialogs
+            .push(local_changes_dialog(BranchCheckout::FastForward {
+                branch: conflict.branch.clone(),
+                target: conflict.target.clone(),
+            }));
+    }
+}
+
+/// Ask whether to stash or discard the local changes `target` would overwrite
+fn local_changes_dialog(target: BranchCheckout) -> Dialog {
+    let problem = match &target {
+        BranchCheckout::Branch { branch, .. } => tr!(
+            "Checking out {} would overwrite uncommitted changes.",
+            branch
+        ),
+        BranchCheckout::FastForward { branch, .. } => tr!(
+            "Fast-forwarding {} to the pulled commits would overwrite uncommitted changes.",
+            branch
+        ),
+    };
+    let options = vec![
+        tr!("Stash them, check out and reapply them").to_string(),
+        tr!("Force the checkout, discarding them").to_string(),
+    ];
+    Dialog::choice(
+        tr!("Local Changes (Enter to pick, Esc to cancel)").to_string(),
+        options,
+        0,
+        DialogAction::LocalChanges(target),
+    )
+    .with_keys(&['s', 'f'])
+    .with_message(problem)
+}
+
+/// Prompt shown outside a repository: `i` initializes one in the start directory and opens it,
+/// `c` opens the clone dialog, `Esc` quits
+pub fn handle_init_keys(app: &mut App, key: KeyEvent) -> Result<()> {
+    match key.code {
+        KeyCode::Char('i') => match git::init_repository(&app.repo_path) {
+            Ok(()) => {
+                app.show_init_prompt = false;
+                app.set_status(tr!(
+                    "Initialized an empty repository in {}",
+                    app.repo_path.display()
+                ));
+                app.open_repo = Some(app.repo_path.clone());
+            }
+            Err(e) => app.set_status(tr!("Init failed: {}", e)),
+        },
+        KeyCode::Char('c') => {
+            app.show_init_prompt = false;
+            app.panels.push(Panel::Clone(CloneDialog::default()));
+        }
+        KeyCode::Esc | KeyCode::Char('q') => app.show_init_prompt = false,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Ask for a path and a revision to check that path's content out from. The path is asked
+/// for first unless the current view supplies it; a revision it supplies is filled in.
+fn open_file_from_revision(
+    app: &mut App,
+    git_repo: &dyn GitBackend,
+    path: String,
+    revision: Option<String>,
+) {
+    if path.is_empty() {
+        app.dialogs.push(Dialog::input(
+            tr!("Check Out File (path, Enter: next, Esc: cancel)").to_string(),
+            path,
+            DialogAction::CheckOutFile { revision },
+        ));
+    } else {
+        open_file_revision_dialog(app, git_repo, path, revision);
+    }
+}
+
+/// Ask for the revision to check `path` out from
+fn open_file_revision_dialog(
+    app: &mut App,
+    git_repo: &dyn GitBackend,
+    path: String,
+    revision: Option<String>,
+) {
+    let title = tr!(
+        "Check Out {} From (Tab to complete, ↑/↓ to pick, Enter to check out, Esc to cancel)",
+        path
+    );
+    push_revision_dialog(
+        app,
+        git_repo.get_revision_candidates(50),
+        title,
+        revision.as_deref().unwrap_or_default(),
+        DialogAction::CheckOutFileFrom { path },
+    );
+}
+
+/// Push a dialog for a revision completed from `candidates`, starting from `text`
+fn push_revision_dialog(
+    app: &mut App,
+    candidates: Result<Vec<String>>,
+    title: String,
+    text: &str,
+    action: DialogAction,
+) {
+    match candidates {
+        Ok(candidates) => {
+            let mut input = RevisionInput::default();
+            input.reset(candidates);
+            input.set(text);
+            app.dialogs.push(Dialog::revision(title, input, action));
+        }
+        Err(e) => app.set_status(tr!("Failed to list revisions: {}", e)),
+    }
+}
+
+fn open_jump_dialog(app: &mut App, git_repo: &dyn GitBackend, text: &str) {
+    let title = tr!("Go to Revision (Tab to complete, ↑/↓ to pick, Enter to jump, Esc to cancel)");
+    push_revision_dialog(
+        app,
+        git_repo.get_revision_candidates(50),
+        title.to_string(),
+        text,
+        DialogAction::JumpToRevision,
+    );
+}
+
+fn open_base_dialog(app: &mut App, git_repo: &dyn GitBackend, text: &str) {
+    let title = tr!("Compare Against (Tab to complete, ↑/↓ to pick, Enter to pin, Esc to cancel)");
+    push_revision_dialog(
+        app,
+        git_repo.get_revision_candidates(0),
+        title.to_string(),
+        text,
+        DialogAction::PinBase,
+    );
+}
+
+/// Pick the upstream of local `branch` from the remote branches
+fn open_upstream_dialog(app: &mut App, branch: String, text: &str) {
+    let candidates = app
+        .branches_state
+        .branches
+        .iter()
+        .filter(|b| b.remote.is_some())
+        .map(|b| b.name.clone())
+        .collect();
+    let title = tr!(
+        "Set Upstream of {} (Tab to complete, ↑/↓ to pick, Enter to set, Esc to cancel)",
+        branch
+    );
+    push_revision_dialog(
+        app,
+        Ok(candidates),
+        title,
+        text,
+        DialogAction::SetUpstream(branch),
+    );
+}
+
+/// Parse the selected `UU` file's conflict markers into an editor
+fn open_conflict_editor(app: &mut App, git_repo: &dyn GitBackend) {
+    let Some(file) = app.files_state.files.get(app.files_state.selected) else {
+        return;
+    };
+    let path = file.path.clone();
+    if file.status != "UU" {
+        app.set_status(tr!("{} is not conflicted", path));
+        return;
+    }
+    let workdir = git_repo.workdir().unwrap_or(&app.repo_path);
+    let text = match std::fs::read(workdir.join(&path)) {
+        Ok(content) => String::from_utf8_lossy(&content).into_owned(),
+        Err(e) => {
+            app.set_status(tr!("Failed to read {}: {}", path, e));
+            return;
+        }
+    };
+    match ConflictFile::parse(&text) {
+        Some(file) if file.conflicts().next().is_some() => {
+            app.panels.push(Panel::ConflictEditor(ConflictEditor {
+                path,
+                file,
+                selected: 0,
+            }));
+        }
+        Some(_) => app.set_status(tr!(
+            "{} has no conflict markers; resolve it with o/t or stage it with s",
+            path
+        )),
+        None => app.set_status(tr!("{} has an unterminated conflict marker", path)),
+    }
+}
+
+/// Conflict editor: pick ours, theirs or both for each region, then write and stage the result
+fn handle_conflict_editor_keys(
+    app: &mut App,
+    key: KeyEvent,
+    git_repo: &dyn GitBackend,
+) -> Result<()> {
+    let Some(Panel::ConflictEditor(editor)) = app.panels.top_mut() else {
+        return Ok(());
+    };
+    let count = editor.conflict_count();
+    let choice = match key.code {
+        KeyCode::Esc => {
+            app.panels.pop();
+            return Ok(());
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            editor.selected = editor.selected.saturating_sub(1);
+            return Ok(());
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            editor.selected = (editor.selected + 1).min(count.saturating_sub(1));
+            return Ok(());
+        }
+        KeyCode::Char('o') => Choice::Ours,
+        KeyCode::Char('t') => Choice::Theirs,
+        KeyCode::Char('b') => Choice::Both,
+        KeyCode::Enter => {
+            let Some(content) = editor.file.resolve() else {
+                let open = editor
+                    .file
+                    .conflicts()
+                    .filter(|c| c.choice.is_none())
+                    .count();
+                app.set_status(tr!("{} of {} conflict(s) still unresolved", open, count));
+                return Ok(());
+            };
+            let path = editor.path.clone();
+            match git_repo.write_resolution(&path, &content) {
+                Ok(_) => {
+                    app.panels.pop();
+                    app.set_status(tr!("Resolved and staged {}", path));
+                    refresh_files(app, git_repo)?;
+                }
+                Err(e) => app.set_status(tr!("Failed to resolve {}: {}", path, e)),
+            }
+            return Ok(());
+        }
+        _ => return Ok(()),
+    };
+    if let Some(conflict) = editor.file.conflict_mut(editor.selected) {
+        conflict.choice = Some(choice);
+    }
+    // Move on to the next region once one is decided
+    editor.selected = (editor.selected + 1).min(count.saturating_sub(1));
+    Ok(())
+}
+
+/// Clone dialog: `Tab` switches fields, `Enter` moves on to the destination and then clones,
+/// `Esc` closes. A successful clone is opened once the main loop returns.
+pub fn handle_clone_keys(app: &mut App, key: KeyEvent) -> Result<()> {
+    let Some(Panel::Clone(dialog)) = app.panels.top_mut() else {
+        return Ok(());
+    };
+    match key.code {
+        KeyCode::Esc => {
+            app.panels.pop();
+        }
+        KeyCode::Tab => dialog.on_destination = !dialog.on_destination,
+        KeyCode::Enter if !dialog.on_destination => dialog.on_destination = true,
+        KeyCode::Enter => clone_repository(app)?,
+        KeyCode::Char(c) => dialog.field_mut().push(c),
+        KeyCode::Backspace => {
+            dialog.field_mut().pop();
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Clone what the clone dialog describes, with the dialog out of the way while it runs;
+/// it comes back on failure so the URL or destination can be corrected
+fn clone_repository(app: &mut App) -> Result<()> {
+    let Some(Panel::Clone(dialog)) = app.panels.pop() else {
+        return Ok(());
+    };
+    let url = dialog.url.trim().to_string();
+    if url.is_empty() {
+        app.set_status(tr!("Enter a URL to clone").to_string());
+        app.panels.push(Panel::Clone(dialog));
+        return Ok(());
+    }
+    let destination = match dialog.destination.trim() {
+        "" => dialog.default_destination(),
+        destination => destination.to_string(),
+    };
+    let dest = std::env::current_dir()?.join(expand_home(&destination));
+    let occupied = dest
+        .read_dir()
+        .map_or(dest.exists(), |mut entries| entries.next().is_some());
+    if occupied {
+        app.set_status(tr!("{} already exists and is not empty", dest.display()));
+        app.panels.push(Panel::Clone(dialog));
+        return Ok(());
+    }
+
+    app.set_status(tr!("Cloning {}...", url));
+    let ssh_keys = app.ssh_keys.clone();
+    let shell_git = app.shell_git;
+    let target = dest.clone();
+    let result = with_progress(app, move |ui| {
+        git::clone_repository(&url, &target, &ssh_keys, shell_git, ui)
+    });
+    match result {
+        Ok(()) => {
+            app.set_status(tr!("Cloned into {}", dest.display()));
+            app.open_repo = Some(dest);
+        }
+        Err(e) => {
+            app.set_status(tr!("Clone failed: {}", e));
+            app.panels.push(Panel::Clone(dialog));
+        }
+    }
+    Ok(())
+}
+
+/// What the thread running a network operation reports back
+enum TransferEvent<T> {
+    Progress(TransferProgress),
+    Credentials {
+        url: String,
+        username: Option<String>,
+        reply: mpsc::Sender<Option<(String, String)>>,
+    },
+    Output(String),
+    Done(Result<T>),
+}
+
+/// The worker thread's end of a network operation: forwards progress and credential prompts
+/// to the UI thread and watches for the user cancelling
+struct WorkerUi<T> {
+    events: mpsc::Sender<TransferEvent<T>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<T> RemoteUi for WorkerUi<T> {
+    fn progress(&mut self, transfer: &TransferProgress) {
+        let _ = self.events.send(TransferEvent::Progress(transfer.clone()));
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn credentials(&mut self, url: &str, username: Option<&str>) -> Option<(String, String)> {
+        let (reply, answer) = mpsc::channel();
+        self.events
+            .send(TransferEvent::Credentials {
+                url: url.to_string(),
+                username: username.map(str::to_string),
+                reply,
+            })
+            .ok()?;
+        answer.recv().ok().flatten()
+    }
+
+    fn output(&mut self, line: &str) {
+        let _ = self.events.send(TransferEvent::Output(line.to_string()));
+    }
+}
+
+/// Run a network operation on a worker thread with its own handle on the repository
+fn with_transfer_progress<T, F>(app: &mut App, git_repo: &dyn GitBackend, operation: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn GitBackend, &mut dyn RemoteUi) -> Result<T> + Send + 'static,
+{
+    let worker_repo = git_repo.reopen()?;
+    with_progress(app, move |ui| operation(&*worker_repo, ui))
+}
+
+/// Run a network operation on a worker thread, keeping the TUI up: the footer's gauge follows
+/// the progress and credential prompts open as a dialog. Esc gives up on the operation: the
+/// worker stops at its next callback, before prompting for credentials or sending a push,
+/// while the UI returns right away.
+fn with_progress<T, F>(app: &mut App, operation: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut dyn RemoteUi) -> Result<T> + Send + 'static,
+{
+    const REPAINT_INTERVAL: Duration = Duration::from_millis(100);
+
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (sender, events) = mpsc::channel();
+    let mut worker_ui = WorkerUi {
+        events: sender.clone(),
+        cancelled: Arc::clone(&cancelled),
+    };
+    thread::spawn(move || {
+        let result = operation(&mut worker_ui);
+        let _ = sender.send(TransferEvent::Done(result));
+    });
+
+    // A terminal of our own, since the main loop's isn't reachable from here; clearing it
+    // makes the first frame paint everything
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout())).ok();
+    if let Some(terminal) = terminal.as_mut() {
+        let _ = terminal.clear();
+    }
+    let mut reply = None;
+
+    let result = loop {
+        // Take in everything the worker reported since the last frame
+        let mut done = None;
+        loop {
+            match events.try_recv() {
+                Ok(TransferEvent::Progress(transfer)) => app.transfer = Some(transfer),
+                Ok(TransferEvent::Output(line)) => {
+                    // An open command log at its last line follows the output
+                    let following = app.command_log_scroll + 1 >= app.command_log.len();
+                    app.log_command_output(line);
+                    if following {
+                        app.command_log_scroll = app.command_log.len() - 1;
+                    }
+                }
+                Ok(TransferEvent::Credentials {
+                    url,
+                    username,
+                    reply: answer,
+                }) => {
+                    app.panels
+                        .push(Panel::Credentials(CredentialPrompt::new(url, username)));
+                    reply = Some(answer);
+                }
+                Ok(TransferEvent::Done(result)) => {
+                    done = Some(result);
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    done = Some(Err(anyhow::anyhow!(tr!(
+                        "The network operation stopped unexpectedly"
+                    ))));
+                    break;
+                }
+            }
+        }
+        if let Some(result) = done {
+            break result;
+        }
+
+        if let Some(terminal) = terminal.as_mut() {
+            if take_redraw_request() {
+                let _ = terminal.clear();
+            }
+            let _ = terminal.draw(|f| render_ui(f, app));
+        }
+        if !event::poll(REPAINT_INTERVAL).unwrap_or(false) {
+            continue;
+        }
+        let Ok(Event::Key(key)) = event::read() else {
+            continue;
+        };
+
+        if let Some(Panel::Credentials(prompt)) = app.panels.top_mut() {
+            // Esc declines the prompt, which fails the operation's authentication
+            let answer = match key.code {
+                KeyCode::Esc => Some(None),
+                KeyCode::Enter if !prompt.on_password => {
+                    prompt.on_password = true;
+                    None
+                }
+                KeyCode::Enter => Some(Some((prompt.username.clone(), prompt.password.clone()))),
+                KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+                    prompt.on_password = !prompt.on_password;
+                    None
+                }
+                KeyCode::Backspace => {
+                    prompt.field_mut().pop();
+                    None
+                }
+                KeyCode::Char(c) => {
+                    prompt.field_mut().push(c);
+                    None
+                }
+                _ => None,
+            };
+            if let Some(answer) = answer {
+                app.panels.pop();
+                if let Some(reply) = reply.take() {
+                    let _ = reply.send(answer);
+                }
+            }
+        } else if key.code == KeyCode::Esc {
+            cancelled.store(true, Ordering::Relaxed);
+            break Err(anyhow::anyhow!(tr!("Cancelled")));
+        }
+    };
+
+    app.transfer = None;
+    app.panels
+        .close(|panel| matches!(panel, Panel::Credentials(_)));
+    request_redraw();
+    result
+}
+
+/// Show the commit dialog (keeps a message restored by undo)
+fn open_commit_dialog(app: &mut App, git_repo: &dyn GitBackend) {
+    if app.commit_message.is_empty() {
+        // Finishing a merge, cherry-pick or revert starts from the message git prepared
+        let prepared = match app.in_progress {
+            Some(InProgress::Merge | InProgress::CherryPick | InProgress::Revert) => {
+                git_repo.prepared_message()
+            }
+            _ => None,
+        };
+        if let Some(message) = prepared.or_else(|| app.commit_template.clone()) {
+            app.commit_message = message;
+        }
+    }
+    let title = if app.is_protected_branch(&app.branches_state.current_branch) {
+        tr!(
+            "Commit Message - {} is protected! (Enter to commit, Esc to cancel)",
+            app.branches_state.current_branch
+        )
+    } else if app.in_progress == Some(InProgress::Merge) {
+        tr!("Merge Commit Message (Enter to commit, Esc to cancel)").to_string()
+    } else {
+        tr!("Commit Message (Enter to commit, Esc to cancel)").to_string()
+    };
+    let dialog = Dialog::input(title, app.commit_message.clone(), DialogAction::Commit);
+    app.dialogs.push(dialog);
+}
+
+/// Route a key to the topmost panel, which gets it raw
+fn handle_panel_keys(app: &mut App, key: KeyEvent, git_repo: &dyn GitBackend) -> Result<()> {
+    match app.panels.top() {
+        // Startup warnings screen: any key dismisses it
+        Some(Panel::StartupWarnings) => {
+            app.panels.pop();
+            Ok(())
+        }
+        // The network operation waiting on it reads the credential prompt's keys
+        Some(Panel::Credentials(_)) | None => Ok(()),
+        Some(Panel::Clone(_)) => handle_clone_keys(app, key),
+        Some(Panel::ConflictEditor(_)) => handle_conflict_editor_keys(app, key, git_repo),
+        Some(Panel::Blame(_)) => handle_blame_keys(app, key, git_repo),
+        Some(Panel::Bisect(_)) => handle_bisect_keys(app, key, git_repo),
+        Some(Panel::Grep(_)) => handle_grep_keys(app, key, git_repo),
+        Some(Panel::Worktrees(_)) => handle_worktree_keys(app, key),
+        Some(Panel::Submodules(_)) => handle_submodule_keys(app, key, git_repo),
+        Some(Panel::StaleBranches(_)) => handle_stale_branch_keys(app, key, git_repo),
+        Some(Panel::CommandLog) => {
+            handle_command_log_keys(app, key);
+            Ok(())
+        }
+        Some(Panel::RebasePlan(_)) => handle_rebase_plan_keys(app, key, git_repo),
+        Some(Panel::BranchFilter(_)) => handle_branch_filter_keys(app, key, git_repo),
+    }
+}
+
+/// Open the command log at its end, where the latest command's output is
+fn open_command_log(app: &mut App) {
+    if !matches!(app.panels.top(), Some(Panel::CommandLog)) {
+        app.panels.push(Panel::CommandLog);
+    }
+    app.command_log_scroll = app.command_log.len().saturating_sub(1);
+}
+
+/// Command log: scroll through the output of shell git commands
+fn handle_command_log_keys(app: &mut App, key: KeyEvent) {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('L') => {
+            app.panels.pop();
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.command_log_scroll = app.command_log_scroll.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            app.command_log_scroll =
+                (app.command_log_scroll + 1).min(app.command_log.len().saturating_sub(1));
+        }
+        KeyCode::PageUp => {
+            app.command_log_scroll = app.command_log_scroll.saturating_sub(10);
+        }
+        KeyCode::PageDown => {
+            app.command_log_scroll =
+                (app.command_log_scroll + 10).min(app.command_log.len().saturating_sub(1));
+        }
+        _ => {}
+    }
+}
+
+/// Branch filter: typing narrows the list, arrows move between matches, `Enter` checks out
+fn handle_branch_filter_keys(
+    app: &mut App,
+    key: KeyEvent,
+    git_repo: &dyn GitBackend,
+) -> Result<()> {
+    let Some(Panel::BranchFilter(filter)) = app.panels.top_mut() else {
+        return Ok(());
+    };
+    match key.code {
+        KeyCode::Esc => {
+            app.panels.pop();
+        }
+        KeyCode::Enter => {
+            let visible = app.branches_state.visible(Some(filter));
+            app.panels.pop();
+            if visible.contains(&app.branches_state.selected) {
+                checkout_selected_branch(app, git_repo)?;
+            }
+        }
+        KeyCode::Up | KeyCode::Down => {
+            let visible = app.branches_state.visible(Some(filter));
+            if let Some(pos) = visible
+                .iter()
+                .position(|&i| i == app.branches_state.selected)
+            {
+                let next = if key.code == KeyCode::Up {
+                    pos.checked_sub(1)
+                } else {
+                    Some(pos + 1)
+                };
+                if let Some(&i) = next.and_then(|next| visible.get(next)) {
+                    app.branches_state.selected = i;
+                }
+            }
+        }
+        KeyCode::Char(c) => {
+            filter.push(c);
+            app.branches_state.select_visible(filter);
+        }
+        KeyCode::Backspace => {
+            filter.pop();
+            app.branches_state.select_visible(filter);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Rebase plan: `J`/`K` move the selected commit, `Enter` rebases in the new order
+fn handle_rebase_plan_keys(app: &mut App, key: KeyEvent, git_repo: &dyn GitBackend) -> Result<()> {
+    let Some(Panel::RebasePlan(plan)) = app.panels.top_mut() else {
+        return Ok(());
+    };
+    match key.code {
+        KeyCode::Esc => {
+            app.panels.pop();
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            plan.selected = plan.selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') if plan.selected + 1 < plan.entries.len() => {
+            plan.selected += 1;
+        }
+        KeyCode::Char('K') => plan.move_selected(true),
+        KeyCode::Char('J') => plan.move_selected(false),
+        KeyCode::Enter => {
+            if !plan.is_reordered() {
+                app.panels.pop();
+                app.set_status(tr!("Plan unchanged; nothing to rebase").to_string());
+                return Ok(());
+            }
+            let ids: Vec<String> = plan.entries.iter().map(|entry| entry.id.clone()).collect();
+            let base = plan.base.clone();
+            match git_repo.rebase_onto(&base, &ids) {
+                Ok(_) => {
+                    app.panels.pop();
+                    app.set_status(tr!("Rebased {} commits onto {}", ids.len(), base));
+                    refresh_history(app, git_repo)?;
+                    refresh_files(app, git_repo)?;
+                }
+                Err(e) => {
+                    app.set_status(tr!("Rebase failed: {}", e));
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Keys for the topmost dialog; an answer closes it and runs its action
+fn handle_dialog_keys(app: &mut App, key: KeyEvent, git_repo: &dyn GitBackend) -> Result<()> {
+    let Some(action) = app.dialogs.top().map(|dialog| dialog.action.clone()) else {
+        return Ok(());
+    };
+    // Keys of particular dialogs: the branch dialog's push toggle and base, the targets of
+    // the patch dialogs and verifying a bundle
+    match (&action, key.code) {
+        (DialogAction::CreateBranch, KeyCode::Char('p'))
+            if key.modifiers.contains(KeyModifiers::CONTROL) =>
+        {
+            app.branch_creation.push = !app.branch_creation.push;
+            update_branch_dialog(app);
+            return Ok(());
+        }
+        (DialogAction::CreateBranch, KeyCode::Tab) => {
+            let options = app
+                .branches_state
+                .branches
+                .iter()
+                .map(|branch| branch.name.clone())
+                .collect();
+            app.dialogs.push(Dialog::choice(
+                tr!("Select Base Branch (Enter to confirm, Esc to go back)").to_string(),
+                options,
+                app.branch_creation.base_branch_selected,
+                DialogAction::ChooseBranchBase,
+            ));
+            return Ok(());
+        }
+        (DialogAction::ApplyPatch, KeyCode::Tab) => {
+            app.apply_to_index = !app.apply_to_index;
+            let title = apply_dialog_title(app);
+            if let Some(dialog) = app.dialogs.top_mut() {
+                dialog.title = title;
+            }
+            return Ok(());
+        }
+        (DialogAction::ExportDiff, KeyCode::Tab) => {
+            app.diff_export_all = !app.diff_export_all;
+            let title = diff_export_dialog_title(app);
+            if let Some(dialog) = app.dialogs.top_mut() {
+                dialog.title = title;
+            }
+            return Ok(());
+        }
+        (DialogAction::BundleFile { .. }, KeyCode::Char('v'))
+            if key.modifiers.contains(KeyModifiers::CONTROL) =>
+        {
+            if let Some(DialogKind::Input(path)) = app.dialogs.top().map(|dialog| &dialog.kind) {
+                let path = path.trim().to_string();
+                if !path.is_empty() {
+                    app.dialogs.pop();
+                    verify_bundle(app, git_repo, &path);
+                }
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let Some(dialog) = app.dialogs.top_mut() else {
+        return Ok(());
+    };
+    let allow_blank = dialog.allow_blank;
+    let answer = match (&mut dialog.kind, key.code) {
+        (_, KeyCode::Esc) => Answer::No,
+        (DialogKind::Confirm, KeyCode::Char('y' | 'Y')) => Answer::Yes,
+        (DialogKind::Confirm, KeyCode::Char('n' | 'N')) => Answer::No,
+        (DialogKind::Input(text), KeyCode::Enter) if allow_blank || !text.trim().is_empty() => {
+            Answer::Text(text.clone())
+        }
+        (DialogKind::Input(text), KeyCode::Char(c)) => {
+            text.push(c);
+            return Ok(());
+        }
+        (DialogKind::Input(text), KeyCode::Backspace) => {
+            text.pop();
+            return Ok(());
+        }
+        (DialogKind::Revision(input), KeyCode::Enter) => match input.value() {
+            Some(revision) => Answer::Text(revision),
+            None => return Ok(()),
+        },
+        (DialogKind::Revision(input), code) => {
+            match code {
+                KeyCode::Tab => input.complete(),
+                KeyCode::Down => input.next_suggestion(),
+                KeyCode::Up => input.previous_suggestion(),
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => input.pop(),
+                _ => {}
+            }
+            return Ok(());
+        }
+        (DialogKind::Choice { keys, .. }, KeyCode::Char(c)) if keys.contains(&c) => {
+            Answer::Choice(keys.iter().position(|&k| k == c).unwrap_or_default())
+        }
+        (DialogKind::Choice { selected, .. }, KeyCode::Up | KeyCode::Char('k')) => {
+            *selected = selected.saturating_sub(1);
+            return Ok(());
+        }
+        (
+            DialogKind::Choice {
+                options, selected, ..
+            },
+            KeyCode::Down | KeyCode::Char('j'),
+        ) => {
+            *selected = (*selected + 1).min(options.len().saturating_sub(1));
+            return Ok(());
+        }
+        (
+            DialogKind::Choice {
+                options, selected, ..
+            },
+            KeyCode::Enter,
+        ) if *selected < options.len() => Answer::Choice(*selected),
+        (DialogKind::Checklist { options, selected }, code) => {
+            match code {
+                KeyCode::Enter => {
+                    Answer::Checked(options.iter().map(|(_, checked)| *checked).collect())
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    *selected = selected.saturating_sub(1);
+                    return Ok(());
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    *selected = (*selected + 1).min(options.len().saturating_sub(1));
+                    return Ok(());
+                }
+                KeyCode::Char(' ') => {
+                    if let Some((_, checked)) = options.get_mut(*selected) {
+                        *checked = !*checked;
+                    }
+                    return Ok(());
+                }
+                KeyCode::Char('a') => {
+                    // Tick everything, or clear everything if all were ticked
+                    let all = options.iter().all(|(_, checked)| *checked);
+                    for (_, checked) in options.iter_mut() {
+                        *checked = !all;
+                    }
+                    return Ok(());
+                }
+                _ => return Ok(()),
+            }
+        }
+        _ => return Ok(()),
+    };
+    match app.dialogs.pop() {
+        Some(dialog) => answer_dialog(app, git_repo, dialog.action, answer),
+        None => Ok(()),
+    }
+}
+
+/// Run what a dialog was opened for, now that it has been answered and closed. Actions that
+/// fail reopen their dialog or push the next one.
+fn answer_dialog(
+    app: &mut App,
+    git_repo: &dyn GitBackend,
+    action: DialogAction,
+    answer: Answer,
+) -> Result<()> {
+    match (action, answer) {
+        (DialogAction::Commit, Answer::Text(message)) => match git_repo.commit(&message) {
+            Ok(_) => {
+                app.set_status(tr!("Committed successfully").to_string());
+                app.commit_message.clear();
+                refresh_files(app, git_repo)?;
+            }
+            Err(e) => {
+                app.set_status(tr!("Commit failed: {}", e));
+                app.commit_message = message;
+                open_commit_dialog(app, git_repo);
+            }
+        },
+        (DialogAction::Commit, _) => app.commit_message.clear(),
+        (DialogAction::CreateBranch, Answer::Text(name)) => create_branch(app, git_repo, name)?,
+        (DialogAction::ChooseBranchBase, Answer::Choice(index)) => {
+            app.branch_creation.base_branch_selected = index;
+            update_branch_dialog(app);
+        }
+        (DialogAction::CreateBranch | DialogAction::ChooseBranchBase, _) => {}
+        (DialogAction::DeleteBranch(name), Answer::Yes) => {
+            // An unmerged branch takes a second confirmation: typing its name
+            match git_repo.unmerged_commits(&name) {
+                Ok(0) => delete_local_branch(app, git_repo, &name, false)?,
+                Ok(count) => {
+                    let warning = tr!(
+                        "WARNING: {} has {} commit(s) not merged into HEAD that will be lost. Type the branch name to force-delete it (Enter to submit, Esc to cancel)",
+                        name,
+                        count
+                    );
+                    app.dialogs.push(
+                        Dialog::input(
+                            tr!("Force-delete unmerged branch '{}'", name),
+                            String::new(),
+                            DialogAction::ForceDeleteBranch(name),
+                        )
+                        .with_message(warning)
+                        .dangerous(),
+                    );
+                }
+                Err(e) => app.set_status(tr!("Failed to delete branch: {}", e)),
+            }
+        }
+        (DialogAction::ForceDeleteBranch(name), Answer::Text(typed)) if typed.trim() == name => {
+            delete_local_branch(app, git_repo, &name, true)?;
+        }
+        (DialogAction::DeleteBranch(_) | DialogAction::ForceDeleteBranch(_), _) => {
+            app.set_status(tr!("Delete cancelled").to_string());
+        }
+        (DialogAction::DeleteRemoteBranch(remote_branch), Answer::Yes) => {
+            // Deleting on the remote is confirmed separately, since it affects everyone
+            let reason = remote_of(git_repo, &remote_branch)
+                .and_then(|remote| git_repo.unsupported_transport(&remote));
+            if let Some(reason) = reason {
+                app.set_status(reason);
+                return Ok(());
+            }
+            let branch = remote_branch.clone();
+            let result = with_transfer_progress(app, git_repo, move |repo, ui| {
+                repo.delete_remote_branch(&branch, ui)
+            });
+            match result {
+                Ok(_) => {
+                    app.set_status(tr!("Deleted {} on the remote", remote_branch));
+                }
+                Err(e) => {
+                    app.set_status(tr!(
+                        "Failed to delete {} on the remote: {}",
+                        remote_branch,
+                        e
+                    ));
+                }
+            }
+            refresh_branches(app, git_repo)?;
+        }
+        (DialogAction::DeleteRemoteBranch(remote_branch), _) => {
+            app.set_status(tr!("Kept {} on the remote", remote_branch));
+        }
+        (DialogAction::DeleteStaleBranch(name), Answer::Yes) => {
+            let result = git_repo
+                .delete_branch(&name, true)
+                .map(|_| tr!("Deleted branch {}", name));
+            stale_branch_cleaned(app, git_repo, &name, result, false)?;
+        }
+        (DialogAction::DeleteStaleBranch(_), _) => {}
+        (DialogAction::Discard { path, keep_staged }, Answer::Text(typed)) => {
+            let typed = typed.trim().to_lowercase();
+            if typed == "y" || typed == "yes" {
+                discard_changes(app, git_repo, path, keep_staged)?;
+            } else {
+                app.set_status(tr!("Discard cancelled").to_string());
+            }
+        }
+        (DialogAction::Discard { .. }, _) => {}
+        (DialogAction::CommitOnDetached, Answer::Text(name)) => {
+            let name = name.trim().to_string();
+            if !name.is_empty() {
+                let result = git_repo
+                    .create_branch(&name, "HEAD")
+                    .and_then(|_| git_repo.checkout_branch(&name, false));
+                if let Err(e) = result {
+                    app.set_status(tr!("Failed to create branch: {}", e));
+                    app.dialogs.push(detached_dialog(name));
+                    return Ok(());
+                }
+                app.set_status(tr!("Created and checked out {}", name));
+                refresh_branches(app, git_repo)?;
+            }
+            open_commit_dialog(app, git_repo);
+        }
+        (DialogAction::ApplyPatch, Answer::Text(path)) => {
+            app.apply_path = path;
+            apply_patch(app, git_repo)?;
+        }
+        (DialogAction::ExportDiff, Answer::Text(path)) => {
+            app.diff_export_path = path;
+            export_diff(app, git_repo);
+        }
+        (DialogAction::AddToGitignore, Answer::Text(pattern)) => {
+            let pattern = pattern.trim().to_string();
+            match git_repo.add_to_gitignore(&pattern) {
+                Ok(()) => {
+                    app.set_status(tr!("Added {} to .gitignore", pattern));
+                    refresh_files(app, git_repo)?;
+                }
+                Err(e) => {
+                    app.set_status(tr!("Failed to update .gitignore: {}", e));
+                    app.dialogs.push(ignore_dialog(pattern));
+                }
+            }
+        }
+        (DialogAction::FormatPatch, Answer::Text(dir)) => {
+            app.patch_dir = dir;
+            format_patches(app, git_repo);
+        }
+        (DialogAction::ExportHistory, Answer::Text(path)) => {
+            app.export_path = path;
+            export_history(app, git_repo)?;
+        }
+        (DialogAction::BundleFile { range }, Answer::Text(path)) => {
+            app.dialogs.push(bundle_range_dialog(path, range));
+        }
+        (DialogAction::CreateBundle { path }, Answer::Text(range)) => {
+            let revisions = range.trim().to_string();
+            let file = app.repo_path.join(expand_home(path.trim()));
+            let display = file.display().to_string();
+            app.set_status(tr!("Writing bundle {}...", display));
+            match with_transfer_progress(app, git_repo, move |repo, ui| {
+                repo.create_bundle(&file, &revisions, ui)
+            }) {
+                Ok(()) => app.set_status(tr!("Wrote bundle {}", display)),
+                Err(e) => {
+                    app.set_status(tr!("Bundle failed: {}", e));
+                    app.dialogs.push(bundle_range_dialog(path, range));
+                }
+            }
+        }
+        (DialogAction::PinBase, Answer::Text(revision)) => {
+            match git_repo.resolve_revision(&revision) {
+                Ok(_) => {
+                    app.set_status(tr!("Comparing against {}", revision));
+                    app.compare_base = Some(revision);
+                    update_base_divergence(app, git_repo);
+                }
+                Err(e) => {
+                    app.set_status(tr!("Unknown revision '{}': {}", revision, e));
+                    open_base_dialog(app, git_repo, &revision);
+                }
+            }
+        }
+        (DialogAction::SetUpstream(branch), Answer::Text(upstream)) => {
+            match git_repo.set_upstream(&branch, Some(&upstream)) {
+                Ok(_) => {
+                    app.set_status(tr!("{} now tracks {}", branch, upstream));
+                    refresh_branches(app, git_repo)?;
+                }
+                Err(e) => {
+                    app.set_status(tr!("Failed to set upstream: {}", e));
+                    open_upstream_dialog(app, branch, &upstream);
+                }
+            }
+        }
+        (DialogAction::JumpToRevision, Answer::Text(revision)) => {
+            match git_repo.resolve_revision(&revision) {
+                Ok(commit_id) => {
+                    if select_history_commit(app, git_repo, &commit_id)? {
+                        app.set_status(tr!("Jumped to {} ({})", revision, commit_id));
+                    } else {
+                        app.set_status(tr!("Commit {} is not in the history", commit_id));
+                    }
+                }
+                Err(e) => {
+                    app.set_status(tr!("Unknown revision '{}': {}", revision, e));
+                    open_jump_dialog(app, git_repo, &revision);
+                }
+            }
+        }
+        (DialogAction::Revert(id), Answer::Choice(choice)) => {
+            let commit_now = choice == 0;
+            match git_repo.revert(&id, commit_now) {
+                Ok(message) => {
+                    if commit_now {
+                        app.set_status(tr!("Reverted {}", id));
+                        refresh_history(app, git_repo)?;
+                    } else {
+                        app.commit_message = message;
+                        app.set_status(tr!(
+                            "Revert of {} staged - press c in Files to edit the message and commit",
+                            id
+                        ));
+                    }
+                }
+                Err(e) => app.set_status(tr!("Revert failed: {}", e)),
+            }
+            refresh_files(app, git_repo)?;
+        }
+        (DialogAction::Merge(branch), Answer::Choice(2)) => {
+            // A squash is committed afresh, so `ff_only` has nothing to refuse
+            match git_repo.merge_squash(&branch) {
+                Ok(message) => {
+                    app.commit_message = message;
+                    app.switch_view(View::Files);
+                    refresh_files(app, git_repo)?;
+                    if git_repo.has_conflicts()? {
+                        app.set_status(tr!(
+                            "Squashing {} has conflicts; resolve them, then c to commit",
+                            branch
+                        ));
+                    } else {
+                        app.set_status(tr!("Changes of {} staged", branch));
+                        open_commit_dialog(app, git_repo);
+                    }
+                }
+                Err(e) => {
+                    app.set_status(tr!("Squash merge failed: {}", e));
+                }
+            }
+        }
+        (DialogAction::Merge(branch), Answer::Choice(choice)) => {
+            let ff_only = choice == 1 || app.ff_only;
+            match git_repo.merge(&branch, ff_only) {
+                Ok(message) => {
+                    app.set_status(message);
+                    refresh_branches(app, git_repo)?;
+                }
+                Err(e) => {
+                    app.set_status(tr!("Merge failed: {}", e));
+                    // Show the conflicted files where they can be resolved
+                    if git_repo.has_conflicts()? {
+                        app.switch_view(View::Files);
+                        refresh_files(app, git_repo)?;
+                    }
+                }
+            }
+        }
+        (DialogAction::DeleteTag(tag), Answer::Choice(0)) => match git_repo.delete_tag(&tag) {
+            Ok(()) => {
+                app.set_status(tr!("Deleted tag {}", tag));
+                refresh_tags(app, git_repo)?;
+            }
+            Err(e) => app.set_status(tr!("Failed to delete tag {}: {}", tag, e)),
+        },
+        (DialogAction::DeleteTag(tag), Answer::Choice(choice)) => {
+            let action = RemoteAction::DeleteRemoteTag {
+                tag,
+                locally: choice == 2,
+            };
+            start_remote_action(app, git_repo, action)?;
+        }
+        (DialogAction::CheckoutTag(tag), Answer::Choice(0)) => match git_repo.checkout_tag(&tag) {
+            Ok(_) => {
+                app.set_status(tr!(
+                    "Checked out tag {} (detached HEAD) - create a branch before committing",
+                    tag
+                ));
+                refresh_branches(app, git_repo)?;
+                refresh_files(app, git_repo)?;
+            }
+            Err(e) => app.set_status(tr!("Checkout failed: {}", e)),
+        },
+        (DialogAction::CheckoutTag(tag), Answer::Choice(_)) => {
+            app.dialogs.push(branch_from_tag_dialog(tag, String::new()));
+        }
+        (DialogAction::BranchFromTag(tag), Answer::Text(name)) => {
+            let name = name.trim().to_string();
+            let result = git_repo
+                .create_branch(&name, &tag)
+                .and_then(|_| git_repo.checkout_branch(&name, false));
+            match result {
+                Ok(_) => {
+                    app.set_status(tr!(
+                        "Created branch {} from tag {} and checked it out",
+                        name,
+                        tag
+                    ));
+                    refresh_branches(app, git_repo)?;
+                    refresh_files(app, git_repo)?;
+                }
+                Err(e) => {
+                    app.set_status(tr!("Failed to create branch from tag: {}", e));
+                    app.dialogs.push(branch_from_tag_dialog(tag, name));
+                }
+            }
+        }
+        (DialogAction::CreateTag { target }, Answer::Text(name)) => {
+            app.dialogs.push(tag_message_dialog(
+                target,
+                name.trim().to_string(),
+                String::new(),
+            ));
+        }
+        (DialogAction::TagMessage { target, name }, Answer::Text(message)) => {
+            let trimmed = message.trim();
+            let annotation = (!trimmed.is_empty()).then_some(trimmed);
+            match git_repo.create_tag(&name, &target, annotation) {
+                Ok(()) => {
+                    app.set_status(if annotation.is_some() {
+                        tr!("Created annotated tag {} at {}", name, target)
+                    } else {
+                        tr!("Created tag {} at {}", name, target)
+                    });
+                    refresh_tags(app, git_repo)?;
+                    if app.current_view == View::History {
+                        refresh_history(app, git_repo)?;
+                    }
+                }
+                Err(e) => {
+                    app.set_status(tr!("Failed to create tag: {}", e));
+                    app.dialogs.push(tag_message_dialog(target, name, message));
+                }
+            }
+        }
+        (DialogAction::LocalChanges(target), Answer::Choice(0)) => {
+            stash_and_checkout(app, git_repo, target)?;
+        }
+        (DialogAction::LocalChanges(target), Answer::Choice(_)) => {
+            checkout_branch(app, git_repo, target, true)?;
+        }
+        (DialogAction::PickRemote { action, remotes }, Answer::Choice(index)) => {
+            if let Some(remote) = remotes.get(index) {
+                run_remote_action(app, git_repo, action, remote)?;
+            }
+        }
+        (DialogAction::Copy, Answer::Choice(choice)) => copy_from_file(app, git_repo, choice),
+        (DialogAction::CleanUpBranches(branches), Answer::Checked(checked)) => {
+            let mut deleted = 0;
+            let mut failed = Vec::new();
+            for (name, _) in branches.iter().zip(checked).filter(|(_, checked)| *checked) {
+                match git_repo.delete_branch(name, false) {
+                    Ok(_) => deleted += 1,
+                    Err(e) => failed.push(format!("{} ({})", name, e)),
+                }
+            }
+            if failed.is_empty() {
+                app.set_status(tr!("Deleted {} merged branch(es)", deleted));
+            } else {
+                app.set_status(tr!(
+                    "Deleted {} merged branch(es); failed: {}",
+                    deleted,
+                    failed.join(", ")
+                ));
+            }
+            refresh_branches(app, git_repo)?;
+        }
+        (DialogAction::CheckOutFile { revision }, Answer::Text(path)) => {
+            open_file_revision_dialog(app, git_repo, path.trim().to_string(), revision);
+        }
+        (DialogAction::CheckOutFileFrom { path }, Answer::Text(revision)) => {
+            match git_repo.checkout_file_from(&revision, &path) {
+                Ok(_) => {
+                    app.set_status(tr!("Checked out {} from {} (unstaged)", path, revision));
+                    refresh_files(app, git_repo)?;
+                }
+                Err(e) => {
+                    app.set_status(tr!("Failed to check out {}: {}", path, e));
+                    open_file_revision_dialog(app, git_repo, path, Some(revision));
+                }
+            }
+        }
+        (DialogAction::AddWorktree, Answer::Text(branch)) => {
+            let branch = branch.trim().to_string();
+            app.dialogs
+                .push(worktree_path_dialog(app, branch, String::new()));
+        }
+        (DialogAction::AddWorktreeAt { branch }, Answer::Text(path)) => {
+            let worktrees = match app.panels.top() {
+                Some(Panel::Worktrees(list)) => list.worktrees.as_slice(),
+                _ => &[],
+            };
+            let location = match path.trim() {
+                "" => default_worktree_path(worktrees, &branch),
+                path => app.repo_path.join(expand_home(path)),
+            };
+            match git_repo.add_worktree(&branch, &location) {
+                Ok(()) => {
+                    let worktrees = git_repo.worktrees()?;
+                    if let Some(Panel::Worktrees(list)) = app.panels.top_mut() {
+                        list.worktrees = worktrees;
+                    }
+                    app.set_status(tr!(
+                        "Added a worktree for {} at {}",
+                        branch,
+                        location.display()
+                    ));
+                    refresh_branches(app, git_repo)?;
+                }
+                Err(e) => {
+                    app.set_status(tr!("Failed to add worktree: {}", e));
+                    let dialog = worktree_path_dialog(app, branch, path);
+                    app.dialogs.push(dialog);
+                }
+            }
+        }
+        (DialogAction::RunMaintenance, Answer::Yes) => run_maintenance(app, git_repo),
+        (DialogAction::RemoveWorktree(name), Answer::Yes) => {
+            match git_repo.remove_worktree(&name) {
+                Ok(()) => {
+                    let worktrees = git_repo.worktrees()?;
+                    if let Some(Panel::Worktrees(list)) = app.panels.top_mut() {
+                        list.selected = list.selected.min(worktrees.len().saturating_sub(1));
+                        list.worktrees = worktrees;
+                    }
+                    app.set_status(tr!("Removed worktree {}", name));
+                }
+                Err(e) => app.set_status(tr!("Failed to remove worktree: {}", e)),
+            }
+        }
+        // Cancelled, or answers these dialogs can't give
+        (
+            DialogAction::CommitOnDetached
+            | DialogAction::ApplyPatch
+            | DialogAction::ExportDiff
+            | DialogAction::AddToGitignore
+            | DialogAction::FormatPatch
+            | DialogAction::ExportHistory
+            | DialogAction::BundleFile { .. }
+            | DialogAction::CreateBundle { .. }
+            | DialogAction::PinBase
+            | DialogAction::SetUpstream(_)
+            | DialogAction::JumpToRevision
+            | DialogAction::Revert(_)
+            | DialogAction::Merge(_)
+            | DialogAction::DeleteTag(_)
+            | DialogAction::CheckoutTag(_)
+            | DialogAction::BranchFromTag(_)
+            | DialogAction::CreateTag { .. }
+            | DialogAction::TagMessage { .. }
+            | DialogAction::LocalChanges(_)
+            | DialogAction::PickRemote { .. }
+            | DialogAction::Copy
+            | DialogAction::CleanUpBranches(_)
+            | DialogAction::CheckOutFile { .. }
+            | DialogAction::CheckOutFileFrom { .. }
+            | DialogAction::AddWorktree
+            | DialogAction::AddWorktreeAt { .. }
+            | DialogAction::RemoveWorktree(_)
+            | DialogAction::RunMaintenance,
+            _,
+        ) => {}
+    }
+    Ok(())
+}
+
+/// The branch creation dialog, starting from `name`
+fn branch_dialog(app: &App, name: String) -> Dialog {
+    let (title, hint) = branch_dialog_labels(app);
+    Dialog::input(title, name, DialogAction::CreateBranch).with_hint(hint)
+}
+
+/// Show the chosen base and push setting in the open branch creation dialog
+fn update_branch_dialog(app: &mut App) {
+    let (title, hint) = branch_dialog_labels(app);
+    if let Some(dialog) = app.dialogs.find_mut(&DialogAction::CreateBranch) {
+        dialog.title = title;
+        dialog.hint = Some(hint);
+    }
+}
+
+fn branch_dialog_labels(app: &App) -> (String, String) {
+    let base_branch = app
+        .branches_state
+        .branches
+        .get(app.branch_creation.base_branch_selected)
+        .map(|b| b.name.as_str())
+        .unwrap_or(&app.branches_state.current_branch);
+    let title = tr!(
+        "Create Branch from '{}' (Tab to change base, Enter to create, Esc to cancel)",
+        base_branch
+    );
+    let hint = if app.branch_creation.push {
+        tr!("[x] Push to origin after creating (Ctrl+P to toggle)")
+    } else {
+        tr!("[ ] Push to origin after creating (Ctrl+P to toggle)")
+    };
+    (title, hint.to_string())
+}
+
+/// Create the branch named in the branch dialog from the chosen base, pushing it unless that
+/// was unchecked; the dialog reopens if creating it fails
+fn create_branch(app: &mut App, git_repo: &dyn GitBackend, branch_name: String) -> Result<()> {
+    let base_branch = app
+        .branches_state
+        .branches
+        .get(app.branch_creation.base_branch_selected)
+        .map(|b| b.name.clone())
+        .unwrap_or_else(|| app.branches_state.current_branch.clone());
+
+    // A new branch is pushed to the remote its upstream lives on, or origin
+    let remote = git_repo
+        .branch_remote(&branch_name)
+        .unwrap_or_else(|| "origin".to_string());
+    match git_repo.create_branch(&branch_name, &base_branch) {
+        Ok(_) if !app.branch_creation.push => {
+            app.set_status(tr!(
+                "Created branch {} locally; its upstream is set on the first push",
+                branch_name
+            ));
+        }
+        Ok(_) if git_repo.unsupported_transport(&remote).is_some() => {
+            let reason = git_repo.unsupported_transport(&remote).unwrap_or_default();
+            app.set_status(tr!(
+                "Created branch {} locally; not pushed. {}",
+                branch_name,
+                reason
+            ));
+        }
+        Ok(_) => {
+            // Push the new branch to remote
+            app.set_status(tr!("Pushing branch to remote...").to_string());
+            let pushed = branch_name.clone();
+            let push_result = with_transfer_progress(app, git_repo, move |repo, ui| {
+                repo.push_branch(&pushed, None, ui)
+            });
+
+            match push_result {
+                Ok(_) => {
+                    app.set_status(tr!("Created and pushed branch: {}", branch_name));
+                }
+                Err(e) => {
+                    app.set_status(tr!("Created branch locally but failed to push: {}", e));
+                }
+            }
+        }
+        Err(e) => {
+            app.set_status(tr!("Failed to create branch: {}", e));
+            let dialog = branch_dialog(app, branch_name);
+            app.dialogs.push(dialog);
+            return Ok(());
+        }
+    }
+    refresh_branches(app, git_repo)
+}
+
+/// The remote a remote-tracking branch such as `upstream/feature` belongs to
+fn remote_of(git_repo: &dyn GitBackend, remote_branch: &str) -> Option<String> {
+    git_repo
+        .remotes()
+        .ok()?
+        .into_iter()
+        .filter(|remote| {
+            remote_branch
+                .strip_prefix(remote.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+        })
+        .max_by_key(|remote| remote.len())
+}
+
+/// Asks to delete a branch on its remote, given its remote-tracking branch (`origin/feature`)
+fn remote_branch_delete_dialog(remote_branch: String) -> Dialog {
+    Dialog::confirm(
+        tr!("Delete '{}' on the remote", remote_branch),
+        tr!("This deletes the branch on the remote for everyone. Press y to delete it there, n or Esc to keep it").to_string(),
+        DialogAction::DeleteRemoteBranch(remote_branch),
+    )
+    .dangerous()
+}
+
+/// Delete a local branch, then offer to delete the remote branch it tracked
+fn delete_local_branch(
+    app: &mut App,
+    git_repo: &dyn GitBackend,
+    branch_name: &str,
+    forced: bool,
+) -> Result<()> {
+    let upstream = app
+        .branches_state
+        .branches
+        .iter()
+        .find(|branch| branch.name == branch_name)
+        .and_then(|branch| branch.upstream.clone())
+        .filter(|upstream| {
+            app.branches_state
+                .branches
+                .iter()
+                .any(|b| b.remote.is_some() && b.name == *upstream)
+        });
+    match git_repo.delete_branch(branch_name, forced) {
+        Ok(_) => {
+            app.set_status(tr!("Deleted branch: {}", branch_name));
+            if let Some(upstream) = upstream {
+                app.dialogs.push(remote_branch_delete_dialog(upstream));
+            }
+            refresh_branches(app, git_repo)?;
+        }
+        Err(e) => {
+            app.set_status(tr!("Failed to delete branch: {}", e));
+        }
+    }
+    Ok(())
+}
+
+/// Apply the patch file from the dialog, or the clipboard when no path is given
+fn apply_patch(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    let path = app.apply_path.trim();
+    let patch = if path.is_empty() {
+        arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get_text())
+            .map(String::into_bytes)
+            .map_err(|e| tr!("Can't read the clipboard: {}", e))
+    } else {
+        let path = app.repo_path.join(path);
+        std::fs::read(&path).map_err(|e| tr!("Can't read {}: {}", path.display(), e))
+    };
+    let patch = match patch {
+        Ok(patch) => patch,
+        Err(message) => {
+            app.set_status(message);
+            let dialog = apply_dialog(app);
+            app.dialogs.push(dialog);
+            return Ok(());
+        }
+    };
+
+    match git_repo.apply_patch(&patch, app.apply_to_index) {
+        Ok((applied, rejected)) => {
+            refresh_files(app, git_repo)?;
+            if rejected.is_empty() {
+                app.set_status(tr!("Applied {} hunk(s)", applied));
+            } else {
+                app.set_status(tr!(
+                    "Applied {} hunk(s), {} rejected",
+                    applied,
+                    rejected.len()
+                ));
+                app.files_state.rejected_hunks = Some(rejected.concat());
+                app.reset_diff_scroll();
+            }
+        }
+        Err(e) => {
+            app.set_status(tr!("Failed to apply patch: {}", e));
+            let dialog = apply_dialog(app);
+            app.dialogs.push(dialog);
+        }
+    }
+    Ok(())
+}
+
+fn apply_dialog(app: &App) -> Dialog {
+    Dialog::input(
+        apply_dialog_title(app),
+        app.apply_path.clone(),
+        DialogAction::ApplyPatch,
+    )
+    .allowing_blank()
+}
+
+fn apply_dialog_title(app: &App) -> String {
+    let target = if app.apply_to_index {
+        tr!("index")
+    } else {
+        tr!("worktree")
+    };
+    tr!(
+        "Apply Patch to {} (empty path reads the clipboard; Tab: target, Enter to apply, Esc to cancel)",
+        target
+    )
+}
+
+/// Ask before discarding the changes to `path`, or all of them; with `confirm = "minimal"`
+/// discard right away
+fn confirm_discard(
+    app: &mut App,
+    git_repo: &dyn GitBackend,
+    path: Option<String>,
+    keep_staged: bool,
+) -> Result<()> {
+    if app.confirm == ConfirmLevel::Minimal {
+        return discard_changes(app, git_repo, path, keep_staged);
+    }
+    let (title, warning) = match &path {
+        Some(path) => (
+            tr!("Discard changes to '{}'", path),
+            tr!("WARNING: This will permanently discard all uncommitted changes to this file!"),
+        ),
+        None => (
+            tr!("Discard ALL changes").to_string(),
+            tr!("WARNING: This will discard ALL uncommitted changes and delete untracked files!"),
+        ),
+    };
+    let dialog = Dialog::input(
+        title,
+        String::new(),
+        DialogAction::Discard { path, keep_staged },
+    )
+    .with_message(warning.to_string())
+    .with_hint(tr!("Type 'yes' or 'y' to confirm (Enter to submit, Esc to cancel)").to_string())
+    .dangerous();
+    app.dialogs.push(dialog);
+    Ok(())
+}
+
+/// Throw away the changes to `path` (only its unstaged ones with `keep_staged`), or all of
+/// them without a path
+fn discard_changes(
+    app: &mut App,
+    git_repo: &dyn GitBackend,
+    path: Option<String>,
+    keep_staged: bool,
+) -> Result<()> {
+    let result = match path {
+        Some(path) => git_repo
+            .discard_file(&path, keep_staged)
+            .map(|_| tr!("Discarded changes to: {}", path)),
+        None => git_repo
+            .discard_all()
+            .map(|_| tr!("Discarded all changes").to_string()),
+    };
+    match result {
+        Ok(message) => {
+            app.set_status(message);
+            refresh_files(app, git_repo)?;
+        }
+        Err(e) => {
+            app.set_status(tr!("Failed to discard changes: {}", e));
+        }
+    }
+    Ok(())
+}
+
+fn ignore_dialog(pattern: String) -> Dialog {
+    Dialog::input(
+        tr!("Add to .gitignore (path or glob, Enter: add, Esc: cancel)").to_string(),
+        pattern,
+        DialogAction::AddToGitignore,
+    )
+}
+
+/// Ask for a branch to create before committing on a detached HEAD, starting from `name`
+fn detached_dialog(name: String) -> Dialog {
+    Dialog::input(
+        tr!("Commit on detached HEAD").to_string(),
+        name,
+        DialogAction::CommitOnDetached,
+    )
+    .with_message(tr!("HEAD is detached; a commit here won't belong to any branch.").to_string())
+    .with_hint(
+        tr!("Branch to create (Enter to create and commit, empty Enter to commit detached, Esc to cancel)")
+            .to_string(),
+    )
+    .allowing_blank()
+}
+
+/// Export the marked range, or the selected commit, as patch files
+fn format_patches(app: &mut App, git_repo: &dyn GitBackend) {
+    let (from, to) = match app.history_range() {
+        Some((from, to)) => (Some(from), to),
+        None => match app.history_state.commits.get(app.history_state.selected) {
+            Some(commit) => (None, commit.id.clone()),
+            None => return,
+        },
+    };
+    let dir = app.repo_path.join(app.patch_dir.trim());
+    match git_repo.format_patch(from.as_deref(), &to, &dir) {
+        Ok(paths) => {
+            app.set_status(tr!(
+                "Wrote {} patch file(s) to {}",
+                paths.len(),
+                dir.display()
+            ));
+        }
+        Err(e) => {
+            app.set_status(tr!("Format-patch failed: {}", e));
+            let dialog = patch_dialog(app);
+            app.dialogs.push(dialog);
+        }
+    }
+}
+
+/// Ask for the directory to format-patch the marked range, or the selected commit, into
+fn patch_dialog(app: &App) -> Dialog {
+    let what = match app.history_range() {
+        Some((from, to)) => format!("{}..{}", from, to),
+        None => app
+            .history_state
+            .commits
+            .get(app.history_state.selected)
+            .map(|commit| commit.id.clone())
+            .unwrap_or_default(),
+    };
+    Dialog::input(
+        tr!(
+            "Format-patch {} into directory (Enter to write, Esc to cancel)",
+            what
+        ),
+        app.patch_dir.clone(),
+        DialogAction::FormatPatch,
+    )
+}
+
+/// Ask for the `.bundle` file, relative to the repository, then for the revisions to put in
+/// it, as `git bundle create` takes them
+fn bundle_dialog(path: String, range: String) -> Dialog {
+    Dialog::input(
+        tr!("Create Bundle (file, Enter: next, Ctrl+V: verify the file, Esc: cancel)").to_string(),
+        path,
+        DialogAction::BundleFile { range },
+    )
+}
+
+fn bundle_range_dialog(path: String, range: String) -> Dialog {
+    Dialog::input(
+        tr!(
+            "Revisions to Bundle into {} (Enter: create, Esc: cancel)",
+            path
+        ),
+        range,
+        DialogAction::CreateBundle { path },
+    )
+}
+
+/// Check that the bundle at `path` applies to this repository, showing git's output, which
+/// lists the refs it holds, in the command log
+fn verify_bundle(app: &mut App, git_repo: &dyn GitBackend, path: &str) {
+    let path = app.repo_path.join(expand_home(path));
+    open_command_log(app);
+    let display = path.display().to_string();
+    match with_transfer_progress(app, git_repo, move |repo, ui| repo.verify_bundle(&path, ui)) {
+        Ok(()) => app.set_status(tr!("{} is a valid bundle for this repository", display)),
+        Err(e) => app.set_status(tr!("Bundle verification failed: {}", e)),
+    }
+}
+
+/// Write the selected file's diff, or every changed file's, as shown in the Files view to the
+/// dialog's path, relative to the repository
+fn export_diff(app: &mut App, git_repo: &dyn GitBackend) {
+    // A file listed as staged and unstaged gets both patches, the staged one first
+    let files: Vec<(&str, bool)> = if app.diff_export_all {
+        app.files_state
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.staged))
+            .collect()
+    } else {
+        app.files_state
+            .files
+            .get(app.files_state.selected)
+            .map(|file| (file.path.as_str(), file.staged))
+            .into_iter()
+            .collect()
+    };
+    let mut patch = String::new();
+    for (path, staged) in &files {
+        match git_repo.get_diff_for_file(path, *staged, app.ignore_whitespace, None) {
+            Ok(diff) => patch.push_str(&diff),
+            Err(e) => {
+                app.set_status(tr!("Error getting diff: {}", e));
+                return;
+            }
+        }
+    }
+    let count = files.len();
+    let path = app.repo_path.join(app.diff_export_path.trim());
+    match std::fs::write(&path, patch) {
+        Ok(()) => {
+            app.set_status(tr!(
+                "Wrote the diff of {} file(s) to {}",
+                count,
+                path.display()
+            ));
+        }
+        Err(e) => {
+            app.set_status(tr!("Export failed: {}", e));
+            let dialog = diff_export_dialog(app);
+            app.dialogs.push(dialog);
+        }
+    }
+}
+
+fn diff_export_dialog(app: &App) -> Dialog {
+    Dialog::input(
+        diff_export_dialog_title(app),
+        app.diff_export_path.clone(),
+        DialogAction::ExportDiff,
+    )
+}
+
+fn diff_export_dialog_title(app: &App) -> String {
+    let what = if app.diff_export_all {
+        tr!("all changes")
+    } else {
+        app.files_state
+            .files
+            .get(app.files_state.selected)
+            .map_or("", |file| file.path.as_str())
+    };
+    tr!(
+        "Export Diff of {} (Tab: file/all, Enter: write, Esc: cancel)",
+        what
+    )
+}
+
+/// Write the whole history in the current scope to the dialog's path, relative to the repository
+fn export_history(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    while app.history_state.has_more {
+        load_more_history(app, git_repo)?;
+    }
+    let path = app.repo_path.join(app.export_path.trim());
+    match export::export_commits(git_repo, &app.history_state.commits, &path) {
+        Ok(count) => {
+            app.set_status(tr!("Exported {} commits to {}", count, path.display()));
+        }
+        Err(e) => {
+            app.set_status(tr!("Export failed: {}", e));
+            app.dialogs.push(export_dialog(app.export_path.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn export_dialog(path: String) -> Dialog {
+    Dialog::input(
+        tr!(
+            "Export History to File (.json for JSON, otherwise CSV; Enter to write, Esc to cancel)"
+        )
+        .to_string(),
+        path,
+        DialogAction::ExportHistory,
+    )
+}
+
+/// Move the divider between the current view's list and its diff or detail pane by 5%, growing
+/// the pane or the list, and remember the split in the config file
+fn resize_split(app: &mut App, grow_pane: bool) {
+    const STEP: u16 = 5;
+    let Some((split, key)) = split_mut(app) else {
+        return;
+    };
+    let resized = if grow_pane {
+        split.saturating_sub(STEP)
+    } else {
+        *split + STEP
+    };
+    *split = resized.clamp(SPLIT_RANGE.0, SPLIT_RANGE.1);
+    let list = *split;
+    save_split(app, key, list);
+}
+
+/// Split of the current view and its config key
+fn split_mut(app: &mut App) -> Option<(&mut u16, &'static str)> {
+    match app.current_view {
+        View::Files => Some((&mut app.files_split, "files_split")),
+        View::History => Some((&mut app.history_split, "history_split")),
+        _ => None,
+    }
+}
+
+fn save_split(app: &mut App, key: &str, list: u16) {
+    match AppConfig::save_setting(key, &list.to_string()) {
+        Ok(()) => app.set_status(tr!("List {}%, diff {}%", list, 100 - list)),
+        Err(e) => app.set_status(tr!("Resized, but failed to save the split: {}", e)),
+    }
+}
+
+/// Dashboard: one key into each detailed view
+fn handle_dashboard_keys(app: &mut App, key: KeyEvent, git_repo: &dyn GitBackend) -> Result<()> {
+    match key.code {
+        KeyCode::Char('f') => {
+            app.switch_view(View::Files);
+            refresh_files(app, git_repo)?;
+        }
+        KeyCode::Char('h') => {
+            app.switch_view(View::History);
+            refresh_history(app, git_repo)?;
+        }
+        KeyCode::Char('b') => {
+            app.switch_view(View::Branches);
+            refresh_branches(app, git_repo)?;
+        }
+        KeyCode::Char('t') => {
+            app.switch_view(View::Tags);
+            refresh_tags(app, git_repo)?;
+        }
+        KeyCode::Char('g') => {
+            app.dialogs.push(Dialog::confirm(
+                tr!("Run git gc").to_string(),
+                tr!("gc prunes loose objects and repacks, which can take a while on a large repository. Press y to run it, n or Esc to cancel").to_string(),
+                DialogAction::RunMaintenance,
+            ));
+            return Ok(());
+        }
+        _ => return Ok(()),
+    }
+    announce_selection(app);
+    Ok(())
+}
+
+fn run_maintenance(app: &mut App, git_repo: &dyn GitBackend) {
+    // Stream gc's output into the command log while it runs
+    open_command_log(app);
+    app.set_status(tr!("Running git gc... (Esc to stop)").to_string());
+    let result = with_transfer_progress(app, git_repo, |repo, ui| repo.run_maintenance(ui));
+    match result {
+        Ok(()) => app.set_status(tr!("Maintenance finished").to_string()),
+        Err(e) => app.set_status(tr!("Maintenance failed: {}", e)),
+    }
+}
+
+fn handle_files_keys(app: &mut App, key: KeyEvent, git_repo: &dyn GitBackend) -> Result<()> {
+    match key.code {
+        KeyCode::Char('<') => resize_split(app, true),
+        KeyCode::Char('>') => resize_split(app, false),
+        KeyCode::Char('s') => {
+            // Stage selected file
+            if let Some(file) = app.files_state.files.get(app.files_state.selected) {
+                match git_repo.stage_file(&file.path) {
+                    Ok(_) => {
+                        app.set_status(tr!("Staged: {}", file.path));
+                        refresh_files(app, git_repo)?;
+                    }
+                    Err(e) => {
+                        app.set_status(tr!("Failed to stage: {}", e));
+                    }
+                }
+            }
+        }
+        KeyCode::Char('U') => {
+            // Unstage selected file
+            if let Some(file) = app.files_state.files.get(app.files_state.selected) {
+                if !file.staged {
+                    app.set_status(tr!("{} has no staged changes", file.path));
+                    return Ok(());
+                }
+                match git_repo.unstage_file(&file.path) {
+                    Ok(_) => {
+                        app.set_status(tr!("Unstaged: {}", file.path));
+                        refresh_files(app, git_repo)?;
+                    }
+                    Err(e) => {
+                        app.set_status(tr!("Failed to unstage: {}", e));
+                    }
+                }
+            }
+        }
+        KeyCode::Char('a') => {
+            // Stage all files
+            match git_repo.stage_all() {
+                Ok(_) => {
+                    app.set_status(tr!("Staged all files").to_string());
+                    refresh_files(app, git_repo)?;
+                }
+                Err(e) => {
+                    app.set_status(tr!("Failed to stage all: {}", e));
+                }
+            }
+        }
+        KeyCode::Char('I') => {
+            let dialog = apply_dialog(app);
+            app.dialogs.push(dialog);
+        }
+        KeyCode::Char('x') if !app.files_state.files.is_empty() => {
+            if app.diff_export_path.is_empty() {
+                app.diff_export_path = "changes.patch".to_string();
+            }
+            let dialog = diff_export_dialog(app);
+            app.dialogs.push(dialog);
+        }
+        KeyCode::Char('i') => {
+            // Ignore the selected file; the path can be edited into a glob first
+            if let Some(file) = app.files_state.files.get(app.files_state.selected) {
+                app.dialogs.push(ignore_dialog(file.path.clone()));
+            }
+        }
+        KeyCode::Esc => {
+            app.files_state.rejected_hunks = None;
+        }
+        KeyCode::Char('A') => {
+            // Absorb staged hunks into fixup commits for the commits they amend
+            match git_repo.absorb_staged() {
+                Ok((0, _)) => {
+                    app.set_status(
+                        tr!("Nothing to absorb: no staged hunk maps to an unpushed commit")
+                            .to_string(),
+                    );
+                }
+                Ok((fixups, skipped)) => {
+                    app.set_status(tr!(
+                        "Created {} fixup commit(s); {} hunk(s) left staged",
+                        fixups,
+                        skipped
+                    ));
+                    refresh_files(app, git_repo)?;
+                    refresh_history(app, git_repo)?;
+                }
+                Err(e) => {
+                    app.set_status(tr!("Absorb failed: {}", e));
+                }
+            }
+        }
+        KeyCode::Char('c') => {
+            // Commits on a detached HEAD are easily lost; offer a branch first
+            if app.detached_at.is_some() {
+                let name = app.branch_prefix.clone().unwrap_or_default();
+                app.dialogs.push(detached_dialog(name));
+            } else {
+                open_commit_dialog(app, git_repo);
+            }
+        }
+        KeyCode::Char('u') => {
+            undo_last_commit(app, git_repo)?;
+        }
+        KeyCode::Char(side @ ('o' | 't')) => {
+            // Take one side of a conflict wholesale
+            let Some(file) = app.files_state.files.get(app.files_state.selected) else {
+                return Ok(());
+            };
+            if file.status != "UU" {
+                app.set_status(tr!("{} is not conflicted", file.path));
+                return Ok(());
+            }
+            let path = file.path.clone();
+            let theirs = side == 't';
+            match git_repo.resolve_conflict(&path, theirs) {
+                Ok(_) => {
+                    app.set_status(if theirs {
+                        tr!("Resolved {} with theirs", path)
+                    } else {
+                        tr!("Resolved {} with ours", path)
+                    });
+                    refresh_files(app, git_repo)?;
+                }
+                Err(e) => app.set_status(tr!("Failed to resolve {}: {}", path, e)),
+            }
+        }
+        KeyCode::Char('e') => {
+            open_conflict_editor(app, git_repo);
+        }
+        KeyCode::Char('G') => {
+            let path = app
+                .files_state
+                .files
+                .get(app.files_state.selected)
+                .map(|file| file.path.clone())
+                .unwrap_or_default();
+            open_file_from_revision(app, git_repo, path, None);
+        }
+        KeyCode::Char('m') => {
+            // Hand the terminal to the configured mergetool for the selected file
+            let Some(file) = app.files_state.files.get(app.files_state.selected) else {
+                return Ok(());
+            };
+            let path = file.path.clone();
+            if file.status != "UU" {
+                app.set_status(tr!("{} is not conflicted", path));
+                return Ok(());
+            }
+            match with_terminal_suspended(|| git_repo.mergetool(&path)) {
+                Ok(_) => app.set_status(tr!("Mergetool finished for {}", path)),
+                Err(e) => app.set_status(tr!("Mergetool failed for {}: {}", path, e)),
+            }
+            refresh_files(app, git_repo)?;
+        }
+        KeyCode::Char('y') if !app.files_state.files.is_empty() => {
+            let options = vec![
+                tr!("File path").to_string(),
+                tr!("Current hunk").to_string(),
+                tr!("Whole diff").to_string(),
+            ];
+            let dialog = Dialog::choice(
+                tr!("Copy to Clipboard (Esc to cancel)").to_string(),
+                options,
+                0,
+                DialogAction::Copy,
+            )
+            .with_keys(&['p', 'h', 'd']);
+            app.dialogs.push(dialog);
+        }
+        KeyCode::Char('b') => {
+            // Blame the selected file as of HEAD
+            let Some(file) = app.files_state.files.get(app.files_state.selected) else {
+                return Ok(());
+            };
+            let path = file.old_path.clone().unwrap_or_else(|| file.path.clone());
+            match git_repo.blame(&path, "HEAD") {
+                Ok(lines) => {
+                    app.panels.push(Panel::Blame(BlameView {
+                        path,
+                        revision: "HEAD".to_string(),
+                        lines,
+                        ..BlameView::default()
+                    }));
+                }
+                Err(e) => app.set_status(tr!("Failed to blame {}: {}", path, e)),
+            }
+        }
+        KeyCode::Char('M') => match git_repo.submodules() {
+            Ok(submodules) if submodules.is_empty() => {
+                app.set_status(tr!("This repository has no submodules").to_string());
+            }
+            Ok(submodules) => {
+                // Start on the selected file when it is a submodule
+                let selected = app
+                    .files_state
+                    .files
+                    .get(app.files_state.selected)
+                    .and_then(|file| submodules.iter().position(|sm| sm.path == file.path))
+                    .unwrap_or(0);
+                app.panels.push(Panel::Submodules(SubmoduleList {
+                    submodules,
+                    selected,
+                }));
+            }
+            Err(e) => app.set_status(tr!("Failed to list submodules: {}", e)),
+        },
+        KeyCode::Char('T') => {
+            // Hand the terminal to the difftool for the selected file
+            let Some(file) = app.files_state.files.get(app.files_state.selected) else {
+                return Ok(());
+            };
+            let path = file.path.clone();
+            let staged = file.staged;
+            if file.status == "??" {
+                app.set_status(tr!("{} is untracked; there is nothing to compare", path));
+                return Ok(());
+            }
+            let command = app.difftool.clone();
+            match with_terminal_suspended(|| git_repo.difftool(&path, staged, command.as_deref())) {
+                Ok(_) => app.set_status(tr!("Difftool finished for {}", path)),
+                Err(e) => app.set_status(tr!("Difftool failed for {}: {}", path, e)),
+            }
+        }
+        KeyCode::Char('f') => {
+            // Fetch only, so incoming changes can be looked at before pulling
+            start_remote_action(app, git_repo, RemoteAction::Fetch)?;
+        }
+        KeyCode::Char('p') => {
+            start_remote_action(
+                app,
+                git_repo,
+                RemoteAction::Pull {
+                    ff_only: app.ff_only,
+                },
+            )?;
+        }
+        KeyCode::Char('F') => {
+            // Pull, but only if it fast-forwards
+            start_remote_action(app, git_repo, RemoteAction::Pull { ff_only: true })?;
+        }
+        KeyCode::Char('P') => {
+            start_remote_action(app, git_repo, RemoteAction::Push)?;
+        }
+        KeyCode::Char('S') => {
+            start_remote_action(app, git_repo, RemoteAction::Sync)?;
+        }
+        KeyCode::Char('d') => {
+            // Discard changes to selected file
+            if let Some(file) = app.files_state.files.get(app.files_state.selected) {
+                // The unstaged row of a file with staged changes too only throws away its side
+                let keep_staged =
+                    !file.staged && !file.status.starts_with(' ') && file.status != "UU";
+                return confirm_discard(app, git_repo, Some(file.path.clone()), keep_staged);
+            }
+        }
+        // Discard all changes
+        KeyCode::Char('D') if !app.files_state.files.is_empty() => {
+            return confirm_discard(app, git_repo, None, false);
+        }
+        KeyCode::Enter => {
+            // Update diff for selected file
+            update_file_diff(app, git_repo)?;
+        }
+        KeyCode::Char('l') => {
+            // Load the rest of a diff cut at the line limit
+            let Some(file) = app.files_state.files.get(app.files_state.selected) else {
+                return Ok(());
+            };
+            if app.files_state.diff_line_limit().is_some() {
+                app.files_state.full_diff_path = Some(file.path.clone());
+                update_file_diff(app, git_repo)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn handle_branches_keys(app: &mut App, key: KeyEvent, git_repo: &dyn GitBackend) -> Result<()> {
+    match key.code {
+        KeyCode::Char('W') => match git_repo.worktrees() {
+            Ok(worktrees) => {
+                let selected = worktrees.iter().position(|w| w.is_current).unwrap_or(0);
+                app.panels.push(Panel::Worktrees(WorktreeList {
+                    worktrees,
+                    selected,
+                }));
+            }
+            Err(e) => app.set_status(tr!("Failed to list worktrees: {}", e)),
+        },
+        KeyCode::Char('n') => {
+            // Open branch creation dialog
+            app.branch_creation.push = app.push_new_branches;
+            // Set default base to current branch index
+            if let Some(pos) = app
+                .branches_state
+                .branches
+                .iter()
+                .position(|b| b.is_current)
+            {
+                app.branch_creation.base_branch_selected = pos;
+            }
+            let name = app.branch_prefix.clone().unwrap_or_default();
+            let dialog = branch_dialog(app, name);
+            app.dialogs.push(dialog);
+        }
+        KeyCode::Char('d') => {
+            // Open delete confirmation dialog
+            if let Some(branch) = app.branches_state.branches.get(app.branches_state.selected) {
+                let short_name = match &branch.remote {
+                    Some(remote) => branch
+                        .name
+                        .strip_prefix(remote.as_str())
+                        .map_or(branch.name.as_str(), |name| name.trim_start_matches('/')),
+                    None => branch.name.as_str(),
+                };
+                if app.is_protected_branch(short_name) {
+                    app.set_status(tr!(
+                        "Branch {} is protected and can't be deleted",
+                        branch.name
+                    ));
+                } else if branch.remote.is_some() {
+                    let dialog = remote_branch_delete_dialog(branch.name.clone());
+                    app.dialogs.push(dialog);
+                } else if !branch.is_current {
+                    let name = branch.name.clone();
+                    // A merged branch loses nothing, so below the `all` level it goes unasked
+                    if app.confirm != ConfirmLevel::All
+                        && git_repo
+                            .unmerged_commits(&name)
+                            .is_ok_and(|count| count == 0)
+                    {
+                        return delete_local_branch(app, git_repo, &name, false);
+                    }
+                    app.dialogs.push(
+                        Dialog::confirm(
+                            tr!("Delete branch '{}'", name),
+                            tr!("Press y to delete it, n or Esc to keep it").to_string(),
+                            DialogAction::DeleteBranch(name),
+                        )
+                        .dangerous(),
+                    );
+                } else {
+                    app.set_status(tr!("Cannot delete the current branch").to_string());
+                }
+            }
+        }
+        KeyCode::Char('c') => {
+            // Offer every merged, unprotected local branch for deletion
+            match git_repo.merged_branches() {
+                Ok(branches) => {
+                    let branches: Vec<String> = branches
+                        .into_iter()
+                        .filter(|name| !app.is_protected_branch(name))
+                        .collect();
+                    if branches.is_empty() {
+                        app.set_status(tr!("No merged branches to clean up").to_string());
+                    } else {
+                        let options = branches.iter().map(|name| (name.clone(), true)).collect();
+                        let dialog = Dialog::checklist(
+                            tr!("Clean Up Merged Branches").to_string(),
+                            options,
+                            DialogAction::CleanUpBranches(branches),
+                        )
+                        .with_message(tr!(
+                            "Merged into {}. j/k: select | Space: toggle | a: all/none | Enter: delete checked | Esc: cancel",
+                            app.branches_state.current_branch
+                        ))
+                        .dangerous();
+                        app.dialogs.push(dialog);
+                    }
+                }
+                Err(e) => {
+                    app.set_status(tr!("Failed to find merged branches: {}", e));
+                }
+            }
+        }
+        KeyCode::Char('a') => {
+            let branches = stale_branches(app, git_repo)?;
+            if branches.is_empty() {
+                app.set_status(tr!(
+                    "No branches merged or older than {} days",
+                    app.stale_branch_days
+                ));
+            } else {
+                app.panels.push(Panel::StaleBranches(StaleBranchReport {
+                    branches,
+                    ..Default::default()
+                }));
+            }
+        }
+        KeyCode::Char('M') => {
+            // Merge the selected branch into the current one
+            let Some(branch) = app
+                .branches_state
+                .branches
+                .get(app.branches_state.selected)
+                .filter(|branch| !branch.is_current)
+                .map(|branch| branch.name.clone())
+            else {
+                return Ok(());
+            };
+            if git_repo.is_worktree_dirty()? {
+                app.set_status(tr!("Commit or discard your changes before merging").to_string());
+            } else {
+                let options = vec![
+                    tr!("Merge, fast-forwarding when possible").to_string(),
+                    tr!("Fast-forward only").to_string(),
+                    tr!("Squash into staged changes and write one commit").to_string(),
+                ];
+                let title = tr!(
+                    "Merge {} into {} (Enter to pick, Esc to cancel)",
+                    branch,
+                    app.branches_state.current_branch
+                );
+                let dialog = Dialog::choice(title, options, 0, DialogAction::Merge(branch))
+                    .with_keys(&['m', 'f', 's']);
+                app.dialogs.push(dialog);
+            }
+        }
+        KeyCode::Char('R') => {
+            // Rebase the current branch onto the selected one
+            let Some(onto) = app
+                .branches_state
+                .branches
+                .get(app.branches_state.selected)
+                .filter(|branch| !branch.is_current)
+                .map(|branch| branch.name.clone())
+            else {
+                return Ok(());
+            };
+            if let Some(operation) = app.in_progress {
+                app.set_status(tr!(
+                    "A {} is already in progress; continue (C), skip (S) or abort (X) it",
+                    operation.name()
+                ));
+            } else if git_repo.is_worktree_dirty()? {
+                app.set_status(tr!("Commit or discard your changes before rebasing").to_string());
+            } else {
+                let result = git_repo.rebase_start(&onto);
+                report_rebase(app, git_repo, result, &onto)?;
+            }
+        }
+        KeyCode::Char(action @ ('C' | 'S')) => {
+            let Some(operation) = app.in_progress else {
+                return Ok(());
+            };
+            let skip = action == 'S';
+            let result = if skip {
+                git_repo.skip_operation()
+            } else {
+                git_repo.continue_operation()
+            };
+            match (operation, result) {
+                (InProgress::Rebase(_), result) => report_rebase(app, git_repo, result, "")?,
+                (_, Ok(_)) => {
+                    app.set_status(if skip {
+                        tr!("Skipped the {}", operation.name())
+                    } else {
+                        tr!("Committed the {}", operation.name())
+                    });
+                    refresh_branches(app, git_repo)?;
+                    refresh_files(app, git_repo)?;
+                    refresh_history(app, git_repo)?;
+                }
+                (_, Err(e)) if skip => app.set_status(tr!("Failed to skip: {}", e)),
+                (_, Err(e)) => app.set_status(tr!("Failed to continue: {}", e)),
+            }
+        }
+        KeyCode::Char('X') => {
+            let Some(operation) = app.in_progress else {
+                return Ok(());
+            };
+            match git_repo.abort_operation() {
+                Ok(_) => app.set_status(tr!("Aborted the {}", operation.name())),
+                Err(e) => app.set_status(tr!("Failed to abort: {}", e)),
+            }
+            refresh_branches(app, git_repo)?;
+            refresh_files(app, git_repo)?;
+        }
+        KeyCode::Char('t') => {
+            // Pick the upstream for the selected local branch from the remote branches
+            if let Some(branch) = app.branches_state.branches.get(app.branches_state.selected) {
+                if branch.remote.is_some() {
+                    app.set_status(tr!("Only local branches have an upstream").to_string());
+                } else {
+                    open_upstream_dialog(app, branch.name.clone(), "");
+                }
+            }
+        }
+        KeyCode::Char('T') => {
+            if let Some(branch) = app
+                .branches_state
+                .branches
+                .get(app.branches_state.selected)
+                .filter(|branch| branch.upstream.is_some())
+                .map(|branch| branch.name.clone())
+            {
+                match git_repo.set_upstream(&branch, None) {
+                    Ok(_) => {
+                        app.set_status(tr!("{} no longer tracks an upstream", branch));
+                        refresh_branches(app, git_repo)?;
+                    }
+                    Err(e) => {
+                        app.set_status(tr!("Failed to unset upstream: {}", e));
+                    }
+                }
+            }
+        }
+        KeyCode::Char('/') => {
+            app.panels.push(Panel::BranchFilter(String::new()));
+        }
+        KeyCode::Char('G') => {
+            let revision = app
+                .branches_state
+                .branches
+                .get(app.branches_state.selected)
+                .map(|branch| branch.name.clone());
+            open_file_from_revision(app, git_repo, String::new(), revision);
+        }
+        KeyCode::Char('s') => {
+            app.branches_state.sort_by_recency = !app.branches_state.sort_by_recency;
+            app.branches_state.sort();
+        }
+        KeyCode::Enter | KeyCode::Char('o') => {
+            checkout_selected_branch(app, git_repo)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Check out the selected branch; a remote branch becomes a local branch tracking it
+fn checkout_selected_branch(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    let Some(branch) = app.branches_state.branches.get(app.branches_state.selected) else {
+        return Ok(());
+    };
+    if branch.remote.is_none() && branch.is_current {
+        return Ok(());
+    }
+    let target = BranchCheckout::Branch {
+        branch: branch.name.clone(),
+        remote: branch.remote.clone(),
+    };
+    checkout_branch(app, git_repo, target, false)
+}
+
+/// Check out `target`, discarding local changes with `force`. When local changes would be
+/// overwritten, ask what to do with them instead of failing.
+fn checkout_branch(
+    app: &mut App,
+    git_repo: &dyn GitBackend,
+    target: BranchCheckout,
+    force: bool,
+) -> Result<()> {
+    match run_checkout(git_repo, &target, force) {
+        Ok(message) => {
+            app.set_status(message);
+            refresh_branches(app, git_repo)?;
+            refresh_files(app, git_repo)?;
+        }
+        Err(e) if git::is_checkout_conflict(&e) => app.dialogs.push(local_changes_dialog(target)),
+        Err(e) => app.set_status(tr!("Checkout failed: {}", e)),
+    }
+    Ok(())
+}
+
+/// Stash local changes, check out `target` and reapply them there, like
+/// `git stash && git checkout && git stash pop`
+fn stash_and_checkout(
+    app: &mut App,
+    git_repo: &dyn GitBackend,
+    target: BranchCheckout,
+) -> Result<()> {
+    let message = match &target {
+        BranchCheckout::Branch { branch, .. } => format!("Before checking out {}", branch),
+        BranchCheckout::FastForward { branch, .. } => format!("Before pulling into {}", branch),
+    };
+    let stashed = match git_repo.stash_push(&message) {
+        Ok(stashed) => stashed,
+        Err(e) => {
+            app.set_status(tr!("Failed to stash: {}", e));
+            return Ok(());
+        }
+    };
+
+    match run_checkout(git_repo, &target, false) {
+        Ok(message) if !stashed => app.set_status(message),
+        Ok(message) => match git_repo.stash_pop() {
+            Ok(_) => app.set_status(tr!("{} and reapplied your changes", message)),
+            Err(e) => app.set_status(tr!(
+                "{}, but reapplying your changes failed ({}); they are kept in the latest stash",
+                message,
+                e
+            )),
+        },
+        Err(e) => {
+            // Put the changes back where they were
+            let restored = !stashed || git_repo.stash_pop().is_ok();
+            app.set_status(if restored {
+                tr!("Checkout failed: {}", e)
+            } else {
+                tr!(
+                    "Checkout failed: {}; your changes are kept in the latest stash",
+                    e
+                )
+            });
+        }
+    }
+    refresh_branches(app, git_repo)?;
+    refresh_files(app, git_repo)?;
+    Ok(())
+}
+
+/// Check out `target`, returning the status message to show
+fn run_checkout(git_repo: &dyn GitBackend, target: &BranchCheckout, force: bool) -> Result<String> {
+    match target {
+        BranchCheckout::Branch {
+            branch,
+            remote: Some(remote),
+        } => {
+            let local_name = git_repo.checkout_remote_branch(branch, remote, force)?;
+            Ok(tr!("Checked out: {} (tracking {})", local_name, branch))
+        }
+        BranchCheckout::Branch {
+            branch,
+            remote: None,
+        } => {
+            git_repo.checkout_branch(branch, force)?;
+            Ok(tr!("Checked out: {}", branch))
+        }
+        BranchCheckout::FastForward { branch, target } => {
+            git_repo.fast_forward(branch, target, force)?;
+            Ok(tr!("Fast-forwarded {}", branch))
+        }
+    }
+}
+
+/// Show how a rebase step went: finished, or stopped on a conflict to resolve in the Files view
+fn report_rebase(
+    app: &mut App,
+    git_repo: &dyn GitBackend,
+    result: Result<Option<(usize, usize)>>,
+    onto: &str,
+) -> Result<()> {
+    match result {
+        Ok(None) if onto.is_empty() => app.set_status(tr!("Rebase finished").to_string()),
+        Ok(None) => app.set_status(tr!("Rebased onto {}", onto)),
+        Ok(Some((current, total))) => {
+            app.set_status(tr!(
+                "Rebasing {}/{}: conflicts; resolve and stage them, then C to continue, S to skip or X to abort",
+                current,
+                total
+            ));
+            app.switch_view(View::Files);
+        }
+        Err(e) => app.set_status(tr!("Rebase failed: {}", e)),
+    }
+    refresh_branches(app, git_repo)?;
+    refresh_files(app, git_repo)?;
+    Ok(())
+}
+
+/// In screen-reader mode, describe the new selection on the status line
+fn announce_selection(app: &mut App) {
+    if app.accessible {
+        let summary = app.selection_summary();
+        app.set_status(summary);
+    }
+}
+
+fn handle_tags_keys(app: &mut App, key: KeyEvent, git_repo: &dyn GitBackend) -> Result<()> {
+    let selected = app
+        .tags_state
+        .tags
+        .get(app.tags_state.selected)
+        .map(|tag| tag.name.clone());
+    match key.code {
+        KeyCode::Char('n') => {
+            app.dialogs.push(tag_dialog("HEAD".to_string()));
+            return Ok(());
+        }
+        KeyCode::Char('d') => {
+            if let Some(tag) = selected {
+                let options = vec![
+                    tr!("Delete locally").to_string(),
+                    tr!("Delete on the remote").to_string(),
+                    tr!("Delete both").to_string(),
+                ];
+                let dialog = Dialog::choice(
+                    tr!("Delete tag '{}' (Enter to pick, Esc to cancel)", tag),
+                    options,
+                    0,
+                    DialogAction::DeleteTag(tag),
+                )
+                .with_keys(&['l', 'r', 'b'])
+                .with_message(
+                    tr!("Deleting a tag on the remote affects everyone who fetches from it.")
+                        .to_string(),
+                )
+                .dangerous();
+                app.dialogs.push(dialog);
+            }
+            return Ok(());
+        }
+        KeyCode::Char('p') => {
+            if let Some(tag) = selected {
+                start_remote_action(app, git_repo, RemoteAction::PushTags { tag: Some(tag) })?;
+            }
+            return Ok(());
+        }
+        KeyCode::Char('P') => {
+            start_remote_action(app, git_repo, RemoteAction::PushTags { tag: None })?;
+            return Ok(());
+        }
+        _ => {}
+    }
+    if matches!(key.code, KeyCode::Enter | KeyCode::Char('o')) {
+        // Checkout selected tag, refusing while tracked files have local changes
+        if let Some(tag_name) = app
+            .tags_state
+            .tags
+            .get(app.tags_state.selected)
+            .map(|t| t.name.clone())
+        {
+            match git_repo.is_worktree_dirty() {
+                Ok(true) => {
+                    app.set_status(tr!(
+                        "Working tree has uncommitted changes; commit or discard them before checking out tag {}",
+                        tag_name
+                    ));
+                }
+                Ok(false) => {
+                    let options = vec![
+                        tr!("Check out detached").to_string(),
+                        tr!("Create a branch from the tag").to_string(),
+                    ];
+                    let dialog = Dialog::choice(
+                        tr!("Checkout tag '{}' (Enter to pick, Esc to cancel)", tag_name),
+                        options,
+                        0,
+                        DialogAction::CheckoutTag(tag_name),
+                    )
+                    .with_keys(&['d', 'b'])
+                    .with_message(
+                        tr!("Checking out a tag leaves HEAD detached; new commits won't belong to any branch.")
+                            .to_string(),
+                    );
+                    app.dialogs.push(dialog);
+                }
+                Err(e) => {
+                    app.set_status(tr!("Failed to read working tree status: {}", e));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Ask for the name of a new tag at `target`: the selected commit in History, `HEAD` in Tags
+fn tag_dialog(target: String) -> Dialog {
+    Dialog::input(
+        tr!("New tag at {} (Enter: next, Esc: cancel)", target),
+        String::new(),
+        DialogAction::CreateTag { target },
+    )
+}
+
+/// Ask for the message of tag `name`, which makes it annotated
+fn tag_message_dialog(target: String, name: String, message: String) -> Dialog {
+    Dialog::input(
+        tr!("Message of tag {} (Enter: create, Esc: cancel)", name),
+        message,
+        DialogAction::TagMessage { target, name },
+    )
+    .with_hint(tr!("With a message the tag is annotated, without one lightweight.").to_string())
+    .allowing_blank()
+}
+
+fn branch_from_tag_dialog(tag: String, name: String) -> Dialog {
+    Dialog::input(
+        tr!(
+            "New branch from tag '{}' (Enter to create and checkout, Esc to cancel)",
+            tag
+        ),
+        name,
+        DialogAction::BranchFromTag(tag),
+    )
+}
+
+/// Content search: edit the pattern and revision, then browse the matches and open one in
+/// blame or the editor
+fn handle_grep_keys(app: &mut App, key: KeyEvent, git_repo: &dyn GitBackend) -> Result<()> {
+    let Some(Panel::Grep(search)) = app.panels.top_mut() else {
+        return Ok(());
+    };
+
+    if !search.browsing {
+        let field = if search.editing_revision {
+            &mut search.revision
+        } else {
+            &mut search.pattern
+        };
+        match key.code {
+            KeyCode::Esc => {
+                app.panels.pop();
+            }
+            KeyCode::Tab => search.editing_revision = !search.editing_revision,
+            KeyCode::Char(c) => field.push(c),
+            KeyCode::Backspace => {
+                field.pop();
+            }
+            KeyCode::Enter => {
+                if search.pattern.is_empty() {
+                    return Ok(());
+                }
+                let revision = Some(search.revision.trim()).filter(|r| !r.is_empty());
+                match git_repo.grep(&search.pattern, revision, GREP_MATCH_LIMIT) {
+                    Ok(matches) => {
+                        search.truncated = matches.len() >= GREP_MATCH_LIMIT;
+                        search.browsing = !matches.is_empty();
+                        search.matches = matches;
+                        search.selected = 0;
+                        search.preview = None;
+                        load_grep_preview(search, git_repo);
+                        let count = search.matches.len();
+                        app.set_status(tr!("{} match(es)", count));
+                    }
+                    Err(e) => app.set_status(tr!("Search failed: {}", e)),
+                }
+            }
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    let last = search.matches.len().saturating_sub(1);
+    match key.code {
+        KeyCode::Esc => {
+            app.panels.pop();
+        }
+        KeyCode::Char('/') => search.browsing = false,
+        KeyCode::Up | KeyCode::Char('k') => search.selected = search.selected.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => search.selected = (search.selected + 1).min(last),
+        KeyCode::PageUp => search.selected = search.selected.saturating_sub(10),
+        KeyCode::PageDown => search.selected = (search.selected + 10).min(last),
+        KeyCode::Enter => {
+            // Blame the file where the search looked, on the matched line
+            let Some(found) = search.matches.get(search.selected) else {
+                return Ok(());
+            };
+            let (path, line) = (found.path.clone(), found.line);
+            let revision = match search.revision.trim() {
+                "" => "HEAD".to_string(),
+                revision => revision.to_string(),
+            };
+            match git_repo.blame(&path, &revision) {
+                Ok(lines) => {
+                    app.panels.push(Panel::Blame(BlameView {
+                        path,
+                        revision,
+                        selected: line.saturating_sub(1).min(lines.len().saturating_sub(1)),
+                        lines,
+                        ..BlameView::default()
+                    }));
+                }
+                Err(e) => app.set_status(tr!("Failed to blame {}: {}", path, e)),
+            }
+            return Ok(());
+        }
+        KeyCode::Char('e') => {
+            let Some(found) = search.matches.get(search.selected) else {
+                return Ok(());
+            };
+            let (path, line) = (found.path.clone(), found.line);
+            match with_terminal_suspended(|| git_repo.edit_file(&path, line)) {
+                Ok(()) => {
+                    if let Some(Panel::Grep(search)) = app.panels.top_mut() {
+                        search.preview = None;
+                    }
+                }
+                Err(e) => app.set_status(tr!("Failed to open the editor: {}", e)),
+            }
+            refresh_files(app, git_repo)?;
+        }
+        _ => return Ok(()),
+    }
+    if let Some(Panel::Grep(search)) = app.panels.top_mut() {
+        load_grep_preview(search, git_repo);
+    }
+    Ok(())
+}
+
+/// Load the file of the selected match for the preview, unless it is already showing
+fn load_grep_preview(search: &mut GrepSearch, git_repo: &dyn GitBackend) {
+    let Some(found) = search.matches.get(search.selected) else {
+        search.preview = None;
+        return;
+    };
+    if search
+        .preview
+        .as_ref()
+        .is_some_and(|(path, _)| *path == found.path)
+    {
+        return;
+    }
+    let revision = Some(search.revision.trim()).filter(|r| !r.is_empty());
+    let lines = git_repo
+        .file_text(&found.path, revision)
+        .map(|text| text.lines().map(str::to_string).collect())
+        .unwrap_or_default();
+    search.preview = Some((found.path.clone(), lines));
+}
+
+/// Bisect panel: mark the target good, bad or skipped, jump to the culprit, or abort
+fn handle_bisect_keys(app: &mut App, key: KeyEvent, git_repo: &dyn GitBackend) -> Result<()> {
+    let Some(Panel::Bisect(panel)) = app.panels.top_mut() else {
+        return Ok(());
+    };
+    let mark = match key.code {
+        KeyCode::Char('g') => BisectMark::Good,
+        KeyCode::Char('b') => BisectMark::Bad,
+        KeyCode::Char('s') => BisectMark::Skip,
+        KeyCode::Esc => {
+            app.panels.pop();
+            return Ok(());
+        }
+        KeyCode::Enter => {
+            let BisectStep::Found(id) = &panel.step else {
+                return Ok(());
+            };
+            let id = id.clone();
+            app.panels.pop();
+            if select_history_commit(app, git_repo, &id)? {
+                app.history_state.commit_diff = None;
+                update_commit_detail(app, git_repo)?;
+            }
+            return Ok(());
+        }
+        KeyCode::Char('x') => {
+            if app.in_progress != Some(InProgress::Bisect) {
+                return Ok(());
+            }
+            app.panels.pop();
+            match git_repo.bisect_reset() {
+                Ok(original) => app.set_status(tr!("Ended the bisect; back on {}", original)),
+                Err(e) => app.set_status(tr!("Failed to end the bisect: {}", e)),
+            }
+            refresh_history(app, git_repo)?;
+            refresh_branches(app, git_repo)?;
+            return refresh_files(app, git_repo);
+        }
+        _ => return Ok(()),
+    };
+
+    let target = panel.target.clone();
+    match git_repo.bisect_mark(&target, mark) {
+        Ok(step) => {
+            panel.step = step.clone();
+            refresh_history(app, git_repo)?;
+            refresh_branches(app, git_repo)?;
+            refresh_files(app, git_repo)?;
+            // Follow the commit to test next, or the culprit once it's isolated
+            let next = match &step {
+                BisectStep::Testing { commit, .. } | BisectStep::Found(commit) => {
+                    Some(commit.clone())
+                }
+                _ => None,
+            };
+            if let Some(next) = next {
+                if select_history_commit(app, git_repo, &next)? {
+                    app.history_state.commit_diff = None;
+                    update_commit_detail(app, git_repo)?;
+                }
+                if let Some(Panel::Bisect(panel)) = app.panels.top_mut() {
+                    panel.target = next;
+                }
+            }
+        }
+        Err(e) => app.set_status(tr!("Bisect failed: {}", e)),
+    }
+    Ok(())
+}
+
+/// Move through a blame, open a line's commit in History, or reblame from before that commit
+fn handle_blame_keys(app: &mut App, key: KeyEvent, git_repo: &dyn GitBackend) -> Result<()> {
+    let Some(Panel::Blame(blame)) = app.panels.top_mut() else {
+        return Ok(());
+    };
+    let last = blame.lines.len().saturating_sub(1);
+    match key.code {
+        KeyCode::Esc => {
+            app.panels.pop();
+        }
+        KeyCode::Up | KeyCode::Char('k') => blame.selected = blame.selected.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => blame.selected = (blame.selected + 1).min(last),
+        KeyCode::PageUp => blame.selected = blame.selected.saturating_sub(10),
+        KeyCode::PageDown => blame.selected = (blame.selected + 10).min(last),
+        KeyCode::Enter => {
+            let Some(id) = blame.lines.get(blame.selected).map(|line| line.id.clone()) else {
+                return Ok(());
+            };
+            app.panels.pop();
+            // A search the blame was opened from would cover the commit
+            app.panels.close(|panel| matches!(panel, Panel::Grep(_)));
+            app.switch_view(View::History);
+            if select_history_commit(app, git_repo, &id)? {
+                app.history_state.commit_diff = None;
+                update_commit_detail(app, git_repo)?;
+            } else {
+                app.set_status(tr!("Commit {} is not in the history", id));
+            }
+        }
+        KeyCode::Char('p') => {
+            // Blame the file as it was just before the selected line's commit
+            let Some(line) = blame.lines.get(blame.selected) else {
+                return Ok(());
+            };
+            let path = line.path.clone();
+            let revision = format!("{}^", line.id);
+            match git_repo.blame(&path, &revision) {
+                Ok(lines) => {
+                    let previous = (
+                        std::mem::replace(&mut blame.path, path),
+                        std::mem::replace(&mut blame.revision, revision),
+                        blame.selected,
+                    );
+                    blame.previous.push(previous);
+                    blame.selected = blame.selected.min(lines.len().saturating_sub(1));
+                    blame.lines = lines;
+                }
+                Err(e) => app.set_status(tr!("Can't reblame at {}: {}", revision, e)),
+            }
+        }
+        KeyCode::Backspace => {
+            let Some((path, revision, selected)) = blame.previous.pop() else {
+                return Ok(());
+            };
+            match git_repo.blame(&path, &revision) {
+                Ok(lines) => {
+                    blame.path = path;
+                    blame.revision = revision;
+                    blame.selected = selected.min(lines.len().saturating_sub(1));
+                    blame.lines = lines;
+                }
+                Err(e) => app.set_status(tr!("Failed to blame {}: {}", path, e)),
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Submodule list: init, update or sync the selected submodule
+fn handle_submodule_keys(app: &mut App, key: KeyEvent, git_repo: &dyn GitBackend) -> Result<()> {
+    let Some(Panel::Submodules(list)) = app.panels.top_mut() else {
+        return Ok(());
+    };
+    let name = list
+        .submodules
+        .get(list.selected)
+        .map(|submodule| submodule.name.clone());
+
+    let result = match key.code {
+        KeyCode::Esc => {
+            app.panels.pop();
+            return Ok(());
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            list.selected = list.selected.saturating_sub(1);
+            return Ok(());
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            list.selected = (list.selected + 1).min(list.submodules.len().saturating_sub(1));
+            return Ok(());
+        }
+        KeyCode::Char('i') => {
+            let Some(name) = name else {
+                return Ok(());
+            };
+            git_repo
+                .init_submodule(&name)
+                .map(|()| tr!("Initialized submodule {}", name))
+        }
+        KeyCode::Char('u') => {
+            let Some(name) = name else {
+                return Ok(());
+            };
+            app.set_status(tr!("Updating submodule {}...", name));
+            let worker_name = name.clone();
+            with_transfer_progress(app, git_repo, move |repo, ui| {
+                repo.update_submodule(&worker_name, ui)
+            })
+            .map(|()| tr!("Updated submodule {}", name))
+        }
+        KeyCode::Char('s') => {
+            let Some(name) = name else {
+                return Ok(());
+            };
+            git_repo
+                .sync_submodule(&name)
+                .map(|()| tr!("Synced the URL of submodule {}", name))
+        }
+        _ => return Ok(()),
+    };
+
+    match result {
+        Ok(message) => app.set_status(message),
+        Err(e) => app.set_status(tr!("Submodule operation failed: {}", e)),
+    }
+    // The states shown, and the gitlinks in the Files view, may have changed either way
+    let submodules = git_repo.submodules()?;
+    if let Some(Panel::Submodules(list)) = app.panels.top_mut() {
+        list.selected = list.selected.min(submodules.len().saturating_sub(1));
+        list.submodules = submodules;
+    }
+    refresh_files(app, git_repo)
+}
+
+/// Worktree list: open gtek in one, add one for a branch, or remove one
+fn handle_worktree_keys(app: &mut App, key: KeyEvent) -> Result<()> {
+    let Some(Panel::Worktrees(list)) = app.panels.top_mut() else {
+        return Ok(());
+    };
+
+    match key.code {
+        KeyCode::Esc => {
+            app.panels.pop();
+        }
+        KeyCode::Up | KeyCode::Char('k') => list.selected = list.selected.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => {
+            list.selected = (list.selected + 1).min(list.worktrees.len().saturating_sub(1));
+        }
+        KeyCode::Char('a') => {
+            // Start from the selected local branch in the Branches view
+            let branch = app
+                .branches_state
+                .branches
+                .get(app.branches_state.selected)
+                .filter(|branch| branch.remote.is_none() && !branch.is_current)
+                .map(|branch| branch.name.clone())
+                .unwrap_or_default();
+            app.dialogs.push(Dialog::input(
+                tr!("New Worktree (branch, Enter: next, Esc: cancel)").to_string(),
+                branch,
+                DialogAction::AddWorktree,
+            ));
+        }
+        KeyCode::Char('d') => match list.worktrees.get(list.selected) {
+            Some(worktree) if worktree.name.is_none() => {
+                app.set_status(tr!("The main working tree can't be removed").to_string());
+            }
+            Some(worktree) if worktree.is_current => {
+                app.set_status(tr!("Open another worktree before removing this one").to_string());
+            }
+            Some(worktree) => {
+                let name = worktree.name.clone().unwrap_or_default();
+                let dialog = Dialog::confirm(
+                    tr!("Remove worktree {}", name),
+                    tr!(
+                        "Remove {} and its directory? Press y to remove it, n or Esc to keep it",
+                        worktree.path.display()
+                    ),
+                    DialogAction::RemoveWorktree(name),
+                )
+                .dangerous();
+                app.dialogs.push(dialog);
+            }
+            None => {}
+        },
+        KeyCode::Enter => {
+            let Some(worktree) = list.worktrees.get(list.selected) else {
+                return Ok(());
+            };
+            let path = worktree.path.clone();
+            if worktree.prunable {
+                app.set_status(tr!(
+                    "{} no longer exists; remove the entry with d",
+                    path.display()
+                ));
+            } else if !worktree.is_current {
+                // The main loop reopens gtek rooted at the worktree
+                app.panels.pop();
+                app.set_status(tr!("Opened worktree {}", path.display()));
+                app.open_repo = Some(path);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Ask where to put a new worktree for `branch`, starting from `path`
+fn worktree_path_dialog(app: &App, branch: String, path: String) -> Dialog {
+    let worktrees = match app.panels.top() {
+        Some(Panel::Worktrees(list)) => list.worktrees.as_slice(),
+        _ => &[],
+    };
+    let default = default_worktree_path(worktrees, &branch);
+    Dialog::input(
+        tr!(
+            "Path of the worktree for {} (Enter: add, Esc: cancel)",
+            branch
+        ),
+        path,
+        DialogAction::AddWorktreeAt { branch },
+    )
+    .with_message(tr!("A missing branch is created at HEAD.").to_string())
+    .with_hint(tr!("Empty puts it at {}", default.display()))
+    .allowing_blank()
+}
+
+/// Where a new worktree for `branch` goes by default: next to the main working tree, named
+/// after it and the branch, e.g. `../project-feature-x`
+fn default_worktree_path(worktrees: &[WorktreeInfo], branch: &str) -> PathBuf {
+    let main = worktrees
+        .first()
+        .map(|worktree| worktree.path.as_path())
+        .unwrap_or(Path::new("."));
+    let name = main
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let dir = format!("{}-{}", name, branch.replace('/', "-"));
+    main.parent().unwrap_or(main).join(dir)
+}
+
+/// Select commit `id` (short form) in History, paging in history up to it. Returns whether the
+/// walk has it.
+fn select_history_commit(app: &mut App, git_repo: &dyn GitBackend, id: &str) -> Result<bool> {
+    let Some(pos) = app.history_state.cursor.position(id) else {
+        return Ok(false);
+    };
+    while app.history_state.has_more && app.history_state.commits.len() <= pos {
+        load_more_history(app, git_repo)?;
+    }
+    app.history_state.selected = pos;
+    Ok(true)
+}
+
+/// Copy the selected file's path (0), the hunk the diff is scrolled into (1), or its whole
+/// diff (2)
+fn copy_from_file(app: &mut App, git_repo: &dyn GitBackend, choice: usize) {
+    let Some(file) = app.files_state.files.get(app.files_state.selected) else {
+        return;
+    };
+    let (text, what) = match choice {
+        0 => (file.path.clone(), tr!("path")),
+        1 => {
+            let diff = app.files_state.displayed_diff().unwrap_or_default();
+            // Before the first hunk, that one is the current one
+            let header = current_hunk(diff, app.files_state.diff_scroll)
+                .or_else(|| hunk_starts(diff).first().copied());
+            match header {
+                Some(header) => (hunk_text(diff, header), tr!("hunk")),
+                None => {
+                    app.set_status(tr!("No hunk to copy").to_string());
+                    return;
+                }
+            }
+        }
+        _ => {
+            // Regenerated without the line limit, so a long diff is copied whole
+            match git_repo.get_diff_for_file(&file.path, file.staged, app.ignore_whitespace, None) {
+                Ok(diff) => (diff, tr!("diff")),
+                Err(e) => {
+                    app.set_status(tr!("Error getting diff: {}", e));
+                    return;
+                }
+            }
+        }
+    };
+    match copy_to_clipboard(&text) {
+        Ok(()) => app.set_status(tr!("Copied the {} to the clipboard", what)),
+        Err(e) => app.set_status(tr!("Failed to copy: {}", e)),
+    }
+}
+
+fn update_file_diff(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    app.files_state.changed_lines = None;
+    app.files_state.rejected_hunks = None;
+    if let Some(file) = app.files_state.files.get(app.files_state.selected) {
+        match git_repo.get_diff_for_file(
+            &file.path,
+            file.staged,
+            app.ignore_whitespace,
+            app.files_state.diff_line_limit(),
+        ) {
+            Ok(diff) => {
+                app.files_state.current_diff = Some(diff);
+            }
+            Err(e) => {
+                app.files_state.current_diff = Some(tr!("Error getting diff: {}", e));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Pick up changes made outside gtek while the Files view is showing. Unlike a manual
+/// refresh this keeps the selected file and diff scroll position, and marks what changed.
+pub fn auto_refresh(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    if app.is_overlay_open() {
+        return Ok(());
+    }
+    // The dashboard's counts follow the working tree too; the Files view refreshes its diff
+    // when it is switched to
+    if app.current_view == View::Dashboard {
+        update_head_state(app, git_repo);
+        app.files_state.files = git_repo.get_status()?;
+        app.files_state.selected = app
+            .files_state
+            .selected
+            .min(app.files_state.files.len().saturating_sub(1));
+        app.stash_count = git_repo.stash_count()?;
+        return Ok(());
+    }
+    if app.current_view != View::Files {
+        return Ok(());
+    }
+
+    // A merge or rebase started on the command line shows up in the header
+    update_head_state(app, git_repo);
+    let files = git_repo.get_status()?;
+    if files != app.files_state.files {
+        let selected = app
+            .files_state
+            .files
+            .get(app.files_state.selected)
+            .map(|file| (file.path.clone(), file.staged));
+        app.files_state.files = files;
+        match selected.and_then(|(path, staged)| {
+            app.files_state
+                .files
+                .iter()
+                .position(|f| f.path == path && f.staged == staged)
+        }) {
+            Some(pos) => app.files_state.selected = pos,
+            None => {
+                // The selected file is gone; fall back to a fresh diff of its neighbour
+                app.files_state.selected = app
+                    .files_state
+                    .selected
+                    .min(app.files_state.files.len().saturating_sub(1));
+                app.reset_diff_scroll();
+                if app.files_state.files.is_empty() {
+                    app.files_state.current_diff = None;
+                } else {
+                    update_file_diff(app, git_repo)?;
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    // The selected file's content can change without its status changing
+    let Some(file) = app.files_state.files.get(app.files_state.selected) else {
+        return Ok(());
+    };
+    let Ok(diff) = git_repo.get_diff_for_file(
+        &file.path,
+        file.staged,
+        app.ignore_whitespace,
+        app.files_state.diff_line_limit(),
+    ) else {
+        return Ok(());
+    };
+    if app.files_state.current_diff.as_deref() != Some(diff.as_str()) {
+        let old = app.files_state.current_diff.take().unwrap_or_default();
+        let line_count = diff.lines().count();
+        app.files_state.changed_lines = Some(changed_region(&old, &diff));
+        app.files_state.diff_scroll = app
+            .files_state
+            .diff_scroll
+            .min(line_count.saturating_sub(1));
+        app.files_state.current_diff = Some(diff);
+    }
+    Ok(())
+}
+
+/// Lines of `new` that differ from `old`, between their common prefix and suffix
+fn changed_region(old: &str, new: &str) -> Range<usize> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let end = new.len() - suffix;
+    // A pure removal leaves nothing between prefix and suffix; mark the line after the gap
+    if end > prefix {
+        prefix..end
+    } else {
+        prefix..(prefix + 1).min(new.len())
+    }
+}
+
+/// Recount HEAD against the pinned comparison base after anything that may move either side
+/// Pick up repository state the header shows: a stopped rebase or a detached HEAD
+pub fn update_head_state(app: &mut App, git_repo: &dyn GitBackend) {
+    app.in_progress = git_repo.in_progress();
+    app.detached_at = git_repo.detached_head();
+}
+
+pub fn update_base_divergence(app: &mut App, git_repo: &dyn GitBackend) {
+    app.base_divergence = app
+        .compare_base
+        .as_deref()
+        .and_then(|base| git_repo.ahead_behind(base).ok());
+}
+
+fn refresh_files(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    update_base_divergence(app, git_repo);
+    update_head_state(app, git_repo);
+    app.files_state.files = git_repo.get_status()?;
+    if !app.files_state.files.is_empty() {
+        app.files_state.selected = app
+            .files_state
+            .selected
+            .min(app.files_state.files.len() - 1);
+        update_file_diff(app, git_repo)?;
+    } else {
+        app.files_state.current_diff = None;
+    }
+    Ok(())
+}
+
+/// Everything the dashboard summarizes: working tree, branches, recent commits and the stash
+fn refresh_dashboard(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    refresh_files(app, git_repo)?;
+    refresh_branches(app, git_repo)?;
+    refresh_history(app, git_repo)?;
+    app.stash_count = git_repo.stash_count()?;
+    Ok(())
+}
+
+pub fn refresh_history(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    // Reload at least as many commits as were already paged in
+    update_base_divergence(app, git_repo);
+    let limit = app.history_state.commits.len().max(app.history_page_size);
+    let history = &mut app.history_state;
+    history.cursor = git_repo.history_cursor(history.filter)?;
+    history.commits = git_repo.get_commit_page(&mut history.cursor, limit)?;
+    history.graph = graph::Layout::default();
+    history.graph.extend(&mut history.commits);
+    history.has_more = history.cursor.has_more();
+    if !app.history_state.commits.is_empty() {
+        app.history_state.selected = app
+            .history_state
+            .selected
+            .min(app.history_state.commits.len() - 1);
+    }
+    Ok(())
+}
+
+/// Start the History list over after the walk filter changed
+fn reload_filtered_history(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    app.history_state.selected = 0;
+    app.history_state.detail = None;
+    app.history_state.commit_diff = None;
+    app.history_state.commits.clear();
+    refresh_history(app, git_repo)
+}
+
+/// Append the next page of history, carrying the graph lanes on from the loaded commits
+fn load_more_history(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    let history = &mut app.history_state;
+    let mut page = git_repo.get_commit_page(&mut history.cursor, app.history_page_size)?;
+    history.graph.extend(&mut page);
+    history.commits.extend(page);
+    history.has_more = history.cursor.has_more();
+    Ok(())
+}
+
+/// Page in more history once the selection gets close to the end of the loaded list
+fn ensure_history_loaded(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    const PREFETCH_MARGIN: usize = 20;
+    if app.history_state.has_more
+        && app.history_state.selected + PREFETCH_MARGIN >= app.history_state.commits.len()
+    {
+        load_more_history(app, git_repo)?;
+    }
+    Ok(())
+}
+
+fn refresh_branches(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    update_base_divergence(app, git_repo);
+    update_head_state(app, git_repo);
+    let selected = app
+        .branches_state
+        .branches
+        .get(app.branches_state.selected)
+        .map(|branch| branch.name.clone());
+    app.branches_state.branches = git_repo.get_branches()?;
+    app.branches_state.current_branch = git_repo.get_current_branch()?;
+    // Sorting keeps the selection on the branch that was selected before the reload
+    if let Some(pos) = selected.and_then(|name| {
+        app.branches_state
+            .branches
+            .iter()
+            .position(|branch| branch.name == name)
+    }) {
+        app.branches_state.selected = pos;
+    }
+    app.branches_state.sort();
+    if !app.branches_state.branches.is_empty() {
+        app.branches_state.selected = app
+            .branches_state
+            .selected
+            .min(app.branches_state.branches.len() - 1);
+    }
+    Ok(())
+}
+
+fn refresh_tags(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    app.tags_state.tags = git_repo.get_tags()?;
+    if !app.tags_state.tags.is_empty() {
+        app.tags_state.selected = app.tags_state.selected.min(app.tags_state.tags.len() - 1);
+    }
+    Ok(())
+}
+
+fn refresh_current_view(app: &mut App, git_repo: &dyn GitBackend) -> Result<()> {
+    match app.current_view {
+        View::Dashboard => refresh_dashboard(app, git_repo)?,
+        View::Files => refresh_files(app, git_repo)?,
+        View::History => refresh_history(app, git_repo)?,
         View::Branches => refresh_branches(app, git_repo)?,
+        View::Tags => refresh_tags(app, git_repo)?,
+    }
+    Ok(())
+}
+
+/// Local, unprotected branches other than the current one that are merged into HEAD or whose
+/// tip is at least `stale_branch_days` old, oldest first
+fn stale_branches(app: &App, git_repo: &dyn GitBackend) -> Result<Vec<StaleBranch>> {
+    let now = chrono::Utc::now().timestamp();
+    let mut stale = Vec::new();
+    for branch in &app.branches_state.branches {
+        if branch.is_current || branch.remote.is_some() || app.is_protected_branch(&branch.name) {
+            continue;
+        }
+        let age_days = (now - branch.tip_time).max(0) / 86_400;
+        let unmerged = git_repo.unmerged_commits(&branch.name)?;
+        if unmerged == 0 || age_days >= app.stale_branch_days {
+            stale.push(StaleBranch {
+                name: branch.name.clone(),
+                age_days,
+                unmerged,
+                tip_subject: branch.tip_subject.clone(),
+            });
+        }
+    }
+    stale.sort_by_key(|branch| std::cmp::Reverse(branch.age_days));
+    Ok(stale)
+}
+
+fn handle_stale_branch_keys(app: &mut App, key: KeyEvent, git_repo: &dyn GitBackend) -> Result<()> {
+    let Some(Panel::StaleBranches(report)) = app.panels.top_mut() else {
+        return Ok(());
+    };
+    let Some(branch) = report.branches.get(report.selected).cloned() else {
+        if key.code == KeyCode::Esc {
+            app.panels.pop();
+        }
+        return Ok(());
+    };
+
+    let result = match key.code {
+        KeyCode::Esc => {
+            app.panels.pop();
+            return Ok(());
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            report.selected = report.selected.saturating_sub(1);
+            return Ok(());
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            report.selected = (report.selected + 1).min(report.branches.len() - 1);
+            return Ok(());
+        }
+        KeyCode::Char('d') if branch.unmerged > 0 => {
+            let message = tr!(
+                "{} has {} commit(s) not in {} that will be lost. Press y to delete it, n or Esc to keep it",
+                branch.name,
+                branch.unmerged,
+                app.branches_state.current_branch
+            );
+            app.dialogs.push(
+                Dialog::confirm(
+                    tr!("Delete unmerged branch '{}'", branch.name),
+                    message,
+                    DialogAction::DeleteStaleBranch(branch.name),
+                )
+                .dangerous(),
+            );
+            return Ok(());
+        }
+        KeyCode::Char('d') => git_repo
+            .delete_branch(&branch.name, false)
+            .map(|_| tr!("Deleted branch {}", branch.name)),
+        KeyCode::Char('a') => git_repo
+            .archive_branch(&branch.name)
+            .map(|tag| tr!("Archived branch {} as tag {}", branch.name, tag)),
+        _ => return Ok(()),
+    };
+    stale_branch_cleaned(
+        app,
+        git_repo,
+        &branch.name,
+        result,
+        key.code == KeyCode::Char('a'),
+    )
+}
+
+/// Drop a deleted or archived branch from the stale report
+fn stale_branch_cleaned(
+    app: &mut App,
+    git_repo: &dyn GitBackend,
+    name: &str,
+    result: Result<String>,
+    archived: bool,
+) -> Result<()> {
+    match result {
+        Ok(message) => {
+            if let Some(Panel::StaleBranches(report)) = app.panels.top_mut() {
+                report.branches.retain(|branch| branch.name != name);
+                report.selected = report.selected.min(report.branches.len().saturating_sub(1));
+            }
+            app.set_status(message);
+            refresh_branches(app, git_repo)?;
+            if archived {
+                refresh_tags(app, git_repo)?;
+            }
+        }
+        Err(e) => app.set_status(tr!("Failed to clean up {}: {}", name, e)),
     }
     Ok(())
 }
@@ -0,0 +1,471 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::app::View;
+use crate::i18n::tr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyContext {
+    Global,
+    Dashboard,
+    Files,
+    History,
+    Branches,
+    Tags,
+}
+
+impl KeyContext {
+    pub const ALL: [KeyContext; 6] = [
+        KeyContext::Global,
+        KeyContext::Dashboard,
+        KeyContext::Files,
+        KeyContext::History,
+        KeyContext::Branches,
+        KeyContext::Tags,
+    ];
+
+    pub fn for_view(view: View) -> Self {
+        match view {
+            View::Dashboard => KeyContext::Dashboard,
+            View::Files => KeyContext::Files,
+            View::History => KeyContext::History,
+            View::Branches => KeyContext::Branches,
+            View::Tags => KeyContext::Tags,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyContext::Global => "global",
+            KeyContext::Dashboard => "dashboard",
+            KeyContext::Files => "files",
+            KeyContext::History => "history",
+            KeyContext::Branches => "branches",
+            KeyContext::Tags => "tags",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|ctx| ctx.name() == name)
+    }
+}
+
+/// A key as written in the config file; only Ctrl and Alt are significant modifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+impl KeyBinding {
+    const fn plain(code: KeyCode) -> Self {
+        Self {
+            code,
+            ctrl: false,
+            alt: false,
+        }
+    }
+
+    fn from_event(key: &KeyEvent) -> Self {
+        Self {
+            code: key.code,
+            ctrl: key.modifiers.contains(KeyModifiers::CONTROL),
+            alt: key.modifiers.contains(KeyModifiers::ALT),
+        }
+    }
+
+    /// Parse `"x"`, `"X"`, `"enter"`, `"pgdn"`, `"ctrl+r"`, `"alt+f2"` and similar
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut binding = Self::plain(KeyCode::Null);
+        let mut rest = text;
+        loop {
+            let lower = rest.to_lowercase();
+            if lower.starts_with("ctrl+") && rest.len() > 5 {
+                binding.ctrl = true;
+                rest = &rest[5..];
+            } else if lower.starts_with("alt+") && rest.len() > 4 {
+                binding.alt = true;
+                rest = &rest[4..];
+            } else {
+                break;
+            }
+        }
+
+        let mut chars = rest.chars();
+        binding.code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                name => {
+                    let number = name.strip_prefix('f')?.parse::<u8>().ok()?;
+                    if !(1..=12).contains(&number) {
+                        return None;
+                    }
+                    KeyCode::F(number)
+                }
+            },
+        };
+        Some(binding)
+    }
+
+    pub fn display(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(' ') => "space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::F(n) => format!("f{}", n),
+            KeyCode::PageUp => "pgup".to_string(),
+            KeyCode::PageDown => "pgdn".to_string(),
+            code => format!("{:?}", code).to_lowercase(),
+        };
+        let mut text = String::new();
+        if self.ctrl {
+            text.push_str("ctrl+");
+        }
+        if self.alt {
+            text.push_str("alt+");
+        }
+        text.push_str(&key);
+        text
+    }
+}
+
+/// Built-in actions with their default keys, grouped by the context that handles them
+const DEFAULT_BINDINGS: &[(KeyContext, &str, &[KeyCode])] = &[
+    (KeyContext::Global, "quit", &[KeyCode::Char('q')]),
+    (KeyContext::Global, "dashboard", &[KeyCode::Char('0')]),
+    (KeyContext::Global, "files_view", &[KeyCode::Char('1')]),
+    (KeyContext::Global, "history_view", &[KeyCode::Char('2')]),
+    (KeyContext::Global, "branches_view", &[KeyCode::Char('3')]),
+    (KeyContext::Global, "tags_view", &[KeyCode::Char('4')]),
+    (KeyContext::Global, "refresh", &[KeyCode::Char('r')]),
+    (KeyContext::Global, "command_log", &[KeyCode::Char('L')]),
+    (KeyContext::Global, "clone", &[KeyCode::Char('O')]),
+    (KeyContext::Global, "search", &[KeyCode::Char('?')]),
+    (
+        KeyContext::Global,
+        "toggle_whitespace",
+        &[KeyCode::Char('w')],
+    ),
+    (KeyContext::Global, "up", &[KeyCode::Up, KeyCode::Char('k')]),
+    (
+        KeyContext::Global,
+        "down",
+        &[KeyCode::Down, KeyCode::Char('j')],
+    ),
+    (KeyContext::Global, "page_up", &[KeyCode::PageUp]),
+    (KeyContext::Global, "page_down", &[KeyCode::PageDown]),
+    (KeyContext::Dashboard, "files", &[KeyCode::Char('f')]),
+    (KeyContext::Dashboard, "history", &[KeyCode::Char('h')]),
+    (KeyContext::Dashboard, "branches", &[KeyCode::Char('b')]),
+    (KeyContext::Dashboard, "tags", &[KeyCode::Char('t')]),
+    (KeyContext::Dashboard, "maintenance", &[KeyCode::Char('g')]),
+    (KeyContext::Files, "stage", &[KeyCode::Char('s')]),
+    (KeyContext::Files, "stage_all", &[KeyCode::Char('a')]),
+    (KeyContext::Files, "unstage", &[KeyCode::Char('U')]),
+    (KeyContext::Files, "discard", &[KeyCode::Char('d')]),
+    (KeyContext::Files, "discard_all", &[KeyCode::Char('D')]),
+    (KeyContext::Files, "commit", &[KeyCode::Char('c')]),
+    (KeyContext::Files, "absorb", &[KeyCode::Char('A')]),
+    (KeyContext::Files, "apply_patch", &[KeyCode::Char('I')]),
+    (KeyContext::Files, "export_diff", &[KeyCode::Char('x')]),
+    (KeyContext::Files, "ignore", &[KeyCode::Char('i')]),
+    (KeyContext::Files, "dismiss_rejects", &[KeyCode::Esc]),
+    (KeyContext::Files, "undo_commit", &[KeyCode::Char('u')]),
+    (KeyContext::Files, "take_ours", &[KeyCode::Char('o')]),
+    (KeyContext::Files, "take_theirs", &[KeyCode::Char('t')]),
+    (KeyContext::Files, "edit_conflicts", &[KeyCode::Char('e')]),
+    (KeyContext::Files, "mergetool", &[KeyCode::Char('m')]),
+    (KeyContext::Files, "difftool", &[KeyCode::Char('T')]),
+    (KeyContext::Files, "copy", &[KeyCode::Char('y')]),
+    (KeyContext::Files, "blame", &[KeyCode::Char('b')]),
+    (KeyContext::Files, "submodules", &[KeyCode::Char('M')]),
+    (
+        KeyContext::Files,
+        "file_from_revision",
+        &[KeyCode::Char('G')],
+    ),
+    (KeyContext::Files, "fetch", &[KeyCode::Char('f')]),
+    (KeyContext::Files, "pull", &[KeyCode::Char('p')]),
+    (KeyContext::Files, "pull_ff_only", &[KeyCode::Char('F')]),
+    (KeyContext::Files, "push", &[KeyCode::Char('P')]),
+    (KeyContext::Files, "sync", &[KeyCode::Char('S')]),
+    (KeyContext::Files, "refresh_diff", &[KeyCode::Enter]),
+    (KeyContext::Files, "load_full_diff", &[KeyCode::Char('l')]),
+    (KeyContext::Files, "next_hunk", &[KeyCode::Char('n')]),
+    (KeyContext::Files, "previous_hunk", &[KeyCode::Char('N')]),
+    (KeyContext::Files, "widen_diff", &[KeyCode::Char('<')]),
+    (KeyContext::Files, "narrow_diff", &[KeyCode::Char('>')]),
+    (KeyContext::History, "details", &[KeyCode::Enter]),
+    (KeyContext::History, "commit_diff", &[KeyCode::Char('d')]),
+    (KeyContext::History, "mark_range", &[KeyCode::Char('v')]),
+    (KeyContext::History, "close_details", &[KeyCode::Esc]),
+    (KeyContext::History, "next_hunk", &[KeyCode::Char('n')]),
+    (KeyContext::History, "previous_hunk", &[KeyCode::Char('N')]),
+    (KeyContext::History, "goto_revision", &[KeyCode::Char('g')]),
+    (KeyContext::History, "undo_commit", &[KeyCode::Char('u')]),
+    (
+        KeyContext::History,
+        "toggle_all_branches",
+        &[KeyCode::Char('a')],
+    ),
+    (KeyContext::History, "first_parent", &[KeyCode::Char('F')]),
+    (
+        KeyContext::History,
+        "checkout_detached",
+        &[KeyCode::Char('o')],
+    ),
+    (
+        KeyContext::History,
+        "file_from_revision",
+        &[KeyCode::Char('G')],
+    ),
+    (KeyContext::History, "revert", &[KeyCode::Char('R')]),
+    (KeyContext::History, "tag", &[KeyCode::Char('t')]),
+    (KeyContext::History, "bisect", &[KeyCode::Char('m')]),
+    (KeyContext::History, "rebase_plan", &[KeyCode::Char('i')]),
+    (KeyContext::History, "format_patch", &[KeyCode::Char('p')]),
+    (KeyContext::History, "export", &[KeyCode::Char('x')]),
+    (KeyContext::History, "bundle", &[KeyCode::Char('e')]),
+    (KeyContext::History, "pin_base", &[KeyCode::Char('b')]),
+    (KeyContext::History, "unpin_base", &[KeyCode::Char('B')]),
+    (KeyContext::History, "widen_detail", &[KeyCode::Char('<')]),
+    (KeyContext::History, "narrow_detail", &[KeyCode::Char('>')]),
+    (KeyContext::Branches, "new_branch", &[KeyCode::Char('n')]),
+    (KeyContext::Branches, "delete_branch", &[KeyCode::Char('d')]),
+    (
+        KeyContext::Branches,
+        "cleanup_merged",
+        &[KeyCode::Char('c')],
+    ),
+    (
+        KeyContext::Branches,
+        "stale_branches",
+        &[KeyCode::Char('a')],
+    ),
+    (KeyContext::Branches, "filter", &[KeyCode::Char('/')]),
+    (
+        KeyContext::Branches,
+        "file_from_revision",
+        &[KeyCode::Char('G')],
+    ),
+    (KeyContext::Branches, "toggle_sort", &[KeyCode::Char('s')]),
+    (KeyContext::Branches, "merge", &[KeyCode::Char('M')]),
+    (KeyContext::Branches, "worktrees", &[KeyCode::Char('W')]),
+    (KeyContext::Branches, "rebase", &[KeyCode::Char('R')]),
+    (
+        KeyContext::Branches,
+        "continue_operation",
+        &[KeyCode::Char('C')],
+    ),
+    (
+        KeyContext::Branches,
+        "skip_operation",
+        &[KeyCode::Char('S')],
+    ),
+    (
+        KeyContext::Branches,
+        "abort_operation",
+        &[KeyCode::Char('X')],
+    ),
+    (KeyContext::Branches, "set_upstream", &[KeyCode::Char('t')]),
+    (
+        KeyContext::Branches,
+        "unset_upstream",
+        &[KeyCode::Char('T')],
+    ),
+    (
+        KeyContext::Branches,
+        "checkout",
+        &[KeyCode::Enter, KeyCode::Char('o')],
+    ),
+    (
+        KeyContext::Tags,
+        "checkout_tag",
+        &[KeyCode::Enter, KeyCode::Char('o')],
+    ),
+    (KeyContext::Tags, "new_tag", &[KeyCode::Char('n')]),
+    (KeyContext::Tags, "delete_tag", &[KeyCode::Char('d')]),
+    (KeyContext::Tags, "push_tag", &[KeyCode::Char('p')]),
+    (KeyContext::Tags, "push_all_tags", &[KeyCode::Char('P')]),
+];
+
+fn default_keys(context: KeyContext, action: &str) -> Option<&'static [KeyCode]> {
+    DEFAULT_BINDINGS
+        .iter()
+        .find(|(ctx, name, _)| *ctx == context && *name == action)
+        .map(|(_, _, keys)| *keys)
+}
+
+/// Translates user-configured keys into the default keys the input handlers match on.
+///
+/// Each context maps a pressed key to the default key of the action it triggers, or to
+/// `None` when the key's default action was moved elsewhere and nothing replaced it.
+#[derive(Debug, Default)]
+pub struct Keymap {
+    bindings: HashMap<KeyContext, HashMap<KeyBinding, Option<KeyCode>>>,
+}
+
+impl Keymap {
+    /// Build the keymap from `[keybindings.<context>]` overrides, returning a warning for
+    /// every conflict, shadowed action and unparsable entry found along the way
+    pub fn from_config(
+        overrides: &BTreeMap<String, BTreeMap<String, String>>,
+    ) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut user_bindings: HashMap<KeyContext, Vec<(&'static str, KeyBinding)>> =
+            HashMap::new();
+
+        for (context_name, actions) in overrides {
+            let Some(context) = KeyContext::from_name(context_name) else {
+                warnings.push(tr!("Unknown keybinding context [{}]", context_name));
+                continue;
+            };
+            for (action, key) in actions {
+                let Some((_, name, _)) = DEFAULT_BINDINGS
+                    .iter()
+                    .find(|(ctx, name, _)| *ctx == context && name == action)
+                else {
+                    warnings.push(tr!("[{}] unknown action '{}'", context.name(), action));
+                    continue;
+                };
+                let Some(binding) = KeyBinding::parse(key) else {
+                    warnings.push(tr!(
+                        "[{}] {}: cannot parse key '{}'",
+                        context.name(),
+                        action,
+                        key
+                    ));
+                    continue;
+                };
+                user_bindings
+                    .entry(context)
+                    .or_default()
+                    .push((*name, binding));
+            }
+        }
+
+        // Effective key -> action table per context; user bindings win over defaults
+        let mut tables: HashMap<KeyContext, Vec<(KeyBinding, &'static str)>> = HashMap::new();
+        let mut keymap = Keymap::default();
+
+        for context in KeyContext::ALL {
+            let user = user_bindings.remove(&context).unwrap_or_default();
+            let mut table: Vec<(KeyBinding, &'static str)> = Vec::new();
+            let mut map: HashMap<KeyBinding, Option<KeyCode>> = HashMap::new();
+
+            for (action, binding) in &user {
+                if let Some((_, existing)) = table.iter().find(|(key, _)| key == binding) {
+                    warnings.push(tr!(
+                        "[{}] '{}' is bound to both {} and {}; {} keeps it and {} keeps its default",
+                        context.name(),
+                        binding.display(),
+                        existing,
+                        action,
+                        existing,
+                        action
+                    ));
+                    continue;
+                }
+                table.push((*binding, action));
+                let target = default_keys(context, action).and_then(|keys| keys.first().copied());
+                map.insert(*binding, target);
+            }
+
+            for (ctx, action, keys) in DEFAULT_BINDINGS {
+                if *ctx != context {
+                    continue;
+                }
+                // Only accepted user bindings count; conflict losers keep their defaults
+                let rebound = table.iter().any(|(_, name)| name == action);
+                let mut shadowed_by = Vec::new();
+                for code in keys.iter() {
+                    let binding = KeyBinding::plain(*code);
+                    if let Some((_, owner)) = table.iter().find(|(key, _)| *key == binding) {
+                        if owner != action {
+                            shadowed_by.push(*owner);
+                        }
+                    } else if rebound {
+                        // The action moved to a user key; its old default does nothing now
+                        map.insert(binding, None);
+                    } else {
+                        table.push((binding, action));
+                        map.insert(binding, Some(*code));
+                    }
+                }
+                if !rebound && !shadowed_by.is_empty() && shadowed_by.len() == keys.len() {
+                    warnings.push(tr!(
+                        "[{}] {} is unreachable: its default key is now bound to {}",
+                        context.name(),
+                        action,
+                        shadowed_by.join(", ")
+                    ));
+                }
+            }
+
+            tables.insert(context, table);
+            keymap.bindings.insert(context, map);
+        }
+
+        // View bindings are consulted before global ones, so overlaps hide the global action
+        let global = tables.remove(&KeyContext::Global).unwrap_or_default();
+        for context in KeyContext::ALL {
+            let Some(table) = tables.get(&context) else {
+                continue;
+            };
+            for (binding, action) in table {
+                if let Some((_, global_action)) = global.iter().find(|(key, _)| key == binding) {
+                    warnings.push(tr!(
+                        "[{}] '{}' for {} hides global {} in this view",
+                        context.name(),
+                        binding.display(),
+                        action,
+                        global_action
+                    ));
+                }
+            }
+        }
+
+        (keymap, warnings)
+    }
+
+    /// Map a pressed key to the default key the handlers understand.
+    /// Returns `None` when the key has been unbound and should be ignored.
+    pub fn translate(&self, view: View, key: KeyEvent) -> Option<KeyEvent> {
+        let binding = KeyBinding::from_event(&key);
+        let mut unbound = false;
+
+        for context in [KeyContext::for_view(view), KeyContext::Global] {
+            match self
+                .bindings
+                .get(&context)
+                .and_then(|map| map.get(&binding))
+            {
+                Some(Some(code)) => return Some(KeyEvent::new(*code, KeyModifiers::NONE)),
+                Some(None) => unbound = true,
+                None => {}
+            }
+        }
+
+        if unbound {
+            None
+        } else {
+            Some(key)
+        }
+    }
+}
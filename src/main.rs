@@ -1,69 +1,155 @@
 mod app;
+mod config;
+mod conflict;
+mod diagnose;
+mod dialog;
+mod export;
 mod git;
+mod graph;
+mod i18n;
+mod image;
 mod input;
+mod keymap;
+mod panel;
+mod terminal;
+mod theme;
 mod ui;
 mod ui_branches;
+mod ui_dashboard;
 mod ui_files;
 mod ui_history;
+mod ui_tags;
 
 use anyhow::Result;
-use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
+use crossterm::event::{self, Event};
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
-use std::time::Duration;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use app::App;
-use git::GitRepo;
-use input::{handle_key_event, handle_mouse_event};
+use config::{AppConfig, RepoConfig};
+use git::{GitBackend, GitRepo};
+use input::{
+    auto_refresh, handle_clone_keys, handle_init_keys, handle_key_event, handle_mouse_event,
+    refresh_history, update_head_state,
+};
+use panel::Panel;
+use terminal::TerminalGuard;
+use theme::Theme;
 use ui::render_ui;
 
+/// Command line options: an optional repository path plus flags
+struct CliArgs {
+    repo_path: Option<std::path::PathBuf>,
+    accessible: bool,
+    diagnose: bool,
+    /// `--no-color`, or `NO_COLOR` set in the environment
+    no_color: bool,
+}
+
+fn parse_args() -> CliArgs {
+    let mut args = CliArgs {
+        repo_path: None,
+        accessible: false,
+        diagnose: false,
+        no_color: std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()),
+    };
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--accessible" => args.accessible = true,
+            "--diagnose" => args.diagnose = true,
+            "--no-color" => args.no_color = true,
+            _ => args.repo_path = Some(std::path::PathBuf::from(arg)),
+        }
+    }
+    args
+}
+
 fn main() -> Result<()> {
+    let args = parse_args();
+
     // Get repository path from args or use current directory
-    let repo_path = std::env::args()
-        .nth(1)
-        .map(std::path::PathBuf::from)
+    let repo_path = args
+        .repo_path
         .unwrap_or_else(|| std::env::current_dir().unwrap());
 
-    // Open git repository
-    let git_repo = GitRepo::open(&repo_path)?;
-    // Test comment
-    // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    // Load user config; a broken file falls back to defaults with a warning
+    let (config, config_error) = match AppConfig::load() {
+        Ok(config) => (config, None),
+        Err(e) => (AppConfig::default(), Some(e.to_string())),
+    };
 
-    // Create app state
-    let mut app = App::new(repo_path);
+    // Load the message catalog before any UI text is produced
+    let locale = i18n::resolve_locale(config.locale.as_deref());
+    let catalog_error = i18n::init(locale.as_deref(), config.locale.is_some()).err();
 
-    // Initialize data
-    app.branches_state.current_branch = git_repo.get_current_branch()?;
-    app.branches_state.branches = git_repo.get_branches()?;
-    app.files_state.files = git_repo.get_status()?;
-    if !app.files_state.files.is_empty() {
-        if let Ok(diff) = git_repo.get_diff_for_file(&app.files_state.files[0].path) {
-            app.files_state.current_diff = Some(diff);
-        }
+    // Diagnostics run without the TUI and print straight to stdout, in the configured language
+    if args.diagnose {
+        return diagnose::run(&repo_path);
     }
-    app.history_state.commits = git_repo.get_commits(100)?;
+
+    // Setup terminal
+    let terminal_guard = TerminalGuard::enter()?;
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::new(backend)?;
 
     // Setup panic hook to restore terminal
     let original_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic| {
-        restore_terminal();
+        terminal::restore();
         original_hook(panic);
     }));
 
-    // Main loop
-    let result = run_app(&mut terminal, &mut app, &git_repo);
+    // Main loop, once per repository: a clone switches to the new one
+    let mut repo_path = repo_path;
+    let mut warnings: Vec<String> = config_error
+        .into_iter()
+        .chain(catalog_error.map(|e| e.to_string()))
+        .collect();
+    let mut status = None;
+    let result = loop {
+        let session = match GitRepo::open(&repo_path) {
+            Ok(mut git_repo) => open_app(
+                &mut git_repo,
+                repo_path.clone(),
+                config.clone(),
+                args.accessible,
+                args.no_color,
+                std::mem::take(&mut warnings),
+            )
+            .and_then(|mut app| {
+                // Say how the switch went, e.g. where the clone went
+                app.status_message = app.status_message.take().or(status.take());
+                run_app(&mut terminal, &mut app, &git_repo).map(|_| app)
+            }),
+            Err(_) => {
+                // Nothing to show without a repository but the way to get one
+                let mut app = App::new(repo_path.clone(), config.clone());
+                app.accessible |= args.accessible;
+                if args.no_color {
+                    app.theme = Theme::monochrome();
+                }
+                app.panels
+                    .close(|panel| matches!(panel, Panel::StartupWarnings));
+                app.show_init_prompt = true;
+                run_without_repo(&mut terminal, &mut app).map(|_| app)
+            }
+        };
+        match session {
+            Ok(mut app) => match app.open_repo.take() {
+                Some(path) => {
+                    repo_path = path;
+                    status = app.status_message.take();
+                }
+                None => break Ok(()),
+            },
+            Err(e) => break Err(e),
+        }
+    };
 
     // Restore terminal - always do this
-    restore_terminal();
+    drop(terminal_guard);
 
     // After restoring terminal, we can safely show errors
     if let Err(err) = result {
@@ -73,20 +159,115 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn restore_terminal() {
-    // Disable raw mode
-    let _ = disable_raw_mode();
+/// Set up the app for an opened repository: settings from its `.gtek.toml`, the network
+/// settings, startup warnings and the initial contents of every view
+fn open_app(
+    git_repo: &mut GitRepo,
+    repo_path: PathBuf,
+    mut config: AppConfig,
+    accessible: bool,
+    no_color: bool,
+    warnings: Vec<String>,
+) -> Result<App> {
+    // Repository settings from .gtek.toml override the user's
+    let repo_config_error = match git_repo.workdir().map(RepoConfig::load) {
+        Some(Ok(repo_config)) => {
+            repo_config.apply(&mut config);
+            None
+        }
+        Some(Err(e)) => Some(e.to_string()),
+        None => None,
+    };
 
-    // Restore terminal state
-    let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture);
+    git_repo.set_ssh_keys(config.ssh_keys());
+    git_repo.set_shell_git(config.shell_git);
+    git_repo.set_diff_context(config.diff_context.unwrap_or(3));
+
+    // Create app state
+    let mut app = App::new(repo_path, config);
+    app.accessible |= accessible;
+    // Wins over any theme, the repository's included
+    if no_color {
+        app.theme = Theme::monochrome();
+    }
+    for error in warnings.into_iter().chain(repo_config_error) {
+        app.startup_warnings.insert(0, error);
+    }
+    let warnings_shown = app
+        .panels
+        .iter()
+        .any(|panel| matches!(panel, Panel::StartupWarnings));
+    if !app.startup_warnings.is_empty() && !warnings_shown {
+        app.panels.push(Panel::StartupWarnings);
+    }
+
+    // Initialize data
+    app.branches_state.current_branch = git_repo.get_current_branch()?;
+    app.branches_state.branches = git_repo.get_branches()?;
+    app.branches_state.sort();
+    app.tags_state.tags = git_repo.get_tags()?;
+    app.files_state.files = git_repo.get_status()?;
+    if !app.files_state.files.is_empty() {
+        if let Ok(diff) = git_repo.get_diff_for_file(
+            &app.files_state.files[0].path,
+            app.files_state.files[0].staged,
+            app.ignore_whitespace,
+            app.files_state.diff_line_limit(),
+        ) {
+            app.files_state.current_diff = Some(diff);
+        }
+    }
+    refresh_history(&mut app, &*git_repo)?;
+    update_head_state(&mut app, &*git_repo);
+    app.stash_count = git_repo.stash_count()?;
+    // Say up front when origin's transport is missing; each network action checks its own remote
+    app.status_message = git_repo.unsupported_transport("origin");
+    Ok(app)
+}
+
+/// Offer to initialize or clone a repository until one is ready to open or the user quits
+fn run_without_repo(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+) -> Result<()> {
+    while app.open_repo.is_none()
+        && (app.show_init_prompt || matches!(app.panels.top(), Some(Panel::Clone(_))))
+    {
+        if terminal::take_redraw_request() {
+            terminal.clear()?;
+        }
+        terminal.draw(|f| render_ui(f, app))?;
+
+        if event::poll(Duration::from_millis(100))? {
+            if let Event::Key(key) = event::read()? {
+                if app.show_init_prompt {
+                    handle_init_keys(app, key)?;
+                } else {
+                    handle_clone_keys(app, key)?;
+                    // Backing out of the clone dialog returns to the choice
+                    let cloning = matches!(app.panels.top(), Some(Panel::Clone(_)));
+                    if !cloning && app.open_repo.is_none() {
+                        app.show_init_prompt = true;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
 }
 
 fn run_app(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     app: &mut App,
-    git_repo: &GitRepo,
+    git_repo: &dyn GitBackend,
 ) -> Result<()> {
+    const AUTO_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+    let mut last_refresh = Instant::now();
+
     loop {
+        if terminal::take_redraw_request() {
+            terminal.clear()?;
+        }
         terminal.draw(|f| render_ui(f, app))?;
 
         if event::poll(Duration::from_millis(100))? {
@@ -95,15 +276,20 @@ fn run_app(
                     handle_key_event(app, key, git_repo)?;
                 }
                 Event::Mouse(mouse) => {
-                    handle_mouse_event(app, mouse)?;
+                    handle_mouse_event(app, mouse, git_repo)?;
                 }
                 _ => {}
             }
         }
 
-        if app.should_quit {
+        if app.should_quit || app.open_repo.is_some() {
             break;
         }
+
+        if last_refresh.elapsed() >= AUTO_REFRESH_INTERVAL {
+            auto_refresh(app, git_repo)?;
+            last_refresh = Instant::now();
+        }
     }
 
     Ok(())
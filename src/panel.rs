@@ -0,0 +1,70 @@
+use crate::app::{
+    BisectPanel, BlameView, CloneDialog, ConflictEditor, CredentialPrompt, GrepSearch, RebasePlan,
+    StaleBranchReport, SubmoduleList, WorktreeList,
+};
+
+/// A panel over the current view on the `App::panels` stack. Only the topmost one gets keys,
+/// and only while no dialog is open; closing it pops it.
+#[derive(Debug)]
+pub enum Panel {
+    /// Config and keybinding problems found at startup; any key dismisses them
+    StartupWarnings,
+    /// Open while a network operation waits for credentials
+    Credentials(CredentialPrompt),
+    /// Repository to clone and where to put it
+    Clone(CloneDialog),
+    /// Sides picked for the conflict regions of a `UU` file
+    ConflictEditor(ConflictEditor),
+    Blame(BlameView),
+    Bisect(BisectPanel),
+    Grep(GrepSearch),
+    Worktrees(WorktreeList),
+    Submodules(SubmoduleList),
+    StaleBranches(StaleBranchReport),
+    /// Output of the system git commands, scrolled by `App::command_log_scroll`
+    CommandLog,
+    /// Commits above a base, reordered before rebasing onto it
+    RebasePlan(RebasePlan),
+    /// Text typed after `/` in Branches; only matching branches are listed
+    BranchFilter(String),
+}
+
+/// Open panels, the last one on top
+#[derive(Debug, Default)]
+pub struct PanelStack {
+    panels: Vec<Panel>,
+}
+
+impl PanelStack {
+    pub fn push(&mut self, panel: Panel) {
+        self.panels.push(panel);
+    }
+
+    pub fn pop(&mut self) -> Option<Panel> {
+        self.panels.pop()
+    }
+
+    pub fn top(&self) -> Option<&Panel> {
+        self.panels.last()
+    }
+
+    pub fn top_mut(&mut self) -> Option<&mut Panel> {
+        self.panels.last_mut()
+    }
+
+    /// Close the topmost panel if it is the one `is_panel` picks out
+    pub fn close(&mut self, is_panel: impl Fn(&Panel) -> bool) {
+        if self.top().is_some_and(is_panel) {
+            self.panels.pop();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.panels.is_empty()
+    }
+
+    /// Bottom to top, the order they are drawn in
+    pub fn iter(&self) -> impl Iterator<Item = &Panel> {
+        self.panels.iter()
+    }
+}
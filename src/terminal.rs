@@ -0,0 +1,147 @@
+use anyhow::Result;
+use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set after the terminal was handed to another program; the next frame must repaint everything
+static REDRAW_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// Opened on the first copy and kept for the app's lifetime: on X11 and Wayland the owner
+    /// serves the copied text, so it would vanish with a dropped clipboard unless a clipboard
+    /// manager took it over
+    static CLIPBOARD: RefCell<Option<arboard::Clipboard>> = const { RefCell::new(None) };
+}
+
+/// Puts the terminal into raw mode on the alternate screen, and restores it when dropped,
+/// including when startup or the main loop bails out with an error.
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn enter() -> Result<Self> {
+        // Construct first so a failure halfway through still restores what was changed
+        let guard = TerminalGuard;
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Leave raw mode and the alternate screen; safe to call more than once (e.g. from the panic hook)
+pub fn restore() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture);
+}
+
+/// Re-enters the TUI when dropped, unless a panic is unwinding and the hook already restored
+struct Resume;
+
+impl Drop for Resume {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            return;
+        }
+        let _ = enable_raw_mode();
+        let _ = execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture);
+        REDRAW_REQUESTED.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Give the plain terminal to `operation` (an editor, hook, difftool, credential prompt, ...)
+/// and bring the TUI back afterwards, whether the operation succeeds, fails or panics.
+pub fn with_terminal_suspended<T>(operation: impl FnOnce() -> T) -> T {
+    restore();
+    let _resume = Resume;
+    operation()
+}
+
+/// Ask for a full repaint, e.g. after something else drew on the screen
+pub fn request_redraw() {
+    REDRAW_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// True once after the terminal was suspended; the caller should clear before drawing
+pub fn take_redraw_request() -> bool {
+    REDRAW_REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+/// Put `text` on the system clipboard. Without one (no display server, e.g. over SSH) the
+/// terminal is asked to take it with an OSC 52 escape, which most terminal emulators honour.
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    let copied = CLIPBOARD.with_borrow_mut(|clipboard| {
+        if clipboard.is_none() {
+            *clipboard = arboard::Clipboard::new().ok();
+        }
+        clipboard
+            .as_mut()
+            .is_some_and(|clipboard| clipboard.set_text(text).is_ok())
+    });
+    if copied {
+        return Ok(());
+    }
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Standard base64 with padding, as OSC 52 expects
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_matches_rfc_4648() {
+        let cases = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(base64(input.as_bytes()), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn base64_uses_the_whole_alphabet() {
+        assert_eq!(base64(&[0xfb, 0xff, 0xbf]), "+/+/");
+        assert_eq!(base64(&[0x00, 0x10, 0x83]), "ABCD");
+        assert_eq!(base64("€".as_bytes()), "4oKs");
+    }
+}
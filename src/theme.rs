@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::i18n::tr;
+
+/// Colors the UI draws with, named by what they mark rather than how they look
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// Regular text
+    pub text: Color,
+    /// Secondary text: dates, hints, placeholders, inactive sides
+    pub dimmed: Color,
+    /// Key help in the footer
+    pub help: Color,
+    /// Pane and dialog borders
+    pub border: Color,
+    /// Field labels, headings, branch names and hunk headers
+    pub label: Color,
+    /// Commit ids, the status message, the active field and anything asking for attention
+    pub accent: Color,
+    /// Errors, destructive dialogs and conflicts
+    pub danger: Color,
+    /// Done, clean and up-to-date states, authors
+    pub success: Color,
+    /// Added lines and files
+    pub added: Color,
+    /// Removed lines and files
+    pub removed: Color,
+    /// Renames, conflict markers, locked worktrees and matched paths
+    pub special: Color,
+    /// Untracked files
+    pub untracked: Color,
+    /// Tag names
+    pub tag: Color,
+    /// Text of the selected row
+    pub selection_fg: Color,
+    /// Background of the selected row
+    pub selection_bg: Color,
+    /// Background of rows and lines singled out besides the selection: a marked range, lines
+    /// changed on disk
+    pub highlight_bg: Color,
+    /// History graph lanes, cycled through
+    pub graph: Vec<Color>,
+    /// No colors at all: the selection and highlights are drawn with reverse video and
+    /// underlining instead
+    pub monochrome: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    /// Light text on a dark terminal
+    pub fn dark() -> Self {
+        Self {
+            text: Color::White,
+            dimmed: Color::DarkGray,
+            help: Color::Gray,
+            border: Color::Cyan,
+            label: Color::Cyan,
+            accent: Color::Yellow,
+            danger: Color::Red,
+            success: Color::Green,
+            added: Color::Green,
+            removed: Color::Red,
+            special: Color::Magenta,
+            untracked: Color::Blue,
+            tag: Color::LightYellow,
+            selection_fg: Color::Black,
+            selection_bg: Color::White,
+            highlight_bg: Color::DarkGray,
+            graph: vec![
+                Color::Magenta,
+                Color::Cyan,
+                Color::Green,
+                Color::Yellow,
+                Color::Blue,
+                Color::Red,
+            ],
+            monochrome: false,
+        }
+    }
+
+    /// Dark text on a light terminal; yellow and cyan, unreadable on white, give way to darker
+    /// shades from the 256-color palette
+    pub fn light() -> Self {
+        let amber = Color::Indexed(130);
+        let teal = Color::Indexed(30);
+        Self {
+            text: Color::Black,
+            dimmed: Color::DarkGray,
+            help: Color::DarkGray,
+            border: Color::Blue,
+            label: Color::Blue,
+            accent: amber,
+            danger: Color::Red,
+            success: Color::Green,
+            added: Color::Green,
+            removed: Color::Red,
+            special: Color::Magenta,
+            untracked: teal,
+            tag: amber,
+            selection_fg: Color::White,
+            selection_bg: Color::Blue,
+            highlight_bg: Color::Indexed(253),
+            graph: vec![
+                Color::Magenta,
+                Color::Blue,
+                Color::Green,
+                amber,
+                teal,
+                Color::Red,
+            ],
+            monochrome: false,
+        }
+    }
+
+    /// The terminal's own colors only, for `NO_COLOR`, `--no-color` or `theme = "monochrome"`
+    pub fn monochrome() -> Self {
+        Self {
+            text: Color::Reset,
+            dimmed: Color::Reset,
+            help: Color::Reset,
+            border: Color::Reset,
+            label: Color::Reset,
+            accent: Color::Reset,
+            danger: Color::Reset,
+            success: Color::Reset,
+            added: Color::Reset,
+            removed: Color::Reset,
+            special: Color::Reset,
+            untracked: Color::Reset,
+            tag: Color::Reset,
+            selection_fg: Color::Reset,
+            selection_bg: Color::Reset,
+            highlight_bg: Color::Reset,
+            graph: vec![Color::Reset],
+            monochrome: true,
+        }
+    }
+
+    /// The `theme` preset (`dark`, the default, `light` or `monochrome`) with `[colors]` overrides applied,
+    /// returning a warning for every unknown name and unparsable color
+    pub fn from_config(
+        name: Option<&str>,
+        colors: &BTreeMap<String, String>,
+    ) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut theme = match name {
+            None | Some("dark") | Some("default") => Self::dark(),
+            Some("light") => Self::light(),
+            Some("monochrome") => Self::monochrome(),
+            Some(other) => {
+                warnings.push(tr!("Unknown theme '{}'; using the dark colors", other));
+                Self::dark()
+            }
+        };
+
+        for (role, value) in colors {
+            if role == "graph" {
+                match value
+                    .split(',')
+                    .map(parse_color)
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Some(lanes) if !lanes.is_empty() => theme.graph = lanes,
+                    _ => warnings.push(tr!("[colors] graph: cannot parse '{}'", value)),
+                }
+                continue;
+            }
+            let Some(slot) = theme.role_mut(role) else {
+                warnings.push(tr!("[colors] unknown color '{}'", role));
+                continue;
+            };
+            match parse_color(value) {
+                Some(color) => *slot = color,
+                None => warnings.push(tr!("[colors] {}: cannot parse '{}'", role, value)),
+            }
+        }
+        (theme, warnings)
+    }
+
+    fn role_mut(&mut self, role: &str) -> Option<&mut Color> {
+        Some(match role {
+            "text" => &mut self.text,
+            "dimmed" => &mut self.dimmed,
+            "help" => &mut self.help,
+            "border" => &mut self.border,
+            "label" => &mut self.label,
+            "accent" => &mut self.accent,
+            "danger" => &mut self.danger,
+            "success" => &mut self.success,
+            "added" => &mut self.added,
+            "removed" => &mut self.removed,
+            "special" => &mut self.special,
+            "untracked" => &mut self.untracked,
+            "tag" => &mut self.tag,
+            "selection_fg" => &mut self.selection_fg,
+            "selection_bg" => &mut self.selection_bg,
+            "highlight_bg" => &mut self.highlight_bg,
+            _ => return None,
+        })
+    }
+
+    /// The selected row of a list
+    pub fn selection(&self) -> Style {
+        if self.monochrome {
+            return Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD);
+        }
+        Style::default()
+            .fg(self.selection_fg)
+            .bg(self.selection_bg)
+            .add_modifier(Modifier::BOLD)
+    }
+
+    /// Rows and lines singled out besides the selection: a marked range, lines changed on disk
+    pub fn highlight(&self) -> Style {
+        if self.monochrome {
+            return Style::default().add_modifier(Modifier::UNDERLINED);
+        }
+        Style::default().bg(self.highlight_bg)
+    }
+
+    /// Color of a History graph lane
+    pub fn lane(&self, index: usize) -> Color {
+        self.graph[index % self.graph.len()]
+    }
+}
+
+/// A color name (`red`, `lightblue`, `darkgray`), a 256-color index (`208`) or a truecolor hex
+/// value (`#ff8800`)
+fn parse_color(value: &str) -> Option<Color> {
+    Color::from_str(value.trim()).ok()
+}
@@ -1,106 +1,299 @@
+use std::rc::Rc;
+
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, Paragraph},
+    widgets::{Block, Borders, Clear, Gauge, List, ListItem, ListState, Paragraph},
     Frame,
 };
 
-use crate::app::{App, View};
+use crate::app::{
+    App, BisectPanel, BisectStep, BlameView, CloneDialog, ConflictEditor, CredentialPrompt,
+    GrepSearch, InProgress, RebasePlan, RevisionInput, StaleBranchReport, SubmoduleList,
+    TransferProgress, View, WorktreeList,
+};
+use crate::conflict::{Choice, Segment};
+use crate::dialog::{Dialog, DialogKind};
+use crate::git::format_bytes;
+use crate::i18n::tr;
+use crate::panel::Panel;
 use crate::ui_branches::render_branches;
+use crate::ui_dashboard::render_dashboard;
 use crate::ui_files::render_files;
 use crate::ui_history::render_history;
+use crate::ui_tags::render_tags;
 
 pub fn render_ui(f: &mut Frame, app: &App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3), // Header
-            Constraint::Min(0),    // Main content
-            Constraint::Length(3), // Footer
-        ])
-        .split(f.area());
+    let chunks = screen_layout(f.area());
 
     // Render header
     render_header(f, app, chunks[0]);
 
     // Render main content based on current view
     match app.current_view {
+        View::Dashboard => render_dashboard(f, app, chunks[1]),
         View::Files => render_files(f, app, chunks[1]),
         View::History => render_history(f, app, chunks[1]),
         View::Branches => render_branches(f, app, chunks[1]),
+        View::Tags => render_tags(f, app, chunks[1]),
     }
 
     // Render footer
     render_footer(f, app, chunks[2]);
 
-    // Render commit dialog if active
-    if app.show_commit_dialog {
-        render_commit_dialog(f, app);
+    if app.show_init_prompt {
+        render_init_prompt(f, app);
     }
 
-    // Render branch creation dialog if active
-    if app.show_branch_dialog {
-        render_branch_dialog(f, app);
-    }
-
-    // Render delete confirmation dialog if active
-    if app.show_delete_confirm {
-        render_delete_confirm_dialog(f, app);
+    // Render the panels over the view, bottom to top
+    for panel in app.panels.iter() {
+        match panel {
+            Panel::StartupWarnings => render_startup_warnings(f, app),
+            Panel::Credentials(prompt) => render_credential_prompt(f, app, prompt),
+            Panel::Clone(dialog) => render_clone_dialog(f, app, dialog),
+            Panel::ConflictEditor(editor) => render_conflict_editor(f, app, editor),
+            Panel::Blame(blame) => render_blame(f, app, blame),
+            Panel::Bisect(panel) => render_bisect_panel(f, app, panel),
+            Panel::Grep(search) => render_grep(f, app, search),
+            Panel::Worktrees(list) => render_worktree_list(f, app, list),
+            Panel::Submodules(list) => render_submodule_list(f, app, list),
+            Panel::StaleBranches(report) => render_stale_branches(f, app, report),
+            Panel::CommandLog => render_command_log(f, app),
+            Panel::RebasePlan(plan) => render_rebase_plan(f, app, plan),
+            // Shown in the Branches pane title
+            Panel::BranchFilter(_) => {}
+        }
     }
 
-    // Render discard confirmation dialog if active
-    if app.show_discard_confirm {
-        render_discard_confirm_dialog(f, app);
+    // Render confirm, input and choice dialogs over any panel they were opened from, the
+    // topmost last
+    for dialog in app.dialogs.iter() {
+        render_dialog(f, app, dialog);
     }
 }
 
 fn render_header(f: &mut Frame, app: &App, area: Rect) {
-    let title = vec![
+    if app.accessible {
+        let view_name = match app.current_view {
+            View::Dashboard => tr!("Home"),
+            View::Files => tr!("Files"),
+            View::History => tr!("History"),
+            View::Branches => tr!("Branches"),
+            View::Tags => tr!("Tags"),
+        };
+        let head = match &app.detached_at {
+            Some(id) => tr!("detached at {}", id),
+            None => tr!("branch {}", app.branches_state.current_branch),
+        };
+        let mut text = tr!(
+            "GitUI, {}, {} view. Views: 0 Home, 1 Files, 2 History, 3 Branches, 4 Tags",
+            head,
+            view_name
+        );
+        text.push_str(&tr!(". {}", spoken_summary(app)));
+        if let Some(base) = &app.compare_base {
+            text.push_str(&match app.base_divergence {
+                Some((ahead, behind)) => {
+                    tr!(". {} ahead, {} behind {}", ahead, behind, base)
+                }
+                None => tr!(". {} not found", base),
+            });
+        }
+        if let Some(operation) = app.in_progress {
+            let (label, hint) = in_progress_banner(operation);
+            text.push_str(&tr!(". {}: {}", label, hint));
+        }
+        let header = Paragraph::new(text);
+        f.render_widget(header, area);
+        return;
+    }
+
+    let mut title = vec![
         Span::styled(
             "GitUI",
             Style::default()
-                .fg(Color::Cyan)
+                .fg(app.theme.label)
                 .add_modifier(Modifier::BOLD),
         ),
         Span::raw(" | "),
-        Span::styled(
-            format!("Branch: {}", app.branches_state.current_branch),
-            Style::default().fg(Color::Green),
-        ),
+        match &app.detached_at {
+            Some(id) => Span::styled(
+                tr!("detached @ {}", id),
+                Style::default()
+                    .fg(app.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            None => Span::styled(
+                tr!("Branch: {}", app.branches_state.current_branch),
+                Style::default().fg(app.theme.success),
+            ),
+        },
         Span::raw(" | "),
-        Span::styled("Views: ", Style::default().fg(Color::White)),
+        Span::styled(tr!("Views: "), Style::default().fg(app.theme.text)),
+        Span::styled("[0]", get_view_style(app, View::Dashboard)),
+        Span::raw(format!(" {} ", tr!("Home"))),
         Span::styled("[1]", get_view_style(app, View::Files)),
-        Span::raw(" Files "),
+        Span::raw(format!(" {} ", tr!("Files"))),
         Span::styled("[2]", get_view_style(app, View::History)),
-        Span::raw(" History "),
+        Span::raw(format!(" {} ", tr!("History"))),
         Span::styled("[3]", get_view_style(app, View::Branches)),
-        Span::raw(" Branches"),
+        Span::raw(format!(" {} ", tr!("Branches"))),
+        Span::styled("[4]", get_view_style(app, View::Tags)),
+        Span::raw(format!(" {}", tr!("Tags"))),
+        Span::raw(" | "),
     ];
+    title.extend(summary_spans(app));
+    if let Some(base) = &app.compare_base {
+        let comparison = match app.base_divergence {
+            Some((ahead, behind)) => tr!("vs {}: ↑{} ↓{}", base, ahead, behind),
+            None => tr!("vs {}: ?", base),
+        };
+        title.push(Span::raw(" | "));
+        title.push(Span::styled(
+            comparison,
+            Style::default().fg(app.theme.accent),
+        ));
+    }
+    if let Some(operation) = app.in_progress {
+        let (label, hint) = in_progress_banner(operation);
+        title.push(Span::raw(" | "));
+        title.push(Span::styled(
+            format!(" {} ", label),
+            Style::default()
+                .fg(app.theme.text)
+                .bg(app.theme.danger)
+                .add_modifier(Modifier::BOLD),
+        ));
+        title.push(Span::styled(
+            format!(" {}", hint),
+            Style::default().fg(app.theme.danger),
+        ));
+    }
 
     let header = Paragraph::new(Line::from(title))
-        .block(Block::default().borders(Borders::ALL))
+        .block(Block::default().borders(pane_borders(app)))
         .alignment(Alignment::Left);
 
     f.render_widget(header, area);
 }
 
+/// Compact working-tree state for the header, e.g. `●3 ✚5 …2 ↑1`, each count only when nonzero
+fn summary_spans(app: &App) -> Vec<Span<'static>> {
+    let summary = app.files_state.summary();
+    let mut spans = Vec::new();
+    let mut push = |count: usize, text: String, color: Color| {
+        if count > 0 {
+            if !spans.is_empty() {
+                spans.push(Span::raw(" "));
+            }
+            spans.push(Span::styled(text, Style::default().fg(color)));
+        }
+    };
+    push(
+        summary.conflicted,
+        tr!("✖{} conflicted", summary.conflicted),
+        app.theme.danger,
+    );
+    push(
+        summary.staged,
+        tr!("●{} staged", summary.staged),
+        app.theme.success,
+    );
+    push(
+        summary.modified,
+        tr!("✚{} modified", summary.modified),
+        app.theme.danger,
+    );
+    push(
+        summary.untracked,
+        tr!("…{} untracked", summary.untracked),
+        app.theme.untracked,
+    );
+    let (ahead, behind) = app
+        .branches_state
+        .current_ahead_behind()
+        .unwrap_or_default();
+    push(ahead, format!("↑{}", ahead), app.theme.label);
+    push(behind, format!("↓{}", behind), app.theme.label);
+    if spans.is_empty() {
+        spans.push(Span::styled(
+            tr!("✔ clean"),
+            Style::default().fg(app.theme.success),
+        ));
+    }
+    spans
+}
+
+/// The header counts in words for screen readers
+fn spoken_summary(app: &App) -> String {
+    let summary = app.files_state.summary();
+    let (ahead, behind) = app
+        .branches_state
+        .current_ahead_behind()
+        .unwrap_or_default();
+    tr!(
+        "{} staged, {} modified, {} untracked, {} conflicted, {} ahead and {} behind upstream",
+        summary.staged,
+        summary.modified,
+        summary.untracked,
+        summary.conflicted,
+        ahead,
+        behind
+    )
+}
+
+/// Banner text for an operation stopped midway, and how to get on with it
+pub fn in_progress_banner(operation: InProgress) -> (String, &'static str) {
+    let continue_or_abort = tr!("stage resolved files, then C: continue / X: abort in Branches");
+    let continue_skip_or_abort =
+        tr!("stage resolved files, then C: continue / S: skip / X: abort in Branches");
+    match operation {
+        InProgress::Merge => (tr!("MERGING").to_string(), continue_or_abort),
+        InProgress::Rebase(Some((current, total))) => (
+            tr!("REBASING {}/{}", current, total),
+            continue_skip_or_abort,
+        ),
+        InProgress::Rebase(None) => (
+            tr!("REBASING").to_string(),
+            tr!("finish with git rebase --continue or --abort"),
+        ),
+        InProgress::CherryPick => (tr!("CHERRY-PICKING").to_string(), continue_skip_or_abort),
+        InProgress::Revert => (tr!("REVERTING").to_string(), continue_skip_or_abort),
+        InProgress::Bisect => (
+            tr!("BISECTING").to_string(),
+            tr!("m: mark good/bad/skip in History / X: abort in Branches"),
+        ),
+        InProgress::ApplyMailbox => (
+            tr!("APPLYING PATCHES").to_string(),
+            tr!("finish with git am --continue or --abort"),
+        ),
+    }
+}
+
 fn render_footer(f: &mut Frame, app: &App, area: Rect) {
     let help_text = match app.current_view {
+        View::Dashboard => tr!("f:Files | h:History | b:Branches | t:Tags | g:Run gc | 0-4:Switch View | ?:Search | r:Refresh | q:Quit"),
         View::Files => {
-            "↑/↓:Navigate | PgUp/PgDn:Scroll | s:Stage | a:Stage All | d:Discard | D:Discard All | c:Commit | p:Pull | P:Push | S:Sync | r:Refresh | q:Quit"
+            tr!("↑/↓:Navigate | PgUp/PgDn:Scroll | n/N:Next/Prev Hunk | </>:Resize | l:Load Full Diff | s:Stage | U:Unstage | a:Stage All | d:Discard | D:Discard All | G:File From Revision | c:Commit | A:Absorb | I:Apply Patch | x:Export Diff | i:Ignore | u:Undo Commit | o/t:Take Ours/Theirs | e:Edit Conflicts | m:Mergetool | T:Difftool | y:Copy | b:Blame | M:Submodules | f:Fetch | p:Pull | F:Pull (FF only) | P:Push | S:Sync | r:Refresh | q:Quit")
         }
         View::History => {
-            "↑/↓:Navigate | r:Refresh | q:Quit"
+            tr!("↑/↓:Navigate | Enter:Details | d:Diff | v:Mark Range | Esc:Close | PgUp/PgDn:Scroll | n/N:Next/Prev Hunk | </>:Resize | g:Go to Revision | u:Undo Commit | a:All/Current Branch | F:First Parent | o:Checkout Detached | G:File From Commit | t:Tag | m:Bisect | R:Revert | i:Reorder | p:Format-patch | x:Export | e:Bundle | b/B:Pin/Unpin Base | r:Refresh | q:Quit")
         }
         View::Branches => {
-            "↑/↓:Navigate | n:New Branch | d:Delete | Enter:Checkout | r:Refresh | q:Quit"
+            tr!("↑/↓:Navigate | /:Filter | G:File From Branch | s:Sort | n:New Branch | d:Delete | c:Clean Up Merged | a:Stale Branches | M:Merge | R:Rebase | C/S/X:Continue/Skip/Abort | t/T:Set/Unset Upstream | W:Worktrees | Enter:Checkout | r:Refresh | q:Quit")
         }
+        View::Tags => tr!("↑/↓:Navigate | Enter:Checkout Tag | n:New Tag | d:Delete | p/P:Push Tag/All Tags | r:Refresh | q:Quit"),
     };
 
+    if let Some(transfer) = &app.transfer {
+        render_transfer_progress(f, app, transfer, area);
+        return;
+    }
+
     let mut footer_lines = vec![Line::from(Span::styled(
         help_text,
-        Style::default().fg(Color::Gray),
+        Style::default().fg(app.theme.help),
     ))];
 
     if let Some(status) = &app.status_message {
@@ -109,180 +302,1125 @@ fn render_footer(f: &mut Frame, app: &App, area: Rect) {
             Line::from(Span::styled(
                 status,
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(app.theme.accent)
                     .add_modifier(Modifier::BOLD),
             )),
         );
     }
 
     let footer = Paragraph::new(footer_lines)
-        .block(Block::default().borders(Borders::ALL))
+        .block(Block::default().borders(pane_borders(app)))
         .alignment(Alignment::Left);
 
     f.render_widget(footer, area);
 }
 
-fn render_commit_dialog(f: &mut Frame, app: &App) {
-    let area = centered_rect(60, 20, f.area());
-
-    let block = Block::default()
-        .title("Commit Message (Enter to commit, Esc to cancel)")
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Yellow));
+/// Footer gauge for a running fetch or push: objects, then deltas once all objects are in
+fn render_transfer_progress(f: &mut Frame, app: &App, transfer: &TransferProgress, area: Rect) {
+    let (label, done, total) = if transfer.sending {
+        (
+            tr!(
+                "Sending objects: {}/{} ({})",
+                transfer.objects,
+                transfer.total_objects,
+                format_bytes(transfer.bytes)
+            ),
+            transfer.objects,
+            transfer.total_objects,
+        )
+    } else if transfer.total_deltas > 0 && transfer.objects == transfer.total_objects {
+        (
+            tr!(
+                "Resolving deltas: {}/{}",
+                transfer.deltas,
+                transfer.total_deltas
+            ),
+            transfer.deltas,
+            transfer.total_deltas,
+        )
+    } else {
+        (
+            tr!(
+                "Receiving objects: {}/{} ({})",
+                transfer.objects,
+                transfer.total_objects,
+                format_bytes(transfer.bytes)
+            ),
+            transfer.objects,
+            transfer.total_objects,
+        )
+    };
+    let ratio = if total == 0 {
+        0.0
+    } else {
+        (done as f64 / total as f64).min(1.0)
+    };
 
-    let text = Paragraph::new(app.commit_message.as_str())
+    let block = Block::default().borders(pane_borders(app));
+    // Without colors the bar can't be told from its background; the label says it all
+    if app.accessible || app.theme.monochrome {
+        f.render_widget(Paragraph::new(label).block(block), area);
+        return;
+    }
+    let gauge = Gauge::default()
         .block(block)
-        .style(Style::default().fg(Color::White));
-
-    f.render_widget(Clear, area);
-    f.render_widget(text, area);
+        .gauge_style(
+            Style::default()
+                .fg(app.theme.label)
+                .bg(app.theme.highlight_bg),
+        )
+        .ratio(ratio)
+        .label(label);
+    f.render_widget(gauge, area);
 }
 
-fn render_branch_dialog(f: &mut Frame, app: &App) {
-    let area = centered_rect(70, 60, f.area());
-
+/// A dialog from the stack: a question, a text field, a revision or a list to pick from
+fn render_dialog(f: &mut Frame, app: &App, dialog: &Dialog) {
+    let area = match dialog.kind {
+        DialogKind::Choice { .. } | DialogKind::Checklist { .. } => centered_rect(70, 60, f.area()),
+        DialogKind::Revision(_) => centered_rect(70, 50, f.area()),
+        _ => centered_rect(70, 30, f.area()),
+    };
     f.render_widget(Clear, area);
 
-    if app.branch_creation.selecting_base {
-        // Show base branch selection
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Min(0)])
-            .split(area);
+    if let DialogKind::Revision(input) = &dialog.kind {
+        render_revision_input(f, app, area, &dialog.title, input);
+        return;
+    }
 
-        let branches: Vec<ratatui::widgets::ListItem> = app
-            .branches_state
-            .branches
-            .iter()
-            .enumerate()
-            .map(|(i, branch)| {
-                let style = if i == app.branch_creation.base_branch_selected {
-                    Style::default()
-                        .fg(Color::Black)
-                        .bg(Color::White)
-                        .add_modifier(Modifier::BOLD)
-                } else {
-                    Style::default().fg(Color::White)
-                };
+    let border = if dialog.danger {
+        app.theme.danger
+    } else {
+        app.theme.accent
+    };
+    let title = match &dialog.kind {
+        DialogKind::Checklist { options, .. } => {
+            let checked = options.iter().filter(|(_, checked)| *checked).count();
+            tr!(
+                "{} ({} of {} checked)",
+                dialog.title,
+                checked,
+                options.len()
+            )
+        }
+        _ => dialog.title.clone(),
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(pane_borders(app))
+        .border_style(Style::default().fg(border));
 
-                let prefix = if branch.is_current { "* " } else { "  " };
-                let content = Line::from(vec![Span::raw(prefix), Span::raw(&branch.name)]);
+    let mut lines = Vec::new();
+    if let Some(message) = &dialog.message {
+        lines.push(Line::from(message.as_str()));
+        lines.push(Line::from(""));
+    }
+    let (rows, selected) = match &dialog.kind {
+        DialogKind::Choice {
+            options,
+            selected,
+            keys,
+        } => {
+            let rows: Vec<String> = options
+                .iter()
+                .enumerate()
+                .map(|(i, option)| match keys.get(i) {
+                    Some(key) => format!("{}: {}", key, option),
+                    None => option.clone(),
+                })
+                .collect();
+            (rows, *selected)
+        }
+        DialogKind::Checklist { options, selected } => {
+            let rows = options
+                .iter()
+                .map(|(option, checked)| {
+                    let mark = if *checked { "[x] " } else { "[ ] " };
+                    format!("{}{}", mark, option)
+                })
+                .collect();
+            (rows, *selected)
+        }
+        DialogKind::Input(text) => {
+            lines.push(Line::from(text.as_str()));
+            (Vec::new(), 0)
+        }
+        DialogKind::Confirm | DialogKind::Revision(_) => (Vec::new(), 0),
+    };
+    if let Some(hint) = &dialog.hint {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            hint.as_str(),
+            Style::default().fg(app.theme.dimmed),
+        )));
+    }
+    if !matches!(
+        dialog.kind,
+        DialogKind::Choice { .. } | DialogKind::Checklist { .. }
+    ) {
+        let paragraph = Paragraph::new(lines)
+            .block(block)
+            .style(Style::default().fg(app.theme.text))
+            .wrap(ratatui::widgets::Wrap { trim: false });
+        f.render_widget(paragraph, area);
+        return;
+    }
 
-                ratatui::widgets::ListItem::new(content).style(style)
-            })
-            .collect();
+    // Lists scroll below the message, which keeps the height it wraps to
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let width = usize::from(inner.width.max(1));
+    let message_height: usize = lines
+        .iter()
+        .map(|line| line.width().div_ceil(width).max(1))
+        .sum();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(message_height.min(usize::from(u16::MAX)) as u16),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+    let message = Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .wrap(ratatui::widgets::Wrap { trim: false });
+    f.render_widget(message, chunks[0]);
 
-        let list = ratatui::widgets::List::new(branches).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Select Base Branch (Enter to confirm, Esc to cancel)")
-                .border_style(Style::default().fg(Color::Yellow)),
-        );
+    let items: Vec<ListItem> = rows
+        .into_iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let style = if i == selected {
+                app.theme.selection()
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+            ListItem::new(Line::from(vec![
+                Span::raw(selection_prefix(app, i == selected)),
+                Span::raw(row),
+            ]))
+            .style(style)
+        })
+        .collect();
+    let mut state = ListState::default().with_selected(Some(selected));
+    f.render_stateful_widget(List::new(items), chunks[1], &mut state);
+}
 
-        f.render_widget(list, chunks[0]);
+/// Pane borders; screen-reader mode drops the box-drawing glyphs entirely
+pub fn pane_borders(app: &App) -> Borders {
+    if app.accessible {
+        Borders::NONE
     } else {
-        // Show branch name input
-        let base_branch = app
-            .branches_state
-            .branches
-            .get(app.branch_creation.base_branch_selected)
-            .map(|b| b.name.as_str())
-            .unwrap_or(&app.branches_state.current_branch);
-
-        let title = format!(
-            "Create Branch from '{}' (Tab to change base, Enter to create, Esc to cancel)",
-            base_branch
-        );
-
-        let block = Block::default()
-            .title(title)
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Yellow));
-
-        let text = Paragraph::new(app.branch_creation.new_branch_name.as_str())
-            .block(block)
-            .style(Style::default().fg(Color::White));
+        Borders::ALL
+    }
+}
 
-        f.render_widget(text, area);
+/// Textual selection marker so the highlighted row isn't conveyed by color alone
+pub fn selection_prefix(app: &App, selected: bool) -> &'static str {
+    match (app.accessible, selected) {
+        (true, true) => "> ",
+        (true, false) => "  ",
+        (false, _) => "",
     }
 }
 
 fn get_view_style(app: &App, view: View) -> Style {
     if app.current_view == view {
         Style::default()
-            .fg(Color::Yellow)
+            .fg(app.theme.accent)
             .add_modifier(Modifier::BOLD)
     } else {
-        Style::default().fg(Color::Gray)
+        Style::default().fg(app.theme.help)
+    }
+}
+
+fn render_rebase_plan(f: &mut Frame, app: &App, plan: &RebasePlan) {
+    let area = centered_rect(80, 70, f.area());
+    let hints = plan.conflict_hints();
+
+    let mut lines = vec![
+        Line::from(tr!(
+            "Replayed top to bottom onto {}. j/k: select | J/K: move | Enter: run | Esc: cancel",
+            plan.base
+        )),
+        Line::from(""),
+    ];
+    for (i, entry) in plan.entries.iter().enumerate() {
+        let selected = i == plan.selected;
+        let style = if selected {
+            app.theme.selection()
+        } else {
+            Style::default().fg(app.theme.text)
+        };
+        lines.push(Line::from(vec![
+            Span::raw(selection_prefix(app, selected)),
+            Span::styled(format!("pick {} {}", entry.id, entry.subject), style),
+        ]));
+        for (other, files) in &hints[i] {
+            lines.push(Line::from(Span::styled(
+                tr!(
+                    "    ⚠ now crosses {}, which also touches {}",
+                    other,
+                    files.join(", ")
+                ),
+                Style::default().fg(app.theme.accent),
+            )));
+        }
+    }
+
+    let block = Block::default()
+        .title(tr!("Rebase Plan ({} commits)", plan.entries.len()))
+        .borders(pane_borders(app))
+        .border_style(Style::default().fg(app.theme.accent));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(ratatui::widgets::Wrap { trim: false });
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+fn render_stale_branches(f: &mut Frame, app: &App, report: &StaleBranchReport) {
+    let area = centered_rect(80, 60, f.area());
+
+    let mut lines = vec![
+        Line::from(tr!(
+            "j/k: select | d: delete | a: archive as a tag and delete | Esc: close"
+        )),
+        Line::from(""),
+    ];
+    if report.branches.is_empty() {
+        lines.push(Line::from(tr!("No stale branches left")));
+    }
+    for (i, branch) in report.branches.iter().enumerate() {
+        let selected = i == report.selected;
+        let style = if selected {
+            app.theme.selection()
+        } else {
+            Style::default().fg(app.theme.text)
+        };
+        let (state, state_color) = if branch.unmerged == 0 {
+            (tr!("merged").to_string(), app.theme.success)
+        } else {
+            (tr!("{} unmerged", branch.unmerged), app.theme.accent)
+        };
+        lines.push(Line::from(vec![
+            Span::raw(selection_prefix(app, selected)),
+            Span::styled(branch.name.clone(), style),
+            Span::raw("  "),
+            Span::styled(state, Style::default().fg(state_color)),
+            Span::styled(
+                format!("  {}  ", tr!("{} days", branch.age_days)),
+                Style::default().fg(app.theme.dimmed),
+            ),
+            Span::raw(branch.tip_subject.clone()),
+        ]));
+    }
+
+    let block = Block::default()
+        .title(tr!(
+            "Stale Branches - merged or older than {} days ({})",
+            app.stale_branch_days,
+            report.branches.len()
+        ))
+        .borders(pane_borders(app))
+        .border_style(Style::default().fg(app.theme.danger));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(ratatui::widgets::Wrap { trim: false });
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+fn render_worktree_list(f: &mut Frame, app: &App, list: &WorktreeList) {
+    let area = centered_rect(80, 60, f.area());
+
+    let mut lines = vec![
+        Line::from(tr!(
+            "j/k: select | Enter: open in gtek | a: add for a branch | d: remove | Esc: close"
+        )),
+        Line::from(""),
+    ];
+    for (i, worktree) in list.worktrees.iter().enumerate() {
+        let selected = i == list.selected;
+        let style = if selected {
+            app.theme.selection()
+        } else {
+            Style::default().fg(app.theme.text)
+        };
+        let marker = if worktree.is_current { "* " } else { "  " };
+        let mut spans = vec![
+            Span::raw(selection_prefix(app, selected)),
+            Span::styled(format!("{}{}", marker, worktree.path.display()), style),
+            Span::raw(" "),
+            match &worktree.branch {
+                Some(branch) => Span::styled(
+                    format!("[{}]", branch),
+                    Style::default().fg(app.theme.success),
+                ),
+                // A missing directory has no HEAD to read
+                None if worktree.prunable => Span::raw(""),
+                None => Span::styled(tr!("(detached)"), Style::default().fg(app.theme.accent)),
+            },
+        ];
+        if worktree.name.is_none() {
+            spans.push(Span::styled(
+                tr!(" main"),
+                Style::default().fg(app.theme.label),
+            ));
+        }
+        if let Some(reason) = &worktree.locked {
+            let locked = if reason.is_empty() {
+                tr!(" locked").to_string()
+            } else {
+                tr!(" locked: {}", reason)
+            };
+            spans.push(Span::styled(locked, Style::default().fg(app.theme.special)));
+        }
+        if worktree.prunable {
+            spans.push(Span::styled(
+                tr!(" prunable (directory missing)"),
+                Style::default().fg(app.theme.danger),
+            ));
+        }
+        lines.push(Line::from(spans));
     }
+
+    let block = Block::default()
+        .title(tr!("Worktrees ({})", list.worktrees.len()))
+        .borders(pane_borders(app))
+        .border_style(Style::default().fg(app.theme.border));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(ratatui::widgets::Wrap { trim: false });
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
 }
 
-fn render_delete_confirm_dialog(f: &mut Frame, app: &App) {
+fn render_submodule_list(f: &mut Frame, app: &App, list: &SubmoduleList) {
+    let area = centered_rect(80, 60, f.area());
+
+    let mut lines = vec![
+        Line::from(tr!(
+            "j/k: select | i: init | u: update (clone and check out) | s: sync URL | Esc: close"
+        )),
+        Line::from(""),
+    ];
+    for (i, submodule) in list.submodules.iter().enumerate() {
+        let selected = i == list.selected;
+        let style = if selected {
+            app.theme.selection()
+        } else {
+            Style::default().fg(app.theme.text)
+        };
+        let (state, color) = if !submodule.initialized {
+            (tr!("uninitialized"), app.theme.dimmed)
+        } else if !submodule.checked_out {
+            (tr!("not checked out"), app.theme.accent)
+        } else if submodule.out_of_date {
+            (tr!("out of date"), app.theme.accent)
+        } else {
+            (tr!("up to date"), app.theme.success)
+        };
+        let mut spans = vec![
+            Span::raw(selection_prefix(app, selected)),
+            Span::styled(submodule.path.clone(), style),
+            Span::raw(" "),
+            Span::styled(format!("[{}]", state), Style::default().fg(color)),
+        ];
+        if submodule.dirty {
+            spans.push(Span::styled(
+                tr!(" dirty"),
+                Style::default().fg(app.theme.danger),
+            ));
+        }
+        if submodule.url_changed {
+            spans.push(Span::styled(
+                tr!(" URL changed (sync)"),
+                Style::default().fg(app.theme.special),
+            ));
+        }
+        if let Some(url) = &submodule.url {
+            spans.push(Span::styled(
+                format!(" {}", url),
+                Style::default().fg(app.theme.dimmed),
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    let block = Block::default()
+        .title(tr!("Submodules ({})", list.submodules.len()))
+        .borders(pane_borders(app))
+        .border_style(Style::default().fg(app.theme.border));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(ratatui::widgets::Wrap { trim: false });
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+fn render_credential_prompt(f: &mut Frame, app: &App, prompt: &CredentialPrompt) {
     let area = centered_rect(60, 30, f.area());
 
-    let default_name = String::from("unknown");
-    let branch_name = app.branch_to_delete.as_ref().unwrap_or(&default_name);
-    let title = format!("Delete branch '{}'", branch_name);
-    let instruction = "Type 'yes' or 'y' to confirm (Enter to submit, Esc to cancel)";
+    let field_style = |active: bool| {
+        if active {
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(app.theme.text)
+        }
+    };
+    let masked = "•".repeat(prompt.password.chars().count());
+    let lines = vec![
+        Line::from(prompt.url.as_str()),
+        Line::from(""),
+        Line::from(vec![
+            Span::raw(selection_prefix(app, !prompt.on_password)),
+            Span::styled(tr!("Username: "), field_style(!prompt.on_password)),
+            Span::raw(prompt.username.as_str()),
+        ]),
+        Line::from(vec![
+            Span::raw(selection_prefix(app, prompt.on_password)),
+            Span::styled(tr!("Password or token: "), field_style(prompt.on_password)),
+            Span::raw(masked),
+        ]),
+    ];
 
     let block = Block::default()
-        .title(title)
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Red));
+        .title(tr!(
+            "Credentials (Tab: switch field, Enter: next/submit, Esc: cancel)"
+        ))
+        .borders(pane_borders(app))
+        .border_style(Style::default().fg(app.theme.accent));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(ratatui::widgets::Wrap { trim: false });
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+fn render_init_prompt(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 30, f.area());
+
+    let block = Block::default()
+        .title(tr!("No Git Repository"))
+        .borders(pane_borders(app))
+        .border_style(Style::default().fg(app.theme.accent));
 
     let text = vec![
-        Line::from(instruction),
+        Line::from(tr!(
+            "{} is not inside a git repository.",
+            app.repo_path.display()
+        )),
         Line::from(""),
-        Line::from(app.delete_confirmation.as_str()),
+        Line::from(tr!(
+            "i: initialize a new repository here | c: clone one | Esc: quit"
+        )),
     ];
 
     let paragraph = Paragraph::new(text)
         .block(block)
-        .style(Style::default().fg(Color::White));
+        .style(Style::default().fg(app.theme.text))
+        .wrap(ratatui::widgets::Wrap { trim: false });
 
     f.render_widget(Clear, area);
     f.render_widget(paragraph, area);
 }
 
-fn render_discard_confirm_dialog(f: &mut Frame, app: &App) {
+fn render_clone_dialog(f: &mut Frame, app: &App, dialog: &CloneDialog) {
     let area = centered_rect(60, 30, f.area());
 
-    let title = if app.discard_all {
-        "Discard ALL changes".to_string()
-    } else {
-        let default_name = String::from("unknown");
-        let file_name = app.file_to_discard.as_ref().unwrap_or(&default_name);
-        format!("Discard changes to '{}'", file_name)
+    let field_style = |active: bool| {
+        if active {
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(app.theme.text)
+        }
     };
-    
-    let warning = if app.discard_all {
-        "WARNING: This will discard ALL uncommitted changes and delete untracked files!"
+    // An empty destination means a directory named after the repository
+    let destination = if dialog.destination.is_empty() && !dialog.url.trim().is_empty() {
+        Span::styled(
+            dialog.default_destination(),
+            Style::default().fg(app.theme.dimmed),
+        )
     } else {
-        "WARNING: This will permanently discard all uncommitted changes to this file!"
+        Span::raw(dialog.destination.as_str())
     };
-    let instruction = "Type 'yes' or 'y' to confirm (Enter to submit, Esc to cancel)";
+    let lines = vec![
+        Line::from(vec![
+            Span::raw(selection_prefix(app, !dialog.on_destination)),
+            Span::styled(tr!("URL: "), field_style(!dialog.on_destination)),
+            Span::raw(dialog.url.as_str()),
+        ]),
+        Line::from(vec![
+            Span::raw(selection_prefix(app, dialog.on_destination)),
+            Span::styled(tr!("Into: "), field_style(dialog.on_destination)),
+            destination,
+        ]),
+    ];
+
+    let block = Block::default()
+        .title(tr!(
+            "Clone Repository (Tab: switch field, Enter: next/clone, Esc: cancel)"
+        ))
+        .borders(pane_borders(app))
+        .border_style(Style::default().fg(app.theme.border));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(ratatui::widgets::Wrap { trim: false });
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+fn render_conflict_editor(f: &mut Frame, app: &App, editor: &ConflictEditor) {
+    let area = centered_rect(90, 90, f.area());
+
+    let count = editor.conflict_count();
+    let mut lines = Vec::new();
+    let mut selected_line = 0;
+    let mut index = 0;
+    for segment in &editor.file.segments {
+        let conflict = match segment {
+            Segment::Common(common) => {
+                lines.extend(common.iter().map(|line| {
+                    Line::styled(
+                        line.trim_end_matches(['\r', '\n']).to_string(),
+                        Style::default().fg(app.theme.dimmed),
+                    )
+                }));
+                continue;
+            }
+            Segment::Conflict(conflict) => conflict,
+        };
+
+        let selected = index == editor.selected;
+        if selected {
+            selected_line = lines.len();
+        }
+        let choice = match conflict.choice {
+            None => tr!("unresolved"),
+            Some(Choice::Ours) => tr!("ours"),
+            Some(Choice::Theirs) => tr!("theirs"),
+            Some(Choice::Both) => tr!("both"),
+        };
+        let header_style = if selected {
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD | Modifier::REVERSED)
+        } else if conflict.choice.is_none() {
+            Style::default()
+                .fg(app.theme.danger)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(app.theme.success)
+        };
+        lines.push(Line::from(vec![
+            Span::raw(selection_prefix(app, selected)),
+            Span::styled(
+                tr!("Conflict {}/{}: {}", index + 1, count, choice),
+                header_style,
+            ),
+        ]));
+
+        // Dim the side a choice throws away
+        let side_style = |color: Color, kept: bool| {
+            if conflict.choice.is_none() || kept {
+                Style::default().fg(color)
+            } else {
+                Style::default()
+                    .fg(app.theme.dimmed)
+                    .add_modifier(Modifier::CROSSED_OUT)
+            }
+        };
+        let keeps_ours = matches!(conflict.choice, Some(Choice::Ours | Choice::Both));
+        let keeps_theirs = matches!(conflict.choice, Some(Choice::Theirs | Choice::Both));
+        let marker_style = Style::default()
+            .fg(app.theme.special)
+            .add_modifier(Modifier::BOLD);
+        lines.push(Line::styled(
+            format!("<<<<<<< {}", conflict.ours_label),
+            marker_style,
+        ));
+        lines.extend(conflict.ours.iter().map(|line| {
+            Line::styled(
+                line.trim_end_matches(['\r', '\n']).to_string(),
+                side_style(app.theme.success, keeps_ours),
+            )
+        }));
+        lines.push(Line::styled("=======", marker_style));
+        lines.extend(conflict.theirs.iter().map(|line| {
+            Line::styled(
+                line.trim_end_matches(['\r', '\n']).to_string(),
+                side_style(app.theme.label, keeps_theirs),
+            )
+        }));
+        lines.push(Line::styled(
+            format!(">>>>>>> {}", conflict.theirs_label),
+            marker_style,
+        ));
+        index += 1;
+    }
+
+    // Keep the selected region's header a few rows below the top
+    let height = area.height.saturating_sub(2) as usize;
+    let scroll = selected_line.saturating_sub(height / 4);
+
+    let block = Block::default()
+        .title(tr!(
+            "Resolve {} (j/k: conflict, o/t/b: ours/theirs/both, Enter: write and stage, Esc: cancel)",
+            editor.path
+        ))
+        .borders(pane_borders(app))
+        .border_style(Style::default().fg(app.theme.special));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .scroll((scroll as u16, 0));
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+fn render_grep(f: &mut Frame, app: &App, search: &GrepSearch) {
+    let area = centered_rect(90, 85, f.area());
 
+    let title = if search.browsing {
+        let count = if search.truncated {
+            tr!("first {} matches", search.matches.len())
+        } else {
+            tr!("{} match(es)", search.matches.len())
+        };
+        tr!(
+            "Search - {} (j/k: select, Enter: blame, e: edit, /: new search, Esc: close)",
+            count
+        )
+    } else {
+        tr!("Search Tracked Files (Tab: pattern/revision, Enter: search, Esc: close)").to_string()
+    };
     let block = Block::default()
         .title(title)
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Red));
+        .borders(pane_borders(app))
+        .border_style(Style::default().fg(app.theme.border));
+    let inner = block.inner(area);
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
 
-    let text = vec![
-        Line::from(Span::styled(warning, Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))),
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Percentage(45),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+
+    // Query: the pattern, and the revision to search instead of the working tree
+    let label_style = |active: bool| {
+        if active && !search.browsing {
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(app.theme.text)
+        }
+    };
+    let revision = if search.revision.is_empty() && !search.editing_revision {
+        Span::styled(tr!("(working tree)"), Style::default().fg(app.theme.dimmed))
+    } else {
+        Span::raw(search.revision.as_str())
+    };
+    let query = vec![
+        Line::from(vec![
+            Span::styled(tr!("Pattern: "), label_style(!search.editing_revision)),
+            Span::raw(search.pattern.as_str()),
+        ]),
+        Line::from(vec![
+            Span::styled(tr!("Revision: "), label_style(search.editing_revision)),
+            revision,
+        ]),
+    ];
+    f.render_widget(Paragraph::new(query), chunks[0]);
+
+    // Matches, scrolled to keep the selected one in view
+    let height = chunks[1].height as usize;
+    let top = search
+        .selected
+        .saturating_sub(height / 2)
+        .min(search.matches.len().saturating_sub(height));
+    let matches: Vec<Line> = search
+        .matches
+        .iter()
+        .enumerate()
+        .skip(top)
+        .take(height)
+        .map(|(i, found)| {
+            let selected = search.browsing && i == search.selected;
+            let line = Line::from(vec![
+                Span::raw(selection_prefix(app, selected)),
+                Span::styled(found.path.as_str(), Style::default().fg(app.theme.special)),
+                Span::raw(":"),
+                Span::styled(
+                    found.line.to_string(),
+                    Style::default().fg(app.theme.success),
+                ),
+                Span::raw(": "),
+                Span::raw(found.text.trim_start()),
+            ]);
+            if selected {
+                line.patch_style(Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                line
+            }
+        })
+        .collect();
+    f.render_widget(Paragraph::new(matches), chunks[1]);
+
+    // Preview of the selected match's surroundings
+    let (Some(found), Some((_, lines))) = (search.matches.get(search.selected), &search.preview)
+    else {
+        return;
+    };
+    let height = chunks[2].height.saturating_sub(1) as usize;
+    let first = found
+        .line
+        .saturating_sub(1)
+        .saturating_sub(height / 2)
+        .min(lines.len().saturating_sub(height));
+    let width = (first + height).min(lines.len()).to_string().len();
+    let mut preview = vec![Line::from(Span::styled(
+        tr!("── {}:{}", found.path, found.line),
+        Style::default().fg(app.theme.dimmed),
+    ))];
+    preview.extend(
+        lines
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(height)
+            .map(|(i, text)| {
+                let line = Line::from(vec![
+                    Span::styled(
+                        format!("{:>width$} ", i + 1, width = width),
+                        Style::default().fg(app.theme.dimmed),
+                    ),
+                    Span::raw(text.as_str()),
+                ]);
+                if i + 1 == found.line {
+                    line.patch_style(app.theme.highlight())
+                } else {
+                    line
+                }
+            }),
+    );
+    f.render_widget(
+        Paragraph::new(preview).style(Style::default().fg(app.theme.text)),
+        chunks[2],
+    );
+}
+
+fn render_bisect_panel(f: &mut Frame, app: &App, panel: &BisectPanel) {
+    let area = centered_rect(70, 40, f.area());
+
+    let subject = |id: &str| {
+        app.history_state
+            .commits
+            .iter()
+            .find(|commit| commit.id == id)
+            .map(|commit| commit.message.lines().next().unwrap_or("").to_string())
+            .unwrap_or_default()
+    };
+    let commit_line = |id: &str| {
+        Line::from(vec![
+            Span::styled(format!("{} ", id), Style::default().fg(app.theme.accent)),
+            Span::raw(subject(id)),
+        ])
+    };
+
+    let mut lines = vec![
+        Line::from(if app.in_progress == Some(InProgress::Bisect) {
+            tr!("g: good | b: bad | s: skip | x: abort, back to where the bisect started | Esc: close")
+        } else {
+            tr!("g: good | b: bad | Esc: close")
+        }),
         Line::from(""),
-        Line::from(instruction),
+        Line::from(tr!("Marking:")),
+        commit_line(&panel.target),
         Line::from(""),
-        Line::from(app.discard_confirmation.as_str()),
     ];
+    match &panel.step {
+        BisectStep::NeedBad => {
+            lines.push(Line::from(tr!(
+                "Mark a commit that has the bug bad (b) to start"
+            )));
+        }
+        BisectStep::NeedGood => {
+            lines.push(Line::from(tr!(
+                "Now mark a commit without the bug good: Esc, select it in History, then m and g"
+            )));
+        }
+        BisectStep::Testing {
+            commit,
+            remaining,
+            steps,
+        } => {
+            lines.push(Line::from(tr!(
+                "Checked out {} to test: {} suspects left, roughly {} more step(s)",
+                commit,
+                remaining,
+                steps
+            )));
+        }
+        BisectStep::Found(id) => {
+            lines.push(Line::from(Span::styled(
+                tr!("The first bad commit is (Enter: show it, x: end the bisect):"),
+                Style::default()
+                    .fg(app.theme.danger)
+                    .add_modifier(Modifier::BOLD),
+            )));
+            lines.push(commit_line(id));
+        }
+        BisectStep::Inconclusive(ids) => {
+            lines.push(Line::from(Span::styled(
+                tr!("Only skipped commits are left; the first bad commit is one of:"),
+                Style::default().fg(app.theme.accent),
+            )));
+            lines.extend(ids.iter().map(|id| commit_line(id)));
+        }
+    }
+
+    let block = Block::default()
+        .title(tr!("Bisect"))
+        .borders(pane_borders(app))
+        .border_style(Style::default().fg(app.theme.border));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(ratatui::widgets::Wrap { trim: false });
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+fn render_blame(f: &mut Frame, app: &App, blame: &BlameView) {
+    let area = centered_rect(90, 85, f.area());
+
+    let block = Block::default()
+        .title(tr!(
+            "Blame {} @ {} - line {} of {} (Enter: commit, p: reblame at parent, Backspace: back, Esc: close)",
+            blame.path,
+            blame.revision,
+            blame.selected + 1,
+            blame.lines.len()
+        ))
+        .borders(pane_borders(app))
+        .border_style(Style::default().fg(app.theme.border));
+
+    let width = blame.lines.len().to_string().len();
+    let text: Vec<Line> = blame
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            // Only the first line of each run from one commit repeats who and when
+            let starts_run = i == 0 || blame.lines[i - 1].id != line.id;
+            let (id, author, date) = if starts_run {
+                (line.id.as_str(), line.author.as_str(), line.date.as_str())
+            } else {
+                ("", "", "")
+            };
+            let author: String = author.chars().take(16).collect();
+            let spans = vec![
+                Span::styled(format!("{:7} ", id), Style::default().fg(app.theme.accent)),
+                Span::styled(
+                    format!("{:16} ", author),
+                    Style::default().fg(app.theme.label),
+                ),
+                Span::styled(
+                    format!("{:10} ", date),
+                    Style::default().fg(app.theme.dimmed),
+                ),
+                Span::styled(
+                    format!("{:>width$} ", i + 1, width = width),
+                    Style::default().fg(app.theme.dimmed),
+                ),
+                Span::raw(line.content.as_str()),
+            ];
+            let line = Line::from(spans);
+            if i == blame.selected {
+                line.patch_style(Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                line
+            }
+        })
+        .collect();
+
+    // Keep the selected line in the middle once scrolling starts
+    let height = area.height.saturating_sub(2) as usize;
+    let top = blame
+        .selected
+        .saturating_sub(height / 2)
+        .min(blame.lines.len().saturating_sub(height));
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .style(Style::default().fg(app.theme.text))
+        .scroll((top as u16, 0));
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+fn render_command_log(f: &mut Frame, app: &App) {
+    let area = centered_rect(80, 70, f.area());
+
+    let block = Block::default()
+        .title(tr!("Command Log (j/k: scroll, Esc: close)"))
+        .borders(pane_borders(app))
+        .border_style(Style::default().fg(app.theme.border));
+
+    let text: Vec<Line> = if app.command_log.is_empty() {
+        vec![Line::from(Span::styled(
+            tr!("No commands run yet; set shell_git = true to run network operations through git"),
+            Style::default().fg(app.theme.dimmed),
+        ))]
+    } else {
+        app.command_log
+            .iter()
+            .map(|line| {
+                if line.starts_with("$ ") {
+                    Line::from(Span::styled(
+                        line.as_str(),
+                        Style::default()
+                            .fg(app.theme.accent)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                } else {
+                    Line::from(line.as_str())
+                }
+            })
+            .collect()
+    };
 
+    // Keep the scrolled-to line at the bottom, so the latest output fills the pane
+    let height = area.height.saturating_sub(2) as usize;
+    let top = (app.command_log_scroll + 1).saturating_sub(height);
     let paragraph = Paragraph::new(text)
         .block(block)
-        .style(Style::default().fg(Color::White));
+        .style(Style::default().fg(app.theme.text))
+        .scroll((top as u16, 0));
 
     f.render_widget(Clear, area);
     f.render_widget(paragraph, area);
 }
 
+fn render_startup_warnings(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 50, f.area());
+
+    let block = Block::default()
+        .title(tr!("Configuration Warnings (press any key to continue)"))
+        .borders(pane_borders(app))
+        .border_style(Style::default().fg(app.theme.accent));
+
+    let text: Vec<Line> = app
+        .startup_warnings
+        .iter()
+        .map(|warning| Line::from(format!("• {}", warning)))
+        .collect();
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .style(Style::default().fg(app.theme.text))
+        .wrap(ratatui::widgets::Wrap { trim: false });
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// A revision text field with its completion suggestions below
+fn render_revision_input(f: &mut Frame, app: &App, area: Rect, title: &str, input: &RevisionInput) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(area);
+
+    let text = Paragraph::new(input.text.as_str())
+        .block(
+            Block::default()
+                .title(title)
+                .borders(pane_borders(app))
+                .border_style(Style::default().fg(app.theme.accent)),
+        )
+        .style(Style::default().fg(app.theme.text));
+
+    f.render_widget(text, chunks[0]);
+
+    let suggestions: Vec<ratatui::widgets::ListItem> = input
+        .suggestions
+        .iter()
+        .enumerate()
+        .map(|(i, suggestion)| {
+            let style = if i == input.selected_suggestion {
+                app.theme.selection()
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+
+            ratatui::widgets::ListItem::new(Line::from(format!(
+                "{}{}",
+                selection_prefix(app, i == input.selected_suggestion),
+                suggestion
+            )))
+            .style(style)
+        })
+        .collect();
+
+    let list = ratatui::widgets::List::new(suggestions).block(
+        Block::default()
+            .borders(pane_borders(app))
+            .title(tr!("Suggestions"))
+            .border_style(Style::default().fg(app.theme.accent)),
+    );
+
+    f.render_widget(list, chunks[1]);
+}
+
+/// Header, main content and footer of the screen; mouse handling maps clicks with the same split
+pub fn screen_layout(area: Rect) -> Rc<[Rect]> {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Main content
+            Constraint::Length(3), // Footer
+        ])
+        .split(area)
+}
+
+/// A list on the left taking `left_percent` of the width and a diff or detail pane on the right
+pub fn split_panes(area: Rect, left_percent: u16) -> Rc<[Rect]> {
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(left_percent),
+            Constraint::Percentage(100 - left_percent),
+        ])
+        .split(area)
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
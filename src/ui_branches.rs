@@ -1,52 +1,134 @@
 use ratatui::{
     layout::Rect,
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem},
+    widgets::{Block, List, ListItem},
     Frame,
 };
 
 use crate::app::App;
+use crate::i18n::tr;
+use crate::ui::{pane_borders, selection_prefix};
 
 pub fn render_branches(f: &mut Frame, app: &App, area: Rect) {
-    let branches: Vec<ListItem> = app
-        .branches_state
-        .branches
+    let visible = app.branches_state.visible(app.branch_filter());
+    let now = chrono::Utc::now().timestamp();
+    let branches: Vec<ListItem> = visible
         .iter()
-        .enumerate()
+        .map(|&i| (i, &app.branches_state.branches[i]))
         .map(|(i, branch)| {
             let style = if i == app.branches_state.selected {
-                Style::default()
-                    .fg(Color::Black)
-                    .bg(Color::White)
-                    .add_modifier(Modifier::BOLD)
+                app.theme.selection()
             } else {
-                Style::default().fg(Color::White)
+                Style::default().fg(app.theme.text)
             };
 
             let prefix = if branch.is_current { "* " } else { "  " };
             let branch_style = if branch.is_current {
-                Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+                Style::default()
+                    .fg(app.theme.success)
+                    .add_modifier(Modifier::BOLD)
             } else {
-                Style::default().fg(Color::White)
+                Style::default().fg(app.theme.text)
             };
 
-            let content = Line::from(vec![
+            let mut spans = vec![
+                Span::raw(selection_prefix(app, i == app.branches_state.selected)),
                 Span::styled(prefix, branch_style),
-                Span::styled(&branch.name, branch_style),
-            ]);
+            ];
+            match branch
+                .remote
+                .as_deref()
+                .and_then(|remote| branch.name.strip_prefix(remote))
+            {
+                Some(short_name) => {
+                    spans.push(Span::styled(
+                        branch.remote.as_deref().unwrap_or_default(),
+                        Style::default().fg(app.theme.danger),
+                    ));
+                    spans.push(Span::styled(short_name, branch_style));
+                }
+                None => spans.push(Span::styled(&branch.name, branch_style)),
+            }
+            if let Some(upstream) = &branch.upstream {
+                spans.push(Span::styled(
+                    format!(" → {}", upstream),
+                    Style::default().fg(app.theme.dimmed),
+                ));
+            }
+            if let Some((ahead, behind)) = branch.ahead_behind {
+                if ahead > 0 {
+                    spans.push(Span::styled(
+                        format!(" ↑{}", ahead),
+                        Style::default().fg(app.theme.success),
+                    ));
+                }
+                if behind > 0 {
+                    spans.push(Span::styled(
+                        format!(" ↓{}", behind),
+                        Style::default().fg(app.theme.accent),
+                    ));
+                }
+            }
+            spans.push(Span::styled(
+                format!(
+                    "  {} · {}",
+                    relative_age(branch.tip_time, now),
+                    branch.tip_subject
+                ),
+                Style::default().fg(app.theme.dimmed),
+            ));
+            let content = Line::from(spans);
 
             ListItem::new(content).style(style)
         })
         .collect();
 
-    let title = format!("Branches (Current: {})", app.branches_state.current_branch);
+    let remote_count = app
+        .branches_state
+        .branches
+        .iter()
+        .filter(|branch| branch.remote.is_some())
+        .count();
+    let mut title = tr!(
+        "Branches (Current: {}; {} remote; by {})",
+        app.branches_state.current_branch,
+        remote_count,
+        if app.branches_state.sort_by_recency {
+            tr!("recent commit")
+        } else {
+            tr!("name")
+        }
+    );
+    if let Some(filter) = app.branch_filter() {
+        title.push_str(&tr!(
+            " - /{} ({} of {}; Enter: checkout, Esc: clear)",
+            filter,
+            visible.len(),
+            app.branches_state.branches.len()
+        ));
+    }
     let branches_list = List::new(branches).block(
         Block::default()
-            .borders(Borders::ALL)
+            .borders(pane_borders(app))
             .title(title)
-            .border_style(Style::default().fg(Color::Cyan)),
+            .border_style(Style::default().fg(app.theme.border)),
     );
 
     f.render_widget(branches_list, area);
 }
+
+/// Compact age of a commit, e.g. `3d ago`, like `git branch --format='%(committerdate:relative)'`
+fn relative_age(time: i64, now: i64) -> String {
+    let seconds = (now - time).max(0);
+    let (count, unit) = match seconds {
+        s if s < 60 => return tr!("just now").to_string(),
+        s if s < 3600 => (s / 60, tr!("m")),
+        s if s < 86_400 => (s / 3600, tr!("h")),
+        s if s < 7 * 86_400 => (s / 86_400, tr!("d")),
+        s if s < 30 * 86_400 => (s / (7 * 86_400), tr!("w")),
+        s if s < 365 * 86_400 => (s / (30 * 86_400), tr!("mo")),
+        s => (s / (365 * 86_400), tr!("y")),
+    };
+    tr!("{}{} ago", count, unit)
+}
@@ -0,0 +1,167 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::app::{App, WorkingTreeSummary};
+use crate::i18n::tr;
+use crate::ui::{in_progress_banner, pane_borders};
+
+/// Commits listed under Recent Commits
+const RECENT_COMMITS: usize = 10;
+
+pub fn render_dashboard(f: &mut Frame, app: &App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+        .split(area);
+
+    let label = |text: &str| {
+        Span::styled(
+            format!("{:<14}", text),
+            Style::default().fg(app.theme.label),
+        )
+    };
+    let dimmed = Style::default().fg(app.theme.dimmed);
+
+    // Left side: where the repository stands
+    let current = app
+        .branches_state
+        .branches
+        .iter()
+        .find(|branch| branch.is_current);
+    let mut lines = vec![Line::from(vec![
+        label(tr!("Branch:")),
+        match &app.detached_at {
+            Some(id) => Span::styled(
+                tr!("detached @ {}", id),
+                Style::default().fg(app.theme.accent),
+            ),
+            None => Span::styled(
+                app.branches_state.current_branch.clone(),
+                Style::default()
+                    .fg(app.theme.success)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        },
+    ])];
+
+    let mut upstream = vec![label(tr!("Upstream:"))];
+    match current.and_then(|branch| branch.upstream.as_ref()) {
+        Some(name) => {
+            let (ahead, behind) = current
+                .and_then(|branch| branch.ahead_behind)
+                .unwrap_or_default();
+            upstream.push(Span::raw(format!("{} ", name)));
+            upstream.push(Span::styled(
+                tr!("↑{} ahead ↓{} behind", ahead, behind),
+                Style::default().fg(if ahead + behind > 0 {
+                    app.theme.accent
+                } else {
+                    app.theme.success
+                }),
+            ));
+        }
+        None => upstream.push(Span::styled(tr!("none"), dimmed)),
+    }
+    lines.push(Line::from(upstream));
+
+    let summary = app.files_state.summary();
+    let mut working_tree = vec![label(tr!("Working tree:"))];
+    if summary == WorkingTreeSummary::default() {
+        working_tree.push(Span::styled(
+            tr!("clean"),
+            Style::default().fg(app.theme.success),
+        ));
+    } else {
+        working_tree.push(Span::raw(tr!(
+            "{} staged, {} modified, {} untracked",
+            summary.staged,
+            summary.modified,
+            summary.untracked
+        )));
+        if summary.conflicted > 0 {
+            working_tree.push(Span::styled(
+                tr!(", {} conflicted", summary.conflicted),
+                Style::default()
+                    .fg(app.theme.danger)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+    }
+    lines.push(Line::from(working_tree));
+
+    lines.push(Line::from(vec![
+        label(tr!("Stash:")),
+        if app.stash_count == 0 {
+            Span::styled(tr!("empty"), dimmed)
+        } else {
+            Span::raw(tr!("{} stash(es)", app.stash_count))
+        },
+    ]));
+
+    lines.push(Line::from(vec![
+        label(tr!("In progress:")),
+        match app.in_progress {
+            Some(operation) => {
+                let (name, hint) = in_progress_banner(operation);
+                Span::styled(
+                    format!("{} - {}", name, hint),
+                    Style::default()
+                        .fg(app.theme.danger)
+                        .add_modifier(Modifier::BOLD),
+                )
+            }
+            None => Span::styled(tr!("nothing"), dimmed),
+        },
+    ]));
+
+    lines.extend([
+        Line::from(""),
+        Line::from(tr!(
+            "f: Files | h: History | b: Branches | t: Tags | g: Run gc"
+        )),
+    ]);
+
+    let overview = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(pane_borders(app))
+                .title(tr!("Overview"))
+                .border_style(Style::default().fg(app.theme.border)),
+        )
+        .wrap(Wrap { trim: false });
+    f.render_widget(overview, chunks[0]);
+
+    // Right side: the latest commits
+    let commits: Vec<Line> = app
+        .history_state
+        .commits
+        .iter()
+        .take(RECENT_COMMITS)
+        .map(|commit| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{} ", commit.id),
+                    Style::default().fg(app.theme.accent),
+                ),
+                Span::styled(format!("{} ", commit.date), dimmed),
+                Span::styled(
+                    format!("{} ", commit.author),
+                    Style::default().fg(app.theme.label),
+                ),
+                Span::raw(commit.message.lines().next().unwrap_or("")),
+            ])
+        })
+        .collect();
+    let recent = Paragraph::new(commits).block(
+        Block::default()
+            .borders(pane_borders(app))
+            .title(tr!("Recent Commits"))
+            .border_style(Style::default().fg(app.theme.border)),
+    );
+    f.render_widget(recent, chunks[1]);
+}
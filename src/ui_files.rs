@@ -1,60 +1,79 @@
+use std::collections::HashSet;
+
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    layout::Rect,
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, List, ListItem, Paragraph, Wrap},
     Frame,
 };
 
-use crate::app::App;
+use crate::app::{current_hunk, App};
+use crate::i18n::tr;
+use crate::theme::Theme;
+use crate::ui::{pane_borders, selection_prefix, split_panes};
 
 pub fn render_files(f: &mut Frame, app: &App, area: Rect) {
-    let chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
-        .split(area);
+    let chunks = split_panes(area, app.files_split);
 
-    // Left side: file list
-    let files: Vec<ListItem> = app
-        .files_state
-        .files
-        .iter()
-        .enumerate()
-        .map(|(i, file)| {
-            let style = if i == app.files_state.selected {
-                Style::default()
-                    .fg(Color::Black)
-                    .bg(Color::White)
-                    .add_modifier(Modifier::BOLD)
+    // Left side: file list, staged rows first under their own heading
+    let mut files: Vec<ListItem> = Vec::new();
+    let mut section = None;
+    for (i, file) in app.files_state.files.iter().enumerate() {
+        if section != Some(file.staged) {
+            section = Some(file.staged);
+            let heading = if file.staged {
+                tr!("Staged")
             } else {
-                Style::default().fg(Color::White)
-            };
-
-            let status_color = match file.status.trim() {
-                "A" => Color::Green,
-                "M" | " M" => Color::Yellow,
-                "D" | " D" => Color::Red,
-                "??" => Color::Blue,
-                _ => Color::White,
+                tr!("Unstaged")
             };
+            files.push(ListItem::new(Line::from(Span::styled(
+                heading,
+                Style::default()
+                    .fg(app.theme.label)
+                    .add_modifier(Modifier::BOLD),
+            ))));
+        }
+        let style = if i == app.files_state.selected {
+            app.theme.selection()
+        } else {
+            Style::default().fg(app.theme.text)
+        };
 
-            let content = Line::from(vec![
-                Span::styled(
-                    format!("{} ", file.status),
-                    Style::default().fg(status_color),
-                ),
-                Span::raw(&file.path),
-            ]);
+        let mut spans = vec![Span::raw(selection_prefix(
+            app,
+            i == app.files_state.selected,
+        ))];
+        spans.extend(status_spans(&file.status, file.staged, &app.theme));
+        spans.extend([
+            Span::raw(" "),
+            Span::raw(match &file.old_path {
+                Some(old_path) => format!("{} -> {}", old_path, file.path),
+                None => file.path.clone(),
+            }),
+        ]);
+        if file.additions > 0 {
+            spans.push(Span::styled(
+                format!(" +{}", file.additions),
+                Style::default().fg(app.theme.added),
+            ));
+        }
+        if file.deletions > 0 {
+            spans.push(Span::styled(
+                format!(" -{}", file.deletions),
+                Style::default().fg(app.theme.removed),
+            ));
+        }
+        let content = Line::from(spans);
 
-            ListItem::new(content).style(style)
-        })
-        .collect();
+        files.push(ListItem::new(content).style(style));
+    }
 
     let files_list = List::new(files).block(
         Block::default()
-            .borders(Borders::ALL)
-            .title("Files")
-            .border_style(Style::default().fg(Color::Cyan)),
+            .borders(pane_borders(app))
+            .title(tr!("Files"))
+            .border_style(Style::default().fg(app.theme.border)),
     );
 
     f.render_widget(files_list, chunks[0]);
@@ -62,40 +81,211 @@ pub fn render_files(f: &mut Frame, app: &App, area: Rect) {
     // Right side: diff view
     let diff_text = app
         .files_state
-        .current_diff
-        .as_ref()
-        .map(|s| s.as_str())
-        .unwrap_or("Select a file to view diff");
+        .displayed_diff()
+        .unwrap_or(tr!("Select a file to view diff"));
 
-    let all_lines: Vec<Line> = diff_text
-        .lines()
-        .map(|line| {
-            let style = if line.starts_with('+') {
-                Style::default().fg(Color::Green)
-            } else if line.starts_with('-') {
-                Style::default().fg(Color::Red)
-            } else if line.starts_with("@@") {
-                Style::default().fg(Color::Cyan)
+    let changed = app.files_state.changed_lines.clone().unwrap_or_default();
+    let scroll_offset = app.files_state.diff_scroll;
+    let hunk = current_hunk(diff_text, scroll_offset);
+    let all_lines = diff_lines(diff_text, &app.theme)
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            if changed.contains(&i) {
+                line.patch_style(app.theme.highlight())
+            } else if Some(i) == hunk {
+                line.patch_style(current_hunk_style())
             } else {
-                Style::default().fg(Color::White)
-            };
-
-            Line::from(Span::styled(line, style))
-        })
-        .collect();
+                line
+            }
+        });
 
     // Apply scrolling offset
-    let scroll_offset = app.files_state.diff_scroll;
-    let visible_lines: Vec<Line> = all_lines.into_iter().skip(scroll_offset).collect();
+    let visible_lines: Vec<Line> = all_lines.skip(scroll_offset).collect();
+    let line_count = diff_text.lines().count().max(1);
+    let line = (scroll_offset + 1).min(line_count);
+    let position = tr!(
+        "line {} of {} ({}%)",
+        line,
+        line_count,
+        line * 100 / line_count
+    );
+    let title = if app.files_state.rejected_hunks.is_some() {
+        tr!("Rejected Hunks - {} - Esc to dismiss", position)
+    } else if changed.is_empty() {
+        tr!("Diff - {}", position)
+    } else {
+        tr!(
+            "Diff - {} - lines {}-{} changed on disk",
+            position,
+            changed.start + 1,
+            changed.end
+        )
+    };
+    // Diffstat of the whole working tree
+    let files = &app.files_state.files;
+    let title = if app.files_state.rejected_hunks.is_some() || files.is_empty() {
+        title
+    } else {
+        tr!(
+            "{} - {} file(s), +{} -{}",
+            title,
+            // Files listed as both staged and unstaged count once
+            files
+                .iter()
+                .map(|file| file.path.as_str())
+                .collect::<HashSet<_>>()
+                .len(),
+            files.iter().map(|file| file.additions).sum::<usize>(),
+            files.iter().map(|file| file.deletions).sum::<usize>()
+        )
+    };
 
     let diff_paragraph = Paragraph::new(visible_lines)
         .block(
             Block::default()
-                .borders(Borders::ALL)
-                .title(format!("Diff (scroll: {})", scroll_offset))
-                .border_style(Style::default().fg(Color::Cyan)),
+                .borders(pane_borders(app))
+                .title(title)
+                .border_style(Style::default().fg(app.theme.border)),
         )
         .wrap(Wrap { trim: false });
 
     f.render_widget(diff_paragraph, chunks[1]);
 }
+
+/// A two-column status code with the index column in green and the worktree column in red, as
+/// `git status` colors staged and unstaged changes; untracked and conflicted files get one color.
+/// The column of the side a row isn't about is dimmed.
+fn status_spans<'a>(status: &'a str, staged: bool, theme: &Theme) -> Vec<Span<'a>> {
+    match status {
+        "??" => vec![Span::styled(status, Style::default().fg(theme.untracked))],
+        "UU" => vec![Span::styled(
+            status,
+            Style::default()
+                .fg(theme.danger)
+                .add_modifier(Modifier::BOLD),
+        )],
+        _ => {
+            let (index, worktree) = status.split_at(status.len().min(1));
+            let dimmed = Style::default().fg(theme.dimmed);
+            let (index_style, worktree_style) = if staged {
+                (Style::default().fg(theme.added), dimmed)
+            } else {
+                (dimmed, Style::default().fg(theme.removed))
+            };
+            vec![
+                Span::styled(index, index_style),
+                Span::styled(worktree, worktree_style),
+            ]
+        }
+    }
+}
+
+/// Color patch text line by line: additions, removals, hunk headers and conflict markers.
+/// Lines inside hunks get a gutter with their old and new line numbers.
+pub fn diff_lines<'a>(diff_text: &'a str, theme: &Theme) -> Vec<Line<'a>> {
+    let numbers = line_numbers(diff_text);
+    let widest = numbers
+        .iter()
+        .flat_map(|(old, new)| [*old, *new])
+        .flatten()
+        .max();
+    let width = widest.map_or(0, |n| n.to_string().len());
+    let number = |n: Option<usize>| n.map_or_else(String::new, |n| n.to_string());
+
+    diff_text
+        .lines()
+        .zip(numbers)
+        .map(|(line, (old, new))| {
+            let style = if ["<<<<<<<", "|||||||", "=======", ">>>>>>>"]
+                .iter()
+                .any(|marker| line.starts_with(marker))
+            {
+                Style::default()
+                    .fg(theme.special)
+                    .add_modifier(Modifier::BOLD)
+            } else if line.starts_with('+') {
+                Style::default().fg(theme.added)
+            } else if line.starts_with('-') {
+                Style::default().fg(theme.removed)
+            } else if line.starts_with("@@") {
+                Style::default().fg(theme.label)
+            } else if ["old mode", "new mode", "new file mode", "deleted file mode"]
+                .iter()
+                .any(|prefix| line.starts_with(prefix))
+            {
+                Style::default().fg(theme.accent)
+            } else {
+                Style::default().fg(theme.text)
+            };
+
+            let content = Span::styled(line, style);
+            if widest.is_none() {
+                return Line::from(content);
+            }
+            let gutter = format!(
+                "{:>width$} {:>width$}  ",
+                number(old),
+                number(new),
+                width = width
+            );
+            Line::from(vec![
+                Span::styled(gutter, Style::default().fg(theme.dimmed)),
+                content,
+            ])
+        })
+        .collect()
+}
+
+/// Highlight for the header of the hunk the diff is scrolled into, as `n`/`N` move between them
+pub fn current_hunk_style() -> Style {
+    Style::default().add_modifier(Modifier::REVERSED)
+}
+
+/// Old and new line number of every line of patch text, counted from the hunk headers. Lines
+/// outside hunks have neither; added and removed lines only exist on one side.
+fn line_numbers(diff_text: &str) -> Vec<(Option<usize>, Option<usize>)> {
+    let mut position: Option<(usize, usize)> = None;
+    diff_text
+        .lines()
+        .map(|line| {
+            if let Some(start) = line.strip_prefix("@@ ").and_then(hunk_start) {
+                position = Some(start);
+                return (None, None);
+            }
+            if line.starts_with("diff ") {
+                position = None;
+            }
+            let Some((old, new)) = position.as_mut() else {
+                return (None, None);
+            };
+            match line.chars().next() {
+                Some('+') => {
+                    *new += 1;
+                    (None, Some(*new - 1))
+                }
+                Some('-') => {
+                    *old += 1;
+                    (Some(*old - 1), None)
+                }
+                Some(' ') | None => {
+                    *old += 1;
+                    *new += 1;
+                    (Some(*old - 1), Some(*new - 1))
+                }
+                // `\ No newline at end of file`
+                _ => (None, None),
+            }
+        })
+        .collect()
+}
+
+/// First old and new line of a hunk from the rest of its header, e.g. `-12,7 +12,9 @@`
+fn hunk_start(header: &str) -> Option<(usize, usize)> {
+    let mut ranges = header.split_whitespace();
+    let start = |range: &str| range.split(',').next()?.parse::<usize>().ok();
+    let old = start(ranges.next()?.strip_prefix('-')?)?;
+    let new = start(ranges.next()?.strip_prefix('+')?)?;
+    // An empty side (`-0,0`) starts counting at 1 once lines appear
+    Some((old.max(1), new.max(1)))
+}
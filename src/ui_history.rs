@@ -1,81 +1,118 @@
 use ratatui::{
     layout::Rect,
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem},
+    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 
-use crate::app::App;
+use crate::app::{current_hunk, App, CommitDetail};
+use crate::i18n::tr;
+use crate::ui::{pane_borders, selection_prefix, split_panes};
+use crate::ui_files::{current_hunk_style, diff_lines};
 
 pub fn render_history(f: &mut Frame, app: &App, area: Rect) {
-    let commits: Vec<ListItem> = app
+    // Split off a detail pane when a commit has been opened
+    let pane_open = app.history_state.detail.is_some() || app.history_state.commit_diff.is_some();
+    let (list_area, detail_area) = if pane_open {
+        let chunks = split_panes(area, app.history_split);
+        (chunks[0], Some(chunks[1]))
+    } else {
+        (area, None)
+    };
+
+    let graph_width = app
+        .history_state
+        .commits
+        .iter()
+        .filter_map(|commit| commit.graph_info.as_ref())
+        .map(|graph| graph.cells.len())
+        .max()
+        .unwrap_or(0);
+
+    // Rows between the range anchor and the selection, inclusive
+    let range = app
+        .history_state
+        .range_anchor
+        .as_ref()
+        .and_then(|anchor| {
+            app.history_state
+                .commits
+                .iter()
+                .position(|commit| &commit.id == anchor)
+        })
+        .map(|anchor| {
+            let selected = app.history_state.selected;
+            anchor.min(selected)..=anchor.max(selected)
+        });
+
+    let mut commits: Vec<ListItem> = app
         .history_state
         .commits
         .iter()
         .enumerate()
         .map(|(i, commit)| {
             let style = if i == app.history_state.selected {
-                Style::default()
-                    .fg(Color::Black)
-                    .bg(Color::White)
-                    .add_modifier(Modifier::BOLD)
+                app.theme.selection()
+            } else if range.as_ref().is_some_and(|range| range.contains(&i)) {
+                app.theme.highlight().fg(app.theme.text)
             } else {
-                Style::default().fg(Color::White)
+                Style::default().fg(app.theme.text)
             };
 
-            let mut spans = vec![];
+            let mut spans = vec![Span::raw(selection_prefix(
+                app,
+                i == app.history_state.selected,
+            ))];
 
-            // Add graph visualization
-            if let Some(ref graph_info) = commit.graph_info {
-                if !graph_info.graph_line.trim().is_empty() {
+            // Add graph visualization (skipped in screen-reader mode)
+            if !app.accessible {
+                let cells = commit
+                    .graph_info
+                    .as_ref()
+                    .map(|graph| graph.cells.as_slice())
+                    .unwrap_or_default();
+                for cell in cells {
                     spans.push(Span::styled(
-                        format!("{}│ ", graph_info.graph_line),
+                        cell.glyph.to_string(),
                         Style::default()
-                            .fg(Color::Magenta)
-                            .add_modifier(Modifier::BOLD),
-                    ));
-                } else {
-                    // Fallback if graph is empty
-                    spans.push(Span::styled(
-                        "● │ ",
-                        Style::default()
-                            .fg(Color::Magenta)
+                            .fg(app.theme.lane(cell.color))
                             .add_modifier(Modifier::BOLD),
                     ));
                 }
-            } else {
-                // No graph info, show basic marker
-                spans.push(Span::styled(
-                    "● │ ",
-                    Style::default()
-                        .fg(Color::Magenta)
-                        .add_modifier(Modifier::BOLD),
-                ));
+                // Pad to the widest row so messages line up
+                let padding = graph_width.saturating_sub(cells.len()) + 1;
+                spans.push(Span::raw(" ".repeat(padding)));
             }
 
             // Add commit info
             spans.push(Span::styled(
                 format!("{} ", commit.id),
-                Style::default().fg(Color::Yellow),
+                Style::default().fg(app.theme.accent),
             ));
 
-            // Add branch labels
-            if !commit.branches.is_empty() {
-                for branch_name in &commit.branches {
-                    spans.push(Span::styled(
-                        format!("({}) ", branch_name),
-                        Style::default()
-                            .fg(Color::Cyan)
-                            .add_modifier(Modifier::BOLD),
-                    ));
-                }
+            // Add branch and tag labels
+            for branch_name in &commit.branches {
+                spans.push(Span::styled(
+                    format!("({}) ", branch_name),
+                    Style::default()
+                        .fg(app.theme.label)
+                        .add_modifier(Modifier::BOLD),
+                ));
+            }
+            for tag_name in &commit.tags {
+                spans.push(Span::styled(
+                    format!("({}) ", tag_name),
+                    Style::default()
+                        .fg(app.theme.tag)
+                        .add_modifier(Modifier::BOLD),
+                ));
             }
 
             spans.push(Span::raw(format!("{} ", commit.date)));
             spans.push(Span::styled(
                 commit.author.clone(),
-                Style::default().fg(Color::Green),
+                Style::default().fg(app.theme.success),
             ));
             spans.push(Span::raw(format!(" - {}", commit.message)));
 
@@ -85,12 +122,177 @@ pub fn render_history(f: &mut Frame, app: &App, area: Rect) {
         })
         .collect();
 
+    // More pages are fetched as the selection nears this row
+    if app.history_state.has_more {
+        commits.push(ListItem::new(Line::from(Span::styled(
+            tr!("  loading more…"),
+            Style::default().fg(app.theme.dimmed),
+        ))));
+    }
+
+    let mut scope = if app.history_state.filter.all_branches {
+        tr!("all branches").to_string()
+    } else {
+        tr!("current branch").to_string()
+    };
+    if app.history_state.filter.first_parent {
+        scope.push_str(tr!(", first parent"));
+    }
+    let title = if app.history_state.has_more {
+        tr!(
+            "Commit History: {} ({}+)",
+            scope,
+            app.history_state.commits.len()
+        )
+    } else {
+        tr!(
+            "Commit History: {} ({})",
+            scope,
+            app.history_state.commits.len()
+        )
+    };
     let commits_list = List::new(commits).block(
         Block::default()
-            .borders(Borders::ALL)
-            .title("Commit History")
-            .border_style(Style::default().fg(Color::Cyan)),
+            .borders(pane_borders(app))
+            .title(title)
+            .border_style(Style::default().fg(app.theme.border)),
     );
 
-    f.render_widget(commits_list, area);
+    // Stateful rendering keeps the selected commit scrolled into view
+    let mut list_state = ListState::default().with_selected(Some(app.history_state.selected));
+    f.render_stateful_widget(commits_list, list_area, &mut list_state);
+
+    if let (Some(detail), Some(detail_area)) = (&app.history_state.detail, detail_area) {
+        render_commit_detail(f, app, detail, detail_area);
+    }
+    if let (Some(diff), Some(detail_area)) = (&app.history_state.commit_diff, detail_area) {
+        render_commit_diff(f, app, diff, detail_area);
+    }
+}
+
+fn render_commit_diff(f: &mut Frame, app: &App, diff: &str, area: Rect) {
+    let hunk = current_hunk(diff, app.history_state.detail_scroll);
+    let visible_lines: Vec<Line> = diff_lines(diff, &app.theme)
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            if Some(i) == hunk {
+                line.patch_style(current_hunk_style())
+            } else {
+                line
+            }
+        })
+        .skip(app.history_state.detail_scroll)
+        .collect();
+
+    let title = match (
+        app.history_range(),
+        app.history_state.commits.get(app.history_state.selected),
+    ) {
+        (Some((from, to)), _) => tr!("Diff {}..{} (Esc to close)", from, to),
+        (None, Some(commit)) => tr!("Diff of {} against parent (Esc to close)", commit.id),
+        (None, None) => tr!("Commit Diff (Esc to close)").to_string(),
+    };
+
+    let paragraph = Paragraph::new(visible_lines)
+        .block(
+            Block::default()
+                .borders(pane_borders(app))
+                .title(title)
+                .border_style(Style::default().fg(app.theme.border)),
+        )
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(paragraph, area);
+}
+
+fn render_commit_detail(f: &mut Frame, app: &App, detail: &CommitDetail, area: Rect) {
+    let label = Style::default().fg(app.theme.label);
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled(format!("{:<10}", tr!("commit")), label),
+            Span::styled(detail.id.as_str(), Style::default().fg(app.theme.accent)),
+        ]),
+        Line::from(vec![
+            Span::styled(format!("{:<10}", tr!("Author:")), label),
+            Span::styled(
+                detail.author.as_str(),
+                Style::default().fg(app.theme.success),
+            ),
+            Span::raw(format!("  {}", detail.author_date)),
+        ]),
+        Line::from(vec![
+            Span::styled(format!("{:<10}", tr!("Commit:")), label),
+            Span::styled(
+                detail.committer.as_str(),
+                Style::default().fg(app.theme.success),
+            ),
+            Span::raw(format!("  {}", detail.commit_date)),
+        ]),
+        Line::from(vec![
+            Span::styled(format!("{:<10}", tr!("Parents:")), label),
+            Span::raw(detail.parent_ids.join(" ")),
+        ]),
+        Line::from(""),
+    ];
+
+    for message_line in detail.message.lines() {
+        lines.push(Line::from(format!("    {}", message_line)));
+    }
+
+    let total_insertions: usize = detail.files.iter().map(|f| f.insertions).sum();
+    let total_deletions: usize = detail.files.iter().map(|f| f.deletions).sum();
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        tr!(
+            "{} files changed, {} insertions(+), {} deletions(-)",
+            detail.files.len(),
+            total_insertions,
+            total_deletions
+        ),
+        label,
+    )));
+
+    for file in &detail.files {
+        let status_color = match file.status {
+            'A' => app.theme.added,
+            'D' => app.theme.removed,
+            'R' | 'C' => app.theme.special,
+            _ => app.theme.accent,
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{} ", file.status),
+                Style::default().fg(status_color),
+            ),
+            Span::raw(format!("{} ", file.path)),
+            Span::styled(
+                format!("+{}", file.insertions),
+                Style::default().fg(app.theme.added),
+            ),
+            Span::raw(" "),
+            Span::styled(
+                format!("-{}", file.deletions),
+                Style::default().fg(app.theme.removed),
+            ),
+        ]));
+    }
+
+    let visible_lines: Vec<Line> = lines
+        .into_iter()
+        .skip(app.history_state.detail_scroll)
+        .collect();
+
+    let paragraph = Paragraph::new(visible_lines)
+        .block(
+            Block::default()
+                .borders(pane_borders(app))
+                .title(tr!("Commit Detail (Esc to close)"))
+                .border_style(Style::default().fg(app.theme.border)),
+        )
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(paragraph, area);
 }
@@ -0,0 +1,56 @@
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem},
+    Frame,
+};
+
+use crate::app::App;
+use crate::i18n::tr;
+use crate::ui::{pane_borders, selection_prefix};
+
+pub fn render_tags(f: &mut Frame, app: &App, area: Rect) {
+    let tags: Vec<ListItem> = app
+        .tags_state
+        .tags
+        .iter()
+        .enumerate()
+        .map(|(i, tag)| {
+            let style = if i == app.tags_state.selected {
+                app.theme.selection()
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+
+            let mut spans = vec![
+                Span::raw(selection_prefix(app, i == app.tags_state.selected)),
+                Span::styled(
+                    format!("{} ", tag.target),
+                    Style::default().fg(app.theme.accent),
+                ),
+                Span::styled(
+                    tag.name.as_str(),
+                    Style::default()
+                        .fg(app.theme.label)
+                        .add_modifier(Modifier::BOLD),
+                ),
+            ];
+
+            if let Some(message) = &tag.message {
+                spans.push(Span::raw(format!(" - {}", message)));
+            }
+
+            ListItem::new(Line::from(spans)).style(style)
+        })
+        .collect();
+
+    let tags_list = List::new(tags).block(
+        Block::default()
+            .borders(pane_borders(app))
+            .title(tr!("Tags ({})", app.tags_state.tags.len()))
+            .border_style(Style::default().fg(app.theme.border)),
+    );
+
+    f.render_widget(tags_list, area);
+}